@@ -1,5 +1,10 @@
-use asgard_rust::adapters::db;
-use asgard_rust::application::services::{OrderService, ProductService, UserService};
+use asgard_rust::adapters::{blob, db};
+use asgard_rust::application::ports::BoxedUnitOfWorkFactory;
+use asgard_rust::application::services::{
+  AuditService, CategoryService, CheckoutService, InventoryService, InvoiceService, OrderService,
+  PaymentService, ProductImageService, ProductService, ReturnService, ReviewService, UserService,
+  VariantService, WebhookService, WishlistService,
+};
 use asgard_rust::infrastructure::db as infra_db;
 use asgard_rust::{build_app, AppState};
 use axum::body::Body;
@@ -36,9 +41,10 @@ async fn setup_app() -> Option<(AppState, axum::Router)> {
   }
 
   // Limpiar datos antes de los benchmarks
-  if let Err(e) = sqlx::query("TRUNCATE orders, products, users CASCADE")
-    .execute(&pool)
-    .await
+  if let Err(e) =
+    sqlx::query("TRUNCATE orders, products, users, categories, catalog_skus, outbox, webhook_subscriptions CASCADE")
+      .execute(&pool)
+      .await
   {
     eprintln!("ERROR: Failed to truncate tables: {}", e);
     return None;
@@ -48,18 +54,80 @@ async fn setup_app() -> Option<(AppState, axum::Router)> {
   create_test_data(&pool).await;
 
   let users_repo = db::users_repo::PgUserRepository::new(pool.clone());
-  let products_repo = db::products_repo::PgProductRepository::new(pool.clone());
+  let products_repo = db::products_repo::PgProductRepository::new(pool.clone(), "english");
+  let variants_repo = db::variants_repo::PgVariantRepository::new(pool.clone());
+  let images_repo = db::images_repo::PgProductImageRepository::new(pool.clone());
+  let media_dir = std::env::temp_dir().join("asgard-bench-media");
+  let categories_repo = db::categories_repo::PgCategoryRepository::new(pool.clone());
   let orders_repo = db::orders_repo::PgOrderRepository::new(pool.clone());
+  let payments_repo = db::payments_repo::PgPaymentRepository::new(pool.clone());
+  let inventory_repo = db::inventory_repo::PgInventoryRepository::new(pool.clone());
+  let returns_repo = db::returns_repo::PgReturnRepository::new(pool.clone());
+  let invoices_repo = db::invoices_repo::PgInvoiceRepository::new(pool.clone());
 
   let state = AppState {
     pool: pool.clone(),
-    users: Arc::new(UserService::new(users_repo)),
-    products: Arc::new(ProductService::new(products_repo)),
-    orders: Arc::new(OrderService::new(orders_repo)),
+    sqlite_pool: None,
+    users: Arc::new(UserService::new(Arc::new(users_repo))),
+    products: Arc::new(ProductService::new(Arc::new(products_repo))),
+    variants: Arc::new(VariantService::new(variants_repo)),
+    images: Arc::new(ProductImageService::new(
+      images_repo,
+      Arc::new(blob::fs::FsBlobStore::new(media_dir.clone())),
+    )),
+    categories: Arc::new(CategoryService::new(categories_repo)),
+    orders: Arc::new(OrderService::new(Arc::new(orders_repo))),
+    checkout: Some(Arc::new(CheckoutService::new(BoxedUnitOfWorkFactory::new(
+      db::unit_of_work::PgUnitOfWorkFactory::new(pool.clone()),
+    )))),
+    payments: Arc::new(PaymentService::new(payments_repo)),
+    inventory: Arc::new(InventoryService::new(Arc::new(inventory_repo))),
+    returns: Arc::new(ReturnService::new(returns_repo)),
+    wishlists: Arc::new(WishlistService::new(
+      db::wishlists_repo::PgWishlistRepository::new(pool.clone()),
+    )),
+    reviews: Arc::new(ReviewService::new(
+      db::reviews_repo::PgReviewRepository::new(pool.clone()),
+    )),
+    invoices: Arc::new(InvoiceService::new(invoices_repo, 0)),
+    webhooks: Arc::new(WebhookService::new(
+      db::webhooks_repo::PgWebhookRepository::new(pool.clone()),
+    )),
+    audit: Arc::new(AuditService::new(db::audit_repo::PgAuditRepository::new(
+      pool.clone(),
+    ))),
+    order_stream: Arc::new(asgard_rust::adapters::order_stream::OrderStream::new(1000)),
+    shutdown: asgard_rust::infrastructure::shutdown::Shutdown::new(),
     config: asgard_rust::infrastructure::config::AppConfig {
       host: "127.0.0.1".into(),
       port: 0,
+      storage: asgard_rust::infrastructure::config::Storage::Postgres,
       database_url,
+      admin_api_token: None,
+      payment_webhook_secrets: Default::default(),
+      payment_webhook_tolerance_secs: 300,
+      invoice_tax_rate_bps: 0,
+      product_search_language: "english".into(),
+      product_fuzzy_threshold: 0.4,
+      user_fuzzy_threshold: 0.5,
+      media_storage: asgard_rust::infrastructure::config::MediaStorage::Filesystem {
+        root: media_dir,
+      },
+      media_url_secret: "bench-secret".into(),
+      media_url_ttl_secs: 900,
+      media_max_upload_bytes: 5 * 1024 * 1024,
+      thumbnail_sizes: vec![160, 480],
+      thumbnail_poll_interval_secs: 5,
+      outbox_poll_interval_secs: 1,
+      outbox_max_attempts: 10,
+      webhook_poll_interval_secs: 2,
+      webhook_timeout_secs: 5,
+      webhook_max_attempts: 8,
+      webhook_disable_after_failures: 20,
+      access_token_secret: "bench-secret".into(),
+      access_token_ttl_secs: 3600,
+      order_stream_replay_size: 1000,
+      ws_heartbeat_interval_secs: 30,
     },
   };
 
@@ -86,9 +154,14 @@ async fn create_test_data(pool: &PgPool) {
   // Crear 100 productos de prueba
   for i in 0..100 {
     sqlx::query(
-      "INSERT INTO products (id, sku, name, price_cents, created_at, updated_at) 
-       VALUES ($1, $2, $3, $4, NOW(), NOW()) 
-       ON CONFLICT DO NOTHING",
+      "WITH p AS (
+         INSERT INTO products (id, sku, name, created_at, updated_at)
+         VALUES ($1, $2, $3, NOW(), NOW())
+         ON CONFLICT DO NOTHING
+         RETURNING id
+       )
+       INSERT INTO product_prices (product_id, price_cents, valid_from)
+       SELECT id, $4, NOW() FROM p",
     )
     .bind(Uuid::new_v4())
     .bind(format!("SKU-{:03}", i))
@@ -0,0 +1,27 @@
+-- 0002_payments.sql
+-- Payments confirmed asynchronously through provider webhooks.
+
+CREATE TABLE IF NOT EXISTS payments (
+  id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+  order_id uuid NOT NULL REFERENCES orders(id) ON DELETE RESTRICT,
+  provider text NOT NULL,
+  provider_payment_id text NOT NULL,
+  status text NOT NULL,
+  amount_cents bigint NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  updated_at timestamptz NOT NULL DEFAULT now(),
+  UNIQUE (provider, provider_payment_id)
+);
+
+CREATE INDEX IF NOT EXISTS payments_order_id_idx ON payments (order_id);
+
+-- One row per processed provider event; the primary key deduplicates retries.
+CREATE TABLE IF NOT EXISTS payment_webhook_events (
+  provider text NOT NULL,
+  event_id text NOT NULL,
+  event_type text NOT NULL,
+  payment_id uuid REFERENCES payments(id) ON DELETE CASCADE,
+  payload jsonb NOT NULL,
+  received_at timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (provider, event_id)
+);
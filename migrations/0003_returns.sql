@@ -0,0 +1,50 @@
+-- 0003_returns.sql
+-- Order line items, inventory and the returns (RMA) / refunds workflow.
+
+CREATE TABLE IF NOT EXISTS order_items (
+  id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+  order_id uuid NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+  product_id uuid NOT NULL REFERENCES products(id) ON DELETE RESTRICT,
+  quantity integer NOT NULL CHECK (quantity > 0),
+  unit_price_cents bigint NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS order_items_order_id_idx ON order_items (order_id);
+
+CREATE TABLE IF NOT EXISTS inventory (
+  product_id uuid PRIMARY KEY REFERENCES products(id) ON DELETE CASCADE,
+  quantity bigint NOT NULL CHECK (quantity >= 0),
+  updated_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS return_requests (
+  id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+  order_id uuid NOT NULL REFERENCES orders(id) ON DELETE RESTRICT,
+  status text NOT NULL,
+  note text,
+  decision_note text,
+  restocked boolean NOT NULL DEFAULT false,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  updated_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS return_requests_order_id_idx ON return_requests (order_id);
+
+CREATE TABLE IF NOT EXISTS return_items (
+  return_id uuid NOT NULL REFERENCES return_requests(id) ON DELETE CASCADE,
+  order_item_id uuid NOT NULL REFERENCES order_items(id) ON DELETE RESTRICT,
+  quantity integer NOT NULL CHECK (quantity > 0),
+  reason text NOT NULL,
+  PRIMARY KEY (return_id, order_item_id)
+);
+
+CREATE TABLE IF NOT EXISTS refunds (
+  id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+  payment_id uuid NOT NULL REFERENCES payments(id) ON DELETE RESTRICT,
+  return_id uuid REFERENCES return_requests(id) ON DELETE RESTRICT,
+  amount_cents bigint NOT NULL CHECK (amount_cents > 0),
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS refunds_payment_id_idx ON refunds (payment_id);
@@ -0,0 +1,47 @@
+-- 0004_invoices.sql
+-- Invoices and credit notes with gapless yearly numbering.
+
+-- One counter per document series and year. Numbers are taken with
+-- INSERT .. ON CONFLICT DO UPDATE inside the issuing transaction, so a rollback
+-- also returns the number.
+CREATE TABLE IF NOT EXISTS document_sequences (
+  series text NOT NULL,
+  year integer NOT NULL,
+  last_number integer NOT NULL,
+  PRIMARY KEY (series, year)
+);
+
+CREATE TABLE IF NOT EXISTS invoices (
+  id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+  order_id uuid NOT NULL REFERENCES orders(id) ON DELETE RESTRICT,
+  kind text NOT NULL CHECK (kind IN ('invoice', 'credit_note')),
+  number text NOT NULL UNIQUE,
+  year integer NOT NULL,
+  sequence_number integer NOT NULL,
+  issued_at timestamptz NOT NULL,
+  billing_address jsonb NOT NULL,
+  lines jsonb NOT NULL,
+  tax_rate_bps integer NOT NULL,
+  net_cents bigint NOT NULL,
+  tax_cents bigint NOT NULL,
+  total_cents bigint NOT NULL,
+  credited_invoice_id uuid REFERENCES invoices(id) ON DELETE RESTRICT,
+  refund_id uuid UNIQUE REFERENCES refunds(id) ON DELETE RESTRICT,
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS invoices_one_per_order_idx
+  ON invoices (order_id) WHERE kind = 'invoice';
+CREATE INDEX IF NOT EXISTS invoices_order_id_idx ON invoices (order_id);
+
+-- Issued documents are legal records: corrections go through credit notes.
+CREATE OR REPLACE FUNCTION invoices_are_immutable() RETURNS trigger AS $$
+BEGIN
+  RAISE EXCEPTION 'invoices are immutable' USING ERRCODE = 'restrict_violation';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS invoices_immutable ON invoices;
+CREATE TRIGGER invoices_immutable
+  BEFORE UPDATE OR DELETE ON invoices
+  FOR EACH ROW EXECUTE FUNCTION invoices_are_immutable();
@@ -0,0 +1,25 @@
+-- 0005_categories.sql
+-- Hierarchical product categories and product assignment.
+
+CREATE TABLE IF NOT EXISTS categories (
+  id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+  -- Categories with children cannot be deleted; move or delete them first.
+  parent_id uuid REFERENCES categories(id) ON DELETE RESTRICT,
+  slug text NOT NULL UNIQUE,
+  name text NOT NULL,
+  position integer NOT NULL DEFAULT 0,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  updated_at timestamptz NOT NULL DEFAULT now(),
+  CHECK (parent_id IS DISTINCT FROM id)
+);
+
+CREATE INDEX IF NOT EXISTS categories_parent_id_idx ON categories (parent_id);
+
+CREATE TABLE IF NOT EXISTS product_categories (
+  product_id uuid NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  category_id uuid NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+  PRIMARY KEY (product_id, category_id)
+);
+
+CREATE INDEX IF NOT EXISTS product_categories_category_id_idx
+  ON product_categories (category_id);
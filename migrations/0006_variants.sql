@@ -0,0 +1,72 @@
+-- 0006_variants.sql
+-- Product options (size, color, ...) and variants with their own SKU,
+-- optional price override and stock.
+
+CREATE TABLE IF NOT EXISTS product_options (
+  id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+  product_id uuid NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  name text NOT NULL,
+  position integer NOT NULL,
+  UNIQUE (product_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS product_option_values (
+  id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+  option_id uuid NOT NULL REFERENCES product_options(id) ON DELETE CASCADE,
+  value text NOT NULL,
+  position integer NOT NULL,
+  UNIQUE (option_id, value)
+);
+
+CREATE TABLE IF NOT EXISTS product_variants (
+  id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+  product_id uuid NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  sku text NOT NULL UNIQUE,
+  -- NULL means the variant sells at the product price.
+  price_cents bigint CHECK (price_cents >= 0),
+  stock bigint NOT NULL DEFAULT 0 CHECK (stock >= 0),
+  -- Canonical form of the chosen option values; see
+  -- `domain::catalog::variant_key`.
+  option_key text NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  updated_at timestamptz NOT NULL DEFAULT now(),
+  UNIQUE (product_id, option_key)
+);
+
+CREATE TABLE IF NOT EXISTS product_variant_values (
+  variant_id uuid NOT NULL REFERENCES product_variants(id) ON DELETE CASCADE,
+  option_id uuid NOT NULL REFERENCES product_options(id) ON DELETE CASCADE,
+  value_id uuid NOT NULL REFERENCES product_option_values(id) ON DELETE CASCADE,
+  PRIMARY KEY (variant_id, option_id)
+);
+
+-- A SKU identifies exactly one sellable thing, whether a product or a
+-- variant. Both tables register their SKUs here so the primary key keeps them
+-- unique across the catalog.
+CREATE TABLE IF NOT EXISTS catalog_skus (
+  sku text PRIMARY KEY
+);
+
+INSERT INTO catalog_skus (sku) SELECT sku FROM products ON CONFLICT DO NOTHING;
+
+CREATE OR REPLACE FUNCTION sync_catalog_skus() RETURNS trigger AS $$
+BEGIN
+  IF TG_OP IN ('UPDATE', 'DELETE') THEN
+    DELETE FROM catalog_skus WHERE sku = OLD.sku;
+  END IF;
+  IF TG_OP IN ('INSERT', 'UPDATE') THEN
+    INSERT INTO catalog_skus (sku) VALUES (NEW.sku);
+  END IF;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS products_catalog_skus ON products;
+CREATE TRIGGER products_catalog_skus
+  AFTER INSERT OR DELETE OR UPDATE OF sku ON products
+  FOR EACH ROW EXECUTE FUNCTION sync_catalog_skus();
+
+DROP TRIGGER IF EXISTS product_variants_catalog_skus ON product_variants;
+CREATE TRIGGER product_variants_catalog_skus
+  AFTER INSERT OR DELETE OR UPDATE OF sku ON product_variants
+  FOR EACH ROW EXECUTE FUNCTION sync_catalog_skus();
@@ -0,0 +1,20 @@
+-- 0007_product_search.sql
+-- Full-text search over product names and descriptions.
+
+ALTER TABLE products ADD COLUMN IF NOT EXISTS description text NOT NULL DEFAULT '';
+
+-- Text search configuration the row was indexed with. The application writes
+-- its configured language here; after changing the language, reindex with
+-- `UPDATE products SET search_config = '<language>'`.
+ALTER TABLE products
+  ADD COLUMN IF NOT EXISTS search_config regconfig NOT NULL DEFAULT 'simple';
+
+-- Name matches rank above description matches.
+ALTER TABLE products ADD COLUMN IF NOT EXISTS search_vector tsvector
+  GENERATED ALWAYS AS (
+    setweight(to_tsvector(search_config, name), 'A')
+      || setweight(to_tsvector(search_config, description), 'B')
+  ) STORED;
+
+CREATE INDEX IF NOT EXISTS products_search_vector_idx
+  ON products USING GIN (search_vector);
@@ -0,0 +1,11 @@
+-- 0008_fuzzy_search.sql
+-- Typo-tolerant matching with trigram similarity.
+
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- gin_trgm_ops supports the `<%` word similarity operator used by the
+-- fuzzy product and user searches.
+CREATE INDEX IF NOT EXISTS products_sku_trgm_idx ON products USING GIN (sku gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS products_name_trgm_idx ON products USING GIN (name gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS users_email_trgm_idx ON users USING GIN (email gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS users_name_trgm_idx ON users USING GIN (name gin_trgm_ops);
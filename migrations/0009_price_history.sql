@@ -0,0 +1,34 @@
+-- 0009_price_history.sql
+-- Product prices over time. Each row holds a price for the half-open range
+-- [valid_from, valid_to); the latest row is open-ended. Rows starting in the
+-- future are scheduled changes.
+
+CREATE EXTENSION IF NOT EXISTS btree_gist;
+
+CREATE TABLE IF NOT EXISTS product_prices (
+  id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+  product_id uuid NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  price_cents bigint NOT NULL CHECK (price_cents >= 0),
+  valid_from timestamptz NOT NULL,
+  valid_to timestamptz,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  CHECK (valid_to IS NULL OR valid_to > valid_from),
+  -- A product has at most one price at any instant.
+  EXCLUDE USING gist (product_id WITH =, tstzrange(valid_from, valid_to) WITH &&)
+);
+
+INSERT INTO product_prices (product_id, price_cents, valid_from)
+SELECT id, price_cents, created_at FROM products;
+
+ALTER TABLE products DROP COLUMN price_cents;
+
+-- Price of a product at `at`; NULL before its first price.
+CREATE OR REPLACE FUNCTION product_price_at(p_product_id uuid, at timestamptz)
+RETURNS bigint
+LANGUAGE sql STABLE AS $$
+  SELECT price_cents
+  FROM product_prices
+  WHERE product_id = p_product_id
+    AND valid_from <= at
+    AND (valid_to IS NULL OR valid_to > at)
+$$;
@@ -0,0 +1,14 @@
+-- 0010_product_images.sql
+-- Product images. The bytes live in blob storage under `blob_key`.
+
+CREATE TABLE IF NOT EXISTS product_images (
+  id uuid PRIMARY KEY,
+  product_id uuid NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  blob_key text NOT NULL UNIQUE,
+  content_type text NOT NULL,
+  byte_size bigint NOT NULL CHECK (byte_size > 0),
+  position integer NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS product_images_product_idx ON product_images (product_id, position);
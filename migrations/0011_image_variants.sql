@@ -0,0 +1,25 @@
+-- 0011_image_variants.sql
+-- Resized renditions of product images, generated in the background after
+-- upload. `thumbnail_status` tracks the job for each image.
+
+ALTER TABLE product_images
+  ADD COLUMN IF NOT EXISTS thumbnail_status text NOT NULL DEFAULT 'pending'
+    CHECK (thumbnail_status IN ('pending', 'processing', 'ready', 'failed')),
+  ADD COLUMN IF NOT EXISTS thumbnail_claimed_at timestamptz,
+  ADD COLUMN IF NOT EXISTS thumbnail_error text;
+
+CREATE INDEX IF NOT EXISTS product_images_thumbnail_queue_idx
+  ON product_images (created_at)
+  WHERE thumbnail_status IN ('pending', 'processing');
+
+CREATE TABLE IF NOT EXISTS product_image_variants (
+  image_id uuid NOT NULL REFERENCES product_images(id) ON DELETE CASCADE,
+  name text NOT NULL,
+  blob_key text NOT NULL UNIQUE,
+  content_type text NOT NULL,
+  width integer NOT NULL CHECK (width > 0),
+  height integer NOT NULL CHECK (height > 0),
+  byte_size bigint NOT NULL CHECK (byte_size > 0),
+  created_at timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (image_id, name)
+);
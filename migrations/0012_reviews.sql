@@ -0,0 +1,25 @@
+-- 0012_reviews.sql
+-- Customer reviews. Only approved reviews count towards the rating that is
+-- kept on the product.
+
+ALTER TABLE products
+  ADD COLUMN IF NOT EXISTS rating_average double precision,
+  ADD COLUMN IF NOT EXISTS rating_count integer NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS reviews (
+  id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+  product_id uuid NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  user_id uuid NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  rating smallint NOT NULL CHECK (rating BETWEEN 1 AND 5),
+  title text NOT NULL,
+  body text NOT NULL,
+  status text NOT NULL DEFAULT 'pending'
+    CHECK (status IN ('pending', 'approved', 'rejected')),
+  moderation_note text,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  updated_at timestamptz NOT NULL DEFAULT now(),
+  UNIQUE (user_id, product_id)
+);
+
+CREATE INDEX IF NOT EXISTS reviews_product_idx ON reviews (product_id, status, created_at);
+CREATE INDEX IF NOT EXISTS reviews_moderation_idx ON reviews (created_at) WHERE status = 'pending';
@@ -0,0 +1,22 @@
+-- 0013_wishlists.sql
+-- Named wishlists per user. Each entry remembers the price when it was
+-- added so price drops can be reported.
+
+CREATE TABLE IF NOT EXISTS wishlists (
+  id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+  user_id uuid NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  name text NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  updated_at timestamptz NOT NULL DEFAULT now(),
+  UNIQUE (user_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS wishlist_items (
+  wishlist_id uuid NOT NULL REFERENCES wishlists(id) ON DELETE CASCADE,
+  product_id uuid NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  added_price_cents bigint NOT NULL,
+  added_at timestamptz NOT NULL DEFAULT now(),
+  PRIMARY KEY (wishlist_id, product_id)
+);
+
+CREATE INDEX IF NOT EXISTS wishlist_items_product_idx ON wishlist_items (product_id);
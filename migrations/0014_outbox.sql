@@ -0,0 +1,28 @@
+-- 0014_outbox.sql
+-- Domain events written in the same transaction as the change they describe
+-- and published afterwards by the outbox dispatcher. `event_id` lets
+-- consumers drop the duplicates at-least-once delivery can produce.
+
+CREATE TABLE IF NOT EXISTS outbox (
+  id bigserial PRIMARY KEY,
+  event_id uuid NOT NULL UNIQUE DEFAULT gen_random_uuid(),
+  aggregate_type text NOT NULL,
+  aggregate_id uuid NOT NULL,
+  event_type text NOT NULL,
+  payload jsonb NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  attempts integer NOT NULL DEFAULT 0,
+  next_attempt_at timestamptz NOT NULL DEFAULT now(),
+  last_error text,
+  published_at timestamptz,
+  -- Set once `attempts` reaches the dispatcher's limit; the event is kept for
+  -- inspection but no longer retried.
+  failed_at timestamptz
+);
+
+CREATE INDEX IF NOT EXISTS outbox_due_idx
+  ON outbox (next_attempt_at, id)
+  WHERE published_at IS NULL AND failed_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS outbox_aggregate_idx
+  ON outbox (aggregate_type, aggregate_id, id);
@@ -0,0 +1,56 @@
+-- 0015_webhooks.sql
+-- Partner endpoints notified of domain events. The outbox dispatcher turns
+-- each event into one delivery per matching subscription; a background
+-- worker sends them and logs every attempt.
+
+CREATE TABLE IF NOT EXISTS webhook_subscriptions (
+  id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+  url text NOT NULL,
+  secret text NOT NULL,
+  event_types text[] NOT NULL CHECK (cardinality(event_types) > 0),
+  active boolean NOT NULL DEFAULT true,
+  -- Failed attempts since the last success; reaching the configured limit
+  -- disables the subscription.
+  consecutive_failures integer NOT NULL DEFAULT 0,
+  disabled_at timestamptz,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  updated_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+  id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+  subscription_id uuid NOT NULL REFERENCES webhook_subscriptions(id) ON DELETE CASCADE,
+  event_id uuid NOT NULL,
+  event_type text NOT NULL,
+  payload jsonb NOT NULL,
+  occurred_at timestamptz NOT NULL,
+  status text NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'succeeded', 'failed')),
+  attempts integer NOT NULL DEFAULT 0,
+  next_attempt_at timestamptz NOT NULL DEFAULT now(),
+  response_status integer,
+  last_error text,
+  delivered_at timestamptz,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  updated_at timestamptz NOT NULL DEFAULT now(),
+  -- The outbox may publish an event more than once.
+  UNIQUE (subscription_id, event_id)
+);
+
+CREATE INDEX IF NOT EXISTS webhook_deliveries_due_idx
+  ON webhook_deliveries (next_attempt_at, id)
+  WHERE status = 'pending';
+
+CREATE INDEX IF NOT EXISTS webhook_deliveries_subscription_idx
+  ON webhook_deliveries (subscription_id, created_at DESC, id);
+
+CREATE TABLE IF NOT EXISTS webhook_delivery_attempts (
+  id bigserial PRIMARY KEY,
+  delivery_id uuid NOT NULL REFERENCES webhook_deliveries(id) ON DELETE CASCADE,
+  attempted_at timestamptz NOT NULL DEFAULT now(),
+  response_status integer,
+  error text,
+  duration_ms integer NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS webhook_delivery_attempts_delivery_idx
+  ON webhook_delivery_attempts (delivery_id, id);
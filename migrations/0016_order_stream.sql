@@ -0,0 +1,17 @@
+-- 0016_order_stream.sql
+-- Announces every order event written to the outbox on the `order_events`
+-- channel. Notifications go out when the transaction commits, to every API
+-- instance listening, in commit order; the payload is the outbox id.
+
+CREATE OR REPLACE FUNCTION notify_order_event() RETURNS trigger AS $$
+BEGIN
+  PERFORM pg_notify('order_events', NEW.id::text);
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS outbox_order_events ON outbox;
+CREATE TRIGGER outbox_order_events
+  AFTER INSERT ON outbox
+  FOR EACH ROW WHEN (NEW.aggregate_type = 'order')
+  EXECUTE FUNCTION notify_order_event();
@@ -0,0 +1,39 @@
+-- 0017_order_history.sql
+-- Every change to an order as an append-only entry: who made it, when, and
+-- each changed field before and after. Entries outlive the order, so there is
+-- no foreign key, and they can be neither updated nor deleted.
+
+CREATE TABLE IF NOT EXISTS order_history (
+  id bigserial PRIMARY KEY,
+  order_id uuid NOT NULL,
+  version integer NOT NULL CHECK (version > 0),
+  action text NOT NULL CHECK (action IN ('created', 'updated', 'deleted')),
+  actor text NOT NULL,
+  occurred_at timestamptz NOT NULL DEFAULT now(),
+  -- {"<field>": {"before": <value>, "after": <value>}}
+  changes jsonb NOT NULL,
+  UNIQUE (order_id, version)
+);
+
+CREATE OR REPLACE FUNCTION reject_order_history_change() RETURNS trigger AS $$
+BEGIN
+  RAISE EXCEPTION 'order_history is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS order_history_append_only ON order_history;
+CREATE TRIGGER order_history_append_only
+  BEFORE UPDATE OR DELETE ON order_history
+  FOR EACH ROW EXECUTE FUNCTION reject_order_history_change();
+
+-- Orders that predate the history get their current fields as a version-1
+-- creation, so they have a history and replay to their full state.
+INSERT INTO order_history (order_id, version, action, actor, occurred_at, changes)
+SELECT o.id, 1, 'created', 'system:migration', o.created_at,
+  jsonb_build_object(
+    'user_id', jsonb_build_object('before', NULL, 'after', o.user_id),
+    'status', jsonb_build_object('before', NULL, 'after', o.status),
+    'total_cents', jsonb_build_object('before', NULL, 'after', o.total_cents)
+  )
+FROM orders o
+WHERE NOT EXISTS (SELECT 1 FROM order_history h WHERE h.order_id = o.id);
@@ -0,0 +1,31 @@
+-- 0018_audit_log.sql
+-- Who created, updated or deleted which user, product or order, with each
+-- changed field before and after and the request it came in. Written in the
+-- same transaction as the change; entries can be neither updated nor deleted.
+
+CREATE TABLE IF NOT EXISTS audit_log (
+  id bigserial PRIMARY KEY,
+  occurred_at timestamptz NOT NULL DEFAULT now(),
+  actor text NOT NULL,
+  action text NOT NULL CHECK (action IN ('created', 'updated', 'deleted')),
+  resource_type text NOT NULL,
+  resource_id uuid NOT NULL,
+  -- {"<field>": {"before": <value>, "after": <value>}}
+  changes jsonb NOT NULL,
+  request_id text
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_occurred_at_idx ON audit_log (occurred_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS audit_log_resource_idx ON audit_log (resource_type, resource_id);
+CREATE INDEX IF NOT EXISTS audit_log_actor_idx ON audit_log (actor);
+
+CREATE OR REPLACE FUNCTION reject_audit_log_change() RETURNS trigger AS $$
+BEGIN
+  RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS audit_log_append_only ON audit_log;
+CREATE TRIGGER audit_log_append_only
+  BEFORE UPDATE OR DELETE ON audit_log
+  FOR EACH ROW EXECUTE FUNCTION reject_audit_log_change();
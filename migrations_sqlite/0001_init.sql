@@ -0,0 +1,83 @@
+-- 0001_init.sql
+-- Users, products with their price history, and orders with their items and
+-- history, for `STORAGE=sqlite`. Mirrors the Postgres schema for those
+-- tables. Ids are stored as 16-byte blobs and timestamps as RFC 3339 text in
+-- UTC, which sorts chronologically. References that restrict deletes use the
+-- default NO ACTION, which fails with SQLITE_CONSTRAINT_FOREIGNKEY like any
+-- other violation; RESTRICT reports SQLITE_CONSTRAINT_TRIGGER instead.
+
+CREATE TABLE IF NOT EXISTS users (
+  id blob PRIMARY KEY,
+  email text NOT NULL UNIQUE,
+  name text NOT NULL,
+  created_at text NOT NULL,
+  updated_at text NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS products (
+  id blob PRIMARY KEY,
+  sku text NOT NULL UNIQUE,
+  name text NOT NULL,
+  description text NOT NULL DEFAULT '',
+  created_at text NOT NULL,
+  updated_at text NOT NULL
+);
+
+-- Prices for the half-open range [valid_from, valid_to); the latest row is
+-- open-ended. Rows starting in the future are scheduled changes.
+CREATE TABLE IF NOT EXISTS product_prices (
+  id blob PRIMARY KEY,
+  product_id blob NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+  price_cents integer NOT NULL CHECK (price_cents >= 0),
+  valid_from text NOT NULL,
+  valid_to text,
+  created_at text NOT NULL,
+  CHECK (valid_to IS NULL OR valid_to > valid_from),
+  UNIQUE (product_id, valid_from)
+);
+
+CREATE TABLE IF NOT EXISTS orders (
+  id blob PRIMARY KEY,
+  user_id blob NOT NULL REFERENCES users(id),
+  status text NOT NULL,
+  total_cents integer NOT NULL,
+  created_at text NOT NULL,
+  updated_at text NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS order_items (
+  id blob PRIMARY KEY,
+  order_id blob NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+  product_id blob NOT NULL REFERENCES products(id),
+  quantity integer NOT NULL CHECK (quantity > 0),
+  unit_price_cents integer NOT NULL,
+  created_at text NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS order_items_order_id_idx ON order_items (order_id);
+
+-- Entries outlive the order, so there is no foreign key, and they can be
+-- neither updated nor deleted.
+CREATE TABLE IF NOT EXISTS order_history (
+  id integer PRIMARY KEY AUTOINCREMENT,
+  order_id blob NOT NULL,
+  version integer NOT NULL CHECK (version > 0),
+  action text NOT NULL CHECK (action IN ('created', 'updated', 'deleted')),
+  actor text NOT NULL,
+  occurred_at text NOT NULL,
+  -- {"<field>": {"before": <value>, "after": <value>}}
+  changes text NOT NULL,
+  UNIQUE (order_id, version)
+);
+
+CREATE TRIGGER IF NOT EXISTS order_history_no_update
+  BEFORE UPDATE ON order_history
+  BEGIN
+    SELECT RAISE(ABORT, 'order_history is append-only');
+  END;
+
+CREATE TRIGGER IF NOT EXISTS order_history_no_delete
+  BEFORE DELETE ON order_history
+  BEGIN
+    SELECT RAISE(ABORT, 'order_history is append-only');
+  END;
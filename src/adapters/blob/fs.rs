@@ -0,0 +1,74 @@
+use crate::application::ports::{BlobStore, RepoError};
+use async_trait::async_trait;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Stores each blob as a file under `root`, with `/` in keys mapping to
+/// subdirectories.
+#[derive(Debug, Clone)]
+pub struct FsBlobStore {
+  root: PathBuf,
+}
+
+impl FsBlobStore {
+  pub fn new(root: impl Into<PathBuf>) -> Self {
+    Self { root: root.into() }
+  }
+
+  /// Keys are `/`-separated segments of letters, digits, `-`, `_` and `.`;
+  /// `..` and empty segments are rejected so a key cannot leave `root`.
+  fn path(&self, key: &str) -> Result<PathBuf, RepoError> {
+    let mut path = self.root.clone();
+    for segment in key.split('/') {
+      let valid = !segment.is_empty()
+        && segment != "."
+        && segment != ".."
+        && segment
+          .bytes()
+          .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'));
+      if !valid {
+        return Err(RepoError::Invalid(format!("invalid blob key {key:?}")));
+      }
+      path.push(segment);
+    }
+    Ok(path)
+  }
+}
+
+fn io_err(err: std::io::Error) -> RepoError {
+  if err.kind() == ErrorKind::NotFound {
+    RepoError::NotFound
+  } else {
+    RepoError::Unexpected(err.to_string())
+  }
+}
+
+#[async_trait]
+impl BlobStore for FsBlobStore {
+  async fn put(&self, key: &str, _content_type: &str, bytes: Vec<u8>) -> Result<(), RepoError> {
+    let path = self.path(key)?;
+    if let Some(dir) = path.parent() {
+      tokio::fs::create_dir_all(dir).await.map_err(io_err)?;
+    }
+    // Readers never see a partially written file.
+    let tmp = path.with_extension(format!("tmp-{}", Uuid::new_v4().simple()));
+    tokio::fs::write(&tmp, bytes).await.map_err(io_err)?;
+    if let Err(err) = tokio::fs::rename(&tmp, &path).await {
+      let _ = tokio::fs::remove_file(&tmp).await;
+      return Err(io_err(err));
+    }
+    Ok(())
+  }
+
+  async fn get(&self, key: &str) -> Result<Vec<u8>, RepoError> {
+    tokio::fs::read(self.path(key)?).await.map_err(io_err)
+  }
+
+  async fn delete(&self, key: &str) -> Result<(), RepoError> {
+    match tokio::fs::remove_file(self.path(key)?).await {
+      Err(err) if err.kind() != ErrorKind::NotFound => Err(io_err(err)),
+      _ => Ok(()),
+    }
+  }
+}
@@ -0,0 +1,4 @@
+//! `BlobStore` adapters.
+
+pub mod fs;
+pub mod s3;
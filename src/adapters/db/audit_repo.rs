@@ -0,0 +1,133 @@
+use crate::adapters::db::map_sqlx_err;
+use crate::application::ports::{AuditFilter, AuditRepository, RepoError};
+use crate::domain::models::{AuditContext, AuditEntry, FieldChange};
+use async_trait::async_trait;
+use sqlx::types::Json;
+use sqlx::{PgConnection, PgPool};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct PgAuditRepository {
+  pool: PgPool,
+}
+
+impl PgAuditRepository {
+  pub fn new(pool: PgPool) -> Self {
+    Self { pool }
+  }
+}
+
+/// Records a change in the audit log, unless no field changed. Call it
+/// inside the transaction that makes the change, like `outbox_repo::enqueue`.
+pub(crate) async fn record(
+  conn: &mut PgConnection,
+  ctx: &AuditContext,
+  action: &str,
+  resource_type: &str,
+  resource_id: Uuid,
+  changes: &BTreeMap<String, FieldChange>,
+) -> Result<(), RepoError> {
+  if changes.is_empty() {
+    return Ok(());
+  }
+  sqlx::query!(
+    r#"
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, changes, request_id)
+    VALUES ($1, $2, $3, $4, $5, $6)
+    "#,
+    ctx.actor.to_string(),
+    action,
+    resource_type,
+    resource_id,
+    serde_json::to_value(changes).expect("changes serialize"),
+    ctx.request_id.as_deref(),
+  )
+  .execute(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  Ok(())
+}
+
+#[async_trait]
+impl AuditRepository for PgAuditRepository {
+  async fn list(
+    &self,
+    filter: AuditFilter,
+    limit: i64,
+    offset: i64,
+  ) -> Result<(Vec<AuditEntry>, i64), RepoError> {
+    let rows = sqlx::query!(
+      r#"
+      SELECT id, occurred_at, actor, action, resource_type, resource_id,
+        changes AS "changes: Json<BTreeMap<String, FieldChange>>", request_id,
+        COUNT(*) OVER () AS "total!"
+      FROM audit_log
+      WHERE ($1::text IS NULL OR actor = $1)
+        AND ($2::text IS NULL OR action = $2)
+        AND ($3::text IS NULL OR resource_type = $3)
+        AND ($4::uuid IS NULL OR resource_id = $4)
+        AND ($5::text IS NULL OR request_id = $5)
+        AND ($6::timestamptz IS NULL OR occurred_at >= $6)
+        AND ($7::timestamptz IS NULL OR occurred_at < $7)
+      ORDER BY occurred_at DESC, id DESC
+      LIMIT $8 OFFSET $9
+      "#,
+      filter.actor,
+      filter.action,
+      filter.resource_type,
+      filter.resource_id,
+      filter.request_id,
+      filter.from,
+      filter.to,
+      limit,
+      offset,
+    )
+    .fetch_all(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    let total = match rows.first() {
+      Some(row) => row.total,
+      // Past the last page the window count is gone; count separately.
+      None if offset > 0 => sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM audit_log
+        WHERE ($1::text IS NULL OR actor = $1)
+          AND ($2::text IS NULL OR action = $2)
+          AND ($3::text IS NULL OR resource_type = $3)
+          AND ($4::uuid IS NULL OR resource_id = $4)
+          AND ($5::text IS NULL OR request_id = $5)
+          AND ($6::timestamptz IS NULL OR occurred_at >= $6)
+          AND ($7::timestamptz IS NULL OR occurred_at < $7)
+        "#,
+        filter.actor,
+        filter.action,
+        filter.resource_type,
+        filter.resource_id,
+        filter.request_id,
+        filter.from,
+        filter.to,
+      )
+      .fetch_one(&self.pool)
+      .await
+      .map_err(map_sqlx_err)?,
+      None => 0,
+    };
+    let entries = rows
+      .into_iter()
+      .map(|row| AuditEntry {
+        id: row.id,
+        occurred_at: row.occurred_at,
+        actor: row.actor,
+        action: row.action,
+        resource_type: row.resource_type,
+        resource_id: row.resource_id,
+        changes: row.changes.0,
+        request_id: row.request_id,
+      })
+      .collect();
+    Ok((entries, total))
+  }
+}
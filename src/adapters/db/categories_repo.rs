@@ -0,0 +1,210 @@
+use crate::adapters::db::map_sqlx_err;
+use crate::application::ports::{CategoryRepository, NewCategory, RepoError, UpdateCategory};
+use crate::domain::models::Category;
+use async_trait::async_trait;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct PgCategoryRepository {
+  pool: PgPool,
+}
+
+impl PgCategoryRepository {
+  pub fn new(pool: PgPool) -> Self {
+    Self { pool }
+  }
+}
+
+async fn categories_of(
+  conn: &mut PgConnection,
+  product_id: Uuid,
+) -> Result<Vec<Category>, RepoError> {
+  let categories = sqlx::query_as!(
+    Category,
+    r#"
+    SELECT c.id, c.parent_id, c.slug, c.name, c.position, c.created_at, c.updated_at
+    FROM product_categories pc
+    JOIN categories c ON c.id = pc.category_id
+    WHERE pc.product_id = $1
+    ORDER BY c.position, c.name
+    "#,
+    product_id,
+  )
+  .fetch_all(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  Ok(categories)
+}
+
+#[async_trait]
+impl CategoryRepository for PgCategoryRepository {
+  async fn create(&self, input: NewCategory) -> Result<Category, RepoError> {
+    let category = sqlx::query_as!(
+      Category,
+      r#"
+      INSERT INTO categories (parent_id, slug, name, position)
+      VALUES ($1, $2, $3, $4)
+      RETURNING id, parent_id, slug, name, position, created_at, updated_at
+      "#,
+      input.parent_id,
+      input.slug,
+      input.name,
+      input.position,
+    )
+    .fetch_one(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+    Ok(category)
+  }
+
+  async fn list(&self) -> Result<Vec<Category>, RepoError> {
+    let categories = sqlx::query_as!(
+      Category,
+      r#"
+      SELECT id, parent_id, slug, name, position, created_at, updated_at
+      FROM categories
+      ORDER BY position, name
+      "#,
+    )
+    .fetch_all(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+    Ok(categories)
+  }
+
+  async fn get(&self, id: Uuid) -> Result<Category, RepoError> {
+    let category = sqlx::query_as!(
+      Category,
+      r#"
+      SELECT id, parent_id, slug, name, position, created_at, updated_at
+      FROM categories
+      WHERE id = $1
+      "#,
+      id,
+    )
+    .fetch_one(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+    Ok(category)
+  }
+
+  async fn update(&self, id: Uuid, input: UpdateCategory) -> Result<Category, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+
+    if let Some(Some(parent_id)) = input.parent_id {
+      // Two concurrent moves could each pass the cycle check and still form
+      // a loop together, so moves are serialized against other writes.
+      sqlx::query!("LOCK TABLE categories IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut *tx)
+        .await
+        .map_err(map_sqlx_err)?;
+      let creates_cycle: bool = sqlx::query_scalar!(
+        r#"
+        WITH RECURSIVE subtree AS (
+          SELECT id FROM categories WHERE id = $1
+          UNION
+          SELECT c.id FROM categories c JOIN subtree s ON c.parent_id = s.id
+        )
+        SELECT EXISTS (SELECT 1 FROM subtree WHERE id = $2) AS "exists!"
+        "#,
+        id,
+        parent_id,
+      )
+      .fetch_one(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?;
+      if creates_cycle {
+        return Err(RepoError::Invalid(
+          "a category cannot be moved under itself or one of its subcategories".into(),
+        ));
+      }
+    }
+
+    let category = sqlx::query_as!(
+      Category,
+      r#"
+      UPDATE categories
+      SET
+        parent_id = CASE WHEN $2 THEN $3 ELSE parent_id END,
+        slug = COALESCE($4, slug),
+        name = COALESCE($5, name),
+        position = COALESCE($6, position),
+        updated_at = now()
+      WHERE id = $1
+      RETURNING id, parent_id, slug, name, position, created_at, updated_at
+      "#,
+      id,
+      input.parent_id.is_some(),
+      input.parent_id.flatten(),
+      input.slug,
+      input.name,
+      input.position,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(category)
+  }
+
+  async fn delete(&self, id: Uuid) -> Result<(), RepoError> {
+    let res = sqlx::query!("DELETE FROM categories WHERE id = $1", id)
+      .execute(&self.pool)
+      .await
+      .map_err(map_sqlx_err)?;
+    if res.rows_affected() == 0 {
+      return Err(RepoError::NotFound);
+    }
+    Ok(())
+  }
+
+  async fn set_product_categories(
+    &self,
+    product_id: Uuid,
+    category_ids: Vec<Uuid>,
+  ) -> Result<Vec<Category>, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+
+    // Locking the product serializes concurrent replacements.
+    sqlx::query!(
+      "SELECT id FROM products WHERE id = $1 FOR UPDATE",
+      product_id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    sqlx::query!(
+      "DELETE FROM product_categories WHERE product_id = $1",
+      product_id,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    sqlx::query!(
+      r#"
+      INSERT INTO product_categories (product_id, category_id)
+      SELECT $1, category_id FROM UNNEST($2::uuid[]) AS category_id
+      "#,
+      product_id,
+      &category_ids,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    let categories = categories_of(&mut tx, product_id).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(categories)
+  }
+
+  async fn list_for_product(&self, product_id: Uuid) -> Result<Vec<Category>, RepoError> {
+    let mut conn = self.pool.acquire().await.map_err(map_sqlx_err)?;
+    sqlx::query!("SELECT id FROM products WHERE id = $1", product_id)
+      .fetch_one(&mut *conn)
+      .await
+      .map_err(map_sqlx_err)?;
+    categories_of(&mut conn, product_id).await
+  }
+}
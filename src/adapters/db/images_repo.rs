@@ -0,0 +1,343 @@
+use crate::adapters::db::map_sqlx_err;
+use crate::application::ports::{NewProductImage, ProductImageRepository, RepoError};
+use crate::domain::models::{ImageVariant, ProductImage};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct PgProductImageRepository {
+  pool: PgPool,
+}
+
+impl PgProductImageRepository {
+  pub fn new(pool: PgPool) -> Self {
+    Self { pool }
+  }
+}
+
+/// A `product_images` row; `with_variants` loads the variants.
+struct ImageRow {
+  id: Uuid,
+  product_id: Uuid,
+  blob_key: String,
+  content_type: String,
+  byte_size: i64,
+  position: i32,
+  thumbnail_status: String,
+  created_at: DateTime<Utc>,
+}
+
+impl From<ImageRow> for ProductImage {
+  fn from(row: ImageRow) -> Self {
+    ProductImage {
+      id: row.id,
+      product_id: row.product_id,
+      blob_key: row.blob_key,
+      content_type: row.content_type,
+      byte_size: row.byte_size,
+      position: row.position,
+      thumbnail_status: row.thumbnail_status,
+      variants: Vec::new(),
+      created_at: row.created_at,
+    }
+  }
+}
+
+/// Fills in the variants of each image.
+async fn with_variants(
+  conn: &mut PgConnection,
+  mut images: Vec<ProductImage>,
+) -> Result<Vec<ProductImage>, RepoError> {
+  let ids: Vec<Uuid> = images.iter().map(|image| image.id).collect();
+  let rows = sqlx::query!(
+    r#"
+    SELECT image_id, name, blob_key, content_type, width, height, byte_size
+    FROM product_image_variants
+    WHERE image_id = ANY($1)
+    ORDER BY image_id, width, name
+    "#,
+    &ids,
+  )
+  .fetch_all(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  for row in rows {
+    if let Some(image) = images.iter_mut().find(|image| image.id == row.image_id) {
+      image.variants.push(ImageVariant {
+        name: row.name,
+        blob_key: row.blob_key,
+        content_type: row.content_type,
+        width: row.width,
+        height: row.height,
+        byte_size: row.byte_size,
+      });
+    }
+  }
+  Ok(images)
+}
+
+/// Locks the product row so concurrent uploads and reorders of its images
+/// run one at a time. `NotFound` when the product does not exist.
+async fn lock_product(conn: &mut PgConnection, product_id: Uuid) -> Result<(), RepoError> {
+  sqlx::query!(
+    "SELECT id FROM products WHERE id = $1 FOR UPDATE",
+    product_id,
+  )
+  .fetch_one(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  Ok(())
+}
+
+async fn images_of(
+  conn: &mut PgConnection,
+  product_id: Uuid,
+) -> Result<Vec<ProductImage>, RepoError> {
+  let rows = sqlx::query_as!(
+    ImageRow,
+    r#"
+    SELECT id, product_id, blob_key, content_type, byte_size, position, thumbnail_status,
+      created_at
+    FROM product_images
+    WHERE product_id = $1
+    ORDER BY position, created_at, id
+    "#,
+    product_id,
+  )
+  .fetch_all(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  with_variants(conn, rows.into_iter().map(ProductImage::from).collect()).await
+}
+
+#[async_trait]
+impl ProductImageRepository for PgProductImageRepository {
+  async fn create(
+    &self,
+    product_id: Uuid,
+    input: NewProductImage,
+  ) -> Result<ProductImage, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    lock_product(&mut tx, product_id).await?;
+    let image = sqlx::query_as!(
+      ImageRow,
+      r#"
+      INSERT INTO product_images (id, product_id, blob_key, content_type, byte_size, position)
+      SELECT $1, $2, $3, $4, $5, COALESCE(MAX(position) + 1, 0)
+      FROM product_images
+      WHERE product_id = $2
+      RETURNING id, product_id, blob_key, content_type, byte_size, position,
+        thumbnail_status, created_at
+      "#,
+      input.id,
+      product_id,
+      input.blob_key,
+      input.content_type,
+      input.byte_size,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+
+    Ok(image.into())
+  }
+
+  async fn list(&self, product_id: Uuid) -> Result<Vec<ProductImage>, RepoError> {
+    let mut conn = self.pool.acquire().await.map_err(map_sqlx_err)?;
+    sqlx::query!("SELECT id FROM products WHERE id = $1", product_id)
+      .fetch_one(&mut *conn)
+      .await
+      .map_err(map_sqlx_err)?;
+    images_of(&mut conn, product_id).await
+  }
+
+  async fn get(&self, id: Uuid) -> Result<ProductImage, RepoError> {
+    let mut conn = self.pool.acquire().await.map_err(map_sqlx_err)?;
+    let image = sqlx::query_as!(
+      ImageRow,
+      r#"
+      SELECT id, product_id, blob_key, content_type, byte_size, position, thumbnail_status,
+        created_at
+      FROM product_images
+      WHERE id = $1
+      "#,
+      id,
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    let mut images = with_variants(&mut conn, vec![image.into()]).await?;
+    Ok(images.remove(0))
+  }
+
+  async fn reorder(
+    &self,
+    product_id: Uuid,
+    image_ids: &[Uuid],
+  ) -> Result<Vec<ProductImage>, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    lock_product(&mut tx, product_id).await?;
+    let current: HashSet<Uuid> = images_of(&mut tx, product_id)
+      .await?
+      .into_iter()
+      .map(|image| image.id)
+      .collect();
+    let requested: HashSet<Uuid> = image_ids.iter().copied().collect();
+    if current != requested || requested.len() != image_ids.len() {
+      return Err(RepoError::Invalid(
+        "image_ids must list every image of the product exactly once".into(),
+      ));
+    }
+
+    sqlx::query!(
+      r#"
+      UPDATE product_images i
+      SET position = o.position - 1
+      FROM unnest($2::uuid[]) WITH ORDINALITY AS o(id, position)
+      WHERE i.id = o.id AND i.product_id = $1
+      "#,
+      product_id,
+      image_ids,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let images = images_of(&mut tx, product_id).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(images)
+  }
+
+  async fn delete(&self, product_id: Uuid, id: Uuid) -> Result<ProductImage, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    let image = sqlx::query_as!(
+      ImageRow,
+      r#"
+      SELECT id, product_id, blob_key, content_type, byte_size, position, thumbnail_status,
+        created_at
+      FROM product_images
+      WHERE id = $1 AND product_id = $2
+      FOR UPDATE
+      "#,
+      id,
+      product_id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    // Load the variants before they cascade away so their blobs can go too.
+    let mut images = with_variants(&mut tx, vec![image.into()]).await?;
+    sqlx::query!("DELETE FROM product_images WHERE id = $1", id)
+      .execute(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+
+    Ok(images.remove(0))
+  }
+
+  async fn claim_thumbnail_jobs(
+    &self,
+    limit: i64,
+    stale_after_secs: i64,
+  ) -> Result<Vec<ProductImage>, RepoError> {
+    let rows = sqlx::query_as!(
+      ImageRow,
+      r#"
+      WITH jobs AS (
+        SELECT id
+        FROM product_images
+        WHERE thumbnail_status = 'pending'
+           OR (thumbnail_status = 'processing'
+               AND thumbnail_claimed_at < now() - make_interval(secs => $2))
+        ORDER BY created_at, id
+        LIMIT $1
+        FOR UPDATE SKIP LOCKED
+      )
+      UPDATE product_images i
+      SET thumbnail_status = 'processing', thumbnail_claimed_at = now()
+      FROM jobs
+      WHERE i.id = jobs.id
+      RETURNING i.id, i.product_id, i.blob_key, i.content_type, i.byte_size, i.position,
+                i.thumbnail_status, i.created_at
+      "#,
+      limit,
+      stale_after_secs as f64,
+    )
+    .fetch_all(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    let mut images: Vec<ProductImage> = rows.into_iter().map(ProductImage::from).collect();
+    images.sort_by_key(|image| (image.created_at, image.id));
+    Ok(images)
+  }
+
+  async fn save_variants(&self, id: Uuid, variants: Vec<ImageVariant>) -> Result<(), RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    sqlx::query!("SELECT id FROM product_images WHERE id = $1 FOR UPDATE", id)
+      .fetch_one(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?;
+    // A reclaimed job may have saved variants already; replace them.
+    sqlx::query!("DELETE FROM product_image_variants WHERE image_id = $1", id)
+      .execute(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?;
+    for variant in variants {
+      sqlx::query!(
+        r#"
+        INSERT INTO product_image_variants
+          (image_id, name, blob_key, content_type, width, height, byte_size)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        id,
+        variant.name,
+        variant.blob_key,
+        variant.content_type,
+        variant.width,
+        variant.height,
+        variant.byte_size,
+      )
+      .execute(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?;
+    }
+    sqlx::query!(
+      r#"
+      UPDATE product_images
+      SET thumbnail_status = 'ready', thumbnail_claimed_at = NULL, thumbnail_error = NULL
+      WHERE id = $1
+      "#,
+      id,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(())
+  }
+
+  async fn mark_thumbnails_failed(&self, id: Uuid, error: &str) -> Result<(), RepoError> {
+    let result = sqlx::query!(
+      r#"
+      UPDATE product_images
+      SET thumbnail_status = 'failed', thumbnail_claimed_at = NULL, thumbnail_error = $2
+      WHERE id = $1
+      "#,
+      id,
+      error,
+    )
+    .execute(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+    if result.rows_affected() == 0 {
+      return Err(RepoError::NotFound);
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,114 @@
+use crate::adapters::db;
+use crate::adapters::db::unit_of_work::Db;
+use crate::application::ports::{InventoryRepository, RepoError};
+use crate::domain::models::StockLevel;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct PgInventoryRepository {
+  db: Db,
+}
+
+impl PgInventoryRepository {
+  pub fn new(pool: PgPool) -> Self {
+    Self::with_db(Db::Pool(pool))
+  }
+
+  pub(crate) fn with_db(db: Db) -> Self {
+    Self { db }
+  }
+}
+
+/// [`db::map_sqlx_err`], except that a foreign_key_violation (23503) means
+/// the product does not exist and a check_violation (23514) a stock below
+/// zero.
+fn map_sqlx_err(err: sqlx::Error) -> RepoError {
+  if let sqlx::Error::Database(db_err) = &err {
+    match db_err.code().as_deref() {
+      Some("23503") => return RepoError::NotFound,
+      Some("23514") => return RepoError::Conflict,
+      _ => {}
+    }
+  }
+  db::map_sqlx_err(err)
+}
+
+#[async_trait]
+impl InventoryRepository for PgInventoryRepository {
+  async fn get(&self, product_id: Uuid) -> Result<StockLevel, RepoError> {
+    let mut conn = self.db.acquire().await?;
+    let stock = sqlx::query_as!(
+      StockLevel,
+      r#"
+      SELECT p.id AS product_id,
+        COALESCE(i.quantity, 0) AS "quantity!",
+        COALESCE(i.updated_at, p.created_at) AS "updated_at!"
+      FROM products p
+      LEFT JOIN inventory i ON i.product_id = p.id
+      WHERE p.id = $1
+      "#,
+      product_id,
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(stock)
+  }
+
+  async fn set(&self, product_id: Uuid, quantity: i64) -> Result<StockLevel, RepoError> {
+    let mut conn = self.db.acquire().await?;
+    let stock = sqlx::query_as!(
+      StockLevel,
+      r#"
+      INSERT INTO inventory (product_id, quantity)
+      VALUES ($1, $2)
+      ON CONFLICT (product_id) DO UPDATE
+      SET quantity = EXCLUDED.quantity, updated_at = now()
+      RETURNING product_id, quantity, updated_at
+      "#,
+      product_id,
+      quantity,
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(stock)
+  }
+
+  async fn adjust(&self, product_id: Uuid, delta: i64) -> Result<StockLevel, RepoError> {
+    let mut conn = self.db.acquire().await?;
+    // The check constraint applies to the proposed row before `ON CONFLICT`
+    // is considered, so create the row at zero first and adjust it after.
+    sqlx::query!(
+      r#"
+      INSERT INTO inventory (product_id, quantity)
+      VALUES ($1, 0)
+      ON CONFLICT (product_id) DO NOTHING
+      "#,
+      product_id,
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+    let stock = sqlx::query_as!(
+      StockLevel,
+      r#"
+      UPDATE inventory
+      SET quantity = quantity + $2, updated_at = now()
+      WHERE product_id = $1
+      RETURNING product_id, quantity, updated_at
+      "#,
+      product_id,
+      delta,
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(stock)
+  }
+}
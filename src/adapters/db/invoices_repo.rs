@@ -0,0 +1,338 @@
+use crate::adapters::db::map_sqlx_err;
+use crate::application::ports::{InvoiceRepository, RepoError};
+use crate::domain::invoicing::{document_number, split_gross};
+use crate::domain::models::{BillingAddress, Invoice, InvoiceLine};
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Utc};
+use sqlx::types::Json;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+const INVOICE_PREFIX: &str = "INV";
+const CREDIT_NOTE_PREFIX: &str = "CN";
+
+#[derive(Clone)]
+pub struct PgInvoiceRepository {
+  pool: PgPool,
+}
+
+impl PgInvoiceRepository {
+  pub fn new(pool: PgPool) -> Self {
+    Self { pool }
+  }
+}
+
+/// An `invoices` row with its JSON columns decoded.
+struct InvoiceRow {
+  id: Uuid,
+  order_id: Uuid,
+  kind: String,
+  number: String,
+  issued_at: DateTime<Utc>,
+  billing_address: Json<BillingAddress>,
+  lines: Json<Vec<InvoiceLine>>,
+  tax_rate_bps: i32,
+  net_cents: i64,
+  tax_cents: i64,
+  total_cents: i64,
+  credited_invoice_id: Option<Uuid>,
+  refund_id: Option<Uuid>,
+}
+
+impl From<InvoiceRow> for Invoice {
+  fn from(row: InvoiceRow) -> Self {
+    Invoice {
+      id: row.id,
+      order_id: row.order_id,
+      kind: row.kind,
+      number: row.number,
+      issued_at: row.issued_at,
+      billing_address: row.billing_address.0,
+      lines: row.lines.0,
+      tax_rate_bps: row.tax_rate_bps,
+      net_cents: row.net_cents,
+      tax_cents: row.tax_cents,
+      total_cents: row.total_cents,
+      credited_invoice_id: row.credited_invoice_id,
+      refund_id: row.refund_id,
+    }
+  }
+}
+
+fn line(
+  description: String,
+  product_id: Option<Uuid>,
+  quantity: i32,
+  unit_price_cents: i64,
+  tax_rate_bps: i32,
+) -> InvoiceLine {
+  let total_cents = unit_price_cents * i64::from(quantity);
+  let (net_cents, tax_cents) = split_gross(total_cents, tax_rate_bps);
+  InvoiceLine {
+    description,
+    product_id,
+    quantity,
+    unit_price_cents,
+    net_cents,
+    tax_cents,
+    total_cents,
+  }
+}
+
+/// Hands out the next number of `series` for `year`. The counter row stays
+/// locked until the caller's transaction ends, so numbers are sequential and a
+/// rolled back document does not leave a gap (unlike a Postgres sequence).
+async fn next_number(conn: &mut PgConnection, series: &str, year: i32) -> Result<i32, RepoError> {
+  sqlx::query_scalar!(
+    r#"
+    INSERT INTO document_sequences (series, year, last_number)
+    VALUES ($1, $2, 1)
+    ON CONFLICT (series, year) DO UPDATE
+    SET last_number = document_sequences.last_number + 1
+    RETURNING last_number
+    "#,
+    series,
+    year,
+  )
+  .fetch_one(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)
+}
+
+struct NewDocument<'a> {
+  order_id: Uuid,
+  kind: &'a str,
+  prefix: &'a str,
+  billing_address: &'a BillingAddress,
+  lines: Vec<InvoiceLine>,
+  tax_rate_bps: i32,
+  credited_invoice_id: Option<Uuid>,
+  refund_id: Option<Uuid>,
+}
+
+async fn insert_document(
+  conn: &mut PgConnection,
+  doc: NewDocument<'_>,
+) -> Result<Invoice, RepoError> {
+  let issued_at = Utc::now();
+  let year = issued_at.year();
+  let sequence = next_number(conn, doc.prefix, year).await?;
+  let net_cents = doc.lines.iter().map(|l| l.net_cents).sum::<i64>();
+  let tax_cents = doc.lines.iter().map(|l| l.tax_cents).sum::<i64>();
+
+  let row = sqlx::query_as!(
+    InvoiceRow,
+    r#"
+    INSERT INTO invoices (order_id, kind, number, year, sequence_number, issued_at,
+      billing_address, lines, tax_rate_bps, net_cents, tax_cents, total_cents,
+      credited_invoice_id, refund_id)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+    RETURNING id, order_id, kind, number, issued_at,
+      billing_address AS "billing_address: Json<BillingAddress>",
+      lines AS "lines: Json<Vec<InvoiceLine>>",
+      tax_rate_bps, net_cents, tax_cents, total_cents, credited_invoice_id, refund_id
+    "#,
+    doc.order_id,
+    doc.kind,
+    document_number(doc.prefix, year, sequence),
+    year,
+    sequence,
+    issued_at,
+    Json(doc.billing_address) as _,
+    Json(&doc.lines) as _,
+    doc.tax_rate_bps,
+    net_cents,
+    tax_cents,
+    net_cents + tax_cents,
+    doc.credited_invoice_id,
+    doc.refund_id,
+  )
+  .fetch_one(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+
+  Ok(row.into())
+}
+
+/// Issues a credit note for `refund_id` if the order has been invoiced.
+/// Meant to run inside the transaction that records the refund.
+pub(crate) async fn issue_credit_note(
+  conn: &mut PgConnection,
+  order_id: Uuid,
+  refund_id: Uuid,
+  amount_cents: i64,
+) -> Result<Option<Invoice>, RepoError> {
+  let row = sqlx::query_as!(
+    InvoiceRow,
+    r#"
+    SELECT id, order_id, kind, number, issued_at,
+      billing_address AS "billing_address: Json<BillingAddress>",
+      lines AS "lines: Json<Vec<InvoiceLine>>",
+      tax_rate_bps, net_cents, tax_cents, total_cents, credited_invoice_id, refund_id
+    FROM invoices
+    WHERE order_id = $1 AND kind = 'invoice'
+    "#,
+    order_id,
+  )
+  .fetch_optional(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  let Some(row) = row else {
+    return Ok(None);
+  };
+  let invoice = Invoice::from(row);
+
+  let lines = vec![line(
+    format!("Refund against invoice {}", invoice.number),
+    None,
+    1,
+    amount_cents,
+    invoice.tax_rate_bps,
+  )];
+  insert_document(
+    conn,
+    NewDocument {
+      order_id,
+      kind: "credit_note",
+      prefix: CREDIT_NOTE_PREFIX,
+      billing_address: &invoice.billing_address,
+      lines,
+      tax_rate_bps: invoice.tax_rate_bps,
+      credited_invoice_id: Some(invoice.id),
+      refund_id: Some(refund_id),
+    },
+  )
+  .await
+  .map(Some)
+}
+
+#[async_trait]
+impl InvoiceRepository for PgInvoiceRepository {
+  async fn issue(
+    &self,
+    order_id: Uuid,
+    billing_address: BillingAddress,
+    tax_rate_bps: i32,
+  ) -> Result<Invoice, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+
+    let total_cents: i64 = sqlx::query_scalar!(
+      "SELECT total_cents FROM orders WHERE id = $1 FOR UPDATE",
+      order_id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let paid: bool = sqlx::query_scalar!(
+      r#"
+      SELECT EXISTS (
+        SELECT 1 FROM payments
+        WHERE order_id = $1 AND status IN ('succeeded', 'partially_refunded', 'refunded')
+      ) AS "paid!"
+      "#,
+      order_id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    if !paid {
+      return Err(RepoError::Conflict);
+    }
+
+    let mut lines: Vec<InvoiceLine> = sqlx::query!(
+      r#"
+      SELECT oi.product_id, oi.quantity, oi.unit_price_cents, p.sku, p.name
+      FROM order_items oi
+      JOIN products p ON p.id = oi.product_id
+      WHERE oi.order_id = $1
+      ORDER BY oi.created_at, oi.id
+      "#,
+      order_id,
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?
+    .into_iter()
+    .map(|row| {
+      line(
+        format!("{} ({})", row.name, row.sku),
+        Some(row.product_id),
+        row.quantity,
+        row.unit_price_cents,
+        tax_rate_bps,
+      )
+    })
+    .collect();
+    // Orders created before line items existed only carry a total.
+    if lines.is_empty() {
+      lines.push(line(
+        format!("Order {order_id}"),
+        None,
+        1,
+        total_cents,
+        tax_rate_bps,
+      ));
+    }
+
+    // The partial unique index on (order_id) for invoices turns a concurrent
+    // second issue into a unique violation, i.e. `Conflict`.
+    let invoice = insert_document(
+      &mut tx,
+      NewDocument {
+        order_id,
+        kind: "invoice",
+        prefix: INVOICE_PREFIX,
+        billing_address: &billing_address,
+        lines,
+        tax_rate_bps,
+        credited_invoice_id: None,
+        refund_id: None,
+      },
+    )
+    .await?;
+
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(invoice)
+  }
+
+  async fn get_for_order(&self, order_id: Uuid) -> Result<Invoice, RepoError> {
+    let row = sqlx::query_as!(
+      InvoiceRow,
+      r#"
+      SELECT id, order_id, kind, number, issued_at,
+        billing_address AS "billing_address: Json<BillingAddress>",
+        lines AS "lines: Json<Vec<InvoiceLine>>",
+        tax_rate_bps, net_cents, tax_cents, total_cents, credited_invoice_id, refund_id
+      FROM invoices
+      WHERE order_id = $1 AND kind = 'invoice'
+      "#,
+      order_id,
+    )
+    .fetch_one(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(row.into())
+  }
+
+  async fn list_credit_notes(&self, order_id: Uuid) -> Result<Vec<Invoice>, RepoError> {
+    let rows = sqlx::query_as!(
+      InvoiceRow,
+      r#"
+      SELECT id, order_id, kind, number, issued_at,
+        billing_address AS "billing_address: Json<BillingAddress>",
+        lines AS "lines: Json<Vec<InvoiceLine>>",
+        tax_rate_bps, net_cents, tax_cents, total_cents, credited_invoice_id, refund_id
+      FROM invoices
+      WHERE order_id = $1 AND kind = 'credit_note'
+      ORDER BY year, sequence_number
+      "#,
+      order_id,
+    )
+    .fetch_all(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(rows.into_iter().map(Invoice::from).collect())
+  }
+}
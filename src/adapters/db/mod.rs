@@ -1,3 +1,32 @@
+use crate::application::ports::RepoError;
+
+pub mod audit_repo;
+pub mod categories_repo;
+pub mod images_repo;
+pub mod inventory_repo;
+pub mod invoices_repo;
 pub mod orders_repo;
+pub mod outbox_repo;
+pub mod payments_repo;
 pub mod products_repo;
+pub mod returns_repo;
+pub mod reviews_repo;
+pub mod unit_of_work;
 pub mod users_repo;
+pub mod variants_repo;
+pub mod webhooks_repo;
+pub mod wishlists_repo;
+
+/// How the repositories report database errors: a missing row is
+/// `NotFound`, and a foreign key (23503) or unique (23505) violation, e.g. a
+/// taken SKU or deleting a referenced row, is a `Conflict`.
+pub(crate) fn map_sqlx_err(err: sqlx::Error) -> RepoError {
+  match &err {
+    sqlx::Error::RowNotFound => RepoError::NotFound,
+    sqlx::Error::Database(db_err) => match db_err.code().as_deref() {
+      Some("23503") | Some("23505") => RepoError::Conflict,
+      _ => RepoError::Unexpected(err.to_string()),
+    },
+    _ => RepoError::Unexpected(err.to_string()),
+  }
+}
@@ -1,114 +1,262 @@
-use crate::application::ports::{NewOrder, OrderRepository, RepoError, UpdateOrder};
-use crate::domain::models::Order;
+use crate::adapters::db::unit_of_work::Db;
+use crate::adapters::db::{audit_repo, map_sqlx_err, outbox_repo};
+use crate::application::ports::{NewOrder, NewOrderItem, OrderRepository, RepoError, UpdateOrder};
+use crate::domain::events::DomainEvent;
+use crate::domain::models::{AuditContext, FieldChange, Order, OrderHistoryEntry, OrderItem};
+use crate::domain::order_history;
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
-use sqlx::{PgPool, Row};
+use sqlx::types::Json;
+use sqlx::{Connection, PgConnection, PgPool};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct PgOrderRepository {
-  pool: PgPool,
+  db: Db,
 }
 
 impl PgOrderRepository {
   pub fn new(pool: PgPool) -> Self {
-    Self { pool }
+    Self::with_db(Db::Pool(pool))
+  }
+
+  pub(crate) fn with_db(db: Db) -> Self {
+    Self { db }
   }
 }
 
-fn map_sqlx_err(err: sqlx::Error) -> RepoError {
-  match &err {
-    sqlx::Error::RowNotFound => RepoError::NotFound,
-    sqlx::Error::Database(db_err) => {
-      // foreign_key_violation = 23503, unique_violation = 23505
-      match db_err.code().as_deref() {
-        Some("23503") | Some("23505") => RepoError::Conflict,
-        _ => RepoError::Unexpected(err.to_string()),
-      }
-    }
-    _ => RepoError::Unexpected(err.to_string()),
+/// Appends the change from `before` to `after` to the order's history and
+/// the audit log, unless no tracked field changed.
+async fn append_history(
+  conn: &mut PgConnection,
+  order_id: Uuid,
+  before: Option<&Order>,
+  after: Option<&Order>,
+  ctx: &AuditContext,
+) -> Result<(), RepoError> {
+  let changes = order_history::diff(before, after);
+  if changes.is_empty() {
+    return Ok(());
   }
+  let action = match (before, after) {
+    (None, _) => "created",
+    (_, None) => "deleted",
+    _ => "updated",
+  };
+  // Callers hold the order row lock, so versions do not race. The clock is
+  // read after the lock is taken, unlike `now()`, so later versions never
+  // occur earlier.
+  sqlx::query!(
+    r#"
+    INSERT INTO order_history (order_id, version, action, actor, occurred_at, changes)
+    SELECT $1, COALESCE(MAX(version), 0) + 1, $2, $3, clock_timestamp(), $4
+    FROM order_history
+    WHERE order_id = $1
+    "#,
+    order_id,
+    action,
+    ctx.actor.to_string(),
+    serde_json::to_value(&changes).expect("changes serialize"),
+  )
+  .execute(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  audit_repo::record(conn, ctx, action, "order", order_id, &changes).await
+}
+
+/// Inserts an order and records `OrderCreated`.
+pub(crate) async fn insert_order(
+  conn: &mut PgConnection,
+  input: NewOrder,
+  ctx: &AuditContext,
+) -> Result<Order, RepoError> {
+  let order = sqlx::query_as!(
+    Order,
+    r#"
+    INSERT INTO orders (user_id, status, total_cents)
+    VALUES ($1, $2, $3)
+    RETURNING id, user_id, status, total_cents, created_at, updated_at
+    "#,
+    input.user_id,
+    input.status,
+    input.total_cents,
+  )
+  .fetch_one(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+
+  append_history(conn, order.id, None, Some(&order), ctx).await?;
+  outbox_repo::enqueue(
+    conn,
+    &DomainEvent::OrderCreated {
+      order: order.clone(),
+    },
+  )
+  .await?;
+  Ok(order)
+}
+
+/// Locks the order and returns it, for a later `record_change` to compare
+/// against.
+pub(crate) async fn lock_order(
+  conn: &mut PgConnection,
+  order_id: Uuid,
+) -> Result<Order, RepoError> {
+  let order = sqlx::query_as!(
+    Order,
+    r#"
+    SELECT id, user_id, status, total_cents, created_at, updated_at
+    FROM orders
+    WHERE id = $1
+    FOR UPDATE
+    "#,
+    order_id,
+  )
+  .fetch_one(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  Ok(order)
+}
+
+/// Records the change since `previous` in the order's history and the audit
+/// log, and its current state in the outbox: `OrderStatusChanged` when the
+/// status changed, `OrderUpdated` otherwise.
+pub(crate) async fn record_change(
+  conn: &mut PgConnection,
+  previous: &Order,
+  ctx: &AuditContext,
+) -> Result<Order, RepoError> {
+  let order = sqlx::query_as!(
+    Order,
+    r#"
+    SELECT id, user_id, status, total_cents, created_at, updated_at
+    FROM orders
+    WHERE id = $1
+    "#,
+    previous.id,
+  )
+  .fetch_one(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+
+  append_history(conn, order.id, Some(previous), Some(&order), ctx).await?;
+  let event = if order.status == previous.status {
+    DomainEvent::OrderUpdated {
+      order: order.clone(),
+    }
+  } else {
+    DomainEvent::OrderStatusChanged {
+      order: order.clone(),
+      from: previous.status.clone(),
+    }
+  };
+  outbox_repo::enqueue(conn, &event).await?;
+  Ok(order)
+}
+
+/// Adds a line at the product's current price and updates the order total.
+/// `Conflict` when the product does not exist. Meant to run inside a
+/// transaction; records the change like `record_change`.
+pub(crate) async fn insert_item(
+  conn: &mut PgConnection,
+  order_id: Uuid,
+  input: NewOrderItem,
+  ctx: &AuditContext,
+) -> Result<OrderItem, RepoError> {
+  let price_cents: i64 = sqlx::query_scalar!(
+    r#"SELECT product_price_at(id, now()) AS "price_cents!" FROM products WHERE id = $1"#,
+    input.product_id,
+  )
+  .fetch_optional(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?
+  .ok_or(RepoError::Conflict)?;
+
+  let item = sqlx::query_as!(
+    OrderItem,
+    r#"
+    INSERT INTO order_items (order_id, product_id, quantity, unit_price_cents)
+    VALUES ($1, $2, $3, $4)
+    RETURNING id, order_id, product_id, quantity, unit_price_cents, created_at
+    "#,
+    order_id,
+    input.product_id,
+    input.quantity,
+    price_cents,
+  )
+  .fetch_one(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+
+  let previous = lock_order(conn, order_id).await?;
+  sqlx::query!(
+    "UPDATE orders SET total_cents = total_cents + $2, updated_at = now() WHERE id = $1",
+    order_id,
+    price_cents * i64::from(input.quantity),
+  )
+  .execute(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  record_change(conn, &previous, ctx).await?;
+
+  Ok(item)
 }
 
 #[async_trait]
 impl OrderRepository for PgOrderRepository {
-  async fn create(&self, input: NewOrder) -> Result<Order, RepoError> {
-    let row = sqlx::query(
-      r#"
-      INSERT INTO orders (user_id, status, total_cents)
-      VALUES ($1, $2, $3)
-      RETURNING id, user_id, status, total_cents, created_at, updated_at
-      "#,
-    )
-    .bind(input.user_id)
-    .bind(input.status)
-    .bind(input.total_cents)
-    .fetch_one(&self.pool)
-    .await
-    .map_err(map_sqlx_err)?;
-
-    Ok(Order {
-      id: row.get::<Uuid, _>("id"),
-      user_id: row.get::<Uuid, _>("user_id"),
-      status: row.get::<String, _>("status"),
-      total_cents: row.get::<i64, _>("total_cents"),
-      created_at: row.get::<DateTime<Utc>, _>("created_at"),
-      updated_at: row.get::<DateTime<Utc>, _>("updated_at"),
-    })
+  async fn create(&self, input: NewOrder, ctx: &AuditContext) -> Result<Order, RepoError> {
+    let mut conn = self.db.acquire().await?;
+    let mut tx = conn.begin().await.map_err(map_sqlx_err)?;
+    let order = insert_order(&mut tx, input, ctx).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(order)
   }
 
   async fn list(&self) -> Result<Vec<Order>, RepoError> {
-    let rows = sqlx::query(
+    let mut conn = self.db.acquire().await?;
+    let orders = sqlx::query_as!(
+      Order,
       r#"
       SELECT id, user_id, status, total_cents, created_at, updated_at
       FROM orders
       ORDER BY created_at DESC
       "#,
     )
-    .fetch_all(&self.pool)
+    .fetch_all(&mut *conn)
     .await
     .map_err(map_sqlx_err)?;
 
-    Ok(
-      rows
-        .into_iter()
-        .map(|row| Order {
-          id: row.get::<Uuid, _>("id"),
-          user_id: row.get::<Uuid, _>("user_id"),
-          status: row.get::<String, _>("status"),
-          total_cents: row.get::<i64, _>("total_cents"),
-          created_at: row.get::<DateTime<Utc>, _>("created_at"),
-          updated_at: row.get::<DateTime<Utc>, _>("updated_at"),
-        })
-        .collect(),
-    )
+    Ok(orders)
   }
 
   async fn get(&self, id: Uuid) -> Result<Order, RepoError> {
-    let row = sqlx::query(
+    let mut conn = self.db.acquire().await?;
+    let order = sqlx::query_as!(
+      Order,
       r#"
       SELECT id, user_id, status, total_cents, created_at, updated_at
       FROM orders
       WHERE id = $1
       "#,
+      id,
     )
-    .bind(id)
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *conn)
     .await
     .map_err(map_sqlx_err)?;
 
-    Ok(Order {
-      id: row.get::<Uuid, _>("id"),
-      user_id: row.get::<Uuid, _>("user_id"),
-      status: row.get::<String, _>("status"),
-      total_cents: row.get::<i64, _>("total_cents"),
-      created_at: row.get::<DateTime<Utc>, _>("created_at"),
-      updated_at: row.get::<DateTime<Utc>, _>("updated_at"),
-    })
+    Ok(order)
   }
 
-  async fn update(&self, id: Uuid, input: UpdateOrder) -> Result<Order, RepoError> {
-    let row = sqlx::query(
+  async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateOrder,
+    ctx: &AuditContext,
+  ) -> Result<Order, RepoError> {
+    let mut conn = self.db.acquire().await?;
+    let mut tx = conn.begin().await.map_err(map_sqlx_err)?;
+    let previous = lock_order(&mut tx, id).await?;
+    sqlx::query!(
       r#"
       UPDATE orders
       SET
@@ -116,35 +264,114 @@ impl OrderRepository for PgOrderRepository {
         total_cents = COALESCE($3, total_cents),
         updated_at = now()
       WHERE id = $1
+      "#,
+      id,
+      input.status,
+      input.total_cents,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let order = record_change(&mut tx, &previous, ctx).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(order)
+  }
+
+  async fn delete(&self, id: Uuid, ctx: &AuditContext) -> Result<(), RepoError> {
+    let mut conn = self.db.acquire().await?;
+    let mut tx = conn.begin().await.map_err(map_sqlx_err)?;
+    let order = sqlx::query_as!(
+      Order,
+      r#"
+      DELETE FROM orders
+      WHERE id = $1
       RETURNING id, user_id, status, total_cents, created_at, updated_at
       "#,
+      id,
     )
-    .bind(id)
-    .bind(input.status)
-    .bind(input.total_cents)
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *tx)
     .await
     .map_err(map_sqlx_err)?;
+    append_history(&mut tx, id, Some(&order), None, ctx).await?;
+    let event = DomainEvent::OrderDeleted {
+      order_id: id,
+      user_id: order.user_id,
+    };
+    outbox_repo::enqueue(&mut tx, &event).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(())
+  }
 
-    Ok(Order {
-      id: row.get::<Uuid, _>("id"),
-      user_id: row.get::<Uuid, _>("user_id"),
-      status: row.get::<String, _>("status"),
-      total_cents: row.get::<i64, _>("total_cents"),
-      created_at: row.get::<DateTime<Utc>, _>("created_at"),
-      updated_at: row.get::<DateTime<Utc>, _>("updated_at"),
-    })
+  async fn add_item(
+    &self,
+    order_id: Uuid,
+    input: NewOrderItem,
+    ctx: &AuditContext,
+  ) -> Result<OrderItem, RepoError> {
+    let mut conn = self.db.acquire().await?;
+    let mut tx = conn.begin().await.map_err(map_sqlx_err)?;
+    lock_order(&mut tx, order_id).await?;
+    let item = insert_item(&mut tx, order_id, input, ctx).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(item)
   }
 
-  async fn delete(&self, id: Uuid) -> Result<(), RepoError> {
-    let res = sqlx::query("DELETE FROM orders WHERE id = $1")
-      .bind(id)
-      .execute(&self.pool)
+  async fn list_items(&self, order_id: Uuid) -> Result<Vec<OrderItem>, RepoError> {
+    let mut conn = self.db.acquire().await?;
+    // Distinguish an unknown order from one without items.
+    sqlx::query!("SELECT id FROM orders WHERE id = $1", order_id)
+      .fetch_one(&mut *conn)
       .await
       .map_err(map_sqlx_err)?;
-    if res.rows_affected() == 0 {
+
+    let items = sqlx::query_as!(
+      OrderItem,
+      r#"
+      SELECT id, order_id, product_id, quantity, unit_price_cents, created_at
+      FROM order_items
+      WHERE order_id = $1
+      ORDER BY created_at, id
+      "#,
+      order_id,
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(items)
+  }
+
+  async fn history(&self, order_id: Uuid) -> Result<Vec<OrderHistoryEntry>, RepoError> {
+    let mut conn = self.db.acquire().await?;
+    let rows = sqlx::query!(
+      r#"
+      SELECT order_id, version, action, actor, occurred_at,
+        changes AS "changes: Json<BTreeMap<String, FieldChange>>"
+      FROM order_history
+      WHERE order_id = $1
+      ORDER BY version
+      "#,
+      order_id,
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+    if rows.is_empty() {
       return Err(RepoError::NotFound);
     }
-    Ok(())
+
+    Ok(
+      rows
+        .into_iter()
+        .map(|row| OrderHistoryEntry {
+          order_id: row.order_id,
+          version: row.version,
+          action: row.action,
+          actor: row.actor,
+          occurred_at: row.occurred_at,
+          changes: row.changes.0,
+        })
+        .collect(),
+    )
   }
 }
@@ -0,0 +1,122 @@
+use crate::adapters::db::map_sqlx_err;
+use crate::application::ports::{OutboxRepository, RepoError};
+use crate::domain::events::DomainEvent;
+use crate::domain::models::OutboxEvent;
+use async_trait::async_trait;
+use sqlx::{PgConnection, PgPool};
+
+#[derive(Clone)]
+pub struct PgOutboxRepository {
+  pool: PgPool,
+}
+
+impl PgOutboxRepository {
+  pub fn new(pool: PgPool) -> Self {
+    Self { pool }
+  }
+}
+
+/// Records `event` for publication. Call it inside the transaction that
+/// makes the change, so the event exists exactly when the change does.
+pub(crate) async fn enqueue(conn: &mut PgConnection, event: &DomainEvent) -> Result<(), RepoError> {
+  let (aggregate_type, aggregate_id) = event.aggregate();
+  sqlx::query!(
+    r#"
+    INSERT INTO outbox (aggregate_type, aggregate_id, event_type, payload)
+    VALUES ($1, $2, $3, $4)
+    "#,
+    aggregate_type,
+    aggregate_id,
+    event.event_type(),
+    event.payload(),
+  )
+  .execute(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  Ok(())
+}
+
+#[async_trait]
+impl OutboxRepository for PgOutboxRepository {
+  async fn get(&self, id: i64) -> Result<OutboxEvent, RepoError> {
+    let event = sqlx::query_as!(
+      OutboxEvent,
+      r#"
+      SELECT id, event_id, event_type, aggregate_type, aggregate_id, payload, attempts, created_at
+      FROM outbox
+      WHERE id = $1
+      "#,
+      id,
+    )
+    .fetch_one(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+    Ok(event)
+  }
+
+  async fn claim_due(&self, limit: i64, lease_secs: i64) -> Result<Vec<OutboxEvent>, RepoError> {
+    let mut events = sqlx::query_as!(
+      OutboxEvent,
+      r#"
+      WITH due AS (
+        SELECT id
+        FROM outbox
+        WHERE published_at IS NULL AND failed_at IS NULL AND next_attempt_at <= now()
+        ORDER BY id
+        LIMIT $1
+        FOR UPDATE SKIP LOCKED
+      )
+      UPDATE outbox o
+      SET attempts = o.attempts + 1,
+        next_attempt_at = now() + make_interval(secs => $2)
+      FROM due
+      WHERE o.id = due.id
+      RETURNING o.id, o.event_id, o.event_type, o.aggregate_type, o.aggregate_id, o.payload,
+        o.attempts, o.created_at
+      "#,
+      limit,
+      lease_secs as f64,
+    )
+    .fetch_all(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    events.sort_by_key(|event| event.id);
+    Ok(events)
+  }
+
+  async fn mark_published(&self, id: i64) -> Result<(), RepoError> {
+    sqlx::query!(
+      "UPDATE outbox SET published_at = now(), last_error = NULL WHERE id = $1",
+      id,
+    )
+    .execute(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+    Ok(())
+  }
+
+  async fn mark_failed(
+    &self,
+    id: i64,
+    error: &str,
+    retry_after_secs: Option<i64>,
+  ) -> Result<(), RepoError> {
+    sqlx::query!(
+      r#"
+      UPDATE outbox
+      SET last_error = $2,
+        next_attempt_at = COALESCE(now() + make_interval(secs => $3), next_attempt_at),
+        failed_at = CASE WHEN $3 IS NULL THEN now() END
+      WHERE id = $1
+      "#,
+      id,
+      error,
+      retry_after_secs.map(|secs| secs as f64),
+    )
+    .execute(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+    Ok(())
+  }
+}
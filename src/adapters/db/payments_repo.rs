@@ -0,0 +1,152 @@
+use crate::adapters::db::{map_sqlx_err, orders_repo};
+use crate::application::ports::{
+  NewPayment, PaymentEvent, PaymentEventOutcome, PaymentRepository, PaymentTransition, RepoError,
+};
+use crate::domain::models::{AuditContext, Payment};
+use async_trait::async_trait;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct PgPaymentRepository {
+  pool: PgPool,
+}
+
+impl PgPaymentRepository {
+  pub fn new(pool: PgPool) -> Self {
+    Self { pool }
+  }
+}
+
+#[async_trait]
+impl PaymentRepository for PgPaymentRepository {
+  async fn create(&self, input: NewPayment) -> Result<Payment, RepoError> {
+    let payment = sqlx::query_as!(
+      Payment,
+      r#"
+      INSERT INTO payments (order_id, provider, provider_payment_id, status, amount_cents)
+      VALUES ($1, $2, $3, 'pending', $4)
+      RETURNING id, order_id, provider, provider_payment_id, status, amount_cents,
+        created_at, updated_at
+      "#,
+      input.order_id,
+      input.provider,
+      input.provider_payment_id,
+      input.amount_cents,
+    )
+    .fetch_one(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(payment)
+  }
+
+  async fn list_by_order(&self, order_id: Uuid) -> Result<Vec<Payment>, RepoError> {
+    let payments = sqlx::query_as!(
+      Payment,
+      r#"
+      SELECT id, order_id, provider, provider_payment_id, status, amount_cents,
+        created_at, updated_at
+      FROM payments
+      WHERE order_id = $1
+      ORDER BY created_at DESC
+      "#,
+      order_id,
+    )
+    .fetch_all(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(payments)
+  }
+
+  async fn apply_event(
+    &self,
+    event: PaymentEvent,
+    transition: PaymentTransition,
+    ctx: &AuditContext,
+  ) -> Result<PaymentEventOutcome, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+
+    // The primary key on (provider, event_id) makes a concurrent delivery of the
+    // same event wait for this transaction and then see it as a duplicate.
+    let inserted = sqlx::query!(
+      r#"
+      INSERT INTO payment_webhook_events (provider, event_id, event_type, payload)
+      VALUES ($1, $2, $3, $4)
+      ON CONFLICT (provider, event_id) DO NOTHING
+      "#,
+      &event.provider,
+      &event.event_id,
+      &event.event_type,
+      &event.payload,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    if inserted.rows_affected() == 0 {
+      return Ok(PaymentEventOutcome::Duplicate);
+    }
+
+    let payment = sqlx::query_as!(
+      Payment,
+      r#"
+      SELECT id, order_id, provider, provider_payment_id, status, amount_cents,
+        created_at, updated_at
+      FROM payments
+      WHERE provider = $1 AND provider_payment_id = $2
+      FOR UPDATE
+      "#,
+      &event.provider,
+      &event.provider_payment_id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    sqlx::query!(
+      "UPDATE payment_webhook_events SET payment_id = $3 WHERE provider = $1 AND event_id = $2",
+      &event.provider,
+      &event.event_id,
+      payment.id,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    if !transition.from.contains(&payment.status.as_str()) {
+      tx.commit().await.map_err(map_sqlx_err)?;
+      return Ok(PaymentEventOutcome::Ignored);
+    }
+
+    let payment = sqlx::query_as!(
+      Payment,
+      r#"
+      UPDATE payments
+      SET status = $2, updated_at = now()
+      WHERE id = $1
+      RETURNING id, order_id, provider, provider_payment_id, status, amount_cents,
+        created_at, updated_at
+      "#,
+      payment.id,
+      transition.payment_status,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    let previous = orders_repo::lock_order(&mut tx, payment.order_id).await?;
+    sqlx::query!(
+      "UPDATE orders SET status = $2, updated_at = now() WHERE id = $1",
+      payment.order_id,
+      transition.order_status,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    orders_repo::record_change(&mut tx, &previous, ctx).await?;
+
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(PaymentEventOutcome::Applied(payment))
+  }
+}
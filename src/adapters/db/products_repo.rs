@@ -1,66 +1,149 @@
+use crate::adapters::db::{audit_repo, map_sqlx_err, outbox_repo};
 use crate::application::ports::{NewProduct, ProductRepository, RepoError, UpdateProduct};
-use crate::domain::models::Product;
+use crate::domain::audit;
+use crate::domain::events::DomainEvent;
+use crate::domain::models::{AuditContext, Product, ProductPrice, ProductSearchHit, SimilarityHit};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use sqlx::{PgPool, Row};
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct PgProductRepository {
   pool: PgPool,
+  /// Postgres text search configuration, e.g. `english` or `spanish`.
+  search_language: String,
 }
 
 impl PgProductRepository {
-  pub fn new(pool: PgPool) -> Self {
-    Self { pool }
+  pub fn new(pool: PgPool, search_language: impl Into<String>) -> Self {
+    Self {
+      pool,
+      search_language: search_language.into(),
+    }
   }
 }
 
-fn map_sqlx_err(err: sqlx::Error) -> RepoError {
-  match &err {
-    sqlx::Error::RowNotFound => RepoError::NotFound,
-    sqlx::Error::Database(db_err) => {
-      if db_err.code().as_deref() == Some("23505") {
-        RepoError::Conflict
-      } else {
-        RepoError::Unexpected(err.to_string())
-      }
-    }
-    _ => RepoError::Unexpected(err.to_string()),
+/// Locks the product and returns it at its current price.
+async fn lock_product(conn: &mut PgConnection, id: Uuid) -> Result<Product, RepoError> {
+  sqlx::query_as!(
+    Product,
+    r#"
+    SELECT id, sku, name, description, product_price_at(id, now()) AS "price_cents!",
+      rating_average, rating_count, created_at, updated_at
+    FROM products
+    WHERE id = $1
+    FOR UPDATE
+    "#,
+    id,
+  )
+  .fetch_one(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)
+}
+
+/// Sets the price of `product_id` from `at` until its next price change,
+/// closing the range in effect at `at`. The caller holds the product row
+/// lock.
+async fn set_price(
+  conn: &mut PgConnection,
+  product_id: Uuid,
+  price_cents: i64,
+  at: DateTime<Utc>,
+) -> Result<ProductPrice, RepoError> {
+  let existing = sqlx::query_as!(
+    ProductPrice,
+    r#"
+    UPDATE product_prices SET price_cents = $3
+    WHERE product_id = $1 AND valid_from = $2
+    RETURNING id, product_id, price_cents, valid_from, valid_to, created_at
+    "#,
+    product_id,
+    at,
+    price_cents,
+  )
+  .fetch_optional(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  if let Some(price) = existing {
+    return Ok(price);
   }
+
+  sqlx::query!(
+    r#"
+    UPDATE product_prices SET valid_to = $2
+    WHERE product_id = $1 AND valid_from < $2 AND (valid_to IS NULL OR valid_to > $2)
+    "#,
+    product_id,
+    at,
+  )
+  .execute(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  sqlx::query_as!(
+    ProductPrice,
+    r#"
+    INSERT INTO product_prices (product_id, price_cents, valid_from, valid_to)
+    SELECT $1, $3, $2,
+      (SELECT min(valid_from) FROM product_prices WHERE product_id = $1 AND valid_from > $2)
+    RETURNING id, product_id, price_cents, valid_from, valid_to, created_at
+    "#,
+    product_id,
+    at,
+    price_cents,
+  )
+  .fetch_one(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)
 }
 
 #[async_trait]
 impl ProductRepository for PgProductRepository {
-  async fn create(&self, input: NewProduct) -> Result<Product, RepoError> {
-    let row = sqlx::query(
+  async fn create(&self, input: NewProduct, ctx: &AuditContext) -> Result<Product, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    // The first price starts when the product is created.
+    let product = sqlx::query_as!(
+      Product,
       r#"
-      INSERT INTO products (sku, name, price_cents)
-      VALUES ($1, $2, $3)
-      RETURNING id, sku, name, price_cents, created_at, updated_at
+      WITH p AS (
+        INSERT INTO products (sku, name, description, search_config)
+        VALUES ($1, $2, $3, $5::text::regconfig)
+        RETURNING id, sku, name, description, rating_average, rating_count, created_at, updated_at
+      ), price AS (
+        INSERT INTO product_prices (product_id, price_cents, valid_from)
+        SELECT id, $4, created_at FROM p
+      )
+      SELECT id, sku, name, description, $4::bigint AS "price_cents!", rating_average,
+        rating_count,
+        created_at, updated_at
+      FROM p
       "#,
+      input.sku,
+      input.name,
+      input.description,
+      input.price_cents,
+      &self.search_language,
     )
-    .bind(input.sku)
-    .bind(input.name)
-    .bind(input.price_cents)
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *tx)
     .await
     .map_err(map_sqlx_err)?;
 
-    Ok(Product {
-      id: row.get::<Uuid, _>("id"),
-      sku: row.get::<String, _>("sku"),
-      name: row.get::<String, _>("name"),
-      price_cents: row.get::<i64, _>("price_cents"),
-      created_at: row.get::<DateTime<Utc>, _>("created_at"),
-      updated_at: row.get::<DateTime<Utc>, _>("updated_at"),
-    })
+    let changes = audit::diff(None, Some(&product));
+    audit_repo::record(&mut tx, ctx, "created", "product", product.id, &changes).await?;
+    let event = DomainEvent::ProductCreated {
+      product: product.clone(),
+    };
+    outbox_repo::enqueue(&mut tx, &event).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(product)
   }
 
   async fn list(&self) -> Result<Vec<Product>, RepoError> {
-    let rows = sqlx::query(
+    let products = sqlx::query_as!(
+      Product,
       r#"
-      SELECT id, sku, name, price_cents, created_at, updated_at
+      SELECT id, sku, name, description, product_price_at(id, now()) AS "price_cents!",
+        rating_average, rating_count, created_at, updated_at
       FROM products
       ORDER BY created_at DESC
       "#,
@@ -69,84 +152,375 @@ impl ProductRepository for PgProductRepository {
     .await
     .map_err(map_sqlx_err)?;
 
-    Ok(
-      rows
-        .into_iter()
-        .map(|row| Product {
-          id: row.get::<Uuid, _>("id"),
-          sku: row.get::<String, _>("sku"),
-          name: row.get::<String, _>("name"),
-          price_cents: row.get::<i64, _>("price_cents"),
-          created_at: row.get::<DateTime<Utc>, _>("created_at"),
-          updated_at: row.get::<DateTime<Utc>, _>("updated_at"),
-        })
-        .collect(),
-    )
+    Ok(products)
   }
 
   async fn get(&self, id: Uuid) -> Result<Product, RepoError> {
-    let row = sqlx::query(
+    let product = sqlx::query_as!(
+      Product,
       r#"
-      SELECT id, sku, name, price_cents, created_at, updated_at
+      SELECT id, sku, name, description, product_price_at(id, now()) AS "price_cents!",
+        rating_average, rating_count, created_at, updated_at
       FROM products
       WHERE id = $1
       "#,
+      id,
     )
-    .bind(id)
     .fetch_one(&self.pool)
     .await
     .map_err(map_sqlx_err)?;
 
-    Ok(Product {
-      id: row.get::<Uuid, _>("id"),
-      sku: row.get::<String, _>("sku"),
-      name: row.get::<String, _>("name"),
-      price_cents: row.get::<i64, _>("price_cents"),
-      created_at: row.get::<DateTime<Utc>, _>("created_at"),
-      updated_at: row.get::<DateTime<Utc>, _>("updated_at"),
-    })
+    Ok(product)
   }
 
-  async fn update(&self, id: Uuid, input: UpdateProduct) -> Result<Product, RepoError> {
-    let row = sqlx::query(
+  async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateProduct,
+    ctx: &AuditContext,
+  ) -> Result<Product, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    let previous = lock_product(&mut tx, id).await?;
+    // Rewriting `search_config` also reindexes rows stored under an older
+    // language setting.
+    let updated_at: DateTime<Utc> = sqlx::query_scalar!(
       r#"
       UPDATE products
       SET
         sku = COALESCE($2, sku),
         name = COALESCE($3, name),
-        price_cents = COALESCE($4, price_cents),
+        description = COALESCE($4, description),
+        search_config = $5::text::regconfig,
         updated_at = now()
       WHERE id = $1
-      RETURNING id, sku, name, price_cents, created_at, updated_at
+      RETURNING updated_at
       "#,
+      id,
+      input.sku,
+      input.name,
+      input.description,
+      &self.search_language,
     )
-    .bind(id)
-    .bind(input.sku)
-    .bind(input.name)
-    .bind(input.price_cents)
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *tx)
     .await
     .map_err(map_sqlx_err)?;
+    if let Some(price_cents) = input.price_cents {
+      set_price(&mut tx, id, price_cents, updated_at).await?;
+    }
+    let product = sqlx::query_as!(
+      Product,
+      r#"
+      SELECT id, sku, name, description, product_price_at(id, now()) AS "price_cents!",
+        rating_average, rating_count, created_at, updated_at
+      FROM products
+      WHERE id = $1
+      "#,
+      id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let changes = audit::diff(Some(&previous), Some(&product));
+    audit_repo::record(&mut tx, ctx, "updated", "product", id, &changes).await?;
+    let event = DomainEvent::ProductUpdated {
+      product: product.clone(),
+    };
+    outbox_repo::enqueue(&mut tx, &event).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
 
-    Ok(Product {
-      id: row.get::<Uuid, _>("id"),
-      sku: row.get::<String, _>("sku"),
-      name: row.get::<String, _>("name"),
-      price_cents: row.get::<i64, _>("price_cents"),
-      created_at: row.get::<DateTime<Utc>, _>("created_at"),
-      updated_at: row.get::<DateTime<Utc>, _>("updated_at"),
-    })
+    Ok(product)
   }
 
-  async fn delete(&self, id: Uuid) -> Result<(), RepoError> {
-    let res = sqlx::query("DELETE FROM products WHERE id = $1")
-      .bind(id)
-      .execute(&self.pool)
+  async fn delete(&self, id: Uuid, ctx: &AuditContext) -> Result<(), RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    let previous = lock_product(&mut tx, id).await?;
+    sqlx::query!("DELETE FROM products WHERE id = $1", id)
+      .execute(&mut *tx)
       .await
       .map_err(map_sqlx_err)?;
-    if res.rows_affected() == 0 {
-      return Err(RepoError::NotFound);
+    let changes = audit::diff(Some(&previous), None);
+    audit_repo::record(&mut tx, ctx, "deleted", "product", id, &changes).await?;
+    outbox_repo::enqueue(&mut tx, &DomainEvent::ProductDeleted { product_id: id }).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(())
+  }
+
+  async fn list_by_category(
+    &self,
+    slug: &str,
+    include_descendants: bool,
+  ) -> Result<Vec<Product>, RepoError> {
+    let category_id: Uuid = sqlx::query_scalar!("SELECT id FROM categories WHERE slug = $1", slug)
+      .fetch_one(&self.pool)
+      .await
+      .map_err(map_sqlx_err)?;
+
+    // The recursive step only runs when descendants were asked for, so the
+    // subtree is just the category itself otherwise.
+    let products = sqlx::query_as!(
+      Product,
+      r#"
+      WITH RECURSIVE subtree AS (
+        SELECT id FROM categories WHERE id = $1
+        UNION
+        SELECT c.id
+        FROM categories c
+        JOIN subtree s ON c.parent_id = s.id
+        WHERE $2
+      )
+      SELECT p.id, p.sku, p.name, p.description,
+        product_price_at(p.id, now()) AS "price_cents!", p.rating_average, p.rating_count,
+        p.created_at, p.updated_at
+      FROM products p
+      WHERE EXISTS (
+        SELECT 1
+        FROM product_categories pc
+        JOIN subtree s ON s.id = pc.category_id
+        WHERE pc.product_id = p.id
+      )
+      ORDER BY p.created_at DESC
+      "#,
+      category_id,
+      include_descendants,
+    )
+    .fetch_all(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(products)
+  }
+
+  async fn search(
+    &self,
+    tsquery: &str,
+    limit: i64,
+    offset: i64,
+  ) -> Result<(Vec<ProductSearchHit>, i64), RepoError> {
+    // The snippet is built from HTML-escaped text so the only markup in it is
+    // the `<mark>` highlighting.
+    let rows = sqlx::query!(
+      r#"
+      WITH q AS (SELECT to_tsquery($1::text::regconfig, $2) AS query)
+      SELECT p.id, p.sku, p.name, p.description,
+        product_price_at(p.id, now()) AS "price_cents!", p.rating_average, p.rating_count,
+        p.created_at, p.updated_at,
+        ts_rank_cd(p.search_vector, q.query) AS "rank!",
+        ts_headline(
+          $1::text::regconfig,
+          replace(replace(replace(
+            p.name || ' ' || p.description, '&', '&amp;'), '<', '&lt;'), '>', '&gt;'),
+          q.query,
+          'StartSel=<mark>, StopSel=</mark>, MaxWords=25, MinWords=10'
+        ) AS "snippet!",
+        COUNT(*) OVER () AS "total!"
+      FROM products p, q
+      WHERE p.search_vector @@ q.query
+      ORDER BY "rank!" DESC, p.name, p.id
+      LIMIT $3 OFFSET $4
+      "#,
+      &self.search_language,
+      tsquery,
+      limit,
+      offset,
+    )
+    .fetch_all(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    let total = match rows.first() {
+      Some(row) => row.total,
+      // Past the last page the window count is gone; count separately.
+      None if offset > 0 => sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM products
+        WHERE search_vector @@ to_tsquery($1::text::regconfig, $2)
+        "#,
+        &self.search_language,
+        tsquery,
+      )
+      .fetch_one(&self.pool)
+      .await
+      .map_err(map_sqlx_err)?,
+      None => 0,
+    };
+    let hits = rows
+      .into_iter()
+      .map(|row| ProductSearchHit {
+        product: Product {
+          id: row.id,
+          sku: row.sku,
+          name: row.name,
+          description: row.description,
+          price_cents: row.price_cents,
+          rating_average: row.rating_average,
+          rating_count: row.rating_count,
+          created_at: row.created_at,
+          updated_at: row.updated_at,
+        },
+        rank: row.rank,
+        snippet: row.snippet,
+      })
+      .collect();
+    Ok((hits, total))
+  }
+
+  async fn fuzzy_search(
+    &self,
+    text: &str,
+    threshold: f32,
+    limit: i64,
+  ) -> Result<Vec<SimilarityHit<Product>>, RepoError> {
+    // `<%` compares against the session's word similarity threshold, which
+    // `set_config(.., true)` scopes to this transaction. Filtering with the
+    // operator rather than the function keeps the trigram indexes usable.
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    sqlx::query_scalar!(
+      "SELECT set_config('pg_trgm.word_similarity_threshold', $1, true)",
+      threshold.to_string(),
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let rows = sqlx::query!(
+      r#"
+      SELECT id, sku, name, description, product_price_at(id, now()) AS "price_cents!",
+        rating_average, rating_count, created_at, updated_at,
+        GREATEST(word_similarity($1, sku), word_similarity($1, name)) AS "similarity!"
+      FROM products
+      WHERE $1 <% sku OR $1 <% name
+      ORDER BY "similarity!" DESC, name, id
+      LIMIT $2
+      "#,
+      text,
+      limit,
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+
+    Ok(
+      rows
+        .into_iter()
+        .map(|row| SimilarityHit {
+          item: Product {
+            id: row.id,
+            sku: row.sku,
+            name: row.name,
+            description: row.description,
+            price_cents: row.price_cents,
+            rating_average: row.rating_average,
+            rating_count: row.rating_count,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+          },
+          similarity: row.similarity,
+        })
+        .collect(),
+    )
+  }
+
+  async fn price_history(&self, product_id: Uuid) -> Result<Vec<ProductPrice>, RepoError> {
+    sqlx::query!("SELECT id FROM products WHERE id = $1", product_id)
+      .fetch_one(&self.pool)
+      .await
+      .map_err(map_sqlx_err)?;
+    let prices = sqlx::query_as!(
+      ProductPrice,
+      r#"
+      SELECT id, product_id, price_cents, valid_from, valid_to, created_at
+      FROM product_prices
+      WHERE product_id = $1
+      ORDER BY valid_from
+      "#,
+      product_id,
+    )
+    .fetch_all(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(prices)
+  }
+
+  async fn schedule_price(
+    &self,
+    product_id: Uuid,
+    price_cents: i64,
+    valid_from: DateTime<Utc>,
+    ctx: &AuditContext,
+  ) -> Result<ProductPrice, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    sqlx::query!(
+      "SELECT id FROM products WHERE id = $1 FOR UPDATE",
+      product_id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    // A price starting at the same time is overwritten.
+    let previous = sqlx::query_scalar!(
+      "SELECT price_cents FROM product_prices WHERE product_id = $1 AND valid_from = $2",
+      product_id,
+      valid_from,
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let price = set_price(&mut tx, product_id, price_cents, valid_from).await?;
+    let changes = audit::scheduled_price(valid_from, previous, Some(price.price_cents));
+    audit_repo::record(&mut tx, ctx, "updated", "product", product_id, &changes).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(price)
+  }
+
+  async fn cancel_scheduled_price(
+    &self,
+    product_id: Uuid,
+    price_id: Uuid,
+    ctx: &AuditContext,
+  ) -> Result<(), RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    sqlx::query!(
+      "SELECT id FROM products WHERE id = $1 FOR UPDATE",
+      product_id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let row = sqlx::query!(
+      r#"
+      SELECT price_cents, valid_from, valid_to, valid_from <= now() AS "in_effect!"
+      FROM product_prices
+      WHERE id = $1 AND product_id = $2
+      "#,
+      price_id,
+      product_id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    if row.in_effect {
+      return Err(RepoError::Conflict);
     }
+
+    // The preceding range takes over the cancelled one.
+    sqlx::query!("DELETE FROM product_prices WHERE id = $1", price_id)
+      .execute(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?;
+    sqlx::query!(
+      "UPDATE product_prices SET valid_to = $3 WHERE product_id = $1 AND valid_to = $2",
+      product_id,
+      row.valid_from,
+      row.valid_to,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let changes = audit::scheduled_price(row.valid_from, Some(row.price_cents), None);
+    audit_repo::record(&mut tx, ctx, "updated", "product", product_id, &changes).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
     Ok(())
   }
 }
@@ -0,0 +1,428 @@
+use crate::adapters::db::{invoices_repo, map_sqlx_err, orders_repo};
+use crate::application::ports::{NewReturn, RepoError, ReturnRepository};
+use crate::domain::models::{AuditContext, Refund, ReturnItem, ReturnRequest};
+use async_trait::async_trait;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+/// Orders whose goods the customer has paid for; a partially refunded order
+/// may still return what is left.
+const RETURNABLE_STATUSES: &[&str] = &["paid", "shipped", "delivered", "partially_refunded"];
+
+#[derive(Clone)]
+pub struct PgReturnRepository {
+  pool: PgPool,
+}
+
+impl PgReturnRepository {
+  pub fn new(pool: PgPool) -> Self {
+    Self { pool }
+  }
+}
+
+async fn load_return(conn: &mut PgConnection, id: Uuid) -> Result<ReturnRequest, RepoError> {
+  let row = sqlx::query!(
+    r#"
+    SELECT r.id, r.order_id, r.status, r.note, r.decision_note, r.restocked,
+      r.created_at, r.updated_at,
+      COALESCE((SELECT SUM(f.amount_cents) FROM refunds f WHERE f.return_id = r.id), 0)::bigint
+        AS "refunded_cents!"
+    FROM return_requests r
+    WHERE r.id = $1
+    "#,
+    id,
+  )
+  .fetch_one(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+
+  let items = sqlx::query!(
+    r#"
+    SELECT ri.order_item_id, oi.product_id, ri.quantity, oi.unit_price_cents, ri.reason
+    FROM return_items ri
+    JOIN order_items oi ON oi.id = ri.order_item_id
+    WHERE ri.return_id = $1
+    ORDER BY oi.created_at, oi.id
+    "#,
+    id,
+  )
+  .fetch_all(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?
+  .into_iter()
+  .map(|row| ReturnItem {
+    order_item_id: row.order_item_id,
+    product_id: row.product_id,
+    quantity: row.quantity,
+    unit_price_cents: row.unit_price_cents,
+    reason: row.reason,
+  })
+  .collect();
+
+  Ok(ReturnRequest {
+    id: row.id,
+    order_id: row.order_id,
+    status: row.status,
+    note: row.note,
+    decision_note: row.decision_note,
+    restocked: row.restocked,
+    items,
+    refunded_cents: row.refunded_cents,
+    created_at: row.created_at,
+    updated_at: row.updated_at,
+  })
+}
+
+/// Locks the return and fails with `Conflict` unless it is in `expected`.
+async fn lock_return(conn: &mut PgConnection, id: Uuid, expected: &str) -> Result<Uuid, RepoError> {
+  let row = sqlx::query!(
+    "SELECT order_id, status FROM return_requests WHERE id = $1 FOR UPDATE",
+    id,
+  )
+  .fetch_one(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  if row.status != expected {
+    return Err(RepoError::Conflict);
+  }
+  Ok(row.order_id)
+}
+
+async fn ensure_order_exists(conn: &mut PgConnection, order_id: Uuid) -> Result<(), RepoError> {
+  sqlx::query!("SELECT id FROM orders WHERE id = $1", order_id)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+  Ok(())
+}
+
+#[async_trait]
+impl ReturnRepository for PgReturnRepository {
+  async fn create(&self, order_id: Uuid, input: NewReturn) -> Result<ReturnRequest, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+
+    // Serializes return requests per order so returnable quantities hold.
+    let order = sqlx::query!(
+      "SELECT status FROM orders WHERE id = $1 FOR UPDATE",
+      order_id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    if !RETURNABLE_STATUSES.contains(&order.status.as_str()) {
+      return Err(RepoError::Conflict);
+    }
+
+    for item in &input.items {
+      let ordered: i32 = sqlx::query_scalar!(
+        "SELECT quantity FROM order_items WHERE id = $1 AND order_id = $2",
+        item.order_item_id,
+        order_id,
+      )
+      .fetch_optional(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?
+      .ok_or_else(|| {
+        RepoError::Invalid(format!(
+          "order item {} is not part of this order",
+          item.order_item_id
+        ))
+      })?;
+      let already: i64 = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(SUM(ri.quantity), 0)::bigint AS "already!"
+        FROM return_items ri
+        JOIN return_requests r ON r.id = ri.return_id
+        WHERE ri.order_item_id = $1 AND r.status <> 'rejected'
+        "#,
+        item.order_item_id,
+      )
+      .fetch_one(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?;
+      let returnable = i64::from(ordered) - already;
+      if i64::from(item.quantity) > returnable {
+        return Err(RepoError::Invalid(format!(
+          "only {returnable} unit(s) of order item {} can still be returned",
+          item.order_item_id
+        )));
+      }
+    }
+
+    let id: Uuid = sqlx::query_scalar!(
+      r#"
+      INSERT INTO return_requests (order_id, status, note)
+      VALUES ($1, 'requested', $2)
+      RETURNING id
+      "#,
+      order_id,
+      input.note,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    for item in input.items {
+      sqlx::query!(
+        r#"
+        INSERT INTO return_items (return_id, order_item_id, quantity, reason)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        id,
+        item.order_item_id,
+        item.quantity,
+        item.reason,
+      )
+      .execute(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?;
+    }
+
+    let created = load_return(&mut tx, id).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(created)
+  }
+
+  async fn get(&self, id: Uuid) -> Result<ReturnRequest, RepoError> {
+    let mut conn = self.pool.acquire().await.map_err(map_sqlx_err)?;
+    load_return(&mut conn, id).await
+  }
+
+  async fn list_by_order(&self, order_id: Uuid) -> Result<Vec<ReturnRequest>, RepoError> {
+    let mut conn = self.pool.acquire().await.map_err(map_sqlx_err)?;
+    ensure_order_exists(&mut conn, order_id).await?;
+
+    let ids: Vec<Uuid> = sqlx::query_scalar!(
+      "SELECT id FROM return_requests WHERE order_id = $1 ORDER BY created_at DESC",
+      order_id,
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    let mut returns = Vec::with_capacity(ids.len());
+    for id in ids {
+      returns.push(load_return(&mut conn, id).await?);
+    }
+    Ok(returns)
+  }
+
+  async fn approve(
+    &self,
+    id: Uuid,
+    restock: bool,
+    note: Option<String>,
+  ) -> Result<ReturnRequest, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    lock_return(&mut tx, id, "requested").await?;
+
+    if restock {
+      sqlx::query!(
+        r#"
+        INSERT INTO inventory (product_id, quantity)
+        SELECT oi.product_id, SUM(ri.quantity)
+        FROM return_items ri
+        JOIN order_items oi ON oi.id = ri.order_item_id
+        WHERE ri.return_id = $1
+        GROUP BY oi.product_id
+        ON CONFLICT (product_id) DO UPDATE
+        SET quantity = inventory.quantity + EXCLUDED.quantity, updated_at = now()
+        "#,
+        id,
+      )
+      .execute(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?;
+    }
+
+    sqlx::query!(
+      r#"
+      UPDATE return_requests
+      SET status = 'approved', restocked = $2, decision_note = $3, updated_at = now()
+      WHERE id = $1
+      "#,
+      id,
+      restock,
+      note,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    let approved = load_return(&mut tx, id).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(approved)
+  }
+
+  async fn reject(&self, id: Uuid, note: Option<String>) -> Result<ReturnRequest, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    lock_return(&mut tx, id, "requested").await?;
+
+    sqlx::query!(
+      r#"
+      UPDATE return_requests
+      SET status = 'rejected', decision_note = $2, updated_at = now()
+      WHERE id = $1
+      "#,
+      id,
+      note,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    let rejected = load_return(&mut tx, id).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(rejected)
+  }
+
+  async fn refund(
+    &self,
+    id: Uuid,
+    amount_cents: Option<i64>,
+    ctx: &AuditContext,
+  ) -> Result<(ReturnRequest, Refund), RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    let order_id = lock_return(&mut tx, id, "approved").await?;
+
+    let row = sqlx::query!(
+      r#"
+      SELECT
+        (SELECT COALESCE(SUM(ri.quantity * oi.unit_price_cents), 0)::bigint
+         FROM return_items ri
+         JOIN order_items oi ON oi.id = ri.order_item_id
+         WHERE ri.return_id = $1) AS "value_cents!",
+        (SELECT COALESCE(SUM(f.amount_cents), 0)::bigint
+         FROM refunds f
+         WHERE f.return_id = $1) AS "refunded_cents!"
+      "#,
+      id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let remaining = row.value_cents - row.refunded_cents;
+    let amount = amount_cents.unwrap_or(remaining);
+    if amount <= 0 || amount > remaining {
+      return Err(RepoError::Invalid(format!(
+        "refund must be between 1 and the remaining {remaining} cents of this return"
+      )));
+    }
+
+    let payment = sqlx::query!(
+      r#"
+      SELECT p.id, p.amount_cents,
+        COALESCE((SELECT SUM(f.amount_cents) FROM refunds f WHERE f.payment_id = p.id), 0)::bigint
+          AS "refunded_cents!"
+      FROM payments p
+      WHERE p.order_id = $1 AND p.status IN ('succeeded', 'partially_refunded')
+      ORDER BY p.created_at DESC
+      LIMIT 1
+      FOR UPDATE OF p
+      "#,
+      order_id,
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?
+    .ok_or(RepoError::Conflict)?;
+    let payment_id = payment.id;
+    let refundable = payment.amount_cents - payment.refunded_cents;
+    if amount > refundable {
+      return Err(RepoError::Invalid(format!(
+        "refund exceeds the {refundable} cents still refundable on the payment"
+      )));
+    }
+
+    let refund = sqlx::query_as!(
+      Refund,
+      r#"
+      INSERT INTO refunds (payment_id, return_id, amount_cents)
+      VALUES ($1, $2, $3)
+      RETURNING id, payment_id, return_id, amount_cents, created_at
+      "#,
+      payment_id,
+      id,
+      amount,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    invoices_repo::issue_credit_note(&mut tx, order_id, refund.id, amount).await?;
+
+    let payment_status = if amount == refundable {
+      "refunded"
+    } else {
+      "partially_refunded"
+    };
+    sqlx::query!(
+      "UPDATE payments SET status = $2, updated_at = now() WHERE id = $1",
+      payment_id,
+      payment_status,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    if amount == remaining {
+      sqlx::query!(
+        "UPDATE return_requests SET status = 'refunded', updated_at = now() WHERE id = $1",
+        id,
+      )
+      .execute(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?;
+    }
+
+    // The order is fully refunded once every captured cent has been returned.
+    let previous = orders_repo::lock_order(&mut tx, order_id).await?;
+    sqlx::query!(
+      r#"
+      UPDATE orders o
+      SET status = CASE WHEN t.refunded >= t.captured THEN 'refunded' ELSE 'partially_refunded' END,
+        updated_at = now()
+      FROM (
+        SELECT
+          COALESCE(SUM(p.amount_cents), 0) AS captured,
+          COALESCE(SUM((SELECT SUM(f.amount_cents) FROM refunds f WHERE f.payment_id = p.id)), 0)
+            AS refunded
+        FROM payments p
+        WHERE p.order_id = $1 AND p.status IN ('succeeded', 'partially_refunded', 'refunded')
+      ) t
+      WHERE o.id = $1
+      "#,
+      order_id,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    orders_repo::record_change(&mut tx, &previous, ctx).await?;
+
+    let updated = load_return(&mut tx, id).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok((updated, refund))
+  }
+
+  async fn list_refunds(&self, order_id: Uuid) -> Result<Vec<Refund>, RepoError> {
+    let mut conn = self.pool.acquire().await.map_err(map_sqlx_err)?;
+    ensure_order_exists(&mut conn, order_id).await?;
+
+    let refunds = sqlx::query_as!(
+      Refund,
+      r#"
+      SELECT f.id, f.payment_id, f.return_id, f.amount_cents, f.created_at
+      FROM refunds f
+      JOIN payments p ON p.id = f.payment_id
+      WHERE p.order_id = $1
+      ORDER BY f.created_at
+      "#,
+      order_id,
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(refunds)
+  }
+}
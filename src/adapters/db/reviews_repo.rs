@@ -0,0 +1,229 @@
+use crate::adapters::db::map_sqlx_err;
+use crate::application::ports::{NewReview, RepoError, ReviewRepository};
+use crate::domain::models::Review;
+use async_trait::async_trait;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+/// Order status that makes a customer eligible to review its products.
+const DELIVERED: &str = "delivered";
+
+#[derive(Clone)]
+pub struct PgReviewRepository {
+  pool: PgPool,
+}
+
+impl PgReviewRepository {
+  pub fn new(pool: PgPool) -> Self {
+    Self { pool }
+  }
+}
+
+/// Locks the product row so rating refreshes for the same product run one at
+/// a time and each sees the others' committed changes.
+async fn lock_product(conn: &mut PgConnection, product_id: Uuid) -> Result<(), RepoError> {
+  sqlx::query!(
+    "SELECT id FROM products WHERE id = $1 FOR UPDATE",
+    product_id,
+  )
+  .fetch_one(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  Ok(())
+}
+
+/// Recomputes the product's rating from its approved reviews.
+async fn refresh_rating(conn: &mut PgConnection, product_id: Uuid) -> Result<(), RepoError> {
+  sqlx::query!(
+    r#"
+    UPDATE products p
+    SET rating_average = s.average, rating_count = s.count
+    FROM (
+      SELECT round(avg(rating), 2)::double precision AS average, COUNT(*)::integer AS count
+      FROM reviews
+      WHERE product_id = $1 AND status = 'approved'
+    ) s
+    WHERE p.id = $1
+    "#,
+    product_id,
+  )
+  .execute(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  Ok(())
+}
+
+/// The review's product, read without locking.
+async fn product_of(conn: &mut PgConnection, id: Uuid) -> Result<Uuid, RepoError> {
+  sqlx::query_scalar!("SELECT product_id FROM reviews WHERE id = $1", id)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)
+}
+
+#[async_trait]
+impl ReviewRepository for PgReviewRepository {
+  async fn create(&self, product_id: Uuid, input: NewReview) -> Result<Review, RepoError> {
+    let mut conn = self.pool.acquire().await.map_err(map_sqlx_err)?;
+    let row = sqlx::query!(
+      r#"
+      SELECT
+        EXISTS (SELECT 1 FROM products WHERE id = $1) AS "product_exists!",
+        EXISTS (SELECT 1 FROM users WHERE id = $2) AS "user_exists!",
+        EXISTS (
+          SELECT 1
+          FROM orders o
+          JOIN order_items oi ON oi.order_id = o.id
+          WHERE o.user_id = $2 AND o.status = $3 AND oi.product_id = $1
+        ) AS "purchased!"
+      "#,
+      product_id,
+      input.user_id,
+      DELIVERED,
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+    if !row.product_exists || !row.user_exists {
+      return Err(RepoError::NotFound);
+    }
+    if !row.purchased {
+      return Err(RepoError::Invalid(
+        "only customers with a delivered order of this product can review it".into(),
+      ));
+    }
+
+    let review = sqlx::query_as!(
+      Review,
+      r#"
+      INSERT INTO reviews (product_id, user_id, rating, title, body)
+      VALUES ($1, $2, $3, $4, $5)
+      RETURNING id, product_id, user_id, rating, title, body, status, moderation_note,
+        created_at, updated_at
+      "#,
+      product_id,
+      input.user_id,
+      input.rating,
+      input.title,
+      input.body,
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(review)
+  }
+
+  async fn get(&self, id: Uuid) -> Result<Review, RepoError> {
+    let review = sqlx::query_as!(
+      Review,
+      r#"
+      SELECT id, product_id, user_id, rating, title, body, status, moderation_note,
+        created_at, updated_at
+      FROM reviews
+      WHERE id = $1
+      "#,
+      id,
+    )
+    .fetch_one(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(review)
+  }
+
+  async fn list(
+    &self,
+    product_id: Option<Uuid>,
+    status: &str,
+    limit: i64,
+    offset: i64,
+  ) -> Result<(Vec<Review>, i64), RepoError> {
+    let mut conn = self.pool.acquire().await.map_err(map_sqlx_err)?;
+    if let Some(product_id) = product_id {
+      sqlx::query!("SELECT id FROM products WHERE id = $1", product_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(map_sqlx_err)?;
+    }
+    let reviews = sqlx::query_as!(
+      Review,
+      r#"
+      SELECT id, product_id, user_id, rating, title, body, status, moderation_note,
+        created_at, updated_at
+      FROM reviews
+      WHERE status = $1 AND ($2::uuid IS NULL OR product_id = $2)
+      ORDER BY created_at DESC, id
+      LIMIT $3 OFFSET $4
+      "#,
+      status,
+      product_id,
+      limit,
+      offset,
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+    let total: i64 = sqlx::query_scalar!(
+      r#"
+      SELECT COUNT(*) AS "count!"
+      FROM reviews
+      WHERE status = $1 AND ($2::uuid IS NULL OR product_id = $2)
+      "#,
+      status,
+      product_id,
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok((reviews, total))
+  }
+
+  async fn moderate(
+    &self,
+    id: Uuid,
+    status: &str,
+    note: Option<String>,
+  ) -> Result<Review, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    let product_id = product_of(&mut tx, id).await?;
+    lock_product(&mut tx, product_id).await?;
+    let review = sqlx::query_as!(
+      Review,
+      r#"
+      UPDATE reviews
+      SET status = $2, moderation_note = $3, updated_at = now()
+      WHERE id = $1
+      RETURNING id, product_id, user_id, rating, title, body, status, moderation_note,
+        created_at, updated_at
+      "#,
+      id,
+      status,
+      note,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    refresh_rating(&mut tx, product_id).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+
+    Ok(review)
+  }
+
+  async fn delete(&self, id: Uuid) -> Result<(), RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    let product_id = product_of(&mut tx, id).await?;
+    lock_product(&mut tx, product_id).await?;
+    let result = sqlx::query!("DELETE FROM reviews WHERE id = $1", id)
+      .execute(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?;
+    if result.rows_affected() == 0 {
+      return Err(RepoError::NotFound);
+    }
+    refresh_rating(&mut tx, product_id).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(())
+  }
+}
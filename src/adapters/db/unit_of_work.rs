@@ -0,0 +1,145 @@
+use crate::adapters::db::inventory_repo::PgInventoryRepository;
+use crate::adapters::db::orders_repo::PgOrderRepository;
+use crate::application::ports::{RepoError, UnitOfWork, UnitOfWorkFactory};
+use async_trait::async_trait;
+use sqlx::pool::PoolConnection;
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+type SharedTx = Arc<Mutex<Option<Transaction<'static, Postgres>>>>;
+
+/// Where a repository runs its queries: on pooled connections, or inside a
+/// unit of work's transaction, where its own transactions become savepoints.
+#[derive(Clone)]
+pub(crate) enum Db {
+  Pool(PgPool),
+  Tx(SharedTx),
+}
+
+impl Db {
+  pub(crate) async fn acquire(&self) -> Result<DbConn, RepoError> {
+    match self {
+      Db::Pool(pool) => pool
+        .acquire()
+        .await
+        .map(|conn| DbConn::Pool(Box::new(conn)))
+        .map_err(|err| RepoError::Unexpected(err.to_string())),
+      Db::Tx(tx) => {
+        let guard = tx.clone().lock_owned().await;
+        if guard.is_none() {
+          return Err(RepoError::Unexpected(
+            "unit of work already finished".into(),
+          ));
+        }
+        Ok(DbConn::Tx(guard))
+      }
+    }
+  }
+}
+
+/// A connection from `Db::acquire`; the unit of work's transaction stays
+/// locked while it is held.
+pub(crate) enum DbConn {
+  Pool(Box<PoolConnection<Postgres>>),
+  Tx(OwnedMutexGuard<Option<Transaction<'static, Postgres>>>),
+}
+
+impl Deref for DbConn {
+  type Target = PgConnection;
+
+  fn deref(&self) -> &PgConnection {
+    match self {
+      DbConn::Pool(conn) => conn,
+      DbConn::Tx(tx) => tx.as_ref().expect("checked in acquire"),
+    }
+  }
+}
+
+impl DerefMut for DbConn {
+  fn deref_mut(&mut self) -> &mut PgConnection {
+    match self {
+      DbConn::Pool(conn) => conn,
+      DbConn::Tx(tx) => tx.as_mut().expect("checked in acquire"),
+    }
+  }
+}
+
+#[derive(Clone)]
+pub struct PgUnitOfWorkFactory {
+  pool: PgPool,
+}
+
+impl PgUnitOfWorkFactory {
+  pub fn new(pool: PgPool) -> Self {
+    Self { pool }
+  }
+}
+
+#[async_trait]
+impl UnitOfWorkFactory for PgUnitOfWorkFactory {
+  type Work = PgUnitOfWork;
+
+  async fn begin(&self) -> Result<PgUnitOfWork, RepoError> {
+    let tx = self
+      .pool
+      .begin()
+      .await
+      .map_err(|err| RepoError::Unexpected(err.to_string()))?;
+    let tx: SharedTx = Arc::new(Mutex::new(Some(tx)));
+    Ok(PgUnitOfWork {
+      orders: PgOrderRepository::with_db(Db::Tx(tx.clone())),
+      inventory: PgInventoryRepository::with_db(Db::Tx(tx.clone())),
+      tx,
+    })
+  }
+}
+
+/// Repositories sharing one Postgres transaction. Dropping it (and any
+/// repository clones) uncommitted rolls the transaction back.
+pub struct PgUnitOfWork {
+  tx: SharedTx,
+  orders: PgOrderRepository,
+  inventory: PgInventoryRepository,
+}
+
+impl PgUnitOfWork {
+  async fn finish(self) -> Result<Transaction<'static, Postgres>, RepoError> {
+    // Repositories handed out earlier fail from now on.
+    let taken = self.tx.lock().await.take();
+    taken.ok_or_else(|| RepoError::Unexpected("unit of work already finished".into()))
+  }
+}
+
+#[async_trait]
+impl UnitOfWork for PgUnitOfWork {
+  type Orders = PgOrderRepository;
+  type Inventory = PgInventoryRepository;
+
+  fn orders(&self) -> &PgOrderRepository {
+    &self.orders
+  }
+
+  fn inventory(&self) -> &PgInventoryRepository {
+    &self.inventory
+  }
+
+  async fn commit(self) -> Result<(), RepoError> {
+    self
+      .finish()
+      .await?
+      .commit()
+      .await
+      .map_err(|err| RepoError::Unexpected(err.to_string()))
+  }
+
+  async fn rollback(self) -> Result<(), RepoError> {
+    self
+      .finish()
+      .await?
+      .rollback()
+      .await
+      .map_err(|err| RepoError::Unexpected(err.to_string()))
+  }
+}
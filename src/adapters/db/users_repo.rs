@@ -1,8 +1,10 @@
+use crate::adapters::db::{audit_repo, map_sqlx_err, outbox_repo};
 use crate::application::ports::{NewUser, RepoError, UpdateUser, UserRepository};
-use crate::domain::models::User;
+use crate::domain::audit;
+use crate::domain::events::DomainEvent;
+use crate::domain::models::{AuditContext, SimilarityHit, User};
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
-use sqlx::{PgPool, Row};
+use sqlx::PgPool;
 use uuid::Uuid;
 
 #[derive(Clone)]
@@ -16,48 +18,34 @@ impl PgUserRepository {
   }
 }
 
-fn map_sqlx_err(err: sqlx::Error) -> RepoError {
-  match &err {
-    sqlx::Error::RowNotFound => RepoError::NotFound,
-    sqlx::Error::Database(db_err) => {
-      // Postgres: unique_violation = 23505
-      if db_err.code().as_deref() == Some("23505") {
-        RepoError::Conflict
-      } else {
-        RepoError::Unexpected(err.to_string())
-      }
-    }
-    _ => RepoError::Unexpected(err.to_string()),
-  }
-}
-
 #[async_trait]
 impl UserRepository for PgUserRepository {
-  async fn create(&self, input: NewUser) -> Result<User, RepoError> {
-    let row = sqlx::query(
+  async fn create(&self, input: NewUser, ctx: &AuditContext) -> Result<User, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    let user = sqlx::query_as!(
+      User,
       r#"
       INSERT INTO users (email, name)
       VALUES ($1, $2)
       RETURNING id, email, name, created_at, updated_at
       "#,
+      input.email,
+      input.name,
     )
-    .bind(input.email)
-    .bind(input.name)
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *tx)
     .await
     .map_err(map_sqlx_err)?;
 
-    Ok(User {
-      id: row.get::<Uuid, _>("id"),
-      email: row.get::<String, _>("email"),
-      name: row.get::<String, _>("name"),
-      created_at: row.get::<DateTime<Utc>, _>("created_at"),
-      updated_at: row.get::<DateTime<Utc>, _>("updated_at"),
-    })
+    let changes = audit::diff(None, Some(&user));
+    audit_repo::record(&mut tx, ctx, "created", "user", user.id, &changes).await?;
+    outbox_repo::enqueue(&mut tx, &DomainEvent::UserCreated { user: user.clone() }).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(user)
   }
 
   async fn list(&self) -> Result<Vec<User>, RepoError> {
-    let rows = sqlx::query(
+    let users = sqlx::query_as!(
+      User,
       r#"
       SELECT id, email, name, created_at, updated_at
       FROM users
@@ -68,44 +56,48 @@ impl UserRepository for PgUserRepository {
     .await
     .map_err(map_sqlx_err)?;
 
-    Ok(
-      rows
-        .into_iter()
-        .map(|row| User {
-          id: row.get::<Uuid, _>("id"),
-          email: row.get::<String, _>("email"),
-          name: row.get::<String, _>("name"),
-          created_at: row.get::<DateTime<Utc>, _>("created_at"),
-          updated_at: row.get::<DateTime<Utc>, _>("updated_at"),
-        })
-        .collect(),
-    )
+    Ok(users)
   }
 
   async fn get(&self, id: Uuid) -> Result<User, RepoError> {
-    let row = sqlx::query(
+    let user = sqlx::query_as!(
+      User,
       r#"
       SELECT id, email, name, created_at, updated_at
       FROM users
       WHERE id = $1
       "#,
+      id,
     )
-    .bind(id)
     .fetch_one(&self.pool)
     .await
     .map_err(map_sqlx_err)?;
 
-    Ok(User {
-      id: row.get::<Uuid, _>("id"),
-      email: row.get::<String, _>("email"),
-      name: row.get::<String, _>("name"),
-      created_at: row.get::<DateTime<Utc>, _>("created_at"),
-      updated_at: row.get::<DateTime<Utc>, _>("updated_at"),
-    })
+    Ok(user)
   }
 
-  async fn update(&self, id: Uuid, input: UpdateUser) -> Result<User, RepoError> {
-    let row = sqlx::query(
+  async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateUser,
+    ctx: &AuditContext,
+  ) -> Result<User, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    let previous = sqlx::query_as!(
+      User,
+      r#"
+      SELECT id, email, name, created_at, updated_at
+      FROM users
+      WHERE id = $1
+      FOR UPDATE
+      "#,
+      id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let user = sqlx::query_as!(
+      User,
       r#"
       UPDATE users
       SET
@@ -115,32 +107,88 @@ impl UserRepository for PgUserRepository {
       WHERE id = $1
       RETURNING id, email, name, created_at, updated_at
       "#,
+      id,
+      input.email,
+      input.name,
     )
-    .bind(id)
-    .bind(input.email)
-    .bind(input.name)
-    .fetch_one(&self.pool)
+    .fetch_one(&mut *tx)
     .await
     .map_err(map_sqlx_err)?;
 
-    Ok(User {
-      id: row.get::<Uuid, _>("id"),
-      email: row.get::<String, _>("email"),
-      name: row.get::<String, _>("name"),
-      created_at: row.get::<DateTime<Utc>, _>("created_at"),
-      updated_at: row.get::<DateTime<Utc>, _>("updated_at"),
-    })
+    let changes = audit::diff(Some(&previous), Some(&user));
+    audit_repo::record(&mut tx, ctx, "updated", "user", id, &changes).await?;
+    outbox_repo::enqueue(&mut tx, &DomainEvent::UserUpdated { user: user.clone() }).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(user)
   }
 
-  async fn delete(&self, id: Uuid) -> Result<(), RepoError> {
-    let res = sqlx::query("DELETE FROM users WHERE id = $1")
-      .bind(id)
-      .execute(&self.pool)
-      .await
-      .map_err(map_sqlx_err)?;
-    if res.rows_affected() == 0 {
-      return Err(RepoError::NotFound);
-    }
+  async fn delete(&self, id: Uuid, ctx: &AuditContext) -> Result<(), RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    let user = sqlx::query_as!(
+      User,
+      r#"
+      DELETE FROM users
+      WHERE id = $1
+      RETURNING id, email, name, created_at, updated_at
+      "#,
+      id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let changes = audit::diff(Some(&user), None);
+    audit_repo::record(&mut tx, ctx, "deleted", "user", id, &changes).await?;
+    outbox_repo::enqueue(&mut tx, &DomainEvent::UserDeleted { user_id: id }).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
     Ok(())
   }
+
+  async fn fuzzy_search(
+    &self,
+    text: &str,
+    threshold: f32,
+    limit: i64,
+  ) -> Result<Vec<SimilarityHit<User>>, RepoError> {
+    // See `PgProductRepository::fuzzy_search` for the threshold handling.
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    sqlx::query_scalar!(
+      "SELECT set_config('pg_trgm.word_similarity_threshold', $1, true)",
+      threshold.to_string(),
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let rows = sqlx::query!(
+      r#"
+      SELECT id, email, name, created_at, updated_at,
+        GREATEST(word_similarity($1, email), word_similarity($1, name)) AS "similarity!"
+      FROM users
+      WHERE $1 <% email OR $1 <% name
+      ORDER BY "similarity!" DESC, email, id
+      LIMIT $2
+      "#,
+      text,
+      limit,
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+
+    Ok(
+      rows
+        .into_iter()
+        .map(|row| SimilarityHit {
+          similarity: row.similarity,
+          item: User {
+            id: row.id,
+            email: row.email,
+            name: row.name,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+          },
+        })
+        .collect(),
+    )
+  }
 }
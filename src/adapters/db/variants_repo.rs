@@ -0,0 +1,342 @@
+use crate::adapters::db::map_sqlx_err;
+use crate::application::ports::{
+  NewProductOption, NewVariant, RepoError, UpdateVariant, VariantRepository,
+};
+use crate::domain::catalog::variant_key;
+use crate::domain::models::{ProductOption, ProductVariant};
+use async_trait::async_trait;
+use sqlx::{PgConnection, PgPool};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct PgVariantRepository {
+  pool: PgPool,
+}
+
+impl PgVariantRepository {
+  pub fn new(pool: PgPool) -> Self {
+    Self { pool }
+  }
+}
+
+async fn ensure_product_exists(conn: &mut PgConnection, product_id: Uuid) -> Result<(), RepoError> {
+  sqlx::query!("SELECT id FROM products WHERE id = $1", product_id)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+  Ok(())
+}
+
+async fn load_options(
+  conn: &mut PgConnection,
+  product_id: Uuid,
+) -> Result<Vec<ProductOption>, RepoError> {
+  sqlx::query_as!(
+    ProductOption,
+    r#"
+    SELECT o.id, o.product_id, o.name, o.position,
+      COALESCE(
+        array_agg(ov.value ORDER BY ov.position) FILTER (WHERE ov.id IS NOT NULL),
+        '{}'
+      ) AS "values!"
+    FROM product_options o
+    LEFT JOIN product_option_values ov ON ov.option_id = o.id
+    WHERE o.product_id = $1
+    GROUP BY o.id
+    ORDER BY o.position
+    "#,
+    product_id,
+  )
+  .fetch_all(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)
+}
+
+/// Loads the variants of `product_id`, or the single variant `variant_id`.
+async fn load_variants(
+  conn: &mut PgConnection,
+  product_id: Option<Uuid>,
+  variant_id: Option<Uuid>,
+) -> Result<Vec<ProductVariant>, RepoError> {
+  let rows = sqlx::query!(
+    r#"
+    SELECT v.id, v.product_id, v.sku,
+      COALESCE(v.price_cents, product_price_at(v.product_id, now())) AS "price_cents!",
+      v.price_cents AS price_override_cents,
+      v.stock, v.created_at, v.updated_at
+    FROM product_variants v
+    WHERE ($1::uuid IS NULL OR v.product_id = $1)
+      AND ($2::uuid IS NULL OR v.id = $2)
+    ORDER BY v.created_at, v.id
+    "#,
+    product_id,
+    variant_id,
+  )
+  .fetch_all(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+
+  let ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
+  let mut options: HashMap<Uuid, BTreeMap<String, String>> = HashMap::new();
+  let value_rows = sqlx::query!(
+    r#"
+    SELECT vv.variant_id, o.name, ov.value
+    FROM product_variant_values vv
+    JOIN product_options o ON o.id = vv.option_id
+    JOIN product_option_values ov ON ov.id = vv.value_id
+    WHERE vv.variant_id = ANY($1)
+    "#,
+    &ids,
+  )
+  .fetch_all(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  for row in value_rows {
+    options
+      .entry(row.variant_id)
+      .or_default()
+      .insert(row.name, row.value);
+  }
+
+  Ok(
+    rows
+      .into_iter()
+      .map(|row| {
+        let id = row.id;
+        ProductVariant {
+          id,
+          product_id: row.product_id,
+          sku: row.sku,
+          price_cents: row.price_cents,
+          price_override_cents: row.price_override_cents,
+          stock: row.stock,
+          options: options.remove(&id).unwrap_or_default(),
+          created_at: row.created_at,
+          updated_at: row.updated_at,
+        }
+      })
+      .collect(),
+  )
+}
+
+async fn load_variant(conn: &mut PgConnection, id: Uuid) -> Result<ProductVariant, RepoError> {
+  load_variants(conn, None, Some(id))
+    .await?
+    .pop()
+    .ok_or(RepoError::NotFound)
+}
+
+/// Resolves option names and values to `(option id, value id)` pairs,
+/// requiring exactly one declared value per option of the product.
+async fn resolve_choices(
+  conn: &mut PgConnection,
+  product_id: Uuid,
+  chosen: &BTreeMap<String, String>,
+) -> Result<Vec<(Uuid, Uuid)>, RepoError> {
+  let rows = sqlx::query!(
+    r#"
+    SELECT o.id AS option_id, o.name, ov.id AS value_id, ov.value
+    FROM product_options o
+    JOIN product_option_values ov ON ov.option_id = o.id
+    WHERE o.product_id = $1
+    "#,
+    product_id,
+  )
+  .fetch_all(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+
+  let mut declared: BTreeMap<String, (Uuid, HashMap<String, Uuid>)> = BTreeMap::new();
+  for row in rows {
+    declared
+      .entry(row.name)
+      .or_insert_with(|| (row.option_id, HashMap::new()))
+      .1
+      .insert(row.value, row.value_id);
+  }
+  if let Some(unknown) = chosen.keys().find(|name| !declared.contains_key(*name)) {
+    return Err(RepoError::Invalid(format!("unknown option `{unknown}`")));
+  }
+
+  let mut choices = Vec::with_capacity(declared.len());
+  for (name, (option_id, values)) in &declared {
+    let value = chosen
+      .get(name)
+      .ok_or_else(|| RepoError::Invalid(format!("missing value for option `{name}`")))?;
+    let value_id = values
+      .get(value)
+      .ok_or_else(|| RepoError::Invalid(format!("`{value}` is not a value of option `{name}`")))?;
+    choices.push((*option_id, *value_id));
+  }
+  Ok(choices)
+}
+
+#[async_trait]
+impl VariantRepository for PgVariantRepository {
+  async fn add_option(
+    &self,
+    product_id: Uuid,
+    input: NewProductOption,
+  ) -> Result<ProductOption, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+
+    // Conflicts with the FOR SHARE lock taken when creating variants.
+    sqlx::query!(
+      "SELECT id FROM products WHERE id = $1 FOR UPDATE",
+      product_id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let has_variants: bool = sqlx::query_scalar!(
+      r#"SELECT EXISTS (SELECT 1 FROM product_variants WHERE product_id = $1) AS "exists!""#,
+      product_id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    if has_variants {
+      return Err(RepoError::Conflict);
+    }
+
+    let option_id: Uuid = sqlx::query_scalar!(
+      r#"
+      INSERT INTO product_options (product_id, name, position)
+      SELECT $1, $2, COALESCE(MAX(position) + 1, 0)
+      FROM product_options
+      WHERE product_id = $1
+      RETURNING id
+      "#,
+      product_id,
+      &input.name,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    sqlx::query!(
+      r#"
+      INSERT INTO product_option_values (option_id, value, position)
+      SELECT $1, value, (ordinality - 1)::integer
+      FROM UNNEST($2::text[]) WITH ORDINALITY AS t(value, ordinality)
+      "#,
+      option_id,
+      &input.values,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    let option = load_options(&mut tx, product_id)
+      .await?
+      .into_iter()
+      .find(|o| o.id == option_id)
+      .ok_or_else(|| RepoError::Unexpected("option vanished after insert".into()))?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(option)
+  }
+
+  async fn list_options(&self, product_id: Uuid) -> Result<Vec<ProductOption>, RepoError> {
+    let mut conn = self.pool.acquire().await.map_err(map_sqlx_err)?;
+    ensure_product_exists(&mut conn, product_id).await?;
+    load_options(&mut conn, product_id).await
+  }
+
+  async fn create(&self, product_id: Uuid, input: NewVariant) -> Result<ProductVariant, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+
+    // Keeps the product's options fixed while the combination is checked.
+    sqlx::query!(
+      "SELECT id FROM products WHERE id = $1 FOR SHARE",
+      product_id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let choices = resolve_choices(&mut tx, product_id, &input.options).await?;
+
+    let id: Uuid = sqlx::query_scalar!(
+      r#"
+      INSERT INTO product_variants (product_id, sku, price_cents, stock, option_key)
+      VALUES ($1, $2, $3, $4, $5)
+      RETURNING id
+      "#,
+      product_id,
+      &input.sku,
+      input.price_override_cents,
+      input.stock,
+      variant_key(&choices),
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    let (option_ids, value_ids): (Vec<Uuid>, Vec<Uuid>) = choices.into_iter().unzip();
+    sqlx::query!(
+      r#"
+      INSERT INTO product_variant_values (variant_id, option_id, value_id)
+      SELECT $1, option_id, value_id
+      FROM UNNEST($2::uuid[], $3::uuid[]) AS t(option_id, value_id)
+      "#,
+      id,
+      &option_ids,
+      &value_ids,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    let variant = load_variant(&mut tx, id).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(variant)
+  }
+
+  async fn list_by_product(&self, product_id: Uuid) -> Result<Vec<ProductVariant>, RepoError> {
+    let mut conn = self.pool.acquire().await.map_err(map_sqlx_err)?;
+    ensure_product_exists(&mut conn, product_id).await?;
+    load_variants(&mut conn, Some(product_id), None).await
+  }
+
+  async fn get(&self, id: Uuid) -> Result<ProductVariant, RepoError> {
+    let mut conn = self.pool.acquire().await.map_err(map_sqlx_err)?;
+    load_variant(&mut conn, id).await
+  }
+
+  async fn update(&self, id: Uuid, input: UpdateVariant) -> Result<ProductVariant, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    sqlx::query!(
+      r#"
+      UPDATE product_variants
+      SET
+        sku = COALESCE($2, sku),
+        price_cents = CASE WHEN $3 THEN $4 ELSE price_cents END,
+        stock = COALESCE($5, stock),
+        updated_at = now()
+      WHERE id = $1
+      RETURNING id
+      "#,
+      id,
+      input.sku,
+      input.price_override_cents.is_some(),
+      input.price_override_cents.flatten(),
+      input.stock,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let variant = load_variant(&mut tx, id).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(variant)
+  }
+
+  async fn delete(&self, id: Uuid) -> Result<(), RepoError> {
+    let res = sqlx::query!("DELETE FROM product_variants WHERE id = $1", id)
+      .execute(&self.pool)
+      .await
+      .map_err(map_sqlx_err)?;
+    if res.rows_affected() == 0 {
+      return Err(RepoError::NotFound);
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,484 @@
+use crate::adapters::db::map_sqlx_err;
+use crate::application::ports::{
+  DueWebhookDelivery, NewWebhookSubscription, RepoError, UpdateWebhookSubscription,
+  WebhookAttemptResult, WebhookRepository,
+};
+use crate::domain::models::{OutboxEvent, WebhookAttempt, WebhookDelivery, WebhookSubscription};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct PgWebhookRepository {
+  pool: PgPool,
+}
+
+impl PgWebhookRepository {
+  pub fn new(pool: PgPool) -> Self {
+    Self { pool }
+  }
+}
+
+/// A `webhook_deliveries` row; `with_log` loads the attempt log.
+struct DeliveryRow {
+  id: Uuid,
+  subscription_id: Uuid,
+  event_id: Uuid,
+  event_type: String,
+  payload: serde_json::Value,
+  occurred_at: DateTime<Utc>,
+  status: String,
+  attempts: i32,
+  next_attempt_at: DateTime<Utc>,
+  response_status: Option<i32>,
+  last_error: Option<String>,
+  delivered_at: Option<DateTime<Utc>>,
+  created_at: DateTime<Utc>,
+}
+
+impl From<DeliveryRow> for WebhookDelivery {
+  fn from(row: DeliveryRow) -> Self {
+    WebhookDelivery {
+      id: row.id,
+      subscription_id: row.subscription_id,
+      event_id: row.event_id,
+      event_type: row.event_type,
+      payload: row.payload,
+      occurred_at: row.occurred_at,
+      status: row.status,
+      attempts: row.attempts,
+      next_attempt_at: row.next_attempt_at,
+      response_status: row.response_status,
+      last_error: row.last_error,
+      delivered_at: row.delivered_at,
+      log: Vec::new(),
+      created_at: row.created_at,
+    }
+  }
+}
+
+/// Fills in the attempt log of each delivery.
+async fn with_log(
+  conn: &mut PgConnection,
+  mut deliveries: Vec<WebhookDelivery>,
+) -> Result<Vec<WebhookDelivery>, RepoError> {
+  let ids: Vec<Uuid> = deliveries.iter().map(|d| d.id).collect();
+  let rows = sqlx::query!(
+    r#"
+    SELECT delivery_id, attempted_at, response_status, error, duration_ms
+    FROM webhook_delivery_attempts
+    WHERE delivery_id = ANY($1)
+    ORDER BY id
+    "#,
+    &ids,
+  )
+  .fetch_all(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  for row in rows {
+    if let Some(delivery) = deliveries.iter_mut().find(|d| d.id == row.delivery_id) {
+      delivery.log.push(WebhookAttempt {
+        attempted_at: row.attempted_at,
+        response_status: row.response_status,
+        error: row.error,
+        duration_ms: row.duration_ms,
+      });
+    }
+  }
+  Ok(deliveries)
+}
+
+async fn load_delivery(
+  conn: &mut PgConnection,
+  subscription_id: Uuid,
+  id: Uuid,
+) -> Result<WebhookDelivery, RepoError> {
+  let delivery = sqlx::query_as!(
+    DeliveryRow,
+    r#"
+    SELECT id, subscription_id, event_id, event_type, payload, occurred_at, status, attempts,
+      next_attempt_at, response_status, last_error, delivered_at, created_at
+    FROM webhook_deliveries
+    WHERE id = $1 AND subscription_id = $2
+    "#,
+    id,
+    subscription_id,
+  )
+  .fetch_one(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  let mut deliveries = with_log(conn, vec![delivery.into()]).await?;
+  Ok(deliveries.remove(0))
+}
+
+#[async_trait]
+impl WebhookRepository for PgWebhookRepository {
+  async fn create(&self, input: NewWebhookSubscription) -> Result<WebhookSubscription, RepoError> {
+    let subscription = sqlx::query_as!(
+      WebhookSubscription,
+      r#"
+      INSERT INTO webhook_subscriptions (url, secret, event_types)
+      VALUES ($1, $2, $3)
+      RETURNING id, url, secret, event_types, active, consecutive_failures, disabled_at,
+        created_at, updated_at
+      "#,
+      input.url,
+      input.secret,
+      &input.event_types,
+    )
+    .fetch_one(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(subscription)
+  }
+
+  async fn list(&self) -> Result<Vec<WebhookSubscription>, RepoError> {
+    let subscriptions = sqlx::query_as!(
+      WebhookSubscription,
+      r#"
+      SELECT id, url, secret, event_types, active, consecutive_failures, disabled_at,
+        created_at, updated_at
+      FROM webhook_subscriptions
+      ORDER BY created_at, id
+      "#,
+    )
+    .fetch_all(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(subscriptions)
+  }
+
+  async fn get(&self, id: Uuid) -> Result<WebhookSubscription, RepoError> {
+    let subscription = sqlx::query_as!(
+      WebhookSubscription,
+      r#"
+      SELECT id, url, secret, event_types, active, consecutive_failures, disabled_at,
+        created_at, updated_at
+      FROM webhook_subscriptions
+      WHERE id = $1
+      "#,
+      id,
+    )
+    .fetch_one(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(subscription)
+  }
+
+  async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateWebhookSubscription,
+  ) -> Result<WebhookSubscription, RepoError> {
+    let subscription = sqlx::query_as!(
+      WebhookSubscription,
+      r#"
+      UPDATE webhook_subscriptions
+      SET
+        url = COALESCE($2, url),
+        secret = COALESCE($3, secret),
+        event_types = COALESCE($4, event_types),
+        active = COALESCE($5, active),
+        consecutive_failures = CASE WHEN $5 THEN 0 ELSE consecutive_failures END,
+        disabled_at = CASE
+          WHEN $5 THEN NULL
+          WHEN NOT $5 AND active THEN now()
+          ELSE disabled_at
+        END,
+        updated_at = now()
+      WHERE id = $1
+      RETURNING id, url, secret, event_types, active, consecutive_failures, disabled_at,
+        created_at, updated_at
+      "#,
+      id,
+      input.url,
+      input.secret,
+      input.event_types.as_deref(),
+      input.active,
+    )
+    .fetch_one(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(subscription)
+  }
+
+  async fn delete(&self, id: Uuid) -> Result<(), RepoError> {
+    let res = sqlx::query!("DELETE FROM webhook_subscriptions WHERE id = $1", id)
+      .execute(&self.pool)
+      .await
+      .map_err(map_sqlx_err)?;
+    if res.rows_affected() == 0 {
+      return Err(RepoError::NotFound);
+    }
+    Ok(())
+  }
+
+  async fn list_deliveries(
+    &self,
+    subscription_id: Uuid,
+    limit: i64,
+    offset: i64,
+  ) -> Result<(Vec<WebhookDelivery>, i64), RepoError> {
+    let mut conn = self.pool.acquire().await.map_err(map_sqlx_err)?;
+    sqlx::query!(
+      "SELECT id FROM webhook_subscriptions WHERE id = $1",
+      subscription_id,
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+    let rows = sqlx::query_as!(
+      DeliveryRow,
+      r#"
+      SELECT id, subscription_id, event_id, event_type, payload, occurred_at, status, attempts,
+        next_attempt_at, response_status, last_error, delivered_at, created_at
+      FROM webhook_deliveries
+      WHERE subscription_id = $1
+      ORDER BY created_at DESC, id
+      LIMIT $2 OFFSET $3
+      "#,
+      subscription_id,
+      limit,
+      offset,
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+    let total: i64 = sqlx::query_scalar!(
+      r#"SELECT COUNT(*) AS "count!" FROM webhook_deliveries WHERE subscription_id = $1"#,
+      subscription_id,
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+    let deliveries = with_log(
+      &mut conn,
+      rows.into_iter().map(WebhookDelivery::from).collect(),
+    )
+    .await?;
+
+    Ok((deliveries, total))
+  }
+
+  async fn get_delivery(
+    &self,
+    subscription_id: Uuid,
+    id: Uuid,
+  ) -> Result<WebhookDelivery, RepoError> {
+    let mut conn = self.pool.acquire().await.map_err(map_sqlx_err)?;
+    load_delivery(&mut conn, subscription_id, id).await
+  }
+
+  async fn redeliver(&self, subscription_id: Uuid, id: Uuid) -> Result<WebhookDelivery, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    let active: bool = sqlx::query_scalar!(
+      "SELECT active FROM webhook_subscriptions WHERE id = $1 FOR SHARE",
+      subscription_id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    if !active {
+      return Err(RepoError::Invalid(
+        "the subscription is disabled; enable it before redelivering".into(),
+      ));
+    }
+    sqlx::query!(
+      r#"
+      UPDATE webhook_deliveries
+      SET status = 'pending', attempts = 0, next_attempt_at = now(), updated_at = now()
+      WHERE id = $1 AND subscription_id = $2
+      RETURNING id
+      "#,
+      id,
+      subscription_id,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let delivery = load_delivery(&mut tx, subscription_id, id).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(delivery)
+  }
+
+  async fn enqueue_deliveries(&self, event: &OutboxEvent) -> Result<u64, RepoError> {
+    let res = sqlx::query!(
+      r#"
+      INSERT INTO webhook_deliveries (subscription_id, event_id, event_type, payload, occurred_at)
+      SELECT id, $1, $2, $3, $4
+      FROM webhook_subscriptions
+      WHERE active AND $2 = ANY(event_types)
+      ON CONFLICT (subscription_id, event_id) DO NOTHING
+      "#,
+      event.event_id,
+      &event.event_type,
+      &event.payload,
+      event.created_at,
+    )
+    .execute(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+    Ok(res.rows_affected())
+  }
+
+  async fn claim_deliveries(
+    &self,
+    limit: i64,
+    lease_secs: i64,
+  ) -> Result<Vec<DueWebhookDelivery>, RepoError> {
+    let rows = sqlx::query!(
+      r#"
+      WITH due AS (
+        SELECT d.id
+        FROM webhook_deliveries d
+        JOIN webhook_subscriptions s ON s.id = d.subscription_id
+        WHERE d.status = 'pending' AND d.next_attempt_at <= now() AND s.active
+        ORDER BY d.next_attempt_at, d.id
+        LIMIT $1
+        FOR UPDATE OF d SKIP LOCKED
+      )
+      UPDATE webhook_deliveries d
+      SET attempts = d.attempts + 1,
+        next_attempt_at = now() + make_interval(secs => $2),
+        updated_at = now()
+      FROM due, webhook_subscriptions s
+      WHERE d.id = due.id AND s.id = d.subscription_id
+      RETURNING d.id, d.subscription_id, d.event_id, d.event_type, d.payload, d.occurred_at,
+        d.status, d.attempts, d.next_attempt_at, d.response_status, d.last_error,
+        d.delivered_at, d.created_at, s.url, s.secret
+      "#,
+      limit,
+      lease_secs as f64,
+    )
+    .fetch_all(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    let mut due: Vec<DueWebhookDelivery> = rows
+      .into_iter()
+      .map(|row| DueWebhookDelivery {
+        delivery: WebhookDelivery {
+          id: row.id,
+          subscription_id: row.subscription_id,
+          event_id: row.event_id,
+          event_type: row.event_type,
+          payload: row.payload,
+          occurred_at: row.occurred_at,
+          status: row.status,
+          attempts: row.attempts,
+          next_attempt_at: row.next_attempt_at,
+          response_status: row.response_status,
+          last_error: row.last_error,
+          delivered_at: row.delivered_at,
+          log: Vec::new(),
+          created_at: row.created_at,
+        },
+        url: row.url,
+        secret: row.secret,
+      })
+      .collect();
+    due.sort_by_key(|d| (d.delivery.occurred_at, d.delivery.id));
+    Ok(due)
+  }
+
+  async fn record_attempt(
+    &self,
+    id: Uuid,
+    result: WebhookAttemptResult,
+    retry_after_secs: Option<i64>,
+    disable_after_failures: i32,
+  ) -> Result<(), RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    sqlx::query!(
+      r#"
+      INSERT INTO webhook_delivery_attempts (delivery_id, response_status, error, duration_ms)
+      VALUES ($1, $2, $3, $4)
+      "#,
+      id,
+      result.response_status,
+      result.error.as_deref(),
+      result.duration_ms,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|err| match &err {
+      // foreign_key_violation: the delivery is gone with its subscription.
+      sqlx::Error::Database(db_err) if db_err.code().as_deref() == Some("23503") => {
+        RepoError::NotFound
+      }
+      _ => map_sqlx_err(err),
+    })?;
+
+    let subscription_id: Uuid = if result.succeeded {
+      sqlx::query_scalar!(
+        r#"
+        UPDATE webhook_deliveries
+        SET status = 'succeeded', response_status = $2, last_error = NULL,
+          delivered_at = now(), updated_at = now()
+        WHERE id = $1
+        RETURNING subscription_id
+        "#,
+        id,
+        result.response_status,
+      )
+      .fetch_one(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?
+    } else {
+      sqlx::query_scalar!(
+        r#"
+        UPDATE webhook_deliveries
+        SET status = CASE WHEN $4::double precision IS NULL THEN 'failed' ELSE 'pending' END,
+          response_status = $2, last_error = $3,
+          next_attempt_at = COALESCE(now() + make_interval(secs => $4), next_attempt_at),
+          updated_at = now()
+        WHERE id = $1
+        RETURNING subscription_id
+        "#,
+        id,
+        result.response_status,
+        result.error.as_deref(),
+        retry_after_secs.map(|secs| secs as f64),
+      )
+      .fetch_one(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?
+    };
+
+    if result.succeeded {
+      sqlx::query!(
+        "UPDATE webhook_subscriptions SET consecutive_failures = 0 WHERE id = $1",
+        subscription_id,
+      )
+      .execute(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?;
+    } else {
+      sqlx::query!(
+        r#"
+        UPDATE webhook_subscriptions
+        SET consecutive_failures = consecutive_failures + 1,
+          active = active AND consecutive_failures + 1 < $2,
+          disabled_at = CASE
+            WHEN active AND consecutive_failures + 1 >= $2 THEN now()
+            ELSE disabled_at
+          END,
+          updated_at = now()
+        WHERE id = $1
+        "#,
+        subscription_id,
+        disable_after_failures,
+      )
+      .execute(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?;
+    }
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(())
+  }
+}
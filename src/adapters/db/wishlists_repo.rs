@@ -0,0 +1,336 @@
+use crate::adapters::db::{map_sqlx_err, orders_repo};
+use crate::application::ports::{NewOrder, NewOrderItem, RepoError, WishlistRepository};
+use crate::domain::catalog;
+use crate::domain::models::{AuditContext, OrderItem, Wishlist, WishlistItem};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+/// Status of the order that serves as a user's shopping cart.
+const CART: &str = "cart";
+
+#[derive(Clone)]
+pub struct PgWishlistRepository {
+  pool: PgPool,
+}
+
+impl PgWishlistRepository {
+  pub fn new(pool: PgPool) -> Self {
+    Self { pool }
+  }
+}
+
+/// A `wishlists` row; `with_items` loads the items.
+struct WishlistRow {
+  id: Uuid,
+  user_id: Uuid,
+  name: String,
+  created_at: DateTime<Utc>,
+  updated_at: DateTime<Utc>,
+}
+
+impl From<WishlistRow> for Wishlist {
+  fn from(row: WishlistRow) -> Self {
+    Wishlist {
+      id: row.id,
+      user_id: row.user_id,
+      name: row.name,
+      items: Vec::new(),
+      created_at: row.created_at,
+      updated_at: row.updated_at,
+    }
+  }
+}
+
+/// Items of the given wishlists with each product's current price, keyed by
+/// wishlist.
+async fn items_of(
+  conn: &mut PgConnection,
+  wishlist_ids: &[Uuid],
+) -> Result<Vec<(Uuid, WishlistItem)>, RepoError> {
+  let rows = sqlx::query!(
+    r#"
+    SELECT wi.wishlist_id, wi.product_id, p.name, wi.added_price_cents,
+      product_price_at(p.id, now()) AS "price_cents!", wi.added_at
+    FROM wishlist_items wi
+    JOIN products p ON p.id = wi.product_id
+    WHERE wi.wishlist_id = ANY($1)
+    ORDER BY wi.added_at, wi.product_id
+    "#,
+    wishlist_ids,
+  )
+  .fetch_all(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  Ok(
+    rows
+      .into_iter()
+      .map(|row| {
+        let item = WishlistItem {
+          product_id: row.product_id,
+          name: row.name,
+          added_price_cents: row.added_price_cents,
+          price_cents: row.price_cents,
+          price_drop_cents: catalog::price_drop(row.added_price_cents, row.price_cents),
+          added_at: row.added_at,
+        };
+        (row.wishlist_id, item)
+      })
+      .collect(),
+  )
+}
+
+async fn with_items(
+  conn: &mut PgConnection,
+  mut wishlists: Vec<Wishlist>,
+) -> Result<Vec<Wishlist>, RepoError> {
+  let ids: Vec<Uuid> = wishlists.iter().map(|w| w.id).collect();
+  for (wishlist_id, item) in items_of(conn, &ids).await? {
+    if let Some(wishlist) = wishlists.iter_mut().find(|w| w.id == wishlist_id) {
+      wishlist.items.push(item);
+    }
+  }
+  Ok(wishlists)
+}
+
+/// Locks the wishlist and marks it updated. `NotFound` unless it belongs to
+/// the user.
+async fn touch_wishlist(conn: &mut PgConnection, user_id: Uuid, id: Uuid) -> Result<(), RepoError> {
+  sqlx::query!(
+    "UPDATE wishlists SET updated_at = now() WHERE id = $1 AND user_id = $2 RETURNING id",
+    id,
+    user_id,
+  )
+  .fetch_one(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  Ok(())
+}
+
+async fn load(conn: &mut PgConnection, user_id: Uuid, id: Uuid) -> Result<Wishlist, RepoError> {
+  let wishlist = sqlx::query_as!(
+    WishlistRow,
+    r#"
+    SELECT id, user_id, name, created_at, updated_at
+    FROM wishlists
+    WHERE id = $1 AND user_id = $2
+    "#,
+    id,
+    user_id,
+  )
+  .fetch_one(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  let mut wishlists = with_items(conn, vec![wishlist.into()]).await?;
+  Ok(wishlists.remove(0))
+}
+
+#[async_trait]
+impl WishlistRepository for PgWishlistRepository {
+  async fn create(&self, user_id: Uuid, name: &str) -> Result<Wishlist, RepoError> {
+    let mut conn = self.pool.acquire().await.map_err(map_sqlx_err)?;
+    sqlx::query!("SELECT id FROM users WHERE id = $1", user_id)
+      .fetch_one(&mut *conn)
+      .await
+      .map_err(map_sqlx_err)?;
+    let wishlist = sqlx::query_as!(
+      WishlistRow,
+      r#"
+      INSERT INTO wishlists (user_id, name)
+      VALUES ($1, $2)
+      RETURNING id, user_id, name, created_at, updated_at
+      "#,
+      user_id,
+      name,
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(wishlist.into())
+  }
+
+  async fn list(&self, user_id: Uuid) -> Result<Vec<Wishlist>, RepoError> {
+    let mut conn = self.pool.acquire().await.map_err(map_sqlx_err)?;
+    sqlx::query!("SELECT id FROM users WHERE id = $1", user_id)
+      .fetch_one(&mut *conn)
+      .await
+      .map_err(map_sqlx_err)?;
+    let rows = sqlx::query_as!(
+      WishlistRow,
+      r#"
+      SELECT id, user_id, name, created_at, updated_at
+      FROM wishlists
+      WHERE user_id = $1
+      ORDER BY name, id
+      "#,
+      user_id,
+    )
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+    let wishlists = rows.into_iter().map(Wishlist::from).collect();
+    with_items(&mut conn, wishlists).await
+  }
+
+  async fn get(&self, user_id: Uuid, id: Uuid) -> Result<Wishlist, RepoError> {
+    let mut conn = self.pool.acquire().await.map_err(map_sqlx_err)?;
+    load(&mut conn, user_id, id).await
+  }
+
+  async fn rename(&self, user_id: Uuid, id: Uuid, name: &str) -> Result<Wishlist, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    sqlx::query!(
+      r#"
+      UPDATE wishlists SET name = $3, updated_at = now()
+      WHERE id = $1 AND user_id = $2
+      RETURNING id
+      "#,
+      id,
+      user_id,
+      name,
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let wishlist = load(&mut tx, user_id, id).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(wishlist)
+  }
+
+  async fn delete(&self, user_id: Uuid, id: Uuid) -> Result<(), RepoError> {
+    let result = sqlx::query!(
+      "DELETE FROM wishlists WHERE id = $1 AND user_id = $2",
+      id,
+      user_id,
+    )
+    .execute(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+    if result.rows_affected() == 0 {
+      return Err(RepoError::NotFound);
+    }
+    Ok(())
+  }
+
+  async fn add_item(
+    &self,
+    user_id: Uuid,
+    id: Uuid,
+    product_id: Uuid,
+  ) -> Result<WishlistItem, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    touch_wishlist(&mut tx, user_id, id).await?;
+    let inserted = sqlx::query!(
+      r#"
+      INSERT INTO wishlist_items (wishlist_id, product_id, added_price_cents)
+      SELECT $1, id, product_price_at(id, now())
+      FROM products
+      WHERE id = $2
+      "#,
+      id,
+      product_id,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    if inserted.rows_affected() == 0 {
+      return Err(RepoError::Invalid(format!(
+        "product {product_id} does not exist"
+      )));
+    }
+    let item = items_of(&mut tx, &[id])
+      .await?
+      .into_iter()
+      .map(|(_, item)| item)
+      .find(|item| item.product_id == product_id)
+      .ok_or(RepoError::NotFound)?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(item)
+  }
+
+  async fn remove_item(&self, user_id: Uuid, id: Uuid, product_id: Uuid) -> Result<(), RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    touch_wishlist(&mut tx, user_id, id).await?;
+    let result = sqlx::query!(
+      "DELETE FROM wishlist_items WHERE wishlist_id = $1 AND product_id = $2",
+      id,
+      product_id,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    if result.rows_affected() == 0 {
+      return Err(RepoError::NotFound);
+    }
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(())
+  }
+
+  async fn move_to_cart(
+    &self,
+    user_id: Uuid,
+    id: Uuid,
+    product_id: Uuid,
+    quantity: i32,
+    ctx: &AuditContext,
+  ) -> Result<OrderItem, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    // Locking the user serializes cart creation, so a user has one cart.
+    sqlx::query!("SELECT id FROM users WHERE id = $1 FOR UPDATE", user_id)
+      .fetch_one(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?;
+    touch_wishlist(&mut tx, user_id, id).await?;
+    let removed = sqlx::query!(
+      "DELETE FROM wishlist_items WHERE wishlist_id = $1 AND product_id = $2",
+      id,
+      product_id,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    if removed.rows_affected() == 0 {
+      return Err(RepoError::NotFound);
+    }
+
+    let cart_id: Option<Uuid> = sqlx::query_scalar!(
+      r#"
+      SELECT id FROM orders
+      WHERE user_id = $1 AND status = $2
+      ORDER BY created_at, id
+      LIMIT 1
+      FOR UPDATE
+      "#,
+      user_id,
+      CART,
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let cart_id = match cart_id {
+      Some(cart_id) => cart_id,
+      None => {
+        let cart = NewOrder {
+          user_id,
+          status: CART.into(),
+          total_cents: 0,
+        };
+        orders_repo::insert_order(&mut tx, cart, ctx).await?.id
+      }
+    };
+    let item = orders_repo::insert_item(
+      &mut tx,
+      cart_id,
+      NewOrderItem {
+        product_id,
+        quantity,
+      },
+      ctx,
+    )
+    .await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(item)
+  }
+}
@@ -0,0 +1,106 @@
+use crate::adapters::memory::{MemoryStore, Tables};
+use crate::application::ports::{InventoryRepository, RepoError};
+use crate::domain::models::StockLevel;
+use async_trait::async_trait;
+use chrono::Utc;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct MemoryInventoryRepository {
+  store: MemoryStore,
+}
+
+impl MemoryInventoryRepository {
+  pub fn new(store: MemoryStore) -> Self {
+    Self { store }
+  }
+}
+
+impl Tables {
+  /// The stock of an existing product, zero until it is first set.
+  fn stock_level(&self, product_id: Uuid) -> Result<StockLevel, RepoError> {
+    let product = self.products.get(&product_id).ok_or(RepoError::NotFound)?;
+    Ok(self.stock.get(&product_id).cloned().unwrap_or(StockLevel {
+      product_id,
+      quantity: 0,
+      updated_at: product.created_at,
+    }))
+  }
+}
+
+#[async_trait]
+impl InventoryRepository for MemoryInventoryRepository {
+  async fn get(&self, product_id: Uuid) -> Result<StockLevel, RepoError> {
+    self.store.lock().stock_level(product_id)
+  }
+
+  async fn set(&self, product_id: Uuid, quantity: i64) -> Result<StockLevel, RepoError> {
+    let mut tables = self.store.lock();
+    tables.stock_level(product_id)?;
+    if quantity < 0 {
+      return Err(RepoError::Conflict);
+    }
+    let stock = StockLevel {
+      product_id,
+      quantity,
+      updated_at: Utc::now(),
+    };
+    tables.stock.insert(product_id, stock.clone());
+    Ok(stock)
+  }
+
+  async fn adjust(&self, product_id: Uuid, delta: i64) -> Result<StockLevel, RepoError> {
+    let mut tables = self.store.lock();
+    let current = tables.stock_level(product_id)?;
+    if current.quantity + delta < 0 {
+      return Err(RepoError::Conflict);
+    }
+    let stock = StockLevel {
+      product_id,
+      quantity: current.quantity + delta,
+      updated_at: Utc::now(),
+    };
+    tables.stock.insert(product_id, stock.clone());
+    Ok(stock)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::adapters::memory::products_repo::MemoryProductRepository;
+  use crate::application::ports::{NewProduct, ProductRepository};
+  use crate::domain::models::{Actor, AuditContext};
+
+  #[tokio::test]
+  async fn stock_never_drops_below_zero() {
+    let store = MemoryStore::new();
+    let ctx = AuditContext {
+      actor: Actor::Admin,
+      request_id: None,
+    };
+    let lamp = MemoryProductRepository::new(store.clone())
+      .create(
+        NewProduct {
+          sku: "lamp".into(),
+          name: "Lamp".into(),
+          description: String::new(),
+          price_cents: 1000,
+        },
+        &ctx,
+      )
+      .await
+      .unwrap();
+    let repo = MemoryInventoryRepository::new(store);
+
+    assert_eq!(repo.get(lamp.id).await.unwrap().quantity, 0);
+    repo.set(lamp.id, 2).await.unwrap();
+    assert_eq!(repo.adjust(lamp.id, -2).await.unwrap().quantity, 0);
+    let err = repo.adjust(lamp.id, -1).await.unwrap_err();
+    assert!(matches!(err, RepoError::Conflict));
+    assert_eq!(repo.get(lamp.id).await.unwrap().quantity, 0);
+
+    let err = repo.adjust(Uuid::new_v4(), 1).await.unwrap_err();
+    assert!(matches!(err, RepoError::NotFound));
+  }
+}
@@ -0,0 +1,54 @@
+//! In-memory repositories for demos and tests without Postgres. They keep
+//! the constraints the database enforces: unique emails and SKUs are
+//! `Conflict`, and so are references to missing users or products and
+//! deleting rows that are still referenced or taking stock below zero.
+
+pub mod inventory_repo;
+pub mod orders_repo;
+pub mod products_repo;
+pub mod unit_of_work;
+pub mod users_repo;
+
+use crate::domain::models::{
+  Order, OrderHistoryEntry, OrderItem, Product, ProductPrice, StockLevel, User,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use uuid::Uuid;
+
+/// Tables shared by the in-memory repositories built from it; clones share
+/// the same data.
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+  tables: Arc<Mutex<Tables>>,
+  /// Held by the unit of work in progress, so units of work take turns.
+  units: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl MemoryStore {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Every repository method works under this lock, which makes each of
+  /// them atomic. It is never held across an `.await`.
+  fn lock(&self) -> MutexGuard<'_, Tables> {
+    self.tables.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+}
+
+#[derive(Clone, Default, PartialEq)]
+struct Tables {
+  users: HashMap<Uuid, User>,
+  /// `price_cents` is filled in from `prices` when a product is read.
+  products: HashMap<Uuid, Product>,
+  /// Price history per product, ordered by `valid_from`.
+  prices: HashMap<Uuid, Vec<ProductPrice>>,
+  orders: HashMap<Uuid, Order>,
+  /// In insertion order.
+  order_items: Vec<OrderItem>,
+  /// Kept after the order is deleted, like `order_history`.
+  order_history: HashMap<Uuid, Vec<OrderHistoryEntry>>,
+  /// Products without an entry have a stock of zero.
+  stock: HashMap<Uuid, StockLevel>,
+}
@@ -0,0 +1,288 @@
+use crate::adapters::memory::{MemoryStore, Tables};
+use crate::application::ports::{NewOrder, NewOrderItem, OrderRepository, RepoError, UpdateOrder};
+use crate::domain::models::{AuditContext, Order, OrderHistoryEntry, OrderItem};
+use crate::domain::order_history;
+use async_trait::async_trait;
+use chrono::Utc;
+use std::cmp::Reverse;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct MemoryOrderRepository {
+  store: MemoryStore,
+}
+
+impl MemoryOrderRepository {
+  pub fn new(store: MemoryStore) -> Self {
+    Self { store }
+  }
+}
+
+impl Tables {
+  /// Appends the change from `before` to `after` to the order's history,
+  /// unless no tracked field changed.
+  fn append_history(
+    &mut self,
+    order_id: Uuid,
+    before: Option<&Order>,
+    after: Option<&Order>,
+    ctx: &AuditContext,
+  ) {
+    let changes = order_history::diff(before, after);
+    if changes.is_empty() {
+      return;
+    }
+    let action = match (before, after) {
+      (None, _) => "created",
+      (_, None) => "deleted",
+      _ => "updated",
+    };
+    let history = self.order_history.entry(order_id).or_default();
+    history.push(OrderHistoryEntry {
+      order_id,
+      version: history.len() as i32 + 1,
+      action: action.into(),
+      actor: ctx.actor.to_string(),
+      occurred_at: after.map_or_else(Utc::now, |order| order.updated_at),
+      changes,
+    });
+  }
+}
+
+#[async_trait]
+impl OrderRepository for MemoryOrderRepository {
+  async fn create(&self, input: NewOrder, ctx: &AuditContext) -> Result<Order, RepoError> {
+    let mut tables = self.store.lock();
+    if !tables.users.contains_key(&input.user_id) {
+      return Err(RepoError::Conflict);
+    }
+    let now = Utc::now();
+    let order = Order {
+      id: Uuid::new_v4(),
+      user_id: input.user_id,
+      status: input.status,
+      total_cents: input.total_cents,
+      created_at: now,
+      updated_at: now,
+    };
+    tables.orders.insert(order.id, order.clone());
+    tables.append_history(order.id, None, Some(&order), ctx);
+    Ok(order)
+  }
+
+  async fn list(&self) -> Result<Vec<Order>, RepoError> {
+    let mut orders: Vec<Order> = self.store.lock().orders.values().cloned().collect();
+    orders.sort_by_key(|order| Reverse(order.created_at));
+    Ok(orders)
+  }
+
+  async fn get(&self, id: Uuid) -> Result<Order, RepoError> {
+    let tables = self.store.lock();
+    tables.orders.get(&id).cloned().ok_or(RepoError::NotFound)
+  }
+
+  async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateOrder,
+    ctx: &AuditContext,
+  ) -> Result<Order, RepoError> {
+    let mut tables = self.store.lock();
+    let order = tables.orders.get_mut(&id).ok_or(RepoError::NotFound)?;
+    let previous = order.clone();
+    if let Some(status) = input.status {
+      order.status = status;
+    }
+    if let Some(total_cents) = input.total_cents {
+      order.total_cents = total_cents;
+    }
+    order.updated_at = Utc::now();
+    let order = order.clone();
+    tables.append_history(id, Some(&previous), Some(&order), ctx);
+    Ok(order)
+  }
+
+  async fn delete(&self, id: Uuid, ctx: &AuditContext) -> Result<(), RepoError> {
+    let mut tables = self.store.lock();
+    let order = tables.orders.remove(&id).ok_or(RepoError::NotFound)?;
+    // Items go with their order.
+    tables.order_items.retain(|item| item.order_id != id);
+    tables.append_history(id, Some(&order), None, ctx);
+    Ok(())
+  }
+
+  async fn add_item(
+    &self,
+    order_id: Uuid,
+    input: NewOrderItem,
+    ctx: &AuditContext,
+  ) -> Result<OrderItem, RepoError> {
+    let mut tables = self.store.lock();
+    if !tables.orders.contains_key(&order_id) {
+      return Err(RepoError::NotFound);
+    }
+    let price_cents = tables
+      .product(input.product_id)
+      .map_err(|_| RepoError::Conflict)?
+      .price_cents;
+    let now = Utc::now();
+    let item = OrderItem {
+      id: Uuid::new_v4(),
+      order_id,
+      product_id: input.product_id,
+      quantity: input.quantity,
+      unit_price_cents: price_cents,
+      created_at: now,
+    };
+    tables.order_items.push(item.clone());
+
+    let order = tables.orders.get_mut(&order_id).expect("checked above");
+    let previous = order.clone();
+    order.total_cents += price_cents * i64::from(input.quantity);
+    order.updated_at = now;
+    let order = order.clone();
+    tables.append_history(order_id, Some(&previous), Some(&order), ctx);
+    Ok(item)
+  }
+
+  async fn list_items(&self, order_id: Uuid) -> Result<Vec<OrderItem>, RepoError> {
+    let tables = self.store.lock();
+    if !tables.orders.contains_key(&order_id) {
+      return Err(RepoError::NotFound);
+    }
+    Ok(
+      tables
+        .order_items
+        .iter()
+        .filter(|item| item.order_id == order_id)
+        .cloned()
+        .collect(),
+    )
+  }
+
+  async fn history(&self, order_id: Uuid) -> Result<Vec<OrderHistoryEntry>, RepoError> {
+    let tables = self.store.lock();
+    tables
+      .order_history
+      .get(&order_id)
+      .cloned()
+      .ok_or(RepoError::NotFound)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::adapters::memory::products_repo::MemoryProductRepository;
+  use crate::adapters::memory::users_repo::MemoryUserRepository;
+  use crate::application::ports::{NewProduct, NewUser, ProductRepository, UserRepository};
+  use crate::domain::models::Actor;
+
+  fn ctx() -> AuditContext {
+    AuditContext {
+      actor: Actor::Admin,
+      request_id: None,
+    }
+  }
+
+  fn new_order(user_id: Uuid) -> NewOrder {
+    NewOrder {
+      user_id,
+      status: "cart".into(),
+      total_cents: 0,
+    }
+  }
+
+  #[tokio::test]
+  async fn references_are_checked_like_foreign_keys() {
+    let store = MemoryStore::new();
+    let users = MemoryUserRepository::new(store.clone());
+    let products = MemoryProductRepository::new(store.clone());
+    let orders = MemoryOrderRepository::new(store);
+    let user = users
+      .create(
+        NewUser {
+          email: "ana@example.com".into(),
+          name: "Ana".into(),
+        },
+        &ctx(),
+      )
+      .await
+      .unwrap();
+    let lamp = products
+      .create(
+        NewProduct {
+          sku: "lamp".into(),
+          name: "Lamp".into(),
+          description: String::new(),
+          price_cents: 1000,
+        },
+        &ctx(),
+      )
+      .await
+      .unwrap();
+
+    let err = orders.create(new_order(Uuid::new_v4()), &ctx()).await;
+    assert!(matches!(err, Err(RepoError::Conflict)));
+    let order = orders.create(new_order(user.id), &ctx()).await.unwrap();
+    let line = |product_id| NewOrderItem {
+      product_id,
+      quantity: 2,
+    };
+    let err = orders
+      .add_item(order.id, line(Uuid::new_v4()), &ctx())
+      .await;
+    assert!(matches!(err, Err(RepoError::Conflict)));
+    let item = orders
+      .add_item(order.id, line(lamp.id), &ctx())
+      .await
+      .unwrap();
+    assert_eq!(item.unit_price_cents, 1000);
+    assert_eq!(orders.get(order.id).await.unwrap().total_cents, 2000);
+
+    // Referenced rows cannot go while the order has them.
+    let err = users.delete(user.id, &ctx()).await;
+    assert!(matches!(err, Err(RepoError::Conflict)));
+    let err = products.delete(lamp.id, &ctx()).await;
+    assert!(matches!(err, Err(RepoError::Conflict)));
+    orders.delete(order.id, &ctx()).await.unwrap();
+    products.delete(lamp.id, &ctx()).await.unwrap();
+    users.delete(user.id, &ctx()).await.unwrap();
+  }
+
+  #[tokio::test]
+  async fn history_outlives_the_order() {
+    let store = MemoryStore::new();
+    let user = MemoryUserRepository::new(store.clone())
+      .create(
+        NewUser {
+          email: "ana@example.com".into(),
+          name: "Ana".into(),
+        },
+        &ctx(),
+      )
+      .await
+      .unwrap();
+    let orders = MemoryOrderRepository::new(store);
+    let order = orders.create(new_order(user.id), &ctx()).await.unwrap();
+    let unchanged = UpdateOrder {
+      status: Some("cart".into()),
+      total_cents: None,
+    };
+    orders.update(order.id, unchanged, &ctx()).await.unwrap();
+    let shipped = UpdateOrder {
+      status: Some("shipped".into()),
+      total_cents: None,
+    };
+    orders.update(order.id, shipped, &ctx()).await.unwrap();
+    orders.delete(order.id, &ctx()).await.unwrap();
+
+    let history = orders.history(order.id).await.unwrap();
+    let actions: Vec<&str> = history.iter().map(|e| e.action.as_str()).collect();
+    assert_eq!(actions, ["created", "updated", "deleted"]);
+    assert_eq!(history[2].version, 3);
+    assert_eq!(history[1].actor, "admin");
+    let err = orders.history(Uuid::new_v4()).await.unwrap_err();
+    assert!(matches!(err, RepoError::NotFound));
+  }
+}
@@ -0,0 +1,337 @@
+use crate::adapters::memory::{MemoryStore, Tables};
+use crate::adapters::search;
+use crate::application::ports::{NewProduct, ProductRepository, RepoError, UpdateProduct};
+use crate::domain::catalog;
+use crate::domain::models::{AuditContext, Product, ProductPrice, ProductSearchHit, SimilarityHit};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::cmp::Reverse;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct MemoryProductRepository {
+  store: MemoryStore,
+}
+
+impl MemoryProductRepository {
+  pub fn new(store: MemoryStore) -> Self {
+    Self { store }
+  }
+}
+
+impl Tables {
+  /// The product at the price in effect now.
+  pub(super) fn product(&self, id: Uuid) -> Result<Product, RepoError> {
+    let mut product = self.products.get(&id).cloned().ok_or(RepoError::NotFound)?;
+    let prices = self.prices.get(&id).map(Vec::as_slice).unwrap_or_default();
+    if let Some(price) = catalog::price_at(prices, Utc::now()) {
+      product.price_cents = price.price_cents;
+    }
+    Ok(product)
+  }
+
+  fn all_products(&self) -> Vec<Product> {
+    let mut products: Vec<Product> = self
+      .products
+      .keys()
+      .map(|id| self.product(*id).expect("listed product exists"))
+      .collect();
+    products.sort_by_key(|product| Reverse(product.created_at));
+    products
+  }
+
+  fn sku_taken(&self, sku: &str, except: Option<Uuid>) -> bool {
+    self
+      .products
+      .values()
+      .any(|product| product.sku == sku && Some(product.id) != except)
+  }
+
+  /// Sets the price of `product_id` from `at` until its next price change,
+  /// closing the range in effect at `at`.
+  fn set_price(&mut self, product_id: Uuid, price_cents: i64, at: DateTime<Utc>) -> ProductPrice {
+    let prices = self.prices.entry(product_id).or_default();
+    if let Some(existing) = prices.iter_mut().find(|price| price.valid_from == at) {
+      existing.price_cents = price_cents;
+      return existing.clone();
+    }
+    for price in prices.iter_mut() {
+      if price.valid_from < at && price.valid_to.is_none_or(|to| to > at) {
+        price.valid_to = Some(at);
+      }
+    }
+    let price = ProductPrice {
+      id: Uuid::new_v4(),
+      product_id,
+      price_cents,
+      valid_from: at,
+      valid_to: prices
+        .iter()
+        .map(|price| price.valid_from)
+        .filter(|from| *from > at)
+        .min(),
+      created_at: Utc::now(),
+    };
+    prices.push(price.clone());
+    prices.sort_by_key(|price| price.valid_from);
+    price
+  }
+}
+
+#[async_trait]
+impl ProductRepository for MemoryProductRepository {
+  async fn create(&self, input: NewProduct, _ctx: &AuditContext) -> Result<Product, RepoError> {
+    let mut tables = self.store.lock();
+    if tables.sku_taken(&input.sku, None) {
+      return Err(RepoError::Conflict);
+    }
+    let now = Utc::now();
+    let product = Product {
+      id: Uuid::new_v4(),
+      sku: input.sku,
+      name: input.name,
+      description: input.description,
+      price_cents: input.price_cents,
+      rating_average: None,
+      rating_count: 0,
+      created_at: now,
+      updated_at: now,
+    };
+    tables.products.insert(product.id, product.clone());
+    tables.set_price(product.id, input.price_cents, now);
+    Ok(product)
+  }
+
+  async fn list(&self) -> Result<Vec<Product>, RepoError> {
+    Ok(self.store.lock().all_products())
+  }
+
+  async fn get(&self, id: Uuid) -> Result<Product, RepoError> {
+    self.store.lock().product(id)
+  }
+
+  async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateProduct,
+    _ctx: &AuditContext,
+  ) -> Result<Product, RepoError> {
+    let mut tables = self.store.lock();
+    tables.product(id)?;
+    if let Some(sku) = &input.sku {
+      if tables.sku_taken(sku, Some(id)) {
+        return Err(RepoError::Conflict);
+      }
+    }
+    let now = Utc::now();
+    let product = tables.products.get_mut(&id).expect("checked above");
+    if let Some(sku) = input.sku {
+      product.sku = sku;
+    }
+    if let Some(name) = input.name {
+      product.name = name;
+    }
+    if let Some(description) = input.description {
+      product.description = description;
+    }
+    product.updated_at = now;
+    if let Some(price_cents) = input.price_cents {
+      tables.set_price(id, price_cents, now);
+    }
+    tables.product(id)
+  }
+
+  async fn delete(&self, id: Uuid, _ctx: &AuditContext) -> Result<(), RepoError> {
+    let mut tables = self.store.lock();
+    tables.product(id)?;
+    // Order items restrict deleting their product.
+    if tables.order_items.iter().any(|item| item.product_id == id) {
+      return Err(RepoError::Conflict);
+    }
+    tables.products.remove(&id);
+    tables.prices.remove(&id);
+    tables.stock.remove(&id);
+    Ok(())
+  }
+
+  async fn list_by_category(
+    &self,
+    _slug: &str,
+    _include_descendants: bool,
+  ) -> Result<Vec<Product>, RepoError> {
+    // Categories are not kept in memory, so no slug is known.
+    Err(RepoError::NotFound)
+  }
+
+  async fn search(
+    &self,
+    tsquery: &str,
+    limit: i64,
+    offset: i64,
+  ) -> Result<(Vec<ProductSearchHit>, i64), RepoError> {
+    let products = self.store.lock().all_products();
+    Ok(search::search_products(products, tsquery, limit, offset))
+  }
+
+  async fn fuzzy_search(
+    &self,
+    text: &str,
+    threshold: f32,
+    limit: i64,
+  ) -> Result<Vec<SimilarityHit<Product>>, RepoError> {
+    let products = self.store.lock().all_products();
+    Ok(search::fuzzy_hits(
+      products,
+      text,
+      threshold,
+      limit,
+      |product| [&product.sku, &product.name],
+      |product| (product.name.clone(), product.id),
+    ))
+  }
+
+  async fn price_history(&self, product_id: Uuid) -> Result<Vec<ProductPrice>, RepoError> {
+    let tables = self.store.lock();
+    tables.product(product_id)?;
+    Ok(tables.prices.get(&product_id).cloned().unwrap_or_default())
+  }
+
+  async fn schedule_price(
+    &self,
+    product_id: Uuid,
+    price_cents: i64,
+    valid_from: DateTime<Utc>,
+    _ctx: &AuditContext,
+  ) -> Result<ProductPrice, RepoError> {
+    let mut tables = self.store.lock();
+    tables.product(product_id)?;
+    Ok(tables.set_price(product_id, price_cents, valid_from))
+  }
+
+  async fn cancel_scheduled_price(
+    &self,
+    product_id: Uuid,
+    price_id: Uuid,
+    _ctx: &AuditContext,
+  ) -> Result<(), RepoError> {
+    let mut tables = self.store.lock();
+    tables.product(product_id)?;
+    let prices = tables.prices.entry(product_id).or_default();
+    let index = prices
+      .iter()
+      .position(|price| price.id == price_id)
+      .ok_or(RepoError::NotFound)?;
+    if prices[index].valid_from <= Utc::now() {
+      return Err(RepoError::Conflict);
+    }
+
+    // The preceding range takes over the cancelled one.
+    let cancelled = prices.remove(index);
+    for price in prices.iter_mut() {
+      if price.valid_to == Some(cancelled.valid_from) {
+        price.valid_to = cancelled.valid_to;
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::domain::models::Actor;
+  use chrono::Duration;
+
+  fn ctx() -> AuditContext {
+    AuditContext {
+      actor: Actor::Admin,
+      request_id: None,
+    }
+  }
+
+  fn new_product(sku: &str, name: &str, price_cents: i64) -> NewProduct {
+    NewProduct {
+      sku: sku.into(),
+      name: name.into(),
+      description: String::new(),
+      price_cents,
+    }
+  }
+
+  #[tokio::test]
+  async fn skus_are_unique() {
+    let repo = MemoryProductRepository::new(MemoryStore::new());
+    repo
+      .create(new_product("lamp", "Lamp", 1000), &ctx())
+      .await
+      .unwrap();
+    let err = repo
+      .create(new_product("lamp", "Other lamp", 1000), &ctx())
+      .await
+      .unwrap_err();
+    assert!(matches!(err, RepoError::Conflict));
+  }
+
+  #[tokio::test]
+  async fn prices_are_kept_as_history() {
+    let repo = MemoryProductRepository::new(MemoryStore::new());
+    let lamp = repo
+      .create(new_product("lamp", "Lamp", 1000), &ctx())
+      .await
+      .unwrap();
+    let update = UpdateProduct {
+      sku: None,
+      name: None,
+      description: None,
+      price_cents: Some(1200),
+    };
+    assert_eq!(
+      repo
+        .update(lamp.id, update, &ctx())
+        .await
+        .unwrap()
+        .price_cents,
+      1200
+    );
+    let later = Utc::now() + Duration::days(1);
+    let scheduled = repo
+      .schedule_price(lamp.id, 900, later, &ctx())
+      .await
+      .unwrap();
+    let history = repo.price_history(lamp.id).await.unwrap();
+    let prices: Vec<i64> = history.iter().map(|price| price.price_cents).collect();
+    assert_eq!(prices, [1000, 1200, 900]);
+    assert_eq!(history[1].valid_to, Some(later));
+    assert_eq!(repo.get(lamp.id).await.unwrap().price_cents, 1200);
+
+    let err = repo
+      .cancel_scheduled_price(lamp.id, history[1].id, &ctx())
+      .await
+      .unwrap_err();
+    assert!(matches!(err, RepoError::Conflict));
+    repo
+      .cancel_scheduled_price(lamp.id, scheduled.id, &ctx())
+      .await
+      .unwrap();
+    let history = repo.price_history(lamp.id).await.unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[1].valid_to, None);
+  }
+
+  #[tokio::test]
+  async fn search_matches_word_prefixes() {
+    let repo = MemoryProductRepository::new(MemoryStore::new());
+    for (sku, name) in [("jacket", "Blue jacket"), ("shirt", "Blue shirt")] {
+      repo
+        .create(new_product(sku, name, 1000), &ctx())
+        .await
+        .unwrap();
+    }
+    let (hits, total) = repo.search("blue:* & jack:*", 10, 0).await.unwrap();
+    assert_eq!(total, 1);
+    assert_eq!(hits[0].snippet, "<mark>Blue</mark> <mark>jacket</mark> ");
+    let (hits, total) = repo.search("blue:*", 1, 1).await.unwrap();
+    assert_eq!(total, 2);
+    assert_eq!(hits.len(), 1);
+  }
+}
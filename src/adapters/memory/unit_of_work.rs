@@ -0,0 +1,148 @@
+use crate::adapters::memory::inventory_repo::MemoryInventoryRepository;
+use crate::adapters::memory::orders_repo::MemoryOrderRepository;
+use crate::adapters::memory::{MemoryStore, Tables};
+use crate::application::ports::{RepoError, UnitOfWork, UnitOfWorkFactory};
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OwnedMutexGuard;
+
+#[derive(Clone)]
+pub struct MemoryUnitOfWorkFactory {
+  store: MemoryStore,
+}
+
+impl MemoryUnitOfWorkFactory {
+  pub fn new(store: MemoryStore) -> Self {
+    Self { store }
+  }
+}
+
+#[async_trait]
+impl UnitOfWorkFactory for MemoryUnitOfWorkFactory {
+  type Work = MemoryUnitOfWork;
+
+  async fn begin(&self) -> Result<MemoryUnitOfWork, RepoError> {
+    let turn = self.store.units.clone().lock_owned().await;
+    let snapshot = self.store.lock().clone();
+    let staged = MemoryStore {
+      tables: Arc::new(Mutex::new(snapshot.clone())),
+      units: Default::default(),
+    };
+    Ok(MemoryUnitOfWork {
+      committed: self.store.clone(),
+      snapshot,
+      orders: MemoryOrderRepository::new(staged.clone()),
+      inventory: MemoryInventoryRepository::new(staged.clone()),
+      staged,
+      _turn: turn,
+    })
+  }
+}
+
+/// Repositories over a copy of the tables, which replaces them on commit.
+/// Units of work take turns, but a write from outside one since it began
+/// fails the commit with `Conflict` rather than being lost. Once finished,
+/// its repositories find the tables empty.
+pub struct MemoryUnitOfWork {
+  committed: MemoryStore,
+  /// The tables as the copy was taken.
+  snapshot: Tables,
+  staged: MemoryStore,
+  orders: MemoryOrderRepository,
+  inventory: MemoryInventoryRepository,
+  _turn: OwnedMutexGuard<()>,
+}
+
+impl MemoryUnitOfWork {
+  fn finish(&self) -> Tables {
+    std::mem::take(&mut *self.staged.lock())
+  }
+}
+
+impl Drop for MemoryUnitOfWork {
+  fn drop(&mut self) {
+    self.finish();
+  }
+}
+
+#[async_trait]
+impl UnitOfWork for MemoryUnitOfWork {
+  type Orders = MemoryOrderRepository;
+  type Inventory = MemoryInventoryRepository;
+
+  fn orders(&self) -> &MemoryOrderRepository {
+    &self.orders
+  }
+
+  fn inventory(&self) -> &MemoryInventoryRepository {
+    &self.inventory
+  }
+
+  async fn commit(self) -> Result<(), RepoError> {
+    let staged = self.finish();
+    let mut tables = self.committed.lock();
+    if *tables != self.snapshot {
+      return Err(RepoError::Conflict);
+    }
+    *tables = staged;
+    Ok(())
+  }
+
+  async fn rollback(self) -> Result<(), RepoError> {
+    // Dropping discards the copy.
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::adapters::memory::products_repo::MemoryProductRepository;
+  use crate::application::ports::{InventoryRepository, NewProduct, ProductRepository};
+  use crate::domain::models::{Actor, AuditContext};
+
+  #[tokio::test]
+  async fn changes_show_once_committed_and_never_over_other_writes() {
+    let store = MemoryStore::new();
+    let ctx = AuditContext {
+      actor: Actor::Admin,
+      request_id: None,
+    };
+    let lamp = MemoryProductRepository::new(store.clone())
+      .create(
+        NewProduct {
+          sku: "lamp".into(),
+          name: "Lamp".into(),
+          description: String::new(),
+          price_cents: 1000,
+        },
+        &ctx,
+      )
+      .await
+      .unwrap();
+    let inventory = MemoryInventoryRepository::new(store.clone());
+    inventory.set(lamp.id, 5).await.unwrap();
+    let units = MemoryUnitOfWorkFactory::new(store);
+
+    let work = units.begin().await.unwrap();
+    work.inventory().adjust(lamp.id, -2).await.unwrap();
+    assert_eq!(inventory.get(lamp.id).await.unwrap().quantity, 5);
+    let staged = work.inventory().clone();
+    work.commit().await.unwrap();
+    assert_eq!(inventory.get(lamp.id).await.unwrap().quantity, 3);
+    let err = staged.get(lamp.id).await.unwrap_err();
+    assert!(matches!(err, RepoError::NotFound));
+
+    let work = units.begin().await.unwrap();
+    work.inventory().adjust(lamp.id, -1).await.unwrap();
+    work.rollback().await.unwrap();
+    assert_eq!(inventory.get(lamp.id).await.unwrap().quantity, 3);
+
+    let work = units.begin().await.unwrap();
+    work.inventory().adjust(lamp.id, -1).await.unwrap();
+    inventory.set(lamp.id, 10).await.unwrap();
+    let err = work.commit().await.unwrap_err();
+    assert!(matches!(err, RepoError::Conflict));
+    assert_eq!(inventory.get(lamp.id).await.unwrap().quantity, 10);
+  }
+}
@@ -0,0 +1,176 @@
+use crate::adapters::memory::MemoryStore;
+use crate::adapters::search;
+use crate::application::ports::{NewUser, RepoError, UpdateUser, UserRepository};
+use crate::domain::models::{AuditContext, SimilarityHit, User};
+use async_trait::async_trait;
+use chrono::Utc;
+use std::cmp::Reverse;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct MemoryUserRepository {
+  store: MemoryStore,
+}
+
+impl MemoryUserRepository {
+  pub fn new(store: MemoryStore) -> Self {
+    Self { store }
+  }
+}
+
+#[async_trait]
+impl UserRepository for MemoryUserRepository {
+  async fn create(&self, input: NewUser, _ctx: &AuditContext) -> Result<User, RepoError> {
+    let mut tables = self.store.lock();
+    if tables.users.values().any(|user| user.email == input.email) {
+      return Err(RepoError::Conflict);
+    }
+    let now = Utc::now();
+    let user = User {
+      id: Uuid::new_v4(),
+      email: input.email,
+      name: input.name,
+      created_at: now,
+      updated_at: now,
+    };
+    tables.users.insert(user.id, user.clone());
+    Ok(user)
+  }
+
+  async fn list(&self) -> Result<Vec<User>, RepoError> {
+    let mut users: Vec<User> = self.store.lock().users.values().cloned().collect();
+    users.sort_by_key(|user| Reverse(user.created_at));
+    Ok(users)
+  }
+
+  async fn get(&self, id: Uuid) -> Result<User, RepoError> {
+    let tables = self.store.lock();
+    tables.users.get(&id).cloned().ok_or(RepoError::NotFound)
+  }
+
+  async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateUser,
+    _ctx: &AuditContext,
+  ) -> Result<User, RepoError> {
+    let mut tables = self.store.lock();
+    if !tables.users.contains_key(&id) {
+      return Err(RepoError::NotFound);
+    }
+    if let Some(email) = &input.email {
+      if tables
+        .users
+        .values()
+        .any(|user| user.id != id && &user.email == email)
+      {
+        return Err(RepoError::Conflict);
+      }
+    }
+    let user = tables.users.get_mut(&id).expect("checked above");
+    if let Some(email) = input.email {
+      user.email = email;
+    }
+    if let Some(name) = input.name {
+      user.name = name;
+    }
+    user.updated_at = Utc::now();
+    Ok(user.clone())
+  }
+
+  async fn delete(&self, id: Uuid, _ctx: &AuditContext) -> Result<(), RepoError> {
+    let mut tables = self.store.lock();
+    if !tables.users.contains_key(&id) {
+      return Err(RepoError::NotFound);
+    }
+    // Orders restrict deleting their user.
+    if tables.orders.values().any(|order| order.user_id == id) {
+      return Err(RepoError::Conflict);
+    }
+    tables.users.remove(&id);
+    Ok(())
+  }
+
+  async fn fuzzy_search(
+    &self,
+    text: &str,
+    threshold: f32,
+    limit: i64,
+  ) -> Result<Vec<SimilarityHit<User>>, RepoError> {
+    let users: Vec<User> = self.store.lock().users.values().cloned().collect();
+    Ok(search::fuzzy_hits(
+      users,
+      text,
+      threshold,
+      limit,
+      |user| [&user.email, &user.name],
+      |user| (user.email.clone(), user.id),
+    ))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::domain::models::Actor;
+
+  fn ctx() -> AuditContext {
+    AuditContext {
+      actor: Actor::Admin,
+      request_id: None,
+    }
+  }
+
+  fn new_user(email: &str, name: &str) -> NewUser {
+    NewUser {
+      email: email.into(),
+      name: name.into(),
+    }
+  }
+
+  #[tokio::test]
+  async fn emails_are_unique() {
+    let repo = MemoryUserRepository::new(MemoryStore::new());
+    let ana = repo
+      .create(new_user("ana@example.com", "Ana"), &ctx())
+      .await
+      .unwrap();
+    let ben = repo
+      .create(new_user("ben@example.com", "Ben"), &ctx())
+      .await
+      .unwrap();
+    let err = repo
+      .create(new_user("ana@example.com", "Other"), &ctx())
+      .await
+      .unwrap_err();
+    assert!(matches!(err, RepoError::Conflict));
+    let taken = UpdateUser {
+      email: Some("ana@example.com".into()),
+      name: None,
+    };
+    let err = repo
+      .update(ben.id, taken.clone(), &ctx())
+      .await
+      .unwrap_err();
+    assert!(matches!(err, RepoError::Conflict));
+    // Keeping one's own email is fine.
+    repo.update(ana.id, taken, &ctx()).await.unwrap();
+  }
+
+  #[tokio::test]
+  async fn fuzzy_search_tolerates_typos() {
+    let repo = MemoryUserRepository::new(MemoryStore::new());
+    for (email, name) in [
+      ("ana@example.com", "Ana García"),
+      ("bob@example.com", "Bob Marley"),
+    ] {
+      repo.create(new_user(email, name), &ctx()).await.unwrap();
+    }
+    let hits = repo.fuzzy_search("garcia", 0.5, 10).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].item.email, "ana@example.com");
+    let hits = repo.fuzzy_search("example", 0.5, 1).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].item.email, "ana@example.com");
+  }
+}
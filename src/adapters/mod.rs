@@ -1,2 +1,10 @@
+pub mod blob;
 pub mod db;
+pub mod memory;
+pub mod order_stream;
+pub mod pdf;
+pub(crate) mod search;
+pub mod sqlite;
+pub mod thumbnails;
 pub mod web;
+pub mod webhooks;
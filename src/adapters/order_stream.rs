@@ -0,0 +1,278 @@
+//! Live order events for `GET /orders/stream`.
+//!
+//! Order events written to the outbox are announced on the Postgres
+//! `order_events` channel when their transaction commits (see migration
+//! 0016), so every API instance hears every change, whichever instance made
+//! it. Each instance keeps the latest events in a bounded replay buffer for
+//! clients that reconnect with `Last-Event-ID`; the stream ids are outbox ids.
+
+use crate::adapters::db::outbox_repo::PgOutboxRepository;
+use crate::application::ports::{OutboxRepository, RepoError};
+use crate::domain::models::OutboxEvent;
+use serde_json::{json, Value};
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+pub const CHANNEL: &str = "order_events";
+/// Messages a slow subscriber may fall behind before it has to resync.
+const LIVE_CAPACITY: usize = 256;
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// One change to an order as sent to stream clients.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderStreamEvent {
+  /// Outbox id of the underlying domain event.
+  pub id: i64,
+  /// `order.created`, `order.updated` or `order.deleted`.
+  pub kind: &'static str,
+  /// Owner of the order, for filtering.
+  pub user_id: Uuid,
+  /// The order, or `{id, user_id}` once it is deleted.
+  pub data: Value,
+}
+
+impl OrderStreamEvent {
+  /// The stream event for an outbox event, if it is about an order.
+  pub fn from_outbox(event: &OutboxEvent) -> Option<Self> {
+    let payload = &event.payload;
+    let (kind, data) = match event.event_type.as_str() {
+      "OrderCreated" => ("order.created", payload["order"].clone()),
+      "OrderUpdated" | "OrderStatusChanged" => ("order.updated", payload["order"].clone()),
+      "OrderDeleted" => (
+        "order.deleted",
+        json!({ "id": payload["order_id"], "user_id": payload["user_id"] }),
+      ),
+      _ => return None,
+    };
+    let user_id = data["user_id"].as_str()?.parse().ok()?;
+    Some(Self {
+      id: event.id,
+      kind,
+      user_id,
+      data,
+    })
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderStreamMessage {
+  Event(OrderStreamEvent),
+  /// Events may have been missed; clients should reload `GET /orders`.
+  Resync,
+}
+
+/// What a new subscriber starts from.
+pub struct Subscription {
+  /// Buffered events after the client's `Last-Event-ID`.
+  pub replay: Vec<OrderStreamEvent>,
+  /// The client's last event is no longer buffered, so it may have missed
+  /// some.
+  pub resync: bool,
+  pub live: broadcast::Receiver<OrderStreamMessage>,
+}
+
+/// Fans order events out to the stream clients of this instance.
+pub struct OrderStream {
+  replay: Mutex<VecDeque<OrderStreamEvent>>,
+  replay_size: usize,
+  sender: broadcast::Sender<OrderStreamMessage>,
+}
+
+impl OrderStream {
+  pub fn new(replay_size: usize) -> Self {
+    let (sender, _) = broadcast::channel(LIVE_CAPACITY);
+    Self {
+      replay: Mutex::new(VecDeque::with_capacity(replay_size)),
+      replay_size,
+      sender,
+    }
+  }
+
+  pub fn publish(&self, event: OrderStreamEvent) {
+    let mut replay = self.replay.lock().expect("replay buffer lock");
+    if replay.len() == self.replay_size {
+      replay.pop_front();
+    }
+    replay.push_back(event.clone());
+    // Sent under the lock, so a subscriber sees each event either in its
+    // replay or live, never both or neither.
+    let _ = self.sender.send(OrderStreamMessage::Event(event));
+  }
+
+  /// Forgets the buffered events and tells live subscribers to resync, after
+  /// notifications may have been lost.
+  pub fn reset(&self) {
+    let mut replay = self.replay.lock().expect("replay buffer lock");
+    replay.clear();
+    let _ = self.sender.send(OrderStreamMessage::Resync);
+  }
+
+  /// Subscribes to new events, replaying the buffered ones after
+  /// `last_event_id` when the client is resuming.
+  pub fn subscribe(&self, last_event_id: Option<i64>) -> Subscription {
+    let replay = self.replay.lock().expect("replay buffer lock");
+    let live = self.sender.subscribe();
+    let Some(last_event_id) = last_event_id else {
+      return Subscription {
+        replay: Vec::new(),
+        resync: false,
+        live,
+      };
+    };
+    // Buffer order is commit order, which ids need not follow.
+    match replay.iter().position(|event| event.id == last_event_id) {
+      Some(position) => Subscription {
+        replay: replay.iter().skip(position + 1).cloned().collect(),
+        resync: false,
+        live,
+      },
+      None => Subscription {
+        replay: Vec::new(),
+        resync: true,
+        live,
+      },
+    }
+  }
+
+  /// Starts listening on [`CHANNEL`] and publishing what arrives. Returns
+  /// once the listener is subscribed, so no later change is missed.
+  pub async fn listen(self: Arc<Self>, pool: PgPool) -> Result<JoinHandle<()>, RepoError> {
+    let mut listener = PgListener::connect_with(&pool)
+      .await
+      .map_err(|err| RepoError::Unexpected(err.to_string()))?;
+    listener
+      .listen(CHANNEL)
+      .await
+      .map_err(|err| RepoError::Unexpected(err.to_string()))?;
+    let repo = PgOutboxRepository::new(pool);
+    Ok(tokio::spawn(async move {
+      loop {
+        match listener.try_recv().await {
+          Ok(Some(notification)) => {
+            let Ok(id) = notification.payload().parse::<i64>() else {
+              tracing::warn!(
+                payload = notification.payload(),
+                "bad order event notification"
+              );
+              continue;
+            };
+            match repo.get(id).await {
+              Ok(event) => {
+                if let Some(event) = OrderStreamEvent::from_outbox(&event) {
+                  self.publish(event);
+                }
+              }
+              Err(err) => {
+                tracing::error!(error = %err, id, "could not load order event");
+                self.reset();
+              }
+            }
+          }
+          // The connection dropped and is re-established on the next call;
+          // whatever was announced meanwhile is lost.
+          Ok(None) => {
+            tracing::warn!("order event listener reconnecting");
+            self.reset();
+          }
+          Err(err) => {
+            tracing::error!(error = %err, "order event listener failed");
+            tokio::time::sleep(RECONNECT_DELAY).await;
+          }
+        }
+      }
+    }))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::Utc;
+
+  fn event(id: i64) -> OrderStreamEvent {
+    OrderStreamEvent {
+      id,
+      kind: "order.updated",
+      user_id: Uuid::nil(),
+      data: json!({}),
+    }
+  }
+
+  fn ids(events: &[OrderStreamEvent]) -> Vec<i64> {
+    events.iter().map(|event| event.id).collect()
+  }
+
+  #[test]
+  fn outbox_order_events_map_to_stream_events() {
+    let user_id = Uuid::new_v4();
+    let mut outbox = OutboxEvent {
+      id: 7,
+      event_id: Uuid::new_v4(),
+      event_type: "OrderStatusChanged".into(),
+      aggregate_type: "order".into(),
+      aggregate_id: Uuid::new_v4(),
+      payload: json!({ "order": { "id": "o-1", "user_id": user_id }, "from": "paid" }),
+      attempts: 0,
+      created_at: Utc::now(),
+    };
+    let event = OrderStreamEvent::from_outbox(&outbox).unwrap();
+    assert_eq!(event.kind, "order.updated");
+    assert_eq!(event.user_id, user_id);
+    assert_eq!(event.data["id"], "o-1");
+
+    outbox.event_type = "OrderDeleted".into();
+    outbox.payload = json!({ "order_id": "o-1", "user_id": user_id });
+    let event = OrderStreamEvent::from_outbox(&outbox).unwrap();
+    assert_eq!(event.kind, "order.deleted");
+    assert_eq!(event.data, json!({ "id": "o-1", "user_id": user_id }));
+
+    outbox.event_type = "UserDeleted".into();
+    assert_eq!(OrderStreamEvent::from_outbox(&outbox), None);
+  }
+
+  #[test]
+  fn resuming_replays_buffered_events_in_arrival_order() {
+    let stream = OrderStream::new(3);
+    for id in [1, 3, 2, 4] {
+      stream.publish(event(id));
+    }
+    // Buffer holds 3, 2, 4.
+    let subscription = stream.subscribe(Some(3));
+    assert_eq!(ids(&subscription.replay), [2, 4]);
+    assert!(!subscription.resync);
+    let subscription = stream.subscribe(Some(4));
+    assert!(subscription.replay.is_empty());
+    assert!(!subscription.resync);
+    // Evicted.
+    let subscription = stream.subscribe(Some(1));
+    assert!(subscription.replay.is_empty());
+    assert!(subscription.resync);
+    let subscription = stream.subscribe(None);
+    assert!(subscription.replay.is_empty());
+    assert!(!subscription.resync);
+  }
+
+  #[tokio::test]
+  async fn subscribers_get_new_events_and_resets() {
+    let stream = OrderStream::new(10);
+    stream.publish(event(1));
+    let mut subscription = stream.subscribe(Some(1));
+    stream.publish(event(2));
+    stream.reset();
+    assert_eq!(
+      subscription.live.recv().await.unwrap(),
+      OrderStreamMessage::Event(event(2))
+    );
+    assert_eq!(
+      subscription.live.recv().await.unwrap(),
+      OrderStreamMessage::Resync
+    );
+    assert!(stream.subscribe(Some(2)).resync);
+  }
+}
@@ -0,0 +1,355 @@
+//! Minimal PDF 1.4 writer for invoices and packing slips.
+//!
+//! Documents only use the standard Helvetica fonts, which every viewer ships,
+//! so nothing needs to be embedded and the output is byte-for-byte
+//! deterministic for the same input.
+
+use crate::domain::invoicing::format_cents;
+use crate::domain::models::Invoice;
+use chrono::{DateTime, Utc};
+use std::fmt::Write;
+use uuid::Uuid;
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Font {
+  Regular,
+  Bold,
+}
+
+impl Font {
+  fn resource(self) -> &'static str {
+    match self {
+      Font::Regular => "F1",
+      Font::Bold => "F2",
+    }
+  }
+}
+
+/// Lays text out top to bottom, starting a new page when one fills up.
+struct Layout {
+  pages: Vec<String>,
+  y: f32,
+}
+
+impl Layout {
+  fn new() -> Self {
+    Self {
+      pages: vec![String::new()],
+      y: PAGE_HEIGHT - MARGIN,
+    }
+  }
+
+  fn advance(&mut self, height: f32) {
+    if self.y - height < MARGIN {
+      self.pages.push(String::new());
+      self.y = PAGE_HEIGHT - MARGIN;
+    }
+    self.y -= height;
+  }
+
+  /// Writes one row; each cell is placed at its x offset from the margin.
+  fn row(&mut self, font: Font, size: f32, cells: &[(f32, &str)]) {
+    self.advance(size * 1.5);
+    let page = self.pages.last_mut().expect("layout always has a page");
+    for (x, text) in cells {
+      // Writing to a String cannot fail.
+      let _ = writeln!(
+        page,
+        "BT /{} {size} Tf {:.2} {:.2} Td ({}) Tj ET",
+        font.resource(),
+        MARGIN + x,
+        self.y,
+        encode_text(text)
+      );
+    }
+  }
+
+  fn text(&mut self, font: Font, size: f32, text: &str) {
+    self.row(font, size, &[(0.0, text)]);
+  }
+
+  fn gap(&mut self, height: f32) {
+    self.advance(height);
+  }
+
+  fn rule(&mut self) {
+    self.advance(6.0);
+    let page = self.pages.last_mut().expect("layout always has a page");
+    let _ = writeln!(
+      page,
+      "{MARGIN:.2} {y:.2} m {:.2} {y:.2} l S",
+      PAGE_WIDTH - MARGIN,
+      y = self.y
+    );
+  }
+
+  fn finish(self) -> Vec<u8> {
+    write_document(&self.pages)
+  }
+}
+
+/// Escapes `text` for a PDF literal string in WinAnsi encoding. Bytes outside
+/// printable ASCII are written as octal escapes; characters WinAnsi cannot
+/// represent become `?`.
+fn encode_text(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  for c in text.chars() {
+    let byte = match c {
+      '€' => 0x80,
+      '\u{20}'..='\u{7e}' | '\u{a0}'..='\u{ff}' => c as u32 as u8,
+      _ => b'?',
+    };
+    match byte {
+      b'(' | b')' | b'\\' => {
+        out.push('\\');
+        out.push(byte as char);
+      }
+      0x20..=0x7e => out.push(byte as char),
+      _ => {
+        let _ = write!(out, "\\{byte:03o}");
+      }
+    }
+  }
+  out
+}
+
+fn write_document(pages: &[String]) -> Vec<u8> {
+  // Object layout: 1 catalog, 2 page tree, 3-4 fonts, then a page object and
+  // its content stream for every page.
+  let page_ids: Vec<usize> = (0..pages.len()).map(|i| 5 + i * 2).collect();
+  let mut objects = vec![
+    "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+    format!(
+      "<< /Type /Pages /Kids [{}] /Count {} >>",
+      page_ids
+        .iter()
+        .map(|id| format!("{id} 0 R"))
+        .collect::<Vec<_>>()
+        .join(" "),
+      pages.len()
+    ),
+    "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_string(),
+    "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+      .to_string(),
+  ];
+  for (content, page_id) in pages.iter().zip(&page_ids) {
+    objects.push(format!(
+      "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] \
+       /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+      page_id + 1
+    ));
+    objects.push(format!(
+      "<< /Length {} >>\nstream\n{content}endstream",
+      content.len()
+    ));
+  }
+
+  let mut out = String::from("%PDF-1.4\n");
+  let mut offsets = Vec::with_capacity(objects.len());
+  for (i, object) in objects.iter().enumerate() {
+    offsets.push(out.len());
+    let _ = write!(out, "{} 0 obj\n{object}\nendobj\n", i + 1);
+  }
+  let xref = out.len();
+  let _ = write!(out, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+  for offset in offsets {
+    let _ = writeln!(out, "{offset:010} 00000 n ");
+  }
+  let _ = write!(
+    out,
+    "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+    objects.len() + 1
+  );
+  out.into_bytes()
+}
+
+/// Renders an invoice or credit note.
+pub fn render_invoice(invoice: &Invoice) -> Vec<u8> {
+  let title = if invoice.kind == "credit_note" {
+    "Credit note"
+  } else {
+    "Invoice"
+  };
+  let address = &invoice.billing_address;
+  let mut layout = Layout::new();
+
+  layout.text(Font::Bold, 20.0, &format!("{title} {}", invoice.number));
+  layout.text(
+    Font::Regular,
+    10.0,
+    &format!("Issued {}", invoice.issued_at.format("%Y-%m-%d")),
+  );
+  layout.text(Font::Regular, 10.0, &format!("Order {}", invoice.order_id));
+  layout.gap(10.0);
+
+  layout.text(Font::Bold, 11.0, "Bill to");
+  layout.text(Font::Regular, 10.0, &address.name);
+  layout.text(Font::Regular, 10.0, &address.line1);
+  if let Some(line2) = &address.line2 {
+    layout.text(Font::Regular, 10.0, line2);
+  }
+  layout.text(
+    Font::Regular,
+    10.0,
+    &format!("{} {}", address.postal_code, address.city),
+  );
+  layout.text(Font::Regular, 10.0, &address.country);
+  layout.gap(10.0);
+
+  layout.row(
+    Font::Bold,
+    10.0,
+    &[
+      (0.0, "Description"),
+      (250.0, "Qty"),
+      (290.0, "Unit"),
+      (350.0, "Net"),
+      (410.0, "Tax"),
+      (460.0, "Total"),
+    ],
+  );
+  layout.rule();
+  for line in &invoice.lines {
+    let quantity = line.quantity.to_string();
+    let unit = format_cents(line.unit_price_cents);
+    let net = format_cents(line.net_cents);
+    let tax = format_cents(line.tax_cents);
+    let total = format_cents(line.total_cents);
+    layout.row(
+      Font::Regular,
+      10.0,
+      &[
+        (0.0, &line.description),
+        (250.0, &quantity),
+        (290.0, &unit),
+        (350.0, &net),
+        (410.0, &tax),
+        (460.0, &total),
+      ],
+    );
+  }
+  layout.rule();
+  layout.text(
+    Font::Regular,
+    10.0,
+    &format!("Net: {}", format_cents(invoice.net_cents)),
+  );
+  layout.text(
+    Font::Regular,
+    10.0,
+    &format!(
+      "Tax ({}%): {}",
+      format_cents(i64::from(invoice.tax_rate_bps)),
+      format_cents(invoice.tax_cents)
+    ),
+  );
+  layout.text(
+    Font::Bold,
+    12.0,
+    &format!("Total: {}", format_cents(invoice.total_cents)),
+  );
+  layout.finish()
+}
+
+#[derive(Debug, Clone)]
+pub struct PackingSlip {
+  pub order_id: Uuid,
+  pub ordered_at: DateTime<Utc>,
+  pub customer_name: String,
+  pub customer_email: String,
+  pub lines: Vec<PackingSlipLine>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PackingSlipLine {
+  pub sku: String,
+  pub name: String,
+  pub quantity: i32,
+}
+
+/// Renders the warehouse packing slip: what to pick, without prices.
+pub fn render_packing_slip(slip: &PackingSlip) -> Vec<u8> {
+  let mut layout = Layout::new();
+
+  layout.text(Font::Bold, 20.0, "Packing slip");
+  layout.text(Font::Regular, 10.0, &format!("Order {}", slip.order_id));
+  layout.text(
+    Font::Regular,
+    10.0,
+    &format!("Ordered {}", slip.ordered_at.format("%Y-%m-%d")),
+  );
+  layout.gap(10.0);
+
+  layout.text(Font::Bold, 11.0, "Ship to");
+  layout.text(Font::Regular, 10.0, &slip.customer_name);
+  layout.text(Font::Regular, 10.0, &slip.customer_email);
+  layout.gap(10.0);
+
+  layout.row(
+    Font::Bold,
+    10.0,
+    &[
+      (0.0, "SKU"),
+      (140.0, "Item"),
+      (420.0, "Qty"),
+      (460.0, "Packed"),
+    ],
+  );
+  layout.rule();
+  for line in &slip.lines {
+    let quantity = line.quantity.to_string();
+    layout.row(
+      Font::Regular,
+      10.0,
+      &[
+        (0.0, &line.sku),
+        (140.0, &line.name),
+        (420.0, &quantity),
+        (460.0, "[  ]"),
+      ],
+    );
+  }
+  layout.rule();
+  let units: i32 = slip.lines.iter().map(|l| l.quantity).sum();
+  layout.text(Font::Bold, 10.0, &format!("Total units: {units}"));
+  layout.finish()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn escapes_delimiters_and_encodes_latin1() {
+    assert_eq!(encode_text("a (b) \\c"), "a \\(b\\) \\\\c");
+    assert_eq!(encode_text("Málaga €5 ✓"), "M\\341laga \\2005 ?");
+  }
+
+  #[test]
+  fn xref_offsets_point_at_objects() {
+    let pdf = String::from_utf8(write_document(&["BT ET\n".to_string()])).unwrap();
+    let xref_at: usize = pdf
+      .rsplit("startxref\n")
+      .next()
+      .unwrap()
+      .lines()
+      .next()
+      .unwrap()
+      .parse()
+      .unwrap();
+    assert!(pdf[xref_at..].starts_with("xref"));
+    let offsets: Vec<usize> = pdf[xref_at..]
+      .lines()
+      .skip(3)
+      .take(6)
+      .map(|l| l[..10].parse().unwrap())
+      .collect();
+    for (i, offset) in offsets.iter().enumerate() {
+      assert!(pdf[*offset..].starts_with(&format!("{} 0 obj", i + 1)));
+    }
+  }
+}
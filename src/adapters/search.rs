@@ -0,0 +1,205 @@
+//! Stand-ins for the Postgres text search that storage without it (memory,
+//! SQLite) ranks with instead: pg_trgm word similarity and prefix
+//! `to_tsquery` matching.
+
+use crate::domain::models::{Product, ProductSearchHit, SimilarityHit};
+use std::collections::HashSet;
+
+type Trigram = [char; 3];
+
+/// Lowercased runs of letters and digits, the words pg_trgm and the text
+/// search parser see.
+fn words(text: &str) -> Vec<String> {
+  text
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|word| !word.is_empty())
+    .map(str::to_lowercase)
+    .collect()
+}
+
+/// Trigrams of `word` padded like pg_trgm does, with two spaces in front and
+/// one behind.
+fn trigrams(word: &str) -> Vec<Trigram> {
+  let padded: Vec<char> = format!("  {word} ").chars().collect();
+  padded.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
+/// Like pg_trgm's `word_similarity(needle, haystack)`: the best similarity
+/// between the trigrams of `needle` and any continuous extent of the
+/// trigrams of `haystack`.
+pub(crate) fn word_similarity(needle: &str, haystack: &str) -> f32 {
+  let needle: HashSet<Trigram> = words(needle).iter().flat_map(|w| trigrams(w)).collect();
+  let haystack: Vec<Trigram> = words(haystack).iter().flat_map(|w| trigrams(w)).collect();
+  let mut best = 0.0f32;
+  for start in 0..haystack.len() {
+    let mut extent = HashSet::new();
+    for trigram in &haystack[start..] {
+      extent.insert(*trigram);
+      let shared = needle.intersection(&extent).count();
+      let similarity = shared as f32 / (needle.len() + extent.len() - shared) as f32;
+      best = best.max(similarity);
+    }
+  }
+  best
+}
+
+/// The terms of a `catalog::prefix_tsquery` expression such as
+/// `blue:* & jack:*`.
+fn prefix_terms(tsquery: &str) -> Vec<String> {
+  tsquery
+    .split('&')
+    .map(|term| term.trim().trim_end_matches(":*").to_lowercase())
+    .filter(|term| !term.is_empty())
+    .collect()
+}
+
+/// Ranks `text` against a prefix tsquery: `None` unless every term starts
+/// some word, otherwise the number of words that match a term.
+pub(crate) fn prefix_rank(tsquery: &str, text: &str) -> Option<f32> {
+  let terms = prefix_terms(tsquery);
+  let words = words(text);
+  let all_found = terms
+    .iter()
+    .all(|term| words.iter().any(|word| word.starts_with(term.as_str())));
+  if terms.is_empty() || !all_found {
+    return None;
+  }
+  let matching = words
+    .iter()
+    .filter(|word| terms.iter().any(|term| word.starts_with(term.as_str())))
+    .count();
+  Some(matching as f32)
+}
+
+/// HTML-escapes `text` and wraps words matching the tsquery in `<mark>`.
+pub(crate) fn highlight(tsquery: &str, text: &str) -> String {
+  let terms = prefix_terms(tsquery);
+  let mut snippet = String::new();
+  let mut word = String::new();
+  let flush = |word: &mut String, snippet: &mut String| {
+    if word.is_empty() {
+      return;
+    }
+    let lower = word.to_lowercase();
+    if terms.iter().any(|term| lower.starts_with(term.as_str())) {
+      snippet.push_str(&format!("<mark>{word}</mark>"));
+    } else {
+      snippet.push_str(word);
+    }
+    word.clear();
+  };
+  for c in text.chars() {
+    if c.is_alphanumeric() {
+      word.push(c);
+      continue;
+    }
+    flush(&mut word, &mut snippet);
+    match c {
+      '&' => snippet.push_str("&amp;"),
+      '<' => snippet.push_str("&lt;"),
+      '>' => snippet.push_str("&gt;"),
+      c => snippet.push(c),
+    }
+  }
+  flush(&mut word, &mut snippet);
+  snippet
+}
+
+/// One page of `products` matching a prefix tsquery in their name or
+/// description, best matches first, and the total number of matches.
+pub(crate) fn search_products(
+  products: Vec<Product>,
+  tsquery: &str,
+  limit: i64,
+  offset: i64,
+) -> (Vec<ProductSearchHit>, i64) {
+  let mut hits: Vec<ProductSearchHit> = products
+    .into_iter()
+    .filter_map(|product| {
+      let text = format!("{} {}", product.name, product.description);
+      let rank = prefix_rank(tsquery, &text)?;
+      Some(ProductSearchHit {
+        snippet: highlight(tsquery, &text),
+        rank,
+        product,
+      })
+    })
+    .collect();
+  hits.sort_by(|a, b| {
+    b.rank
+      .total_cmp(&a.rank)
+      .then_with(|| a.product.name.cmp(&b.product.name))
+      .then_with(|| a.product.id.cmp(&b.product.id))
+  });
+  let total = hits.len() as i64;
+  let page = hits
+    .into_iter()
+    .skip(usize::try_from(offset).unwrap_or(0))
+    .take(usize::try_from(limit).unwrap_or(0))
+    .collect();
+  (page, total)
+}
+
+/// The `items` with a field at least `threshold` word-similar to `text`,
+/// most similar first and then in `order`, at most `limit` of them.
+pub(crate) fn fuzzy_hits<T, K: Ord>(
+  items: Vec<T>,
+  text: &str,
+  threshold: f32,
+  limit: i64,
+  fields: impl Fn(&T) -> [&str; 2],
+  order: impl Fn(&T) -> K,
+) -> Vec<SimilarityHit<T>> {
+  let mut hits: Vec<SimilarityHit<T>> = items
+    .into_iter()
+    .map(|item| SimilarityHit {
+      similarity: fields(&item)
+        .iter()
+        .map(|field| word_similarity(text, field))
+        .fold(0.0, f32::max),
+      item,
+    })
+    .filter(|hit| hit.similarity >= threshold)
+    .collect();
+  hits.sort_by(|a, b| {
+    b.similarity
+      .total_cmp(&a.similarity)
+      .then_with(|| order(&a.item).cmp(&order(&b.item)))
+  });
+  hits.truncate(usize::try_from(limit).unwrap_or(0));
+  hits
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn word_similarity_matches_pg_trgm() {
+    // Expected values from `SELECT word_similarity(..)` with pg_trgm.
+    for (needle, haystack, expected) in [
+      ("word", "two words", 0.8),
+      ("jakcet", "Winter Jacket", 0.285_714_3),
+      ("jacke", "Winter Jacket", 0.833_333_3),
+      ("garcia", "Ana García", 0.571_428_6),
+      ("example", "ana@example.com", 1.0),
+    ] {
+      let similarity = word_similarity(needle, haystack);
+      assert!((similarity - expected).abs() < 1e-6, "{needle} {haystack}");
+    }
+    assert_eq!(word_similarity("", "Winter Jacket"), 0.0);
+  }
+
+  #[test]
+  fn prefix_queries_match_and_highlight_words() {
+    assert_eq!(
+      prefix_rank("blue:* & jack:*", "Blue jacket, blue lining"),
+      Some(3.0)
+    );
+    assert_eq!(prefix_rank("blue:* & jack:*", "Blue shirt"), None);
+    assert_eq!(
+      highlight("jack:*", "Jacket <b>&</b> cap"),
+      "<mark>Jacket</mark> &lt;b&gt;&amp;&lt;/b&gt; cap"
+    );
+  }
+}
@@ -0,0 +1,40 @@
+//! SQLite repositories for deployments that cannot run Postgres, over the
+//! schema in `migrations_sqlite`. Search ranks rows in Rust like the
+//! in-memory repositories, categories are not kept, and changes are not
+//! written to the audit log or the outbox.
+//!
+//! SQLite has no row locks. Transactions that read before they write open
+//! with a no-op write to the row instead, which takes the database write
+//! lock up front like `FOR UPDATE` would take the row lock: a transaction
+//! that only takes it later fails with `SQLITE_BUSY` when another
+//! connection wrote in between.
+//!
+//! Queries are checked at run time, not by `sqlx::query!`: the macros
+//! take one `DATABASE_URL` per crate, which is Postgres. Rows are read into
+//! private `FromRow` structs, and the module's tests run every query.
+//!
+//! `RETURNING` rows are only fetched inside a transaction. Outside one,
+//! `fetch_one` hands back the first row before the statement finishes and
+//! its implicit transaction commits, so a query on another connection right
+//! after it can miss the change.
+
+pub mod orders_repo;
+pub mod products_repo;
+pub mod users_repo;
+
+#[cfg(test)]
+pub(crate) async fn test_pool() -> sqlx::SqlitePool {
+  // A single connection that is never recycled, because every connection
+  // to `sqlite::memory:` opens a database of its own.
+  let pool = sqlx::sqlite::SqlitePoolOptions::new()
+    .max_connections(1)
+    .idle_timeout(None)
+    .max_lifetime(None)
+    .connect("sqlite::memory:")
+    .await
+    .expect("in-memory SQLite");
+  crate::infrastructure::db::run_sqlite_migrations(&pool)
+    .await
+    .expect("SQLite migrations");
+  pool
+}
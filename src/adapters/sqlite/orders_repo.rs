@@ -0,0 +1,502 @@
+use crate::application::ports::{NewOrder, NewOrderItem, OrderRepository, RepoError, UpdateOrder};
+use crate::domain::models::{AuditContext, FieldChange, Order, OrderHistoryEntry, OrderItem};
+use crate::domain::order_history;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct SqliteOrderRepository {
+  pool: SqlitePool,
+}
+
+impl SqliteOrderRepository {
+  pub fn new(pool: SqlitePool) -> Self {
+    Self { pool }
+  }
+}
+
+#[derive(sqlx::FromRow)]
+struct OrderRow {
+  id: Uuid,
+  user_id: Uuid,
+  status: String,
+  total_cents: i64,
+  created_at: DateTime<Utc>,
+  updated_at: DateTime<Utc>,
+}
+
+impl From<OrderRow> for Order {
+  fn from(row: OrderRow) -> Self {
+    Self {
+      id: row.id,
+      user_id: row.user_id,
+      status: row.status,
+      total_cents: row.total_cents,
+      created_at: row.created_at,
+      updated_at: row.updated_at,
+    }
+  }
+}
+
+#[derive(sqlx::FromRow)]
+struct OrderItemRow {
+  id: Uuid,
+  order_id: Uuid,
+  product_id: Uuid,
+  quantity: i32,
+  unit_price_cents: i64,
+  created_at: DateTime<Utc>,
+}
+
+impl From<OrderItemRow> for OrderItem {
+  fn from(row: OrderItemRow) -> Self {
+    Self {
+      id: row.id,
+      order_id: row.order_id,
+      product_id: row.product_id,
+      quantity: row.quantity,
+      unit_price_cents: row.unit_price_cents,
+      created_at: row.created_at,
+    }
+  }
+}
+
+#[derive(sqlx::FromRow)]
+struct HistoryRow {
+  order_id: Uuid,
+  version: i32,
+  action: String,
+  actor: String,
+  occurred_at: DateTime<Utc>,
+  changes: Json<BTreeMap<String, FieldChange>>,
+}
+
+impl From<HistoryRow> for OrderHistoryEntry {
+  fn from(row: HistoryRow) -> Self {
+    Self {
+      order_id: row.order_id,
+      version: row.version,
+      action: row.action,
+      actor: row.actor,
+      occurred_at: row.occurred_at,
+      changes: row.changes.0,
+    }
+  }
+}
+
+fn map_sqlx_err(err: sqlx::Error) -> RepoError {
+  match &err {
+    sqlx::Error::RowNotFound => RepoError::NotFound,
+    sqlx::Error::Database(db_err) => {
+      // SQLite extended codes: SQLITE_CONSTRAINT_FOREIGNKEY = 787,
+      // SQLITE_CONSTRAINT_UNIQUE = 2067
+      match db_err.code().as_deref() {
+        Some("787") | Some("2067") => RepoError::Conflict,
+        _ => RepoError::Unexpected(err.to_string()),
+      }
+    }
+    _ => RepoError::Unexpected(err.to_string()),
+  }
+}
+
+/// Appends the change from `before` to `after` to the order's history,
+/// unless no tracked field changed.
+async fn append_history(
+  conn: &mut SqliteConnection,
+  order_id: Uuid,
+  before: Option<&Order>,
+  after: Option<&Order>,
+  ctx: &AuditContext,
+) -> Result<(), RepoError> {
+  let changes = order_history::diff(before, after);
+  if changes.is_empty() {
+    return Ok(());
+  }
+  let action = match (before, after) {
+    (None, _) => "created",
+    (_, None) => "deleted",
+    _ => "updated",
+  };
+  // Callers hold the write lock, so versions do not race.
+  sqlx::query(
+    r#"
+    INSERT INTO order_history (order_id, version, action, actor, occurred_at, changes)
+    SELECT ?1, COALESCE(MAX(version), 0) + 1, ?2, ?3, ?4, ?5
+    FROM order_history
+    WHERE order_id = ?1
+    "#,
+  )
+  .bind(order_id)
+  .bind(action)
+  .bind(ctx.actor.to_string())
+  .bind(after.map_or_else(Utc::now, |order| order.updated_at))
+  .bind(serde_json::to_string(&changes).expect("changes serialize"))
+  .execute(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  Ok(())
+}
+
+/// Takes the write lock with a no-op write to the order and returns it, for
+/// a later `record_change` to compare against.
+async fn lock_order(conn: &mut SqliteConnection, order_id: Uuid) -> Result<Order, RepoError> {
+  let order = sqlx::query_as::<_, OrderRow>(
+    r#"
+    UPDATE orders SET id = id
+    WHERE id = ?1
+    RETURNING id, user_id, status, total_cents, created_at, updated_at
+    "#,
+  )
+  .bind(order_id)
+  .fetch_one(&mut *conn)
+  .await
+  .map(Order::from)
+  .map_err(map_sqlx_err)?;
+  Ok(order)
+}
+
+/// Records the change since `previous` in the order's history.
+async fn record_change(
+  conn: &mut SqliteConnection,
+  previous: &Order,
+  ctx: &AuditContext,
+) -> Result<Order, RepoError> {
+  let order = sqlx::query_as::<_, OrderRow>(
+    r#"
+    SELECT id, user_id, status, total_cents, created_at, updated_at
+    FROM orders
+    WHERE id = ?1
+    "#,
+  )
+  .bind(previous.id)
+  .fetch_one(&mut *conn)
+  .await
+  .map(Order::from)
+  .map_err(map_sqlx_err)?;
+
+  append_history(conn, order.id, Some(previous), Some(&order), ctx).await?;
+  Ok(order)
+}
+
+#[async_trait]
+impl OrderRepository for SqliteOrderRepository {
+  async fn create(&self, input: NewOrder, ctx: &AuditContext) -> Result<Order, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    let order = sqlx::query_as::<_, OrderRow>(
+      r#"
+      INSERT INTO orders (id, user_id, status, total_cents, created_at, updated_at)
+      VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+      RETURNING id, user_id, status, total_cents, created_at, updated_at
+      "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(input.user_id)
+    .bind(input.status)
+    .bind(input.total_cents)
+    .bind(Utc::now())
+    .fetch_one(&mut *tx)
+    .await
+    .map(Order::from)
+    .map_err(map_sqlx_err)?;
+
+    append_history(&mut tx, order.id, None, Some(&order), ctx).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(order)
+  }
+
+  async fn list(&self) -> Result<Vec<Order>, RepoError> {
+    let orders = sqlx::query_as::<_, OrderRow>(
+      r#"
+      SELECT id, user_id, status, total_cents, created_at, updated_at
+      FROM orders
+      ORDER BY created_at DESC
+      "#,
+    )
+    .fetch_all(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(orders.into_iter().map(Order::from).collect())
+  }
+
+  async fn get(&self, id: Uuid) -> Result<Order, RepoError> {
+    let order = sqlx::query_as::<_, OrderRow>(
+      r#"
+      SELECT id, user_id, status, total_cents, created_at, updated_at
+      FROM orders
+      WHERE id = ?1
+      "#,
+    )
+    .bind(id)
+    .fetch_one(&self.pool)
+    .await
+    .map(Order::from)
+    .map_err(map_sqlx_err)?;
+
+    Ok(order)
+  }
+
+  async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateOrder,
+    ctx: &AuditContext,
+  ) -> Result<Order, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    let previous = lock_order(&mut tx, id).await?;
+    sqlx::query(
+      r#"
+      UPDATE orders
+      SET
+        status = COALESCE(?2, status),
+        total_cents = COALESCE(?3, total_cents),
+        updated_at = ?4
+      WHERE id = ?1
+      "#,
+    )
+    .bind(id)
+    .bind(input.status)
+    .bind(input.total_cents)
+    .bind(Utc::now())
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    let order = record_change(&mut tx, &previous, ctx).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(order)
+  }
+
+  async fn delete(&self, id: Uuid, ctx: &AuditContext) -> Result<(), RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    // Items go with their order.
+    let order = sqlx::query_as::<_, OrderRow>(
+      r#"
+      DELETE FROM orders
+      WHERE id = ?1
+      RETURNING id, user_id, status, total_cents, created_at, updated_at
+      "#,
+    )
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await
+    .map(Order::from)
+    .map_err(map_sqlx_err)?;
+    append_history(&mut tx, id, Some(&order), None, ctx).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(())
+  }
+
+  async fn add_item(
+    &self,
+    order_id: Uuid,
+    input: NewOrderItem,
+    ctx: &AuditContext,
+  ) -> Result<OrderItem, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    let previous = lock_order(&mut tx, order_id).await?;
+    let now = Utc::now();
+    let price_cents: i64 = sqlx::query_scalar(
+      r#"
+      SELECT price_cents FROM product_prices
+      WHERE product_id = ?1 AND valid_from <= ?2 AND (valid_to IS NULL OR valid_to > ?2)
+      "#,
+    )
+    .bind(input.product_id)
+    .bind(now)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?
+    .ok_or(RepoError::Conflict)?;
+
+    let item = sqlx::query_as::<_, OrderItemRow>(
+      r#"
+      INSERT INTO order_items (id, order_id, product_id, quantity, unit_price_cents, created_at)
+      VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+      RETURNING id, order_id, product_id, quantity, unit_price_cents, created_at
+      "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(order_id)
+    .bind(input.product_id)
+    .bind(input.quantity)
+    .bind(price_cents)
+    .bind(now)
+    .fetch_one(&mut *tx)
+    .await
+    .map(OrderItem::from)
+    .map_err(map_sqlx_err)?;
+
+    sqlx::query("UPDATE orders SET total_cents = total_cents + ?2, updated_at = ?3 WHERE id = ?1")
+      .bind(order_id)
+      .bind(price_cents * i64::from(input.quantity))
+      .bind(now)
+      .execute(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?;
+    record_change(&mut tx, &previous, ctx).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+
+    Ok(item)
+  }
+
+  async fn list_items(&self, order_id: Uuid) -> Result<Vec<OrderItem>, RepoError> {
+    // Distinguish an unknown order from one without items.
+    sqlx::query("SELECT id FROM orders WHERE id = ?1")
+      .bind(order_id)
+      .fetch_one(&self.pool)
+      .await
+      .map_err(map_sqlx_err)?;
+
+    let items = sqlx::query_as::<_, OrderItemRow>(
+      r#"
+      SELECT id, order_id, product_id, quantity, unit_price_cents, created_at
+      FROM order_items
+      WHERE order_id = ?1
+      ORDER BY created_at, id
+      "#,
+    )
+    .bind(order_id)
+    .fetch_all(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(items.into_iter().map(OrderItem::from).collect())
+  }
+
+  async fn history(&self, order_id: Uuid) -> Result<Vec<OrderHistoryEntry>, RepoError> {
+    let entries = sqlx::query_as::<_, HistoryRow>(
+      r#"
+      SELECT order_id, version, action, actor, occurred_at, changes
+      FROM order_history
+      WHERE order_id = ?1
+      ORDER BY version
+      "#,
+    )
+    .bind(order_id)
+    .fetch_all(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+    if entries.is_empty() {
+      return Err(RepoError::NotFound);
+    }
+    Ok(entries.into_iter().map(OrderHistoryEntry::from).collect())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::adapters::sqlite::products_repo::SqliteProductRepository;
+  use crate::adapters::sqlite::test_pool;
+  use crate::adapters::sqlite::users_repo::SqliteUserRepository;
+  use crate::application::ports::{NewProduct, NewUser, ProductRepository, UserRepository};
+  use crate::domain::models::Actor;
+
+  fn ctx() -> AuditContext {
+    AuditContext {
+      actor: Actor::Admin,
+      request_id: None,
+    }
+  }
+
+  fn new_order(user_id: Uuid) -> NewOrder {
+    NewOrder {
+      user_id,
+      status: "cart".into(),
+      total_cents: 0,
+    }
+  }
+
+  async fn new_user(users: &SqliteUserRepository) -> Uuid {
+    let input = NewUser {
+      email: "ana@example.com".into(),
+      name: "Ana".into(),
+    };
+    users.create(input, &ctx()).await.unwrap().id
+  }
+
+  #[tokio::test]
+  async fn foreign_key_violations_are_conflicts() {
+    let pool = test_pool().await;
+    let users = SqliteUserRepository::new(pool.clone());
+    let products = SqliteProductRepository::new(pool.clone());
+    let orders = SqliteOrderRepository::new(pool);
+    let user_id = new_user(&users).await;
+    let lamp = products
+      .create(
+        NewProduct {
+          sku: "lamp".into(),
+          name: "Lamp".into(),
+          description: String::new(),
+          price_cents: 1000,
+        },
+        &ctx(),
+      )
+      .await
+      .unwrap();
+
+    let err = orders.create(new_order(Uuid::new_v4()), &ctx()).await;
+    assert!(matches!(err, Err(RepoError::Conflict)));
+    let order = orders.create(new_order(user_id), &ctx()).await.unwrap();
+    let line = |product_id| NewOrderItem {
+      product_id,
+      quantity: 2,
+    };
+    let err = orders
+      .add_item(order.id, line(Uuid::new_v4()), &ctx())
+      .await;
+    assert!(matches!(err, Err(RepoError::Conflict)));
+    let item = orders
+      .add_item(order.id, line(lamp.id), &ctx())
+      .await
+      .unwrap();
+    assert_eq!(item.unit_price_cents, 1000);
+    assert_eq!(orders.get(order.id).await.unwrap().total_cents, 2000);
+    assert_eq!(orders.list_items(order.id).await.unwrap(), [item]);
+
+    // Referenced rows cannot go while the order has them.
+    let err = users.delete(user_id, &ctx()).await;
+    assert!(matches!(err, Err(RepoError::Conflict)));
+    let err = products.delete(lamp.id, &ctx()).await;
+    assert!(matches!(err, Err(RepoError::Conflict)));
+    orders.delete(order.id, &ctx()).await.unwrap();
+    products.delete(lamp.id, &ctx()).await.unwrap();
+    users.delete(user_id, &ctx()).await.unwrap();
+  }
+
+  #[tokio::test]
+  async fn history_outlives_the_order() {
+    let pool = test_pool().await;
+    let user_id = new_user(&SqliteUserRepository::new(pool.clone())).await;
+    let orders = SqliteOrderRepository::new(pool.clone());
+    let order = orders.create(new_order(user_id), &ctx()).await.unwrap();
+    let unchanged = UpdateOrder {
+      status: Some("cart".into()),
+      total_cents: None,
+    };
+    orders.update(order.id, unchanged, &ctx()).await.unwrap();
+    let shipped = UpdateOrder {
+      status: Some("shipped".into()),
+      total_cents: None,
+    };
+    orders.update(order.id, shipped, &ctx()).await.unwrap();
+    orders.delete(order.id, &ctx()).await.unwrap();
+
+    let history = orders.history(order.id).await.unwrap();
+    let actions: Vec<&str> = history.iter().map(|e| e.action.as_str()).collect();
+    assert_eq!(actions, ["created", "updated", "deleted"]);
+    assert_eq!(history[2].version, 3);
+    assert_eq!(history[1].changes["status"].after, "shipped");
+
+    // The table refuses to be rewritten.
+    let err = sqlx::query("DELETE FROM order_history")
+      .execute(&pool)
+      .await
+      .unwrap_err();
+    assert!(err.to_string().contains("append-only"));
+  }
+}
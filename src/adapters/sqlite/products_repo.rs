@@ -0,0 +1,481 @@
+use crate::adapters::search;
+use crate::application::ports::{NewProduct, ProductRepository, RepoError, UpdateProduct};
+use crate::domain::models::{AuditContext, Product, ProductPrice, ProductSearchHit, SimilarityHit};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{SqliteConnection, SqlitePool};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct SqliteProductRepository {
+  pool: SqlitePool,
+}
+
+impl SqliteProductRepository {
+  pub fn new(pool: SqlitePool) -> Self {
+    Self { pool }
+  }
+}
+
+/// Selects products at the price in effect at `?1`, the stand-in for
+/// Postgres' `product_price_at`. Reviews are not kept in SQLite, so the
+/// rating columns are left out and products are never rated.
+const SELECT_PRODUCTS: &str = r#"
+  SELECT p.id, p.sku, p.name, p.description, p.created_at, p.updated_at,
+    (SELECT pp.price_cents FROM product_prices pp
+     WHERE pp.product_id = p.id AND pp.valid_from <= ?1
+       AND (pp.valid_to IS NULL OR pp.valid_to > ?1)) AS price_cents
+  FROM products p
+"#;
+
+/// A row of `SELECT_PRODUCTS`.
+#[derive(sqlx::FromRow)]
+struct ProductRow {
+  id: Uuid,
+  sku: String,
+  name: String,
+  description: String,
+  price_cents: i64,
+  created_at: DateTime<Utc>,
+  updated_at: DateTime<Utc>,
+}
+
+impl From<ProductRow> for Product {
+  fn from(row: ProductRow) -> Self {
+    Self {
+      id: row.id,
+      sku: row.sku,
+      name: row.name,
+      description: row.description,
+      price_cents: row.price_cents,
+      rating_average: None,
+      rating_count: 0,
+      created_at: row.created_at,
+      updated_at: row.updated_at,
+    }
+  }
+}
+
+#[derive(sqlx::FromRow)]
+struct PriceRow {
+  id: Uuid,
+  product_id: Uuid,
+  price_cents: i64,
+  valid_from: DateTime<Utc>,
+  valid_to: Option<DateTime<Utc>>,
+  created_at: DateTime<Utc>,
+}
+
+impl From<PriceRow> for ProductPrice {
+  fn from(row: PriceRow) -> Self {
+    Self {
+      id: row.id,
+      product_id: row.product_id,
+      price_cents: row.price_cents,
+      valid_from: row.valid_from,
+      valid_to: row.valid_to,
+      created_at: row.created_at,
+    }
+  }
+}
+
+fn map_sqlx_err(err: sqlx::Error) -> RepoError {
+  match &err {
+    sqlx::Error::RowNotFound => RepoError::NotFound,
+    sqlx::Error::Database(db_err) => {
+      // SQLite extended codes: SQLITE_CONSTRAINT_FOREIGNKEY = 787 (e.g.
+      // deleting an ordered product), SQLITE_CONSTRAINT_UNIQUE = 2067
+      match db_err.code().as_deref() {
+        Some("787") | Some("2067") => RepoError::Conflict,
+        _ => RepoError::Unexpected(err.to_string()),
+      }
+    }
+    _ => RepoError::Unexpected(err.to_string()),
+  }
+}
+
+async fn fetch_product(conn: &mut SqliteConnection, id: Uuid) -> Result<Product, RepoError> {
+  sqlx::query_as::<_, ProductRow>(&format!("{SELECT_PRODUCTS} WHERE p.id = ?2"))
+    .bind(Utc::now())
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await
+    .map(Product::from)
+    .map_err(map_sqlx_err)
+}
+
+/// Takes the write lock with a no-op write to the product, `NotFound` when
+/// there is none.
+async fn lock_product(conn: &mut SqliteConnection, id: Uuid) -> Result<(), RepoError> {
+  let result = sqlx::query("UPDATE products SET id = id WHERE id = ?1")
+    .bind(id)
+    .execute(&mut *conn)
+    .await
+    .map_err(map_sqlx_err)?;
+  if result.rows_affected() == 0 {
+    return Err(RepoError::NotFound);
+  }
+  Ok(())
+}
+
+/// Sets the price of `product_id` from `at` until its next price change,
+/// closing the range in effect at `at`. The caller holds the write lock.
+async fn set_price(
+  conn: &mut SqliteConnection,
+  product_id: Uuid,
+  price_cents: i64,
+  at: DateTime<Utc>,
+) -> Result<ProductPrice, RepoError> {
+  let existing = sqlx::query_as::<_, PriceRow>(
+    r#"
+    UPDATE product_prices SET price_cents = ?3
+    WHERE product_id = ?1 AND valid_from = ?2
+    RETURNING id, product_id, price_cents, valid_from, valid_to, created_at
+    "#,
+  )
+  .bind(product_id)
+  .bind(at)
+  .bind(price_cents)
+  .fetch_optional(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  if let Some(price) = existing {
+    return Ok(price.into());
+  }
+
+  sqlx::query(
+    r#"
+    UPDATE product_prices SET valid_to = ?2
+    WHERE product_id = ?1 AND valid_from < ?2 AND (valid_to IS NULL OR valid_to > ?2)
+    "#,
+  )
+  .bind(product_id)
+  .bind(at)
+  .execute(&mut *conn)
+  .await
+  .map_err(map_sqlx_err)?;
+  sqlx::query_as::<_, PriceRow>(
+    r#"
+    INSERT INTO product_prices (id, product_id, price_cents, valid_from, valid_to, created_at)
+    SELECT ?4, ?1, ?3, ?2,
+      (SELECT min(valid_from) FROM product_prices WHERE product_id = ?1 AND valid_from > ?2),
+      ?5
+    RETURNING id, product_id, price_cents, valid_from, valid_to, created_at
+    "#,
+  )
+  .bind(product_id)
+  .bind(at)
+  .bind(price_cents)
+  .bind(Uuid::new_v4())
+  .bind(Utc::now())
+  .fetch_one(&mut *conn)
+  .await
+  .map(ProductPrice::from)
+  .map_err(map_sqlx_err)
+}
+
+#[async_trait]
+impl ProductRepository for SqliteProductRepository {
+  async fn create(&self, input: NewProduct, _ctx: &AuditContext) -> Result<Product, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+    sqlx::query(
+      r#"
+      INSERT INTO products (id, sku, name, description, created_at, updated_at)
+      VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+      "#,
+    )
+    .bind(id)
+    .bind(input.sku)
+    .bind(input.name)
+    .bind(input.description)
+    .bind(now)
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    // The first price starts when the product is created.
+    set_price(&mut tx, id, input.price_cents, now).await?;
+    let product = fetch_product(&mut tx, id).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(product)
+  }
+
+  async fn list(&self) -> Result<Vec<Product>, RepoError> {
+    let products =
+      sqlx::query_as::<_, ProductRow>(&format!("{SELECT_PRODUCTS} ORDER BY p.created_at DESC"))
+        .bind(Utc::now())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(map_sqlx_err)?;
+
+    Ok(products.into_iter().map(Product::from).collect())
+  }
+
+  async fn get(&self, id: Uuid) -> Result<Product, RepoError> {
+    let mut conn = self.pool.acquire().await.map_err(map_sqlx_err)?;
+    fetch_product(&mut conn, id).await
+  }
+
+  async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateProduct,
+    _ctx: &AuditContext,
+  ) -> Result<Product, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    let now = Utc::now();
+    let result = sqlx::query(
+      r#"
+      UPDATE products
+      SET
+        sku = COALESCE(?2, sku),
+        name = COALESCE(?3, name),
+        description = COALESCE(?4, description),
+        updated_at = ?5
+      WHERE id = ?1
+      "#,
+    )
+    .bind(id)
+    .bind(input.sku)
+    .bind(input.name)
+    .bind(input.description)
+    .bind(now)
+    .execute(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    if result.rows_affected() == 0 {
+      return Err(RepoError::NotFound);
+    }
+    if let Some(price_cents) = input.price_cents {
+      set_price(&mut tx, id, price_cents, now).await?;
+    }
+    let product = fetch_product(&mut tx, id).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(product)
+  }
+
+  async fn delete(&self, id: Uuid, _ctx: &AuditContext) -> Result<(), RepoError> {
+    // Prices go with the product; order items restrict deleting it.
+    let result = sqlx::query("DELETE FROM products WHERE id = ?1")
+      .bind(id)
+      .execute(&self.pool)
+      .await
+      .map_err(map_sqlx_err)?;
+    if result.rows_affected() == 0 {
+      return Err(RepoError::NotFound);
+    }
+    Ok(())
+  }
+
+  async fn list_by_category(
+    &self,
+    _slug: &str,
+    _include_descendants: bool,
+  ) -> Result<Vec<Product>, RepoError> {
+    // Categories are not kept in SQLite, so no slug is known.
+    Err(RepoError::NotFound)
+  }
+
+  async fn search(
+    &self,
+    tsquery: &str,
+    limit: i64,
+    offset: i64,
+  ) -> Result<(Vec<ProductSearchHit>, i64), RepoError> {
+    let products = self.list().await?;
+    Ok(search::search_products(products, tsquery, limit, offset))
+  }
+
+  async fn fuzzy_search(
+    &self,
+    text: &str,
+    threshold: f32,
+    limit: i64,
+  ) -> Result<Vec<SimilarityHit<Product>>, RepoError> {
+    let products = self.list().await?;
+    Ok(search::fuzzy_hits(
+      products,
+      text,
+      threshold,
+      limit,
+      |product| [&product.sku, &product.name],
+      |product| (product.name.clone(), product.id),
+    ))
+  }
+
+  async fn price_history(&self, product_id: Uuid) -> Result<Vec<ProductPrice>, RepoError> {
+    sqlx::query("SELECT id FROM products WHERE id = ?1")
+      .bind(product_id)
+      .fetch_one(&self.pool)
+      .await
+      .map_err(map_sqlx_err)?;
+    let prices = sqlx::query_as::<_, PriceRow>(
+      r#"
+      SELECT id, product_id, price_cents, valid_from, valid_to, created_at
+      FROM product_prices
+      WHERE product_id = ?1
+      ORDER BY valid_from
+      "#,
+    )
+    .bind(product_id)
+    .fetch_all(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(prices.into_iter().map(ProductPrice::from).collect())
+  }
+
+  async fn schedule_price(
+    &self,
+    product_id: Uuid,
+    price_cents: i64,
+    valid_from: DateTime<Utc>,
+    _ctx: &AuditContext,
+  ) -> Result<ProductPrice, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    lock_product(&mut tx, product_id).await?;
+    let price = set_price(&mut tx, product_id, price_cents, valid_from).await?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(price)
+  }
+
+  async fn cancel_scheduled_price(
+    &self,
+    product_id: Uuid,
+    price_id: Uuid,
+    _ctx: &AuditContext,
+  ) -> Result<(), RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    lock_product(&mut tx, product_id).await?;
+    let (valid_from, valid_to) = sqlx::query_as::<_, (DateTime<Utc>, Option<DateTime<Utc>>)>(
+      r#"
+      SELECT valid_from, valid_to
+      FROM product_prices
+      WHERE id = ?1 AND product_id = ?2
+      "#,
+    )
+    .bind(price_id)
+    .bind(product_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    if valid_from <= Utc::now() {
+      return Err(RepoError::Conflict);
+    }
+
+    // The preceding range takes over the cancelled one.
+    sqlx::query("DELETE FROM product_prices WHERE id = ?1")
+      .bind(price_id)
+      .execute(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?;
+    sqlx::query("UPDATE product_prices SET valid_to = ?3 WHERE product_id = ?1 AND valid_to = ?2")
+      .bind(product_id)
+      .bind(valid_from)
+      .bind(valid_to)
+      .execute(&mut *tx)
+      .await
+      .map_err(map_sqlx_err)?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::adapters::sqlite::test_pool;
+  use crate::domain::models::Actor;
+  use chrono::Duration;
+
+  fn ctx() -> AuditContext {
+    AuditContext {
+      actor: Actor::Admin,
+      request_id: None,
+    }
+  }
+
+  fn new_product(sku: &str, name: &str, price_cents: i64) -> NewProduct {
+    NewProduct {
+      sku: sku.into(),
+      name: name.into(),
+      description: String::new(),
+      price_cents,
+    }
+  }
+
+  #[tokio::test]
+  async fn skus_are_unique() {
+    let repo = SqliteProductRepository::new(test_pool().await);
+    repo
+      .create(new_product("lamp", "Lamp", 1000), &ctx())
+      .await
+      .unwrap();
+    let err = repo
+      .create(new_product("lamp", "Other lamp", 900), &ctx())
+      .await
+      .unwrap_err();
+    assert!(matches!(err, RepoError::Conflict));
+  }
+
+  #[tokio::test]
+  async fn prices_are_kept_as_history() {
+    let repo = SqliteProductRepository::new(test_pool().await);
+    let lamp = repo
+      .create(new_product("lamp", "Lamp", 1000), &ctx())
+      .await
+      .unwrap();
+    assert_eq!(lamp.price_cents, 1000);
+    let update = UpdateProduct {
+      sku: None,
+      name: None,
+      description: None,
+      price_cents: Some(1200),
+    };
+    let lamp = repo.update(lamp.id, update, &ctx()).await.unwrap();
+    assert_eq!(lamp.price_cents, 1200);
+
+    let next_week = Utc::now() + Duration::days(7);
+    let scheduled = repo
+      .schedule_price(lamp.id, 800, next_week, &ctx())
+      .await
+      .unwrap();
+    assert_eq!(repo.get(lamp.id).await.unwrap().price_cents, 1200);
+    let history = repo.price_history(lamp.id).await.unwrap();
+    let prices: Vec<i64> = history.iter().map(|p| p.price_cents).collect();
+    assert_eq!(prices, [1000, 1200, 800]);
+    assert_eq!(history[1].valid_to, Some(next_week));
+
+    let err = repo
+      .cancel_scheduled_price(lamp.id, history[0].id, &ctx())
+      .await
+      .unwrap_err();
+    assert!(matches!(err, RepoError::Conflict));
+    repo
+      .cancel_scheduled_price(lamp.id, scheduled.id, &ctx())
+      .await
+      .unwrap();
+    let history = repo.price_history(lamp.id).await.unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[1].valid_to, None);
+  }
+
+  #[tokio::test]
+  async fn search_matches_word_prefixes() {
+    let repo = SqliteProductRepository::new(test_pool().await);
+    repo
+      .create(new_product("lamp", "Desk lamp", 1000), &ctx())
+      .await
+      .unwrap();
+    repo
+      .create(new_product("jacket", "Rain jacket", 5000), &ctx())
+      .await
+      .unwrap();
+    let (hits, total) = repo.search("jack:*", 10, 0).await.unwrap();
+    assert_eq!(total, 1);
+    assert_eq!(hits[0].product.sku, "jacket");
+    let hits = repo.fuzzy_search("jakcet", 0.2, 10).await.unwrap();
+    assert_eq!(hits[0].item.sku, "jacket");
+  }
+}
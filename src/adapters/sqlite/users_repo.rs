@@ -0,0 +1,226 @@
+use crate::adapters::search;
+use crate::application::ports::{NewUser, RepoError, UpdateUser, UserRepository};
+use crate::domain::models::{AuditContext, SimilarityHit, User};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct SqliteUserRepository {
+  pool: SqlitePool,
+}
+
+impl SqliteUserRepository {
+  pub fn new(pool: SqlitePool) -> Self {
+    Self { pool }
+  }
+}
+
+#[derive(sqlx::FromRow)]
+struct UserRow {
+  id: Uuid,
+  email: String,
+  name: String,
+  created_at: DateTime<Utc>,
+  updated_at: DateTime<Utc>,
+}
+
+impl From<UserRow> for User {
+  fn from(row: UserRow) -> Self {
+    Self {
+      id: row.id,
+      email: row.email,
+      name: row.name,
+      created_at: row.created_at,
+      updated_at: row.updated_at,
+    }
+  }
+}
+
+fn map_sqlx_err(err: sqlx::Error) -> RepoError {
+  match &err {
+    sqlx::Error::RowNotFound => RepoError::NotFound,
+    sqlx::Error::Database(db_err) => {
+      // SQLite extended codes: SQLITE_CONSTRAINT_FOREIGNKEY = 787 (deleting
+      // a user with orders), SQLITE_CONSTRAINT_UNIQUE = 2067
+      match db_err.code().as_deref() {
+        Some("787") | Some("2067") => RepoError::Conflict,
+        _ => RepoError::Unexpected(err.to_string()),
+      }
+    }
+    _ => RepoError::Unexpected(err.to_string()),
+  }
+}
+
+#[async_trait]
+impl UserRepository for SqliteUserRepository {
+  async fn create(&self, input: NewUser, _ctx: &AuditContext) -> Result<User, RepoError> {
+    let now = Utc::now();
+    let user = User {
+      id: Uuid::new_v4(),
+      email: input.email,
+      name: input.name,
+      created_at: now,
+      updated_at: now,
+    };
+    sqlx::query(
+      r#"
+      INSERT INTO users (id, email, name, created_at, updated_at)
+      VALUES (?1, ?2, ?3, ?4, ?4)
+      "#,
+    )
+    .bind(user.id)
+    .bind(&user.email)
+    .bind(&user.name)
+    .bind(now)
+    .execute(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(user)
+  }
+
+  async fn list(&self) -> Result<Vec<User>, RepoError> {
+    let users = sqlx::query_as::<_, UserRow>(
+      r#"
+      SELECT id, email, name, created_at, updated_at
+      FROM users
+      ORDER BY created_at DESC
+      "#,
+    )
+    .fetch_all(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(users.into_iter().map(User::from).collect())
+  }
+
+  async fn get(&self, id: Uuid) -> Result<User, RepoError> {
+    let user = sqlx::query_as::<_, UserRow>(
+      r#"
+      SELECT id, email, name, created_at, updated_at
+      FROM users
+      WHERE id = ?1
+      "#,
+    )
+    .bind(id)
+    .fetch_one(&self.pool)
+    .await
+    .map_err(map_sqlx_err)?;
+
+    Ok(user.into())
+  }
+
+  async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateUser,
+    _ctx: &AuditContext,
+  ) -> Result<User, RepoError> {
+    let mut tx = self.pool.begin().await.map_err(map_sqlx_err)?;
+    let user = sqlx::query_as::<_, UserRow>(
+      r#"
+      UPDATE users
+      SET
+        email = COALESCE(?2, email),
+        name = COALESCE(?3, name),
+        updated_at = ?4
+      WHERE id = ?1
+      RETURNING id, email, name, created_at, updated_at
+      "#,
+    )
+    .bind(id)
+    .bind(input.email)
+    .bind(input.name)
+    .bind(Utc::now())
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_sqlx_err)?;
+    tx.commit().await.map_err(map_sqlx_err)?;
+
+    Ok(user.into())
+  }
+
+  async fn delete(&self, id: Uuid, _ctx: &AuditContext) -> Result<(), RepoError> {
+    let result = sqlx::query("DELETE FROM users WHERE id = ?1")
+      .bind(id)
+      .execute(&self.pool)
+      .await
+      .map_err(map_sqlx_err)?;
+    if result.rows_affected() == 0 {
+      return Err(RepoError::NotFound);
+    }
+    Ok(())
+  }
+
+  async fn fuzzy_search(
+    &self,
+    text: &str,
+    threshold: f32,
+    limit: i64,
+  ) -> Result<Vec<SimilarityHit<User>>, RepoError> {
+    // SQLite has no trigram matching, so every user is scored in Rust.
+    let users = self.list().await?;
+    Ok(search::fuzzy_hits(
+      users,
+      text,
+      threshold,
+      limit,
+      |user| [&user.email, &user.name],
+      |user| (user.email.clone(), user.id),
+    ))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::adapters::sqlite::test_pool;
+  use crate::domain::models::Actor;
+
+  fn ctx() -> AuditContext {
+    AuditContext {
+      actor: Actor::Admin,
+      request_id: None,
+    }
+  }
+
+  fn new_user(email: &str, name: &str) -> NewUser {
+    NewUser {
+      email: email.into(),
+      name: name.into(),
+    }
+  }
+
+  #[tokio::test]
+  async fn unique_violations_are_conflicts() {
+    let repo = SqliteUserRepository::new(test_pool().await);
+    let ana = repo
+      .create(new_user("ana@example.com", "Ana"), &ctx())
+      .await
+      .unwrap();
+    let ben = repo
+      .create(new_user("ben@example.com", "Ben"), &ctx())
+      .await
+      .unwrap();
+    assert_eq!(repo.get(ana.id).await.unwrap().email, "ana@example.com");
+    let err = repo
+      .create(new_user("ana@example.com", "Other"), &ctx())
+      .await
+      .unwrap_err();
+    assert!(matches!(err, RepoError::Conflict));
+    let taken = UpdateUser {
+      email: Some("ana@example.com".into()),
+      name: None,
+    };
+    let err = repo.update(ben.id, taken, &ctx()).await.unwrap_err();
+    assert!(matches!(err, RepoError::Conflict));
+
+    let listed: Vec<Uuid> = repo.list().await.unwrap().iter().map(|u| u.id).collect();
+    assert_eq!(listed, [ben.id, ana.id]);
+    repo.delete(ben.id, &ctx()).await.unwrap();
+    let err = repo.delete(ben.id, &ctx()).await.unwrap_err();
+    assert!(matches!(err, RepoError::NotFound));
+  }
+}
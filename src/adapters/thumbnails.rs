@@ -0,0 +1,265 @@
+//! Background generation of resized product image variants.
+//!
+//! Every configured size becomes two files: a lossless WebP and a fallback in
+//! the family of the original (JPEG for photos, PNG for everything else so
+//! transparency survives). Rendering is pure Rust and runs on the blocking
+//! thread pool.
+
+use crate::application::ports::{BlobStore, ProductImageRepository, RepoError};
+use crate::domain::media;
+use crate::domain::models::{ImageVariant, ProductImage};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Images claimed per round.
+const BATCH_SIZE: i64 = 10;
+/// A claim older than this belongs to a worker that died mid-job.
+const STALE_AFTER_SECS: i64 = 300;
+const JPEG_QUALITY: u8 = 85;
+
+/// One encoded variant, ready to be stored.
+#[derive(Debug)]
+pub struct Rendition {
+  pub name: String,
+  pub content_type: &'static str,
+  pub width: u32,
+  pub height: u32,
+  pub bytes: Vec<u8>,
+}
+
+/// Decodes `original` and renders every size in `sizes`. Images smaller than
+/// a size are re-encoded at their own dimensions rather than enlarged.
+pub fn render(original: &[u8], sizes: &[u32]) -> Result<Vec<Rendition>, String> {
+  let decoded = image::load_from_memory(original).map_err(|err| err.to_string())?;
+  let is_jpeg = media::sniff_image_type(original) == Some("image/jpeg");
+  let mut renditions = Vec::with_capacity(sizes.len() * 2);
+  for &size in sizes {
+    let (width, height) = decoded.dimensions();
+    let (width, height) = media::fit_within(width, height, size);
+    let resized = if (width, height) == decoded.dimensions() {
+      decoded.clone()
+    } else {
+      decoded.resize_exact(width, height, FilterType::Lanczos3)
+    };
+
+    let mut webp = Vec::new();
+    let pixels = if resized.color().has_alpha() {
+      DynamicImage::ImageRgba8(resized.to_rgba8())
+    } else {
+      DynamicImage::ImageRgb8(resized.to_rgb8())
+    };
+    pixels
+      .write_with_encoder(WebPEncoder::new_lossless(&mut webp))
+      .map_err(|err| err.to_string())?;
+    renditions.push(Rendition {
+      name: media::variant_name(size, "webp"),
+      content_type: "image/webp",
+      width,
+      height,
+      bytes: webp,
+    });
+
+    let mut fallback = Vec::new();
+    let (extension, content_type) = if is_jpeg {
+      DynamicImage::ImageRgb8(resized.to_rgb8())
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut fallback, JPEG_QUALITY))
+        .map_err(|err| err.to_string())?;
+      ("jpg", "image/jpeg")
+    } else {
+      pixels
+        .write_with_encoder(PngEncoder::new(&mut fallback))
+        .map_err(|err| err.to_string())?;
+      ("png", "image/png")
+    };
+    renditions.push(Rendition {
+      name: media::variant_name(size, extension),
+      content_type,
+      width,
+      height,
+      bytes: fallback,
+    });
+  }
+  Ok(renditions)
+}
+
+/// Why a job did not finish.
+enum JobError {
+  /// The original cannot be rendered; retrying will not help.
+  Unrenderable(String),
+  /// Storage or the database failed; the claim expires and the job is retried.
+  Repo(RepoError),
+}
+
+impl From<RepoError> for JobError {
+  fn from(err: RepoError) -> Self {
+    JobError::Repo(err)
+  }
+}
+
+/// Picks up newly uploaded images and stores their variants.
+pub struct ThumbnailWorker<R: ProductImageRepository> {
+  repo: Arc<R>,
+  blobs: Arc<dyn BlobStore>,
+  sizes: Arc<[u32]>,
+}
+
+impl<R: ProductImageRepository> ThumbnailWorker<R> {
+  pub fn new(repo: R, blobs: Arc<dyn BlobStore>, sizes: Vec<u32>) -> Self {
+    Self {
+      repo: Arc::new(repo),
+      blobs,
+      sizes: sizes.into(),
+    }
+  }
+
+  /// Processes one batch of pending images and returns how many were
+  /// claimed.
+  pub async fn run_once(&self) -> Result<usize, RepoError> {
+    let images = self
+      .repo
+      .claim_thumbnail_jobs(BATCH_SIZE, STALE_AFTER_SECS)
+      .await?;
+    for image in &images {
+      match self.process(image).await {
+        Ok(()) => {}
+        Err(JobError::Unrenderable(reason)) => {
+          tracing::warn!(image_id = %image.id, error = %reason, "cannot render thumbnails");
+          match self.repo.mark_thumbnails_failed(image.id, &reason).await {
+            Ok(()) | Err(RepoError::NotFound) => {}
+            Err(err) => return Err(err),
+          }
+        }
+        Err(JobError::Repo(err)) => {
+          tracing::warn!(image_id = %image.id, error = %err, "thumbnail job will be retried");
+        }
+      }
+    }
+    Ok(images.len())
+  }
+
+  /// Polls for work until the process exits, draining full batches without
+  /// waiting.
+  pub fn spawn(self, poll_interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+      loop {
+        match self.run_once().await {
+          Ok(claimed) if claimed as i64 == BATCH_SIZE => continue,
+          Ok(_) => {}
+          Err(err) => tracing::error!(error = %err, "thumbnail worker round failed"),
+        }
+        tokio::time::sleep(poll_interval).await;
+      }
+    })
+  }
+
+  async fn process(&self, image: &ProductImage) -> Result<(), JobError> {
+    let original = match self.blobs.get(&image.blob_key).await {
+      Err(RepoError::NotFound) => {
+        return Err(JobError::Unrenderable("original file is missing".into()))
+      }
+      result => result?,
+    };
+    let sizes = self.sizes.clone();
+    let renditions = tokio::task::spawn_blocking(move || render(&original, &sizes))
+      .await
+      .map_err(|err| JobError::Repo(RepoError::Unexpected(err.to_string())))?
+      .map_err(JobError::Unrenderable)?;
+
+    let mut variants = Vec::with_capacity(renditions.len());
+    for rendition in renditions {
+      let blob_key = media::variant_blob_key(image.product_id, image.id, &rendition.name);
+      let byte_size = rendition.bytes.len() as i64;
+      self
+        .blobs
+        .put(&blob_key, rendition.content_type, rendition.bytes)
+        .await?;
+      variants.push(ImageVariant {
+        name: rendition.name,
+        blob_key,
+        content_type: rendition.content_type.to_string(),
+        width: rendition.width as i32,
+        height: rendition.height as i32,
+        byte_size,
+      });
+    }
+
+    match self.repo.save_variants(image.id, variants.clone()).await {
+      // The image was deleted while rendering; drop what was written.
+      Err(RepoError::NotFound) => {
+        for variant in &variants {
+          if let Err(err) = self.blobs.delete(&variant.blob_key).await {
+            tracing::warn!(key = variant.blob_key, error = %err, "failed to delete blob");
+          }
+        }
+        Ok(())
+      }
+      result => Ok(result?),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use image::{ImageFormat, Rgba, RgbaImage};
+  use std::io::Cursor;
+
+  fn encoded(format: ImageFormat, width: u32, height: u32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let pixels = RgbaImage::from_pixel(width, height, Rgba([200, 40, 40, 255]));
+    let image = if format == ImageFormat::Jpeg {
+      DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(pixels).to_rgb8())
+    } else {
+      DynamicImage::ImageRgba8(pixels)
+    };
+    image
+      .write_to(&mut Cursor::new(&mut bytes), format)
+      .unwrap();
+    bytes
+  }
+
+  #[test]
+  fn renders_webp_and_a_fallback_per_size_without_enlarging() {
+    let renditions = render(&encoded(ImageFormat::Png, 300, 200), &[100, 480]).unwrap();
+    let summary: Vec<_> = renditions
+      .iter()
+      .map(|r| (r.name.as_str(), r.content_type, r.width, r.height))
+      .collect();
+    assert_eq!(
+      summary,
+      [
+        ("100.webp", "image/webp", 100, 67),
+        ("100.png", "image/png", 100, 67),
+        ("480.webp", "image/webp", 300, 200),
+        ("480.png", "image/png", 300, 200),
+      ]
+    );
+    for rendition in &renditions {
+      assert_eq!(
+        media::sniff_image_type(&rendition.bytes),
+        Some(rendition.content_type)
+      );
+      let decoded = image::load_from_memory(&rendition.bytes).unwrap();
+      assert_eq!(decoded.dimensions(), (rendition.width, rendition.height));
+    }
+  }
+
+  #[test]
+  fn photos_fall_back_to_jpeg() {
+    let renditions = render(&encoded(ImageFormat::Jpeg, 64, 128), &[32]).unwrap();
+    assert_eq!(renditions[1].name, "32.jpg");
+    assert_eq!(renditions[1].content_type, "image/jpeg");
+    assert_eq!((renditions[1].width, renditions[1].height), (16, 32));
+  }
+
+  #[test]
+  fn rejects_undecodable_files() {
+    assert!(render(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR fake png", &[100]).is_err());
+  }
+}
@@ -0,0 +1,39 @@
+use crate::adapters::web::auth::Admin;
+use crate::adapters::web::error::ApiError;
+use crate::infrastructure::access_token;
+use crate::AppState;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize)]
+pub(crate) struct AccessTokenResponse {
+  token: String,
+  user_id: Uuid,
+  expires_at: DateTime<Utc>,
+}
+
+/// Issues an access token for a customer app, valid for
+/// `ACCESS_TOKEN_TTL_SECS`.
+pub(crate) async fn issue_access_token(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<AccessTokenResponse>), ApiError> {
+  let user = state.users.get(id).await.map_err(ApiError::from)?;
+  let expires = Utc::now().timestamp() + state.config.access_token_ttl_secs as i64;
+  Ok((
+    StatusCode::CREATED,
+    Json(AccessTokenResponse {
+      token: access_token::issue(&state.config.access_token_secret, user.id, expires),
+      user_id: user.id,
+      expires_at: Utc
+        .timestamp_opt(expires, 0)
+        .single()
+        .expect("expiry is a valid timestamp"),
+    }),
+  ))
+}
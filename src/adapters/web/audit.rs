@@ -0,0 +1,57 @@
+use crate::adapters::web::auth::Admin;
+use crate::adapters::web::error::ApiError;
+use crate::application::ports::AuditFilter;
+use crate::domain::models::{AuditEntry, Page};
+use crate::AppState;
+use axum::extract::{Query, State};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct AuditQuery {
+  /// E.g. `admin` or `user:<id>`.
+  actor: Option<String>,
+  action: Option<String>,
+  resource_type: Option<String>,
+  resource_id: Option<Uuid>,
+  request_id: Option<String>,
+  from: Option<DateTime<Utc>>,
+  to: Option<DateTime<Utc>>,
+  #[serde(default = "first_page")]
+  page: u32,
+  #[serde(default = "default_per_page")]
+  per_page: u32,
+}
+
+fn first_page() -> u32 {
+  1
+}
+
+fn default_per_page() -> u32 {
+  50
+}
+
+/// Audit log entries matching every given filter, newest first.
+pub(crate) async fn list_audit(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Query(query): Query<AuditQuery>,
+) -> Result<Json<Page<AuditEntry>>, ApiError> {
+  let filter = AuditFilter {
+    actor: query.actor,
+    action: query.action,
+    resource_type: query.resource_type,
+    resource_id: query.resource_id,
+    request_id: query.request_id,
+    from: query.from,
+    to: query.to,
+  };
+  let entries = state
+    .audit
+    .list(filter, query.page, query.per_page)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(entries))
+}
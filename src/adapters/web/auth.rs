@@ -0,0 +1,151 @@
+use crate::adapters::web::error::ApiError;
+use crate::domain::models::{Actor, AuditContext};
+use crate::infrastructure::access_token;
+use crate::AppState;
+use async_trait::async_trait;
+use axum::extract::{FromRequestParts, MatchedPath};
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+use axum::http::StatusCode;
+use chrono::Utc;
+use uuid::Uuid;
+
+/// Set on every request and response by the router; a client may send its
+/// own to correlate calls.
+pub(crate) const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// The routes browsers open with `EventSource` or a WebSocket, which cannot
+/// carry headers; only they take a customer token as `?access_token=`.
+pub(crate) const ORDER_STREAM_ROUTE: &str = "/orders/stream";
+pub(crate) const ORDER_TRACKING_ROUTE: &str = "/ws/orders/:id";
+
+/// Extractor for admin-only handlers: requires `Authorization: Bearer
+/// <ADMIN_API_TOKEN>`.
+#[derive(Debug, Clone, Copy)]
+pub struct Admin;
+
+#[async_trait]
+impl FromRequestParts<AppState> for Admin {
+  type Rejection = ApiError;
+
+  async fn from_request_parts(
+    parts: &mut Parts,
+    state: &AppState,
+  ) -> Result<Self, Self::Rejection> {
+    let Some(expected) = state.config.admin_api_token.as_deref() else {
+      return Err(ApiError::new(StatusCode::FORBIDDEN, "admin API disabled"));
+    };
+    match bearer_token(parts) {
+      Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(Admin),
+      _ => Err(ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized")),
+    }
+  }
+}
+
+/// Extractor for handlers that show each caller its own data: the admin
+/// token sees everything, a customer access token (see
+/// [`access_token`]) only what belongs to that user. Browsers cannot set
+/// headers on `EventSource` or WebSocket requests, so on those routes a
+/// customer token may also come as `?access_token=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Viewer {
+  Admin,
+  User(Uuid),
+}
+
+impl Viewer {
+  /// Whether the caller may see data owned by `user_id`.
+  pub fn can_see(&self, user_id: Uuid) -> bool {
+    match self {
+      Viewer::Admin => true,
+      Viewer::User(id) => *id == user_id,
+    }
+  }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for Viewer {
+  type Rejection = ApiError;
+
+  async fn from_request_parts(
+    parts: &mut Parts,
+    state: &AppState,
+  ) -> Result<Self, Self::Rejection> {
+    authenticate(parts, state)
+      .ok_or_else(|| ApiError::new(StatusCode::UNAUTHORIZED, "unauthorized"))
+  }
+}
+
+/// Who gets the credit for a change, also on endpoints open to everyone:
+/// whoever the credentials identify, `Anonymous` without valid ones. Carries
+/// the `x-request-id` set by the router.
+#[async_trait]
+impl FromRequestParts<AppState> for AuditContext {
+  type Rejection = ApiError;
+
+  async fn from_request_parts(
+    parts: &mut Parts,
+    state: &AppState,
+  ) -> Result<Self, Self::Rejection> {
+    let actor = match authenticate(parts, state) {
+      Some(Viewer::Admin) => Actor::Admin,
+      Some(Viewer::User(id)) => Actor::User(id),
+      None => Actor::Anonymous,
+    };
+    let request_id = parts
+      .headers
+      .get(REQUEST_ID_HEADER)
+      .and_then(|value| value.to_str().ok())
+      .map(str::to_string);
+    Ok(AuditContext { actor, request_id })
+  }
+}
+
+/// The caller identified by the admin token or a valid customer access
+/// token in the `Authorization` header, or by a customer access token in
+/// the `access_token` query parameter of the order stream and tracking
+/// routes. Query strings end up in logs and browser history, so the admin
+/// token is never taken from one.
+fn authenticate(parts: &Parts, state: &AppState) -> Option<Viewer> {
+  if let Some(token) = bearer_token(parts) {
+    if let Some(expected) = state.config.admin_api_token.as_deref() {
+      if constant_time_eq(token.as_bytes(), expected.as_bytes()) {
+        return Some(Viewer::Admin);
+      }
+    }
+    return customer(token, state);
+  }
+  let route = parts.extensions.get::<MatchedPath>()?.as_str();
+  if route != ORDER_STREAM_ROUTE && route != ORDER_TRACKING_ROUTE {
+    return None;
+  }
+  let query = parts.uri.query()?;
+  let token = url::form_urlencoded::parse(query.as_bytes())
+    .find(|(key, _)| key == "access_token")
+    .map(|(_, token)| token.into_owned())?;
+  customer(&token, state)
+}
+
+fn customer(token: &str, state: &AppState) -> Option<Viewer> {
+  access_token::verify(
+    &state.config.access_token_secret,
+    token,
+    Utc::now().timestamp(),
+  )
+  .map(Viewer::User)
+  .ok()
+}
+
+pub(crate) fn bearer_token(parts: &Parts) -> Option<&str> {
+  parts
+    .headers
+    .get(AUTHORIZATION)?
+    .to_str()
+    .ok()?
+    .strip_prefix("Bearer ")
+    .map(str::trim)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+  a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,125 @@
+use crate::adapters::web::auth::Admin;
+use crate::adapters::web::error::ApiError;
+use crate::adapters::web::present;
+use crate::application::ports::{NewCategory, UpdateCategory};
+use crate::domain::models::{Category, CategoryNode};
+use crate::AppState;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CreateCategoryBody {
+  parent_id: Option<Uuid>,
+  slug: String,
+  name: String,
+  #[serde(default)]
+  position: i32,
+}
+
+pub(crate) async fn create_category(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Json(body): Json<CreateCategoryBody>,
+) -> Result<(StatusCode, Json<Category>), ApiError> {
+  let category = state
+    .categories
+    .create(NewCategory {
+      parent_id: body.parent_id,
+      slug: body.slug,
+      name: body.name,
+      position: body.position,
+    })
+    .await
+    .map_err(ApiError::from)?;
+  Ok((StatusCode::CREATED, Json(category)))
+}
+
+pub(crate) async fn list_categories(
+  State(state): State<AppState>,
+) -> Result<Json<Vec<CategoryNode>>, ApiError> {
+  let tree = state.categories.tree().await.map_err(ApiError::from)?;
+  Ok(Json(tree))
+}
+
+pub(crate) async fn get_category(
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+) -> Result<Json<Category>, ApiError> {
+  let category = state.categories.get(id).await.map_err(ApiError::from)?;
+  Ok(Json(category))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct UpdateCategoryBody {
+  /// Absent keeps the parent; `null` moves to the top level.
+  #[serde(default, deserialize_with = "present")]
+  parent_id: Option<Option<Uuid>>,
+  slug: Option<String>,
+  name: Option<String>,
+  position: Option<i32>,
+}
+
+pub(crate) async fn update_category(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+  Json(body): Json<UpdateCategoryBody>,
+) -> Result<Json<Category>, ApiError> {
+  let category = state
+    .categories
+    .update(
+      id,
+      UpdateCategory {
+        parent_id: body.parent_id,
+        slug: body.slug,
+        name: body.name,
+        position: body.position,
+      },
+    )
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(category))
+}
+
+pub(crate) async fn delete_category(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+  state.categories.delete(id).await.map_err(ApiError::from)?;
+  Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SetProductCategoriesBody {
+  category_ids: Vec<Uuid>,
+}
+
+pub(crate) async fn set_product_categories(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(product_id): Path<Uuid>,
+  Json(body): Json<SetProductCategoriesBody>,
+) -> Result<Json<Vec<Category>>, ApiError> {
+  let categories = state
+    .categories
+    .set_product_categories(product_id, body.category_ids)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(categories))
+}
+
+pub(crate) async fn list_product_categories(
+  State(state): State<AppState>,
+  Path(product_id): Path<Uuid>,
+) -> Result<Json<Vec<Category>>, ApiError> {
+  let categories = state
+    .categories
+    .list_for_product(product_id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(categories))
+}
@@ -0,0 +1,49 @@
+use crate::adapters::web::error::ApiError;
+use crate::application::ports::NewOrderItem;
+use crate::domain::models::{AuditContext, Order};
+use crate::AppState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CheckoutItemBody {
+  product_id: Uuid,
+  quantity: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CheckoutBody {
+  user_id: Uuid,
+  items: Vec<CheckoutItemBody>,
+}
+
+/// Places an order and reserves its stock; `409` when a product is short
+/// and `503` when the storage has no checkout, see `AppState::checkout`.
+pub(crate) async fn checkout(
+  audit: AuditContext,
+  State(state): State<AppState>,
+  Json(body): Json<CheckoutBody>,
+) -> Result<(StatusCode, Json<Order>), ApiError> {
+  let items = body
+    .items
+    .into_iter()
+    .map(|item| NewOrderItem {
+      product_id: item.product_id,
+      quantity: item.quantity,
+    })
+    .collect();
+  let checkout = state.checkout.as_ref().ok_or_else(|| {
+    ApiError::new(
+      StatusCode::SERVICE_UNAVAILABLE,
+      "checkout is not available with STORAGE=sqlite",
+    )
+  })?;
+  let order = checkout
+    .checkout(body.user_id, items, &audit)
+    .await
+    .map_err(ApiError::from)?;
+  Ok((StatusCode::CREATED, Json(order)))
+}
@@ -29,6 +29,7 @@ impl From<RepoError> for ApiError {
     match value {
       RepoError::NotFound => ApiError::new(StatusCode::NOT_FOUND, "not found"),
       RepoError::Conflict => ApiError::new(StatusCode::CONFLICT, "conflict"),
+      RepoError::Invalid(msg) => ApiError::new(StatusCode::BAD_REQUEST, msg),
       RepoError::Unexpected(msg) => ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, msg),
     }
   }
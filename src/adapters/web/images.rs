@@ -0,0 +1,209 @@
+use crate::adapters::web::auth::Admin;
+use crate::adapters::web::error::ApiError;
+use crate::domain::models::ProductImage;
+use crate::infrastructure::config::AppConfig;
+use crate::infrastructure::signed_url;
+use crate::AppState;
+use axum::extract::multipart::{Multipart, MultipartError};
+use axum::extract::{Path, Query, State};
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// An image with signed URLs to download it and its generated variants.
+#[derive(Debug, Serialize)]
+pub(crate) struct ImageResponse {
+  #[serde(flatten)]
+  image: ProductImage,
+  url: String,
+  /// Keyed by variant name, e.g. `480.webp`; empty until thumbnails are ready.
+  variant_urls: BTreeMap<String, String>,
+  url_expires_at: DateTime<Utc>,
+}
+
+fn media_path(id: Uuid) -> String {
+  format!("/media/{id}")
+}
+
+fn variant_path(id: Uuid, name: &str) -> String {
+  format!("/media/{id}/{name}")
+}
+
+pub(crate) fn with_url(config: &AppConfig, image: ProductImage) -> ImageResponse {
+  let expires = Utc::now().timestamp() + config.media_url_ttl_secs as i64;
+  let secret = &config.media_url_secret;
+  ImageResponse {
+    url: signed_url::sign(secret, &media_path(image.id), expires),
+    variant_urls: image
+      .variants
+      .iter()
+      .map(|variant| {
+        let path = variant_path(image.id, &variant.name);
+        (
+          variant.name.clone(),
+          signed_url::sign(secret, &path, expires),
+        )
+      })
+      .collect(),
+    url_expires_at: Utc.timestamp_opt(expires, 0).unwrap(),
+    image,
+  }
+}
+
+fn multipart_err(err: MultipartError) -> ApiError {
+  ApiError::new(StatusCode::BAD_REQUEST, err.to_string())
+}
+
+/// Accepts a `multipart/form-data` body with the image in a `file` field.
+pub(crate) async fn upload_image(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(product_id): Path<Uuid>,
+  mut multipart: Multipart,
+) -> Result<(StatusCode, Json<ImageResponse>), ApiError> {
+  let max_bytes = state.config.media_max_upload_bytes;
+  while let Some(mut field) = multipart.next_field().await.map_err(multipart_err)? {
+    if field.name() != Some("file") {
+      continue;
+    }
+    let content_type = field.content_type().map(str::to_string);
+    // Read chunk by chunk so oversized uploads are cut off early.
+    let mut bytes = Vec::new();
+    while let Some(chunk) = field.chunk().await.map_err(multipart_err)? {
+      if bytes.len() + chunk.len() > max_bytes {
+        return Err(ApiError::new(
+          StatusCode::PAYLOAD_TOO_LARGE,
+          format!("images are limited to {max_bytes} bytes"),
+        ));
+      }
+      bytes.extend_from_slice(&chunk);
+    }
+    let image = state
+      .images
+      .upload(product_id, content_type.as_deref(), bytes)
+      .await
+      .map_err(ApiError::from)?;
+    return Ok((StatusCode::CREATED, Json(with_url(&state.config, image))));
+  }
+  Err(ApiError::new(
+    StatusCode::BAD_REQUEST,
+    "missing multipart field `file`",
+  ))
+}
+
+pub(crate) async fn list_images(
+  State(state): State<AppState>,
+  Path(product_id): Path<Uuid>,
+) -> Result<Json<Vec<ImageResponse>>, ApiError> {
+  let images = state
+    .images
+    .list(product_id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(
+    images
+      .into_iter()
+      .map(|image| with_url(&state.config, image))
+      .collect(),
+  ))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ReorderImagesBody {
+  image_ids: Vec<Uuid>,
+}
+
+pub(crate) async fn reorder_images(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(product_id): Path<Uuid>,
+  Json(body): Json<ReorderImagesBody>,
+) -> Result<Json<Vec<ImageResponse>>, ApiError> {
+  let images = state
+    .images
+    .reorder(product_id, &body.image_ids)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(
+    images
+      .into_iter()
+      .map(|image| with_url(&state.config, image))
+      .collect(),
+  ))
+}
+
+pub(crate) async fn delete_image(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path((product_id, image_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, ApiError> {
+  state
+    .images
+    .delete(product_id, image_id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SignedQuery {
+  expires: i64,
+  signature: String,
+}
+
+/// Checks a signed media URL for `path`, returning how many seconds it
+/// remains valid.
+fn verify_signed(config: &AppConfig, path: &str, query: &SignedQuery) -> Result<i64, ApiError> {
+  let now = Utc::now().timestamp();
+  signed_url::verify(
+    &config.media_url_secret,
+    path,
+    query.expires,
+    &query.signature,
+    now,
+  )
+  .map_err(|err| ApiError::new(StatusCode::FORBIDDEN, err.to_string()))?;
+  Ok(query.expires - now)
+}
+
+fn media_response(content_type: String, max_age: i64, bytes: Vec<u8>) -> Response {
+  (
+    [
+      (CONTENT_TYPE, content_type),
+      (CACHE_CONTROL, format!("private, max-age={max_age}")),
+    ],
+    bytes,
+  )
+    .into_response()
+}
+
+/// Serves media through URLs signed by the image endpoints.
+pub(crate) async fn download_media(
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+  Query(query): Query<SignedQuery>,
+) -> Result<Response, ApiError> {
+  let max_age = verify_signed(&state.config, &media_path(id), &query)?;
+  let (image, bytes) = state.images.download(id).await.map_err(ApiError::from)?;
+  Ok(media_response(image.content_type, max_age, bytes))
+}
+
+/// Serves a generated variant, e.g. `/media/:id/480.webp`.
+pub(crate) async fn download_media_variant(
+  State(state): State<AppState>,
+  Path((id, name)): Path<(Uuid, String)>,
+  Query(query): Query<SignedQuery>,
+) -> Result<Response, ApiError> {
+  let max_age = verify_signed(&state.config, &variant_path(id, &name), &query)?;
+  let (variant, bytes) = state
+    .images
+    .download_variant(id, &name)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(media_response(variant.content_type, max_age, bytes))
+}
@@ -0,0 +1,210 @@
+use crate::adapters::pdf::{self, PackingSlip, PackingSlipLine};
+use crate::adapters::web::auth::Admin;
+use crate::adapters::web::error::ApiError;
+use crate::domain::invoicing::format_cents;
+use crate::domain::models::{BillingAddress, Invoice};
+use crate::AppState;
+use axum::extract::{Path, State};
+use axum::http::header::{ACCEPT, CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+use std::fmt::Write;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct IssueInvoiceBody {
+  billing_address: BillingAddress,
+}
+
+pub(crate) async fn issue_invoice(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(order_id): Path<Uuid>,
+  Json(body): Json<IssueInvoiceBody>,
+) -> Result<(StatusCode, Json<Invoice>), ApiError> {
+  let invoice = state
+    .invoices
+    .issue(order_id, body.billing_address)
+    .await
+    .map_err(ApiError::from)?;
+  Ok((StatusCode::CREATED, Json(invoice)))
+}
+
+/// Returns the invoice as JSON, or as an HTML document when the client
+/// prefers `text/html`.
+pub(crate) async fn get_invoice(
+  State(state): State<AppState>,
+  Path(order_id): Path<Uuid>,
+  headers: HeaderMap,
+) -> Result<Response, ApiError> {
+  let invoice = state
+    .invoices
+    .get_for_order(order_id)
+    .await
+    .map_err(ApiError::from)?;
+  let wants_html = headers
+    .get(ACCEPT)
+    .and_then(|v| v.to_str().ok())
+    .is_some_and(|accept| accept.contains("text/html"));
+  if wants_html {
+    Ok(Html(render_html(&invoice)).into_response())
+  } else {
+    Ok(Json(invoice).into_response())
+  }
+}
+
+pub(crate) async fn get_invoice_pdf(
+  State(state): State<AppState>,
+  Path(order_id): Path<Uuid>,
+) -> Result<Response, ApiError> {
+  let invoice = state
+    .invoices
+    .get_for_order(order_id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(pdf_response(
+    &format!("{}.pdf", invoice.number),
+    pdf::render_invoice(&invoice),
+  ))
+}
+
+/// Packing slips are built from the live order, so the warehouse can print
+/// one before the order has been invoiced.
+pub(crate) async fn get_packing_slip_pdf(
+  State(state): State<AppState>,
+  Path(order_id): Path<Uuid>,
+) -> Result<Response, ApiError> {
+  let order = state.orders.get(order_id).await.map_err(ApiError::from)?;
+  let customer = state
+    .users
+    .get(order.user_id)
+    .await
+    .map_err(ApiError::from)?;
+  let items = state
+    .orders
+    .list_items(order_id)
+    .await
+    .map_err(ApiError::from)?;
+  let mut lines = Vec::with_capacity(items.len());
+  for item in items {
+    let product = state
+      .products
+      .get(item.product_id)
+      .await
+      .map_err(ApiError::from)?;
+    lines.push(PackingSlipLine {
+      sku: product.sku,
+      name: product.name,
+      quantity: item.quantity,
+    });
+  }
+  let slip = PackingSlip {
+    order_id,
+    ordered_at: order.created_at,
+    customer_name: customer.name,
+    customer_email: customer.email,
+    lines,
+  };
+  Ok(pdf_response(
+    &format!("packing-slip-{order_id}.pdf"),
+    pdf::render_packing_slip(&slip),
+  ))
+}
+
+fn pdf_response(filename: &str, body: Vec<u8>) -> Response {
+  (
+    [
+      (CONTENT_TYPE, "application/pdf".to_string()),
+      (
+        CONTENT_DISPOSITION,
+        format!("inline; filename=\"{filename}\""),
+      ),
+    ],
+    body,
+  )
+    .into_response()
+}
+
+pub(crate) async fn list_credit_notes(
+  State(state): State<AppState>,
+  Path(order_id): Path<Uuid>,
+) -> Result<Json<Vec<Invoice>>, ApiError> {
+  let notes = state
+    .invoices
+    .list_credit_notes(order_id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(notes))
+}
+
+fn escape(text: &str) -> String {
+  let mut out = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' => out.push_str("&amp;"),
+      '<' => out.push_str("&lt;"),
+      '>' => out.push_str("&gt;"),
+      '"' => out.push_str("&quot;"),
+      '\'' => out.push_str("&#39;"),
+      _ => out.push(c),
+    }
+  }
+  out
+}
+
+pub(crate) fn render_html(invoice: &Invoice) -> String {
+  let title = if invoice.kind == "credit_note" {
+    "Credit note"
+  } else {
+    "Invoice"
+  };
+  let address = &invoice.billing_address;
+  let mut html = String::new();
+
+  // Writing to a String cannot fail.
+  let _ = write!(
+    html,
+    "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+     <title>{title} {number}</title>\n</head>\n<body>\n<h1>{title} {number}</h1>\n\
+     <p>Issued {issued}</p>\n<address>\n{name}<br>\n{line1}<br>\n",
+    number = escape(&invoice.number),
+    issued = invoice.issued_at.format("%Y-%m-%d"),
+    name = escape(&address.name),
+    line1 = escape(&address.line1),
+  );
+  if let Some(line2) = &address.line2 {
+    let _ = writeln!(html, "{}<br>", escape(line2));
+  }
+  let _ = write!(
+    html,
+    "{postal} {city}<br>\n{country}\n</address>\n<table>\n<thead>\n<tr><th>Description</th>\
+     <th>Qty</th><th>Unit price</th><th>Net</th><th>Tax</th><th>Total</th></tr>\n</thead>\n<tbody>\n",
+    postal = escape(&address.postal_code),
+    city = escape(&address.city),
+    country = escape(&address.country),
+  );
+  for line in &invoice.lines {
+    let _ = writeln!(
+      html,
+      "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+      escape(&line.description),
+      line.quantity,
+      format_cents(line.unit_price_cents),
+      format_cents(line.net_cents),
+      format_cents(line.tax_cents),
+      format_cents(line.total_cents),
+    );
+  }
+  let _ = write!(
+    html,
+    "</tbody>\n</table>\n<p>Net: {net}</p>\n<p>Tax ({rate}%): {tax}</p>\n<p><strong>Total: {total}</strong></p>\n\
+     </body>\n</html>\n",
+    net = format_cents(invoice.net_cents),
+    rate = format_cents(i64::from(invoice.tax_rate_bps)),
+    tax = format_cents(invoice.tax_cents),
+    total = format_cents(invoice.total_cents),
+  );
+  html
+}
@@ -1,2 +1,31 @@
+pub mod access_tokens;
+pub mod audit;
+pub mod auth;
+pub mod categories;
+pub mod checkout;
 pub mod error;
+pub mod images;
+pub mod invoices;
+pub mod order_history;
+pub mod order_stream;
+pub mod order_tracking;
+pub mod payments;
+pub mod prices;
+pub mod returns;
+pub mod reviews;
 pub mod router;
+pub mod variants;
+pub mod webhooks;
+pub mod wishlists;
+
+use serde::{Deserialize, Deserializer};
+
+/// For nullable fields in partial updates: tells an absent field (`None`)
+/// apart from an explicit `null` (`Some(None)`). Use with `#[serde(default)]`.
+pub(crate) fn present<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+  T: Deserialize<'de>,
+  D: Deserializer<'de>,
+{
+  Option::<T>::deserialize(deserializer).map(Some)
+}
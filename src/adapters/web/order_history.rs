@@ -0,0 +1,44 @@
+use crate::adapters::web::auth::Admin;
+use crate::adapters::web::error::ApiError;
+use crate::domain::models::{Order, OrderHistoryEntry};
+use crate::AppState;
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// Every change to the order, oldest first; still available once the order
+/// is deleted.
+pub(crate) async fn get_history(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(order_id): Path<Uuid>,
+) -> Result<Json<Vec<OrderHistoryEntry>>, ApiError> {
+  let history = state
+    .orders
+    .history(order_id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(history))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct StateAtQuery {
+  at: DateTime<Utc>,
+}
+
+/// The order as it was at `at`, rebuilt from its history.
+pub(crate) async fn get_state_at(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(order_id): Path<Uuid>,
+  Query(query): Query<StateAtQuery>,
+) -> Result<Json<Order>, ApiError> {
+  let order = state
+    .orders
+    .state_at(order_id, query.at)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(order))
+}
@@ -0,0 +1,71 @@
+use crate::adapters::order_stream::{OrderStreamEvent, OrderStreamMessage};
+use crate::adapters::web::auth::Viewer;
+use crate::AppState;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures::stream::{self, Stream, StreamExt};
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
+
+const LAST_EVENT_ID: &str = "last-event-id";
+
+fn sse_event(event: &OrderStreamEvent) -> Event {
+  Event::default()
+    .id(event.id.to_string())
+    .event(event.kind)
+    .data(event.data.to_string())
+}
+
+/// Tells the client it may have missed events and should reload the orders.
+fn resync_event() -> Event {
+  Event::default().event("resync").data("{}")
+}
+
+/// Server-sent order events visible to the caller. A client reconnecting
+/// with `Last-Event-ID` first gets the buffered events it missed, or a
+/// `resync` event when they are no longer buffered. The stream ends on
+/// shutdown so it does not keep the server from stopping.
+pub(crate) async fn stream_orders(
+  viewer: Viewer,
+  State(state): State<AppState>,
+  headers: HeaderMap,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+  // An id this instance cannot parse was never one of ours: resync.
+  let last_event_id = headers.get(LAST_EVENT_ID).map(|value| {
+    value
+      .to_str()
+      .ok()
+      .and_then(|id| id.parse().ok())
+      .unwrap_or(-1)
+  });
+  let subscription = state.order_stream.subscribe(last_event_id);
+
+  let mut initial = Vec::new();
+  if subscription.resync {
+    initial.push(resync_event());
+  }
+  initial.extend(
+    subscription
+      .replay
+      .iter()
+      .filter(|event| viewer.can_see(event.user_id))
+      .map(sse_event),
+  );
+  let live = stream::unfold(subscription.live, move |mut live| async move {
+    loop {
+      let event = match live.recv().await {
+        Ok(OrderStreamMessage::Event(event)) if viewer.can_see(event.user_id) => sse_event(&event),
+        Ok(OrderStreamMessage::Event(_)) => continue,
+        Ok(OrderStreamMessage::Resync) | Err(RecvError::Lagged(_)) => resync_event(),
+        Err(RecvError::Closed) => return None,
+      };
+      return Some((Ok(event), live));
+    }
+  });
+  let shutdown = state.shutdown.clone();
+  let events = stream::iter(initial.into_iter().map(Ok))
+    .chain(live)
+    .take_until(async move { shutdown.triggered().await });
+  Sse::new(events).keep_alive(KeepAlive::default())
+}
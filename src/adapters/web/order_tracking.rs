@@ -0,0 +1,188 @@
+use crate::adapters::order_stream::OrderStreamMessage;
+use crate::adapters::web::auth::Viewer;
+use crate::adapters::web::error::ApiError;
+use crate::application::ports::RepoError;
+use crate::AppState;
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::Response;
+use serde::Serialize;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+
+/// Messages sent to a tracking client, as JSON text frames.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OrderUpdate {
+  /// The order as it is when the socket opens.
+  Snapshot { order: Value },
+  StatusChanged {
+    from: String,
+    status: String,
+    order: Value,
+  },
+  /// Last message; the server closes the socket after it.
+  Deleted { order_id: Uuid },
+}
+
+/// Pushes status changes of one order over a WebSocket. Customers may only
+/// track their own orders; others get 404 as if the order did not exist.
+pub(crate) async fn track_order(
+  viewer: Viewer,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+  ws: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+  // Subscribe before loading the snapshot, so a change committed in between
+  // is still pushed. Changes the snapshot already reflects are dropped by
+  // `Tracker::changed`, as the status does not move.
+  let live = state.order_stream.subscribe(None).live;
+  let order = state.orders.get(id).await.map_err(ApiError::from)?;
+  if !viewer.can_see(order.user_id) {
+    return Err(ApiError::from(RepoError::NotFound));
+  }
+  let Some(connection) = state.shutdown.hold().await else {
+    return Err(ApiError::new(
+      StatusCode::SERVICE_UNAVAILABLE,
+      "shutting down",
+    ));
+  };
+  let snapshot = serde_json::to_value(&order).expect("orders serialize");
+  Ok(ws.on_upgrade(move |socket| async move {
+    let tracker = Tracker {
+      socket,
+      state,
+      order_id: id,
+      status: order.status,
+    };
+    tracker.run(snapshot, live).await;
+    drop(connection);
+  }))
+}
+
+struct Tracker {
+  socket: WebSocket,
+  state: AppState,
+  order_id: Uuid,
+  status: String,
+}
+
+enum Next {
+  Continue,
+  Close(u16, &'static str),
+  /// The client is gone; nothing left to say.
+  Stop,
+}
+
+impl Tracker {
+  async fn run(
+    mut self,
+    snapshot: Value,
+    mut live: tokio::sync::broadcast::Receiver<OrderStreamMessage>,
+  ) {
+    let interval = Duration::from_secs(self.state.config.ws_heartbeat_interval_secs);
+    let mut heartbeat = tokio::time::interval(interval);
+    heartbeat.tick().await;
+    let mut last_heard = Instant::now();
+    let shutdown = self.state.shutdown.clone();
+    let stopping = shutdown.triggered();
+    tokio::pin!(stopping);
+
+    let mut next = self.send(&OrderUpdate::Snapshot { order: snapshot }).await;
+    while let Next::Continue = next {
+      next = tokio::select! {
+        _ = &mut stopping => Next::Close(close_code::AWAY, "server shutting down"),
+        _ = heartbeat.tick() => {
+          if last_heard.elapsed() > interval * 2 {
+            Next::Stop
+          } else {
+            self.socket.send(Message::Ping(Vec::new())).await.map_or(Next::Stop, |_| Next::Continue)
+          }
+        }
+        incoming = self.socket.recv() => match incoming {
+          Some(Ok(Message::Close(_))) | Some(Err(_)) | None => Next::Stop,
+          Some(Ok(_)) => {
+            last_heard = Instant::now();
+            Next::Continue
+          }
+        },
+        message = live.recv() => match message {
+          Ok(OrderStreamMessage::Event(event)) if event.data["id"] == self.order_id.to_string() => {
+            match event.kind {
+              "order.updated" => self.changed(event.data).await,
+              "order.deleted" => self.deleted().await,
+              _ => Next::Continue,
+            }
+          }
+          Ok(OrderStreamMessage::Event(_)) => Next::Continue,
+          // Changes may have been missed: compare with the stored order.
+          Ok(OrderStreamMessage::Resync) | Err(RecvError::Lagged(_)) => self.reload().await,
+          Err(RecvError::Closed) => Next::Close(close_code::AWAY, "server shutting down"),
+        },
+      };
+    }
+    if let Next::Close(code, reason) = next {
+      let _ = self
+        .socket
+        .send(Message::Close(Some(CloseFrame {
+          code,
+          reason: reason.into(),
+        })))
+        .await;
+    }
+  }
+
+  async fn send(&mut self, update: &OrderUpdate) -> Next {
+    let text = serde_json::to_string(update).expect("updates serialize");
+    match self.socket.send(Message::Text(text)).await {
+      Ok(()) => Next::Continue,
+      Err(_) => Next::Stop,
+    }
+  }
+
+  async fn changed(&mut self, order: Value) -> Next {
+    let Some(status) = order["status"].as_str().map(str::to_string) else {
+      return Next::Continue;
+    };
+    if status == self.status {
+      return Next::Continue;
+    }
+    let from = std::mem::replace(&mut self.status, status.clone());
+    self
+      .send(&OrderUpdate::StatusChanged {
+        from,
+        status,
+        order,
+      })
+      .await
+  }
+
+  async fn deleted(&mut self) -> Next {
+    match self
+      .send(&OrderUpdate::Deleted {
+        order_id: self.order_id,
+      })
+      .await
+    {
+      Next::Continue => Next::Close(close_code::NORMAL, "order deleted"),
+      next => next,
+    }
+  }
+
+  async fn reload(&mut self) -> Next {
+    match self.state.orders.get(self.order_id).await {
+      Ok(order) => {
+        let order = serde_json::to_value(&order).expect("orders serialize");
+        self.changed(order).await
+      }
+      Err(RepoError::NotFound) => self.deleted().await,
+      Err(err) => {
+        tracing::error!(error = %err, order_id = %self.order_id, "could not reload tracked order");
+        Next::Close(close_code::ERROR, "internal error")
+      }
+    }
+  }
+}
@@ -0,0 +1,130 @@
+use crate::adapters::web::auth::Admin;
+use crate::adapters::web::error::ApiError;
+use crate::application::ports::{NewPayment, PaymentEvent, PaymentEventOutcome};
+use crate::domain::models::{Actor, AuditContext, Payment};
+use crate::infrastructure::signature::{self, SignatureError};
+use crate::AppState;
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CreatePaymentBody {
+  provider: String,
+  provider_payment_id: String,
+  amount_cents: i64,
+}
+
+pub(crate) async fn create_payment(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(order_id): Path<Uuid>,
+  Json(body): Json<CreatePaymentBody>,
+) -> Result<(StatusCode, Json<Payment>), ApiError> {
+  let payment = state
+    .payments
+    .create(NewPayment {
+      order_id,
+      provider: body.provider,
+      provider_payment_id: body.provider_payment_id,
+      amount_cents: body.amount_cents,
+    })
+    .await
+    .map_err(ApiError::from)?;
+  Ok((StatusCode::CREATED, Json(payment)))
+}
+
+pub(crate) async fn list_payments(
+  State(state): State<AppState>,
+  Path(order_id): Path<Uuid>,
+) -> Result<Json<Vec<Payment>>, ApiError> {
+  let payments = state
+    .payments
+    .list_by_order(order_id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(payments))
+}
+
+/// Normalized event envelope every provider integration posts.
+#[derive(Debug, Deserialize)]
+struct PaymentEventBody {
+  id: String,
+  #[serde(rename = "type")]
+  event_type: String,
+  payment_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct WebhookResponse {
+  status: &'static str,
+}
+
+pub(crate) async fn payment_webhook(
+  audit: AuditContext,
+  State(state): State<AppState>,
+  Path(provider): Path<String>,
+  headers: HeaderMap,
+  body: Bytes,
+) -> Result<Json<WebhookResponse>, ApiError> {
+  let secret = state
+    .config
+    .payment_webhook_secrets
+    .get(&provider)
+    .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, "unknown payment provider"))?;
+  let header = headers
+    .get(SIGNATURE_HEADER)
+    .and_then(|v| v.to_str().ok())
+    .ok_or_else(|| ApiError::new(StatusCode::UNAUTHORIZED, "missing signature"))?;
+  signature::verify(
+    secret,
+    header,
+    &body,
+    chrono::Utc::now().timestamp(),
+    state.config.payment_webhook_tolerance_secs,
+  )
+  .map_err(|err| match err {
+    SignatureError::Malformed => ApiError::new(StatusCode::BAD_REQUEST, err.to_string()),
+    SignatureError::Expired | SignatureError::Mismatch => {
+      ApiError::new(StatusCode::UNAUTHORIZED, err.to_string())
+    }
+  })?;
+
+  // Only parse the body once it is known to come from the provider.
+  let payload: serde_json::Value = serde_json::from_slice(&body)
+    .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+  let event: PaymentEventBody = serde_json::from_value(payload.clone())
+    .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+
+  // The provider, not the (anonymous) caller, makes the change.
+  let audit = AuditContext {
+    actor: Actor::System(format!("payments:{provider}")),
+    ..audit
+  };
+  let outcome = state
+    .payments
+    .handle_event(
+      PaymentEvent {
+        provider,
+        event_id: event.id,
+        event_type: event.event_type,
+        provider_payment_id: event.payment_id,
+        payload,
+      },
+      &audit,
+    )
+    .await
+    .map_err(ApiError::from)?;
+
+  let status = match outcome {
+    PaymentEventOutcome::Applied(_) => "applied",
+    PaymentEventOutcome::Duplicate => "duplicate",
+    PaymentEventOutcome::Ignored => "ignored",
+  };
+  Ok(Json(WebhookResponse { status }))
+}
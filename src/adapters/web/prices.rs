@@ -0,0 +1,77 @@
+use crate::adapters::web::auth::Admin;
+use crate::adapters::web::error::ApiError;
+use crate::domain::models::{AuditContext, ProductPrice};
+use crate::AppState;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// Past, current and scheduled prices, oldest first.
+pub(crate) async fn list_prices(
+  State(state): State<AppState>,
+  Path(product_id): Path<Uuid>,
+) -> Result<Json<Vec<ProductPrice>>, ApiError> {
+  let prices = state
+    .products
+    .price_history(product_id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(prices))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct PriceAtQuery {
+  /// Defaults to now.
+  at: Option<DateTime<Utc>>,
+}
+
+pub(crate) async fn get_price(
+  State(state): State<AppState>,
+  Path(product_id): Path<Uuid>,
+  Query(query): Query<PriceAtQuery>,
+) -> Result<Json<ProductPrice>, ApiError> {
+  let price = state
+    .products
+    .price_at(product_id, query.at.unwrap_or_else(Utc::now))
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(price))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SchedulePriceBody {
+  price_cents: i64,
+  valid_from: DateTime<Utc>,
+}
+
+pub(crate) async fn schedule_price(
+  _admin: Admin,
+  audit: AuditContext,
+  State(state): State<AppState>,
+  Path(product_id): Path<Uuid>,
+  Json(body): Json<SchedulePriceBody>,
+) -> Result<(StatusCode, Json<ProductPrice>), ApiError> {
+  let price = state
+    .products
+    .schedule_price(product_id, body.price_cents, body.valid_from, &audit)
+    .await
+    .map_err(ApiError::from)?;
+  Ok((StatusCode::CREATED, Json(price)))
+}
+
+pub(crate) async fn cancel_scheduled_price(
+  _admin: Admin,
+  audit: AuditContext,
+  State(state): State<AppState>,
+  Path((product_id, price_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, ApiError> {
+  state
+    .products
+    .cancel_scheduled_price(product_id, price_id, &audit)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(StatusCode::NO_CONTENT)
+}
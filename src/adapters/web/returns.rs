@@ -0,0 +1,160 @@
+use crate::adapters::web::auth::Admin;
+use crate::adapters::web::error::ApiError;
+use crate::application::ports::{NewReturn, NewReturnItem};
+use crate::domain::models::{AuditContext, Refund, ReturnRequest};
+use crate::AppState;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ReturnItemBody {
+  order_item_id: Uuid,
+  quantity: i32,
+  reason: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CreateReturnBody {
+  items: Vec<ReturnItemBody>,
+  note: Option<String>,
+}
+
+pub(crate) async fn create_return(
+  State(state): State<AppState>,
+  Path(order_id): Path<Uuid>,
+  Json(body): Json<CreateReturnBody>,
+) -> Result<(StatusCode, Json<ReturnRequest>), ApiError> {
+  let created = state
+    .returns
+    .create(
+      order_id,
+      NewReturn {
+        items: body
+          .items
+          .into_iter()
+          .map(|item| NewReturnItem {
+            order_item_id: item.order_item_id,
+            quantity: item.quantity,
+            reason: item.reason,
+          })
+          .collect(),
+        note: body.note,
+      },
+    )
+    .await
+    .map_err(ApiError::from)?;
+  Ok((StatusCode::CREATED, Json(created)))
+}
+
+pub(crate) async fn list_returns(
+  State(state): State<AppState>,
+  Path(order_id): Path<Uuid>,
+) -> Result<Json<Vec<ReturnRequest>>, ApiError> {
+  let returns = state
+    .returns
+    .list_by_order(order_id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(returns))
+}
+
+pub(crate) async fn list_refunds(
+  State(state): State<AppState>,
+  Path(order_id): Path<Uuid>,
+) -> Result<Json<Vec<Refund>>, ApiError> {
+  let refunds = state
+    .returns
+    .list_refunds(order_id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(refunds))
+}
+
+pub(crate) async fn get_return(
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+) -> Result<Json<ReturnRequest>, ApiError> {
+  let found = state.returns.get(id).await.map_err(ApiError::from)?;
+  Ok(Json(found))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ApproveReturnBody {
+  #[serde(default = "default_restock")]
+  restock: bool,
+  note: Option<String>,
+}
+
+fn default_restock() -> bool {
+  true
+}
+
+pub(crate) async fn approve_return(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+  Json(body): Json<ApproveReturnBody>,
+) -> Result<Json<ReturnRequest>, ApiError> {
+  let approved = state
+    .returns
+    .approve(id, body.restock, body.note)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(approved))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RejectReturnBody {
+  note: Option<String>,
+}
+
+pub(crate) async fn reject_return(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+  Json(body): Json<RejectReturnBody>,
+) -> Result<Json<ReturnRequest>, ApiError> {
+  let rejected = state
+    .returns
+    .reject(id, body.note)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(rejected))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RefundReturnBody {
+  /// Omit to refund whatever is left of the return's value.
+  amount_cents: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct RefundResponse {
+  #[serde(rename = "return")]
+  return_request: ReturnRequest,
+  refund: Refund,
+}
+
+pub(crate) async fn refund_return(
+  _admin: Admin,
+  audit: AuditContext,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+  Json(body): Json<RefundReturnBody>,
+) -> Result<(StatusCode, Json<RefundResponse>), ApiError> {
+  let (return_request, refund) = state
+    .returns
+    .refund(id, body.amount_cents, &audit)
+    .await
+    .map_err(ApiError::from)?;
+  Ok((
+    StatusCode::CREATED,
+    Json(RefundResponse {
+      return_request,
+      refund,
+    }),
+  ))
+}
@@ -0,0 +1,149 @@
+use crate::adapters::web::auth::Admin;
+use crate::adapters::web::error::ApiError;
+use crate::application::ports::NewReview;
+use crate::domain::models::{Page, Review};
+use crate::AppState;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CreateReviewBody {
+  user_id: Uuid,
+  rating: i16,
+  title: String,
+  #[serde(default)]
+  body: String,
+}
+
+pub(crate) async fn create_review(
+  State(state): State<AppState>,
+  Path(product_id): Path<Uuid>,
+  Json(body): Json<CreateReviewBody>,
+) -> Result<(StatusCode, Json<Review>), ApiError> {
+  let created = state
+    .reviews
+    .create(
+      product_id,
+      NewReview {
+        user_id: body.user_id,
+        rating: body.rating,
+        title: body.title,
+        body: body.body,
+      },
+    )
+    .await
+    .map_err(ApiError::from)?;
+  Ok((StatusCode::CREATED, Json(created)))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ReviewPageQuery {
+  #[serde(default = "first_page")]
+  page: u32,
+  #[serde(default = "default_per_page")]
+  per_page: u32,
+}
+
+fn first_page() -> u32 {
+  1
+}
+
+fn default_per_page() -> u32 {
+  20
+}
+
+/// Approved reviews of the product, newest first.
+pub(crate) async fn list_product_reviews(
+  State(state): State<AppState>,
+  Path(product_id): Path<Uuid>,
+  Query(query): Query<ReviewPageQuery>,
+) -> Result<Json<Page<Review>>, ApiError> {
+  let reviews = state
+    .reviews
+    .list_published(product_id, query.page, query.per_page)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(reviews))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ModerationQueueQuery {
+  #[serde(default = "pending")]
+  status: String,
+  #[serde(default = "first_page")]
+  page: u32,
+  #[serde(default = "default_per_page")]
+  per_page: u32,
+}
+
+fn pending() -> String {
+  "pending".into()
+}
+
+/// Reviews of every product in one moderation state, `pending` by default.
+pub(crate) async fn list_reviews(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Query(query): Query<ModerationQueueQuery>,
+) -> Result<Json<Page<Review>>, ApiError> {
+  let reviews = state
+    .reviews
+    .list_by_status(&query.status, query.page, query.per_page)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(reviews))
+}
+
+pub(crate) async fn get_review(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+) -> Result<Json<Review>, ApiError> {
+  let review = state.reviews.get(id).await.map_err(ApiError::from)?;
+  Ok(Json(review))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ModerateReviewBody {
+  note: Option<String>,
+}
+
+pub(crate) async fn approve_review(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+  Json(body): Json<ModerateReviewBody>,
+) -> Result<Json<Review>, ApiError> {
+  let approved = state
+    .reviews
+    .approve(id, body.note)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(approved))
+}
+
+pub(crate) async fn reject_review(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+  Json(body): Json<ModerateReviewBody>,
+) -> Result<Json<Review>, ApiError> {
+  let rejected = state
+    .reviews
+    .reject(id, body.note)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(rejected))
+}
+
+pub(crate) async fn delete_review(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+  state.reviews.delete(id).await.map_err(ApiError::from)?;
+  Ok(StatusCode::NO_CONTENT)
+}
@@ -1,16 +1,27 @@
+use crate::adapters::web::auth::{
+  Admin, ORDER_STREAM_ROUTE, ORDER_TRACKING_ROUTE, REQUEST_ID_HEADER,
+};
 use crate::adapters::web::error::ApiError;
+use crate::adapters::web::{
+  access_tokens, audit, categories, checkout, images, invoices, order_history, order_stream,
+  order_tracking, payments, prices, returns, reviews, variants, webhooks, wishlists,
+};
 use crate::application::ports::{
-  NewOrder, NewProduct, NewUser, UpdateOrder, UpdateProduct, UpdateUser,
+  NewOrder, NewOrderItem, NewProduct, NewUser, UpdateOrder, UpdateProduct, UpdateUser,
 };
+use crate::domain::models::{AuditContext, Page, Product, ProductSearchHit, SimilarityHit, User};
+use crate::infrastructure::config::Storage;
 use crate::AppState;
-use axum::extract::{Path, State};
-use axum::http::StatusCode;
-use axum::routing::{get, post};
+use axum::extract::DefaultBodyLimit;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderName, StatusCode};
+use axum::routing::{delete, get, post, put};
 use axum::{Json, Router};
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use std::future::Future;
 use std::time::Duration;
 use tower_http::cors::{Any, CorsLayer};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
 use tower_http::trace::TraceLayer;
 use tracing::Level;
 use uuid::Uuid;
@@ -19,20 +30,176 @@ pub fn router(state: AppState) -> Router {
   Router::new()
     .route("/health", get(health))
     .route("/users", post(create_user).get(list_users))
+    .route("/users/search", get(search_users))
     .route(
       "/users/:id",
       get(get_user).put(update_user).delete(delete_user),
     )
+    .route(
+      "/users/:id/access-tokens",
+      post(access_tokens::issue_access_token),
+    )
+    .route(
+      "/users/:id/wishlists",
+      post(wishlists::create_wishlist).get(wishlists::list_wishlists),
+    )
+    .route(
+      "/users/:id/wishlists/:wishlist_id",
+      get(wishlists::get_wishlist)
+        .put(wishlists::rename_wishlist)
+        .delete(wishlists::delete_wishlist),
+    )
+    .route(
+      "/users/:id/wishlists/:wishlist_id/items",
+      post(wishlists::add_wishlist_item),
+    )
+    .route(
+      "/users/:id/wishlists/:wishlist_id/items/:product_id",
+      delete(wishlists::remove_wishlist_item),
+    )
+    .route(
+      "/users/:id/wishlists/:wishlist_id/items/:product_id/move-to-cart",
+      post(wishlists::move_to_cart),
+    )
     .route("/products", post(create_product).get(list_products))
+    .route("/products/search", get(search_products))
+    .route("/products/fuzzy-search", get(fuzzy_search_products))
     .route(
       "/products/:id",
-      get(get_product).put(update_product).delete(delete_product),
+      get(variants::get_product_details)
+        .put(update_product)
+        .delete(delete_product),
+    )
+    .route("/products/:id/stock", get(get_stock).put(set_stock))
+    .route(
+      "/products/:id/prices",
+      get(prices::list_prices).post(prices::schedule_price),
+    )
+    .route(
+      "/products/:id/prices/:price_id",
+      delete(prices::cancel_scheduled_price),
+    )
+    .route("/products/:id/price", get(prices::get_price))
+    .route(
+      "/products/:id/reviews",
+      post(reviews::create_review).get(reviews::list_product_reviews),
+    )
+    .route("/reviews", get(reviews::list_reviews))
+    .route(
+      "/reviews/:id",
+      get(reviews::get_review).delete(reviews::delete_review),
+    )
+    .route("/reviews/:id/approve", post(reviews::approve_review))
+    .route("/reviews/:id/reject", post(reviews::reject_review))
+    .route(
+      "/products/:id/images",
+      // The upload handler enforces its own size limit while streaming.
+      post(images::upload_image)
+        .layer(DefaultBodyLimit::disable())
+        .get(images::list_images),
+    )
+    .route("/products/:id/images/order", put(images::reorder_images))
+    .route(
+      "/products/:id/images/:image_id",
+      delete(images::delete_image),
+    )
+    .route("/media/:id", get(images::download_media))
+    .route("/media/:id/:variant", get(images::download_media_variant))
+    .route(
+      "/products/:id/options",
+      post(variants::add_option).get(variants::list_options),
+    )
+    .route(
+      "/products/:id/variants",
+      post(variants::create_variant).get(variants::list_variants),
+    )
+    .route(
+      "/variants/:id",
+      get(variants::get_variant)
+        .put(variants::update_variant)
+        .delete(variants::delete_variant),
+    )
+    .route(
+      "/products/:id/categories",
+      get(categories::list_product_categories).put(categories::set_product_categories),
+    )
+    .route(
+      "/categories",
+      post(categories::create_category).get(categories::list_categories),
+    )
+    .route(
+      "/categories/:id",
+      get(categories::get_category)
+        .put(categories::update_category)
+        .delete(categories::delete_category),
     )
     .route("/orders", post(create_order).get(list_orders))
+    .route("/checkout", post(checkout::checkout))
+    .route(ORDER_STREAM_ROUTE, get(order_stream::stream_orders))
+    .route(ORDER_TRACKING_ROUTE, get(order_tracking::track_order))
     .route(
       "/orders/:id",
       get(get_order).put(update_order).delete(delete_order),
     )
+    .route(
+      "/orders/:id/items",
+      post(add_order_item).get(list_order_items),
+    )
+    .route("/orders/:id/history", get(order_history::get_history))
+    .route(
+      "/orders/:id/history/state",
+      get(order_history::get_state_at),
+    )
+    .route(
+      "/orders/:id/returns",
+      post(returns::create_return).get(returns::list_returns),
+    )
+    .route("/orders/:id/refunds", get(returns::list_refunds))
+    .route(
+      "/orders/:id/invoice",
+      post(invoices::issue_invoice).get(invoices::get_invoice),
+    )
+    .route("/orders/:id/invoice.pdf", get(invoices::get_invoice_pdf))
+    .route(
+      "/orders/:id/packing-slip.pdf",
+      get(invoices::get_packing_slip_pdf),
+    )
+    .route("/orders/:id/credit-notes", get(invoices::list_credit_notes))
+    .route("/returns/:id", get(returns::get_return))
+    .route("/returns/:id/approve", post(returns::approve_return))
+    .route("/returns/:id/reject", post(returns::reject_return))
+    .route("/returns/:id/refunds", post(returns::refund_return))
+    .route(
+      "/orders/:id/payments",
+      post(payments::create_payment).get(payments::list_payments),
+    )
+    .route(
+      "/webhooks/payments/:provider",
+      post(payments::payment_webhook),
+    )
+    .route("/audit", get(audit::list_audit))
+    .route(
+      "/webhook-subscriptions",
+      post(webhooks::create_webhook).get(webhooks::list_webhooks),
+    )
+    .route(
+      "/webhook-subscriptions/:id",
+      get(webhooks::get_webhook)
+        .put(webhooks::update_webhook)
+        .delete(webhooks::delete_webhook),
+    )
+    .route(
+      "/webhook-subscriptions/:id/deliveries",
+      get(webhooks::list_deliveries),
+    )
+    .route(
+      "/webhook-subscriptions/:id/deliveries/:delivery_id",
+      get(webhooks::get_delivery),
+    )
+    .route(
+      "/webhook-subscriptions/:id/deliveries/:delivery_id/redeliver",
+      post(webhooks::redeliver),
+    )
     .with_state(state)
     .layer(
       CorsLayer::new()
@@ -47,7 +214,13 @@ pub fn router(state: AppState) -> Router {
             Level::INFO,
             "http_request",
             method = %request.method(),
-            uri = %request.uri(),
+            // The query may carry an access token.
+            path = %request.uri().path(),
+            request_id = request
+              .headers()
+              .get(REQUEST_ID_HEADER)
+              .and_then(|value| value.to_str().ok())
+              .unwrap_or_default(),
           )
         })
         .on_response(
@@ -62,6 +235,14 @@ pub fn router(state: AppState) -> Router {
           },
         ),
     )
+    // Outermost, so every layer and handler sees the id.
+    .layer(PropagateRequestIdLayer::new(HeaderName::from_static(
+      REQUEST_ID_HEADER,
+    )))
+    .layer(SetRequestIdLayer::new(
+      HeaderName::from_static(REQUEST_ID_HEADER),
+      MakeRequestUuid,
+    ))
 }
 
 #[derive(Debug, Serialize)]
@@ -70,21 +251,24 @@ struct HealthResponse {
   db: &'static str,
 }
 
+/// Reports on the database users, products and orders are kept in.
 async fn health(State(state): State<AppState>) -> Result<Json<HealthResponse>, ApiError> {
-  let db_ok = db_health(state.pool.clone()).await;
+  let db_ok = match (&state.config.storage, &state.sqlite_pool) {
+    (Storage::Postgres, _) => responds(sqlx::query("SELECT 1").execute(&state.pool)).await,
+    (Storage::Sqlite { .. }, Some(pool)) => responds(sqlx::query("SELECT 1").execute(pool)).await,
+    (Storage::Sqlite { .. }, None) => false,
+    // Nothing to reach.
+    (Storage::Memory, _) => true,
+  };
   Ok(Json(HealthResponse {
     status: "ok",
     db: if db_ok { "ok" } else { "error" },
   }))
 }
 
-async fn db_health(pool: PgPool) -> bool {
+async fn responds<T>(query: impl Future<Output = Result<T, sqlx::Error>>) -> bool {
   matches!(
-    tokio::time::timeout(
-      Duration::from_secs(2),
-      sqlx::query("SELECT 1").execute(&pool)
-    )
-    .await,
+    tokio::time::timeout(Duration::from_secs(2), query).await,
     Ok(Ok(_))
   )
 }
@@ -98,15 +282,19 @@ struct CreateUserBody {
 }
 
 async fn create_user(
+  audit: AuditContext,
   State(state): State<AppState>,
   Json(body): Json<CreateUserBody>,
 ) -> Result<(StatusCode, Json<crate::domain::models::User>), ApiError> {
   let user = state
     .users
-    .create(NewUser {
-      email: body.email,
-      name: body.name,
-    })
+    .create(
+      NewUser {
+        email: body.email,
+        name: body.name,
+      },
+      &audit,
+    )
     .await
     .map_err(ApiError::from)?;
   Ok((StatusCode::CREATED, Json(user)))
@@ -134,6 +322,7 @@ struct UpdateUserBody {
 }
 
 async fn update_user(
+  audit: AuditContext,
   State(state): State<AppState>,
   Path(id): Path<Uuid>,
   Json(body): Json<UpdateUserBody>,
@@ -146,6 +335,7 @@ async fn update_user(
         email: body.email,
         name: body.name,
       },
+      &audit,
     )
     .await
     .map_err(ApiError::from)?;
@@ -153,61 +343,144 @@ async fn update_user(
 }
 
 async fn delete_user(
+  audit: AuditContext,
   State(state): State<AppState>,
   Path(id): Path<Uuid>,
 ) -> Result<StatusCode, ApiError> {
-  state.users.delete(id).await.map_err(ApiError::from)?;
+  state
+    .users
+    .delete(id, &audit)
+    .await
+    .map_err(ApiError::from)?;
   Ok(StatusCode::NO_CONTENT)
 }
 
+#[derive(Debug, Deserialize)]
+struct FuzzySearchQuery {
+  q: String,
+  #[serde(default = "default_per_page")]
+  limit: u32,
+}
+
+async fn search_users(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Query(query): Query<FuzzySearchQuery>,
+) -> Result<Json<Vec<SimilarityHit<User>>>, ApiError> {
+  let users = state
+    .users
+    .fuzzy_search(&query.q, state.config.user_fuzzy_threshold, query.limit)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(users))
+}
+
 // ===== Products =====
 
 #[derive(Debug, Deserialize)]
 struct CreateProductBody {
   sku: String,
   name: String,
+  #[serde(default)]
+  description: String,
   price_cents: i64,
 }
 
 async fn create_product(
+  audit: AuditContext,
   State(state): State<AppState>,
   Json(body): Json<CreateProductBody>,
 ) -> Result<(StatusCode, Json<crate::domain::models::Product>), ApiError> {
   let product = state
     .products
-    .create(NewProduct {
-      sku: body.sku,
-      name: body.name,
-      price_cents: body.price_cents,
-    })
+    .create(
+      NewProduct {
+        sku: body.sku,
+        name: body.name,
+        description: body.description,
+        price_cents: body.price_cents,
+      },
+      &audit,
+    )
     .await
     .map_err(ApiError::from)?;
   Ok((StatusCode::CREATED, Json(product)))
 }
 
+#[derive(Debug, Deserialize)]
+struct ListProductsQuery {
+  /// Category slug to filter by.
+  category: Option<String>,
+  #[serde(default)]
+  include_descendants: bool,
+}
+
 async fn list_products(
   State(state): State<AppState>,
+  Query(query): Query<ListProductsQuery>,
 ) -> Result<Json<Vec<crate::domain::models::Product>>, ApiError> {
-  let products = state.products.list().await.map_err(ApiError::from)?;
+  let products = match query.category {
+    Some(slug) => state
+      .products
+      .list_by_category(&slug, query.include_descendants)
+      .await
+      .map_err(ApiError::from)?,
+    None => state.products.list().await.map_err(ApiError::from)?,
+  };
   Ok(Json(products))
 }
 
-async fn get_product(
+#[derive(Debug, Deserialize)]
+struct SearchProductsQuery {
+  q: String,
+  #[serde(default = "first_page")]
+  page: u32,
+  #[serde(default = "default_per_page")]
+  per_page: u32,
+}
+
+fn first_page() -> u32 {
+  1
+}
+
+fn default_per_page() -> u32 {
+  20
+}
+
+async fn search_products(
   State(state): State<AppState>,
-  Path(id): Path<Uuid>,
-) -> Result<Json<crate::domain::models::Product>, ApiError> {
-  let product = state.products.get(id).await.map_err(ApiError::from)?;
-  Ok(Json(product))
+  Query(query): Query<SearchProductsQuery>,
+) -> Result<Json<Page<ProductSearchHit>>, ApiError> {
+  let results = state
+    .products
+    .search(&query.q, query.page, query.per_page)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(results))
+}
+
+async fn fuzzy_search_products(
+  State(state): State<AppState>,
+  Query(query): Query<FuzzySearchQuery>,
+) -> Result<Json<Vec<SimilarityHit<Product>>>, ApiError> {
+  let products = state
+    .products
+    .fuzzy_search(&query.q, state.config.product_fuzzy_threshold, query.limit)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(products))
 }
 
 #[derive(Debug, Deserialize)]
 struct UpdateProductBody {
   sku: Option<String>,
   name: Option<String>,
+  description: Option<String>,
   price_cents: Option<i64>,
 }
 
 async fn update_product(
+  audit: AuditContext,
   State(state): State<AppState>,
   Path(id): Path<Uuid>,
   Json(body): Json<UpdateProductBody>,
@@ -219,8 +492,10 @@ async fn update_product(
       UpdateProduct {
         sku: body.sku,
         name: body.name,
+        description: body.description,
         price_cents: body.price_cents,
       },
+      &audit,
     )
     .await
     .map_err(ApiError::from)?;
@@ -228,13 +503,52 @@ async fn update_product(
 }
 
 async fn delete_product(
+  audit: AuditContext,
   State(state): State<AppState>,
   Path(id): Path<Uuid>,
 ) -> Result<StatusCode, ApiError> {
-  state.products.delete(id).await.map_err(ApiError::from)?;
+  // Images are only kept in Postgres, see `get_product_details`.
+  let images = if state.config.storage == Storage::Postgres {
+    state.images.list(id).await.map_err(ApiError::from)?
+  } else {
+    Vec::new()
+  };
+  state
+    .products
+    .delete(id, &audit)
+    .await
+    .map_err(ApiError::from)?;
+  state.images.discard_blobs(&images).await;
   Ok(StatusCode::NO_CONTENT)
 }
 
+async fn get_stock(
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+) -> Result<Json<crate::domain::models::StockLevel>, ApiError> {
+  let stock = state.inventory.get(id).await.map_err(ApiError::from)?;
+  Ok(Json(stock))
+}
+
+#[derive(Debug, Deserialize)]
+struct SetStockBody {
+  quantity: i64,
+}
+
+async fn set_stock(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+  Json(body): Json<SetStockBody>,
+) -> Result<Json<crate::domain::models::StockLevel>, ApiError> {
+  let stock = state
+    .inventory
+    .set(id, body.quantity)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(stock))
+}
+
 // ===== Orders =====
 
 #[derive(Debug, Deserialize)]
@@ -245,16 +559,20 @@ struct CreateOrderBody {
 }
 
 async fn create_order(
+  audit: AuditContext,
   State(state): State<AppState>,
   Json(body): Json<CreateOrderBody>,
 ) -> Result<(StatusCode, Json<crate::domain::models::Order>), ApiError> {
   let order = state
     .orders
-    .create(NewOrder {
-      user_id: body.user_id,
-      status: body.status,
-      total_cents: body.total_cents,
-    })
+    .create(
+      NewOrder {
+        user_id: body.user_id,
+        status: body.status,
+        total_cents: body.total_cents,
+      },
+      &audit,
+    )
     .await
     .map_err(ApiError::from)?;
   Ok((StatusCode::CREATED, Json(order)))
@@ -282,6 +600,7 @@ struct UpdateOrderBody {
 }
 
 async fn update_order(
+  audit: AuditContext,
   State(state): State<AppState>,
   Path(id): Path<Uuid>,
   Json(body): Json<UpdateOrderBody>,
@@ -294,6 +613,7 @@ async fn update_order(
         status: body.status,
         total_cents: body.total_cents,
       },
+      &audit,
     )
     .await
     .map_err(ApiError::from)?;
@@ -301,9 +621,49 @@ async fn update_order(
 }
 
 async fn delete_order(
+  audit: AuditContext,
   State(state): State<AppState>,
   Path(id): Path<Uuid>,
 ) -> Result<StatusCode, ApiError> {
-  state.orders.delete(id).await.map_err(ApiError::from)?;
+  state
+    .orders
+    .delete(id, &audit)
+    .await
+    .map_err(ApiError::from)?;
   Ok(StatusCode::NO_CONTENT)
 }
+
+#[derive(Debug, Deserialize)]
+struct AddOrderItemBody {
+  product_id: Uuid,
+  quantity: i32,
+}
+
+async fn add_order_item(
+  audit: AuditContext,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+  Json(body): Json<AddOrderItemBody>,
+) -> Result<(StatusCode, Json<crate::domain::models::OrderItem>), ApiError> {
+  let item = state
+    .orders
+    .add_item(
+      id,
+      NewOrderItem {
+        product_id: body.product_id,
+        quantity: body.quantity,
+      },
+      &audit,
+    )
+    .await
+    .map_err(ApiError::from)?;
+  Ok((StatusCode::CREATED, Json(item)))
+}
+
+async fn list_order_items(
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+) -> Result<Json<Vec<crate::domain::models::OrderItem>>, ApiError> {
+  let items = state.orders.list_items(id).await.map_err(ApiError::from)?;
+  Ok(Json(items))
+}
@@ -0,0 +1,189 @@
+use crate::adapters::web::auth::Admin;
+use crate::adapters::web::error::ApiError;
+use crate::adapters::web::images::{self, ImageResponse};
+use crate::adapters::web::present;
+use crate::application::ports::{NewProductOption, NewVariant, UpdateVariant};
+use crate::domain::models::{ProductDetails, ProductOption, ProductVariant};
+use crate::infrastructure::config::Storage;
+use crate::AppState;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// `ProductDetails` plus the product images with their signed URLs.
+#[derive(Debug, Serialize)]
+pub(crate) struct ProductDetailsResponse {
+  #[serde(flatten)]
+  details: ProductDetails,
+  images: Vec<ImageResponse>,
+}
+
+/// The product with its options, variants and images nested.
+pub(crate) async fn get_product_details(
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+) -> Result<Json<ProductDetailsResponse>, ApiError> {
+  let product = state.products.get(id).await.map_err(ApiError::from)?;
+  // Options, variants and images are only kept in Postgres; products stored
+  // anywhere else have none.
+  let (options, variants, images) = if state.config.storage == Storage::Postgres {
+    (
+      state
+        .variants
+        .list_options(id)
+        .await
+        .map_err(ApiError::from)?,
+      state
+        .variants
+        .list_by_product(id)
+        .await
+        .map_err(ApiError::from)?,
+      state.images.list(id).await.map_err(ApiError::from)?,
+    )
+  } else {
+    Default::default()
+  };
+  Ok(Json(ProductDetailsResponse {
+    details: ProductDetails {
+      product,
+      options,
+      variants,
+    },
+    images: images
+      .into_iter()
+      .map(|image| images::with_url(&state.config, image))
+      .collect(),
+  }))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct AddOptionBody {
+  name: String,
+  values: Vec<String>,
+}
+
+pub(crate) async fn add_option(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(product_id): Path<Uuid>,
+  Json(body): Json<AddOptionBody>,
+) -> Result<(StatusCode, Json<ProductOption>), ApiError> {
+  let option = state
+    .variants
+    .add_option(
+      product_id,
+      NewProductOption {
+        name: body.name,
+        values: body.values,
+      },
+    )
+    .await
+    .map_err(ApiError::from)?;
+  Ok((StatusCode::CREATED, Json(option)))
+}
+
+pub(crate) async fn list_options(
+  State(state): State<AppState>,
+  Path(product_id): Path<Uuid>,
+) -> Result<Json<Vec<ProductOption>>, ApiError> {
+  let options = state
+    .variants
+    .list_options(product_id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(options))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CreateVariantBody {
+  sku: String,
+  price_override_cents: Option<i64>,
+  #[serde(default)]
+  stock: i64,
+  #[serde(default)]
+  options: BTreeMap<String, String>,
+}
+
+pub(crate) async fn create_variant(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(product_id): Path<Uuid>,
+  Json(body): Json<CreateVariantBody>,
+) -> Result<(StatusCode, Json<ProductVariant>), ApiError> {
+  let variant = state
+    .variants
+    .create(
+      product_id,
+      NewVariant {
+        sku: body.sku,
+        price_override_cents: body.price_override_cents,
+        stock: body.stock,
+        options: body.options,
+      },
+    )
+    .await
+    .map_err(ApiError::from)?;
+  Ok((StatusCode::CREATED, Json(variant)))
+}
+
+pub(crate) async fn list_variants(
+  State(state): State<AppState>,
+  Path(product_id): Path<Uuid>,
+) -> Result<Json<Vec<ProductVariant>>, ApiError> {
+  let variants = state
+    .variants
+    .list_by_product(product_id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(variants))
+}
+
+pub(crate) async fn get_variant(
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+) -> Result<Json<ProductVariant>, ApiError> {
+  let variant = state.variants.get(id).await.map_err(ApiError::from)?;
+  Ok(Json(variant))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct UpdateVariantBody {
+  sku: Option<String>,
+  /// Absent keeps the override; `null` reverts to the product price.
+  #[serde(default, deserialize_with = "present")]
+  price_override_cents: Option<Option<i64>>,
+  stock: Option<i64>,
+}
+
+pub(crate) async fn update_variant(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+  Json(body): Json<UpdateVariantBody>,
+) -> Result<Json<ProductVariant>, ApiError> {
+  let variant = state
+    .variants
+    .update(
+      id,
+      UpdateVariant {
+        sku: body.sku,
+        price_override_cents: body.price_override_cents,
+        stock: body.stock,
+      },
+    )
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(variant))
+}
+
+pub(crate) async fn delete_variant(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+  state.variants.delete(id).await.map_err(ApiError::from)?;
+  Ok(StatusCode::NO_CONTENT)
+}
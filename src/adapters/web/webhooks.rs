@@ -0,0 +1,148 @@
+use crate::adapters::web::auth::Admin;
+use crate::adapters::web::error::ApiError;
+use crate::application::ports::{NewWebhookSubscription, UpdateWebhookSubscription};
+use crate::domain::models::{Page, WebhookDelivery, WebhookSubscription};
+use crate::AppState;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CreateWebhookBody {
+  url: String,
+  secret: String,
+  event_types: Vec<String>,
+}
+
+pub(crate) async fn create_webhook(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Json(body): Json<CreateWebhookBody>,
+) -> Result<(StatusCode, Json<WebhookSubscription>), ApiError> {
+  let created = state
+    .webhooks
+    .create(NewWebhookSubscription {
+      url: body.url,
+      secret: body.secret,
+      event_types: body.event_types,
+    })
+    .await
+    .map_err(ApiError::from)?;
+  Ok((StatusCode::CREATED, Json(created)))
+}
+
+pub(crate) async fn list_webhooks(
+  _admin: Admin,
+  State(state): State<AppState>,
+) -> Result<Json<Vec<WebhookSubscription>>, ApiError> {
+  let webhooks = state.webhooks.list().await.map_err(ApiError::from)?;
+  Ok(Json(webhooks))
+}
+
+pub(crate) async fn get_webhook(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+) -> Result<Json<WebhookSubscription>, ApiError> {
+  let webhook = state.webhooks.get(id).await.map_err(ApiError::from)?;
+  Ok(Json(webhook))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct UpdateWebhookBody {
+  url: Option<String>,
+  secret: Option<String>,
+  event_types: Option<Vec<String>>,
+  active: Option<bool>,
+}
+
+pub(crate) async fn update_webhook(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+  Json(body): Json<UpdateWebhookBody>,
+) -> Result<Json<WebhookSubscription>, ApiError> {
+  let updated = state
+    .webhooks
+    .update(
+      id,
+      UpdateWebhookSubscription {
+        url: body.url,
+        secret: body.secret,
+        event_types: body.event_types,
+        active: body.active,
+      },
+    )
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(updated))
+}
+
+pub(crate) async fn delete_webhook(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+  state.webhooks.delete(id).await.map_err(ApiError::from)?;
+  Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct DeliveryPageQuery {
+  #[serde(default = "first_page")]
+  page: u32,
+  #[serde(default = "default_per_page")]
+  per_page: u32,
+}
+
+fn first_page() -> u32 {
+  1
+}
+
+fn default_per_page() -> u32 {
+  20
+}
+
+/// Deliveries of the subscription, newest first, with their attempt logs.
+pub(crate) async fn list_deliveries(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path(id): Path<Uuid>,
+  Query(query): Query<DeliveryPageQuery>,
+) -> Result<Json<Page<WebhookDelivery>>, ApiError> {
+  let deliveries = state
+    .webhooks
+    .list_deliveries(id, query.page, query.per_page)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(deliveries))
+}
+
+pub(crate) async fn get_delivery(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path((id, delivery_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<WebhookDelivery>, ApiError> {
+  let delivery = state
+    .webhooks
+    .get_delivery(id, delivery_id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(delivery))
+}
+
+/// Queues the delivery again; the worker sends it on its next round.
+pub(crate) async fn redeliver(
+  _admin: Admin,
+  State(state): State<AppState>,
+  Path((id, delivery_id)): Path<(Uuid, Uuid)>,
+) -> Result<(StatusCode, Json<WebhookDelivery>), ApiError> {
+  let delivery = state
+    .webhooks
+    .redeliver(id, delivery_id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok((StatusCode::ACCEPTED, Json(delivery)))
+}
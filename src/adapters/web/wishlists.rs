@@ -0,0 +1,130 @@
+use crate::adapters::web::error::ApiError;
+use crate::domain::models::{AuditContext, OrderItem, Wishlist, WishlistItem};
+use crate::AppState;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct WishlistBody {
+  name: String,
+}
+
+pub(crate) async fn create_wishlist(
+  State(state): State<AppState>,
+  Path(user_id): Path<Uuid>,
+  Json(body): Json<WishlistBody>,
+) -> Result<(StatusCode, Json<Wishlist>), ApiError> {
+  let created = state
+    .wishlists
+    .create(user_id, &body.name)
+    .await
+    .map_err(ApiError::from)?;
+  Ok((StatusCode::CREATED, Json(created)))
+}
+
+pub(crate) async fn list_wishlists(
+  State(state): State<AppState>,
+  Path(user_id): Path<Uuid>,
+) -> Result<Json<Vec<Wishlist>>, ApiError> {
+  let wishlists = state
+    .wishlists
+    .list(user_id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(wishlists))
+}
+
+pub(crate) async fn get_wishlist(
+  State(state): State<AppState>,
+  Path((user_id, id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Wishlist>, ApiError> {
+  let wishlist = state
+    .wishlists
+    .get(user_id, id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(wishlist))
+}
+
+pub(crate) async fn rename_wishlist(
+  State(state): State<AppState>,
+  Path((user_id, id)): Path<(Uuid, Uuid)>,
+  Json(body): Json<WishlistBody>,
+) -> Result<Json<Wishlist>, ApiError> {
+  let wishlist = state
+    .wishlists
+    .rename(user_id, id, &body.name)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(Json(wishlist))
+}
+
+pub(crate) async fn delete_wishlist(
+  State(state): State<AppState>,
+  Path((user_id, id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, ApiError> {
+  state
+    .wishlists
+    .delete(user_id, id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct AddItemBody {
+  product_id: Uuid,
+}
+
+pub(crate) async fn add_wishlist_item(
+  State(state): State<AppState>,
+  Path((user_id, id)): Path<(Uuid, Uuid)>,
+  Json(body): Json<AddItemBody>,
+) -> Result<(StatusCode, Json<WishlistItem>), ApiError> {
+  let item = state
+    .wishlists
+    .add_item(user_id, id, body.product_id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok((StatusCode::CREATED, Json(item)))
+}
+
+pub(crate) async fn remove_wishlist_item(
+  State(state): State<AppState>,
+  Path((user_id, id, product_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Result<StatusCode, ApiError> {
+  state
+    .wishlists
+    .remove_item(user_id, id, product_id)
+    .await
+    .map_err(ApiError::from)?;
+  Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct MoveToCartBody {
+  #[serde(default = "one")]
+  quantity: i32,
+}
+
+fn one() -> i32 {
+  1
+}
+
+/// Moves the item into the user's cart and returns the new order line.
+pub(crate) async fn move_to_cart(
+  audit: AuditContext,
+  State(state): State<AppState>,
+  Path((user_id, id, product_id)): Path<(Uuid, Uuid, Uuid)>,
+  Json(body): Json<MoveToCartBody>,
+) -> Result<(StatusCode, Json<OrderItem>), ApiError> {
+  let item = state
+    .wishlists
+    .move_to_cart(user_id, id, product_id, body.quantity, &audit)
+    .await
+    .map_err(ApiError::from)?;
+  Ok((StatusCode::CREATED, Json(item)))
+}
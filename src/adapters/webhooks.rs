@@ -0,0 +1,216 @@
+//! Outgoing webhooks: outbox events fan out into one delivery per matching
+//! subscription, and a background worker POSTs them to the partners.
+//!
+//! Every request carries the event as JSON, signed like incoming payment
+//! webhooks (`t=<unix seconds>,v1=<hex HMAC-SHA256 of "<t>.<body>">`) with the
+//! subscription's secret. Any 2xx response counts as delivered.
+
+use crate::application::ports::{
+  DueWebhookDelivery, EventPublisher, RepoError, WebhookAttemptResult, WebhookRepository,
+};
+use crate::domain::models::{OutboxEvent, WebhookDelivery};
+use crate::infrastructure::signature;
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::Client;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+pub const SIGNATURE_HEADER: &str = "x-asgard-signature";
+pub const EVENT_HEADER: &str = "x-asgard-event";
+pub const DELIVERY_HEADER: &str = "x-asgard-delivery";
+
+/// Deliveries claimed per round.
+const BATCH_SIZE: i64 = 20;
+/// Errors are stored up to this many characters.
+const MAX_ERROR_CHARS: usize = 500;
+const FIRST_RETRY_DELAY_SECS: i64 = 30;
+const MAX_RETRY_DELAY_SECS: i64 = 6 * 3600;
+
+/// Seconds to wait after the `attempts`-th failed attempt: 30 s, 1 min,
+/// 2 min, ... up to six hours, so a receiver gets time to recover.
+pub fn retry_delay_secs(attempts: i32) -> i64 {
+  let exponent = attempts.saturating_sub(1).clamp(0, 20) as u32;
+  (FIRST_RETRY_DELAY_SECS << exponent).min(MAX_RETRY_DELAY_SECS)
+}
+
+/// The request body sent for a delivery; the same on every attempt.
+pub fn delivery_body(delivery: &WebhookDelivery) -> Vec<u8> {
+  let body = json!({
+    "id": delivery.event_id,
+    "type": delivery.event_type,
+    "occurred_at": delivery.occurred_at,
+    "data": delivery.payload,
+  });
+  serde_json::to_vec(&body).expect("JSON values serialize")
+}
+
+/// Turns published outbox events into pending deliveries.
+pub struct WebhookPublisher<R: WebhookRepository> {
+  repo: Arc<R>,
+}
+
+impl<R: WebhookRepository> WebhookPublisher<R> {
+  pub fn new(repo: R) -> Self {
+    Self {
+      repo: Arc::new(repo),
+    }
+  }
+}
+
+#[async_trait]
+impl<R: WebhookRepository> EventPublisher for WebhookPublisher<R> {
+  async fn publish(&self, event: &OutboxEvent) -> Result<(), RepoError> {
+    self.repo.enqueue_deliveries(event).await?;
+    Ok(())
+  }
+}
+
+/// Sends due deliveries and records the outcome of every attempt.
+pub struct WebhookWorker<R: WebhookRepository> {
+  repo: Arc<R>,
+  client: Client,
+  max_attempts: i32,
+  disable_after_failures: i32,
+  timeout: Duration,
+}
+
+impl<R: WebhookRepository> WebhookWorker<R> {
+  pub fn new(repo: R, timeout: Duration, max_attempts: i32, disable_after_failures: i32) -> Self {
+    Self {
+      repo: Arc::new(repo),
+      client: Client::new(),
+      max_attempts,
+      disable_after_failures,
+      timeout,
+    }
+  }
+
+  /// Sends one batch of due deliveries and returns how many were claimed.
+  pub async fn run_once(&self) -> Result<usize, RepoError> {
+    // A claim lasts until the request has surely timed out.
+    let lease_secs = self.timeout.as_secs() as i64 + 30;
+    let due = self.repo.claim_deliveries(BATCH_SIZE, lease_secs).await?;
+    for job in &due {
+      let result = self.send(job).await;
+      let attempts = job.delivery.attempts;
+      let retry_after =
+        (!result.succeeded && attempts < self.max_attempts).then(|| retry_delay_secs(attempts));
+      if !result.succeeded {
+        tracing::warn!(
+          delivery_id = %job.delivery.id,
+          attempts,
+          status = ?result.response_status,
+          error = ?result.error,
+          "webhook delivery failed"
+        );
+      }
+      match self
+        .repo
+        .record_attempt(
+          job.delivery.id,
+          result,
+          retry_after,
+          self.disable_after_failures,
+        )
+        .await
+      {
+        Ok(()) | Err(RepoError::NotFound) => {}
+        Err(err) => return Err(err),
+      }
+    }
+    Ok(due.len())
+  }
+
+  /// Polls for deliveries until the process exits, draining full batches
+  /// without waiting.
+  pub fn spawn(self, poll_interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+      loop {
+        match self.run_once().await {
+          Ok(claimed) if claimed as i64 == BATCH_SIZE => continue,
+          Ok(_) => {}
+          Err(err) => tracing::error!(error = %err, "webhook worker round failed"),
+        }
+        tokio::time::sleep(poll_interval).await;
+      }
+    })
+  }
+
+  async fn send(&self, job: &DueWebhookDelivery) -> WebhookAttemptResult {
+    let body = delivery_body(&job.delivery);
+    let signature = signature::sign(&job.secret, Utc::now().timestamp(), &body);
+    let started = Instant::now();
+    let response = self
+      .client
+      .post(&job.url)
+      .timeout(self.timeout)
+      .header(reqwest::header::CONTENT_TYPE, "application/json")
+      .header(SIGNATURE_HEADER, signature)
+      .header(EVENT_HEADER, &job.delivery.event_type)
+      .header(DELIVERY_HEADER, job.delivery.id.to_string())
+      .body(body)
+      .send()
+      .await;
+    let duration_ms = i32::try_from(started.elapsed().as_millis()).unwrap_or(i32::MAX);
+    match response {
+      Ok(response) => {
+        let status = response.status();
+        WebhookAttemptResult {
+          succeeded: status.is_success(),
+          response_status: Some(i32::from(status.as_u16())),
+          error: (!status.is_success()).then(|| format!("receiver answered {status}")),
+          duration_ms,
+        }
+      }
+      Err(err) => WebhookAttemptResult {
+        succeeded: false,
+        response_status: None,
+        error: Some(err.to_string().chars().take(MAX_ERROR_CHARS).collect()),
+        duration_ms,
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use uuid::Uuid;
+
+  #[test]
+  fn retries_back_off_up_to_six_hours() {
+    assert_eq!(retry_delay_secs(1), 30);
+    assert_eq!(retry_delay_secs(2), 60);
+    assert_eq!(retry_delay_secs(4), 240);
+    assert_eq!(retry_delay_secs(10), 15_360);
+    assert_eq!(retry_delay_secs(11), 6 * 3600);
+    assert_eq!(retry_delay_secs(i32::MAX), 6 * 3600);
+  }
+
+  #[test]
+  fn body_wraps_the_event_payload() {
+    let delivery = WebhookDelivery {
+      id: Uuid::new_v4(),
+      subscription_id: Uuid::new_v4(),
+      event_id: Uuid::new_v4(),
+      event_type: "OrderDeleted".into(),
+      payload: json!({ "order_id": "o-1" }),
+      occurred_at: Utc::now(),
+      status: "pending".into(),
+      attempts: 1,
+      next_attempt_at: Utc::now(),
+      response_status: None,
+      last_error: None,
+      delivered_at: None,
+      log: Vec::new(),
+      created_at: Utc::now(),
+    };
+    let body: serde_json::Value = serde_json::from_slice(&delivery_body(&delivery)).unwrap();
+    assert_eq!(body["id"], json!(delivery.event_id));
+    assert_eq!(body["type"], "OrderDeleted");
+    assert_eq!(body["data"], delivery.payload);
+  }
+}
@@ -1,5 +1,13 @@
-use crate::domain::models::{Order, Product, User};
+use crate::domain::models::{
+  AuditContext, AuditEntry, BillingAddress, Category, ImageVariant, Invoice, Order,
+  OrderHistoryEntry, OrderItem, OutboxEvent, Payment, Product, ProductImage, ProductOption,
+  ProductPrice, ProductSearchHit, ProductVariant, Refund, ReturnRequest, Review, SimilarityHit,
+  StockLevel, User, WebhookDelivery, WebhookSubscription, Wishlist, WishlistItem,
+};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::sync::Arc;
 use thiserror::Error;
 use uuid::Uuid;
 
@@ -9,6 +17,8 @@ pub enum RepoError {
   NotFound,
   #[error("conflict")]
   Conflict,
+  #[error("invalid input: {0}")]
+  Invalid(String),
   #[error("unexpected repository error: {0}")]
   Unexpected(String),
 }
@@ -27,17 +37,32 @@ pub struct UpdateUser {
 
 #[async_trait]
 pub trait UserRepository: Send + Sync + 'static {
-  async fn create(&self, input: NewUser) -> Result<User, RepoError>;
+  /// Changes are recorded in the audit log as made in `ctx`.
+  async fn create(&self, input: NewUser, ctx: &AuditContext) -> Result<User, RepoError>;
   async fn list(&self) -> Result<Vec<User>, RepoError>;
   async fn get(&self, id: Uuid) -> Result<User, RepoError>;
-  async fn update(&self, id: Uuid, input: UpdateUser) -> Result<User, RepoError>;
-  async fn delete(&self, id: Uuid) -> Result<(), RepoError>;
+  async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateUser,
+    ctx: &AuditContext,
+  ) -> Result<User, RepoError>;
+  async fn delete(&self, id: Uuid, ctx: &AuditContext) -> Result<(), RepoError>;
+  /// Users whose email or name contains a word at least `threshold` similar
+  /// to `text`, most similar first.
+  async fn fuzzy_search(
+    &self,
+    text: &str,
+    threshold: f32,
+    limit: i64,
+  ) -> Result<Vec<SimilarityHit<User>>, RepoError>;
 }
 
 #[derive(Debug, Clone)]
 pub struct NewProduct {
   pub sku: String,
   pub name: String,
+  pub description: String,
   pub price_cents: i64,
 }
 
@@ -45,18 +70,149 @@ pub struct NewProduct {
 pub struct UpdateProduct {
   pub sku: Option<String>,
   pub name: Option<String>,
+  pub description: Option<String>,
   pub price_cents: Option<i64>,
 }
 
 #[async_trait]
 pub trait ProductRepository: Send + Sync + 'static {
-  async fn create(&self, input: NewProduct) -> Result<Product, RepoError>;
+  /// Changes are recorded in the audit log as made in `ctx`.
+  async fn create(&self, input: NewProduct, ctx: &AuditContext) -> Result<Product, RepoError>;
   async fn list(&self) -> Result<Vec<Product>, RepoError>;
   async fn get(&self, id: Uuid) -> Result<Product, RepoError>;
-  async fn update(&self, id: Uuid, input: UpdateProduct) -> Result<Product, RepoError>;
+  async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateProduct,
+    ctx: &AuditContext,
+  ) -> Result<Product, RepoError>;
+  async fn delete(&self, id: Uuid, ctx: &AuditContext) -> Result<(), RepoError>;
+  /// Products assigned to the category with `slug`, and with
+  /// `include_descendants` also those in any of its subcategories. Unknown
+  /// slugs are `NotFound`.
+  async fn list_by_category(
+    &self,
+    slug: &str,
+    include_descendants: bool,
+  ) -> Result<Vec<Product>, RepoError>;
+  /// Full-text search for a `to_tsquery` expression, best matches first.
+  /// Returns one page of hits and the total number of matches.
+  async fn search(
+    &self,
+    tsquery: &str,
+    limit: i64,
+    offset: i64,
+  ) -> Result<(Vec<ProductSearchHit>, i64), RepoError>;
+  /// Typo-tolerant search: products whose SKU or name contains a word at
+  /// least `threshold` similar to `text`, most similar first.
+  async fn fuzzy_search(
+    &self,
+    text: &str,
+    threshold: f32,
+    limit: i64,
+  ) -> Result<Vec<SimilarityHit<Product>>, RepoError>;
+  /// The product's prices ordered by `valid_from`, including scheduled
+  /// ones. `NotFound` when the product does not exist.
+  async fn price_history(&self, product_id: Uuid) -> Result<Vec<ProductPrice>, RepoError>;
+  /// Sets the price from `valid_from` until the next scheduled change. An
+  /// entry starting at exactly `valid_from` is overwritten.
+  async fn schedule_price(
+    &self,
+    product_id: Uuid,
+    price_cents: i64,
+    valid_from: DateTime<Utc>,
+    ctx: &AuditContext,
+  ) -> Result<ProductPrice, RepoError>;
+  /// Removes a price that has not taken effect yet; the previous price then
+  /// stays in effect for its range. Prices already in effect are `Conflict`.
+  async fn cancel_scheduled_price(
+    &self,
+    product_id: Uuid,
+    price_id: Uuid,
+    ctx: &AuditContext,
+  ) -> Result<(), RepoError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct NewProductOption {
+  pub name: String,
+  pub values: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewVariant {
+  pub sku: String,
+  pub price_override_cents: Option<i64>,
+  pub stock: i64,
+  /// Option name to value; must name every option of the product.
+  pub options: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UpdateVariant {
+  pub sku: Option<String>,
+  /// `Some(None)` clears the override.
+  pub price_override_cents: Option<Option<i64>>,
+  pub stock: Option<i64>,
+}
+
+#[async_trait]
+pub trait VariantRepository: Send + Sync + 'static {
+  /// Appends an option to the product. Fails with `Conflict` when the name is
+  /// taken or the product already has variants, which would lack a value.
+  async fn add_option(
+    &self,
+    product_id: Uuid,
+    input: NewProductOption,
+  ) -> Result<ProductOption, RepoError>;
+  async fn list_options(&self, product_id: Uuid) -> Result<Vec<ProductOption>, RepoError>;
+  /// Fails with `Invalid` unless `options` picks one declared value for each
+  /// option, and with `Conflict` when the SKU is already used by any product
+  /// or variant or the combination already exists.
+  async fn create(&self, product_id: Uuid, input: NewVariant) -> Result<ProductVariant, RepoError>;
+  async fn list_by_product(&self, product_id: Uuid) -> Result<Vec<ProductVariant>, RepoError>;
+  async fn get(&self, id: Uuid) -> Result<ProductVariant, RepoError>;
+  async fn update(&self, id: Uuid, input: UpdateVariant) -> Result<ProductVariant, RepoError>;
   async fn delete(&self, id: Uuid) -> Result<(), RepoError>;
 }
 
+#[derive(Debug, Clone)]
+pub struct NewCategory {
+  pub parent_id: Option<Uuid>,
+  pub slug: String,
+  pub name: String,
+  pub position: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct UpdateCategory {
+  /// `Some(None)` moves the category to the top level.
+  pub parent_id: Option<Option<Uuid>>,
+  pub slug: Option<String>,
+  pub name: Option<String>,
+  pub position: Option<i32>,
+}
+
+#[async_trait]
+pub trait CategoryRepository: Send + Sync + 'static {
+  /// Unknown parents and duplicate slugs are a `Conflict`.
+  async fn create(&self, input: NewCategory) -> Result<Category, RepoError>;
+  async fn list(&self) -> Result<Vec<Category>, RepoError>;
+  async fn get(&self, id: Uuid) -> Result<Category, RepoError>;
+  /// Fails with `Invalid` when the new parent is the category itself or one
+  /// of its descendants.
+  async fn update(&self, id: Uuid, input: UpdateCategory) -> Result<Category, RepoError>;
+  /// Categories that still have children are a `Conflict`.
+  async fn delete(&self, id: Uuid) -> Result<(), RepoError>;
+  /// Replaces the product's category assignments.
+  async fn set_product_categories(
+    &self,
+    product_id: Uuid,
+    category_ids: Vec<Uuid>,
+  ) -> Result<Vec<Category>, RepoError>;
+  async fn list_for_product(&self, product_id: Uuid) -> Result<Vec<Category>, RepoError>;
+}
+
 #[derive(Debug, Clone)]
 pub struct NewOrder {
   pub user_id: Uuid,
@@ -70,11 +226,697 @@ pub struct UpdateOrder {
   pub total_cents: Option<i64>,
 }
 
+#[derive(Debug, Clone)]
+pub struct NewOrderItem {
+  pub product_id: Uuid,
+  pub quantity: i32,
+}
+
 #[async_trait]
 pub trait OrderRepository: Send + Sync + 'static {
-  async fn create(&self, input: NewOrder) -> Result<Order, RepoError>;
+  /// Changes are recorded in the order's history and the audit log as made
+  /// in `ctx`.
+  async fn create(&self, input: NewOrder, ctx: &AuditContext) -> Result<Order, RepoError>;
   async fn list(&self) -> Result<Vec<Order>, RepoError>;
   async fn get(&self, id: Uuid) -> Result<Order, RepoError>;
-  async fn update(&self, id: Uuid, input: UpdateOrder) -> Result<Order, RepoError>;
+  async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateOrder,
+    ctx: &AuditContext,
+  ) -> Result<Order, RepoError>;
+  async fn delete(&self, id: Uuid, ctx: &AuditContext) -> Result<(), RepoError>;
+  /// Adds a line item at the product's current price and grows the order
+  /// total accordingly. Unknown products are a `Conflict`.
+  async fn add_item(
+    &self,
+    order_id: Uuid,
+    input: NewOrderItem,
+    ctx: &AuditContext,
+  ) -> Result<OrderItem, RepoError>;
+  async fn list_items(&self, order_id: Uuid) -> Result<Vec<OrderItem>, RepoError>;
+  /// The order's history, oldest first; also for deleted orders. `NotFound`
+  /// when the order never existed.
+  async fn history(&self, order_id: Uuid) -> Result<Vec<OrderHistoryEntry>, RepoError>;
+}
+
+// Shared repositories delegate to the one they wrap, so services can run
+// over a repository picked at runtime, e.g. `Arc<dyn UserRepository>`.
+
+#[async_trait]
+impl<R: UserRepository + ?Sized> UserRepository for Arc<R> {
+  async fn create(&self, input: NewUser, ctx: &AuditContext) -> Result<User, RepoError> {
+    (**self).create(input, ctx).await
+  }
+  async fn list(&self) -> Result<Vec<User>, RepoError> {
+    (**self).list().await
+  }
+  async fn get(&self, id: Uuid) -> Result<User, RepoError> {
+    (**self).get(id).await
+  }
+  async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateUser,
+    ctx: &AuditContext,
+  ) -> Result<User, RepoError> {
+    (**self).update(id, input, ctx).await
+  }
+  async fn delete(&self, id: Uuid, ctx: &AuditContext) -> Result<(), RepoError> {
+    (**self).delete(id, ctx).await
+  }
+  async fn fuzzy_search(
+    &self,
+    text: &str,
+    threshold: f32,
+    limit: i64,
+  ) -> Result<Vec<SimilarityHit<User>>, RepoError> {
+    (**self).fuzzy_search(text, threshold, limit).await
+  }
+}
+
+#[async_trait]
+impl<R: ProductRepository + ?Sized> ProductRepository for Arc<R> {
+  async fn create(&self, input: NewProduct, ctx: &AuditContext) -> Result<Product, RepoError> {
+    (**self).create(input, ctx).await
+  }
+  async fn list(&self) -> Result<Vec<Product>, RepoError> {
+    (**self).list().await
+  }
+  async fn get(&self, id: Uuid) -> Result<Product, RepoError> {
+    (**self).get(id).await
+  }
+  async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateProduct,
+    ctx: &AuditContext,
+  ) -> Result<Product, RepoError> {
+    (**self).update(id, input, ctx).await
+  }
+  async fn delete(&self, id: Uuid, ctx: &AuditContext) -> Result<(), RepoError> {
+    (**self).delete(id, ctx).await
+  }
+  async fn list_by_category(
+    &self,
+    slug: &str,
+    include_descendants: bool,
+  ) -> Result<Vec<Product>, RepoError> {
+    (**self).list_by_category(slug, include_descendants).await
+  }
+  async fn search(
+    &self,
+    tsquery: &str,
+    limit: i64,
+    offset: i64,
+  ) -> Result<(Vec<ProductSearchHit>, i64), RepoError> {
+    (**self).search(tsquery, limit, offset).await
+  }
+  async fn fuzzy_search(
+    &self,
+    text: &str,
+    threshold: f32,
+    limit: i64,
+  ) -> Result<Vec<SimilarityHit<Product>>, RepoError> {
+    (**self).fuzzy_search(text, threshold, limit).await
+  }
+  async fn price_history(&self, product_id: Uuid) -> Result<Vec<ProductPrice>, RepoError> {
+    (**self).price_history(product_id).await
+  }
+  async fn schedule_price(
+    &self,
+    product_id: Uuid,
+    price_cents: i64,
+    valid_from: DateTime<Utc>,
+    ctx: &AuditContext,
+  ) -> Result<ProductPrice, RepoError> {
+    (**self)
+      .schedule_price(product_id, price_cents, valid_from, ctx)
+      .await
+  }
+  async fn cancel_scheduled_price(
+    &self,
+    product_id: Uuid,
+    price_id: Uuid,
+    ctx: &AuditContext,
+  ) -> Result<(), RepoError> {
+    (**self)
+      .cancel_scheduled_price(product_id, price_id, ctx)
+      .await
+  }
+}
+
+#[async_trait]
+impl<R: OrderRepository + ?Sized> OrderRepository for Arc<R> {
+  async fn create(&self, input: NewOrder, ctx: &AuditContext) -> Result<Order, RepoError> {
+    (**self).create(input, ctx).await
+  }
+  async fn list(&self) -> Result<Vec<Order>, RepoError> {
+    (**self).list().await
+  }
+  async fn get(&self, id: Uuid) -> Result<Order, RepoError> {
+    (**self).get(id).await
+  }
+  async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateOrder,
+    ctx: &AuditContext,
+  ) -> Result<Order, RepoError> {
+    (**self).update(id, input, ctx).await
+  }
+  async fn delete(&self, id: Uuid, ctx: &AuditContext) -> Result<(), RepoError> {
+    (**self).delete(id, ctx).await
+  }
+  async fn add_item(
+    &self,
+    order_id: Uuid,
+    input: NewOrderItem,
+    ctx: &AuditContext,
+  ) -> Result<OrderItem, RepoError> {
+    (**self).add_item(order_id, input, ctx).await
+  }
+  async fn list_items(&self, order_id: Uuid) -> Result<Vec<OrderItem>, RepoError> {
+    (**self).list_items(order_id).await
+  }
+  async fn history(&self, order_id: Uuid) -> Result<Vec<OrderHistoryEntry>, RepoError> {
+    (**self).history(order_id).await
+  }
+}
+
+#[async_trait]
+pub trait InventoryRepository: Send + Sync + 'static {
+  /// Products without an inventory row have a stock of zero.
+  async fn get(&self, product_id: Uuid) -> Result<StockLevel, RepoError>;
+  async fn set(&self, product_id: Uuid, quantity: i64) -> Result<StockLevel, RepoError>;
+  /// Adds `delta` to the stock atomically, e.g. a negative one to reserve
+  /// units. `Conflict` when the stock would drop below zero.
+  async fn adjust(&self, product_id: Uuid, delta: i64) -> Result<StockLevel, RepoError>;
+}
+
+/// Starts units of work.
+#[async_trait]
+pub trait UnitOfWorkFactory: Send + Sync + 'static {
+  type Work: UnitOfWork;
+  async fn begin(&self) -> Result<Self::Work, RepoError>;
+}
+
+/// Repositories bound to one transaction, so that changes across them are
+/// committed or rolled back together. Their own multi-step methods stay
+/// atomic inside it. Dropping a unit of work without committing rolls it
+/// back; its repositories fail once it is finished.
+#[async_trait]
+pub trait UnitOfWork: Send + Sync + 'static {
+  type Orders: OrderRepository + ?Sized;
+  type Inventory: InventoryRepository + ?Sized;
+  fn orders(&self) -> &Self::Orders;
+  fn inventory(&self) -> &Self::Inventory;
+  async fn commit(self) -> Result<(), RepoError>;
+  async fn rollback(self) -> Result<(), RepoError>;
+}
+
+#[async_trait]
+impl<R: InventoryRepository + ?Sized> InventoryRepository for Arc<R> {
+  async fn get(&self, product_id: Uuid) -> Result<StockLevel, RepoError> {
+    (**self).get(product_id).await
+  }
+  async fn set(&self, product_id: Uuid, quantity: i64) -> Result<StockLevel, RepoError> {
+    (**self).set(product_id, quantity).await
+  }
+  async fn adjust(&self, product_id: Uuid, delta: i64) -> Result<StockLevel, RepoError> {
+    (**self).adjust(product_id, delta).await
+  }
+}
+
+/// Any `UnitOfWorkFactory` behind one type, so that the storage can be
+/// picked at runtime like the `Arc<dyn OrderRepository>` it pairs with.
+#[derive(Clone)]
+pub struct BoxedUnitOfWorkFactory(Arc<dyn BeginBoxed>);
+
+impl BoxedUnitOfWorkFactory {
+  pub fn new<F>(factory: F) -> Self
+  where
+    F: UnitOfWorkFactory,
+    <F::Work as UnitOfWork>::Orders: Sized,
+    <F::Work as UnitOfWork>::Inventory: Sized,
+  {
+    Self(Arc::new(factory))
+  }
+}
+
+#[async_trait]
+impl UnitOfWorkFactory for BoxedUnitOfWorkFactory {
+  type Work = BoxedUnitOfWork;
+
+  async fn begin(&self) -> Result<BoxedUnitOfWork, RepoError> {
+    self.0.begin_boxed().await
+  }
+}
+
+pub struct BoxedUnitOfWork(Box<dyn FinishBoxed>);
+
+#[async_trait]
+impl UnitOfWork for BoxedUnitOfWork {
+  type Orders = dyn OrderRepository;
+  type Inventory = dyn InventoryRepository;
+
+  fn orders(&self) -> &dyn OrderRepository {
+    self.0.orders()
+  }
+
+  fn inventory(&self) -> &dyn InventoryRepository {
+    self.0.inventory()
+  }
+
+  async fn commit(self) -> Result<(), RepoError> {
+    self.0.commit_boxed().await
+  }
+
+  async fn rollback(self) -> Result<(), RepoError> {
+    self.0.rollback_boxed().await
+  }
+}
+
+#[async_trait]
+trait BeginBoxed: Send + Sync + 'static {
+  async fn begin_boxed(&self) -> Result<BoxedUnitOfWork, RepoError>;
+}
+
+#[async_trait]
+impl<F> BeginBoxed for F
+where
+  F: UnitOfWorkFactory,
+  <F::Work as UnitOfWork>::Orders: Sized,
+  <F::Work as UnitOfWork>::Inventory: Sized,
+{
+  async fn begin_boxed(&self) -> Result<BoxedUnitOfWork, RepoError> {
+    Ok(BoxedUnitOfWork(Box::new(self.begin().await?)))
+  }
+}
+
+/// `UnitOfWork` with `commit` and `rollback` callable through a box.
+#[async_trait]
+trait FinishBoxed: Send + Sync + 'static {
+  fn orders(&self) -> &dyn OrderRepository;
+  fn inventory(&self) -> &dyn InventoryRepository;
+  async fn commit_boxed(self: Box<Self>) -> Result<(), RepoError>;
+  async fn rollback_boxed(self: Box<Self>) -> Result<(), RepoError>;
+}
+
+#[async_trait]
+impl<W> FinishBoxed for W
+where
+  W: UnitOfWork,
+  W::Orders: Sized,
+  W::Inventory: Sized,
+{
+  fn orders(&self) -> &dyn OrderRepository {
+    UnitOfWork::orders(self)
+  }
+
+  fn inventory(&self) -> &dyn InventoryRepository {
+    UnitOfWork::inventory(self)
+  }
+
+  async fn commit_boxed(self: Box<Self>) -> Result<(), RepoError> {
+    (*self).commit().await
+  }
+
+  async fn rollback_boxed(self: Box<Self>) -> Result<(), RepoError> {
+    (*self).rollback().await
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct NewPayment {
+  pub order_id: Uuid,
+  pub provider: String,
+  pub provider_payment_id: String,
+  pub amount_cents: i64,
+}
+
+/// A provider notification that already passed signature verification.
+#[derive(Debug, Clone)]
+pub struct PaymentEvent {
+  pub provider: String,
+  pub event_id: String,
+  pub event_type: String,
+  pub provider_payment_id: String,
+  pub payload: serde_json::Value,
+}
+
+/// Status changes a payment event applies to the payment and its order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaymentTransition {
+  /// Payment statuses the transition may be applied to.
+  pub from: &'static [&'static str],
+  pub payment_status: &'static str,
+  pub order_status: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaymentEventOutcome {
+  Applied(Payment),
+  /// The event id was processed before; nothing changed.
+  Duplicate,
+  /// The event was recorded but does not change the payment.
+  Ignored,
+}
+
+#[async_trait]
+pub trait PaymentRepository: Send + Sync + 'static {
+  async fn create(&self, input: NewPayment) -> Result<Payment, RepoError>;
+  async fn list_by_order(&self, order_id: Uuid) -> Result<Vec<Payment>, RepoError>;
+  /// Records `event` and applies `transition` to the matching payment and its
+  /// order in one transaction. Fails with `NotFound` when no payment matches;
+  /// the event is then left unrecorded so a provider retry can succeed.
+  async fn apply_event(
+    &self,
+    event: PaymentEvent,
+    transition: PaymentTransition,
+    ctx: &AuditContext,
+  ) -> Result<PaymentEventOutcome, RepoError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct NewReturnItem {
+  pub order_item_id: Uuid,
+  pub quantity: i32,
+  pub reason: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewReturn {
+  pub items: Vec<NewReturnItem>,
+  pub note: Option<String>,
+}
+
+#[async_trait]
+pub trait ReturnRepository: Send + Sync + 'static {
+  /// Fails with `Conflict` unless the order is paid, shipped, delivered or
+  /// partially refunded, and with `Invalid` when an item does not belong to
+  /// the order or more units would be returned than were ordered.
+  async fn create(&self, order_id: Uuid, input: NewReturn) -> Result<ReturnRequest, RepoError>;
+  async fn get(&self, id: Uuid) -> Result<ReturnRequest, RepoError>;
+  async fn list_by_order(&self, order_id: Uuid) -> Result<Vec<ReturnRequest>, RepoError>;
+  /// Moves a `requested` return to `approved`, optionally putting the
+  /// returned units back into inventory. Other states are a `Conflict`.
+  async fn approve(
+    &self,
+    id: Uuid,
+    restock: bool,
+    note: Option<String>,
+  ) -> Result<ReturnRequest, RepoError>;
+  async fn reject(&self, id: Uuid, note: Option<String>) -> Result<ReturnRequest, RepoError>;
+  /// Refunds an approved return against the order's captured payment and
+  /// updates the payment and order statuses. `None` refunds the remainder.
+  /// Orders that were already invoiced also get a credit note for the refund.
+  async fn refund(
+    &self,
+    id: Uuid,
+    amount_cents: Option<i64>,
+    ctx: &AuditContext,
+  ) -> Result<(ReturnRequest, Refund), RepoError>;
+  async fn list_refunds(&self, order_id: Uuid) -> Result<Vec<Refund>, RepoError>;
+}
+
+#[async_trait]
+pub trait InvoiceRepository: Send + Sync + 'static {
+  /// Issues the order's invoice with the next number of the current year.
+  /// Fails with `Conflict` if the order is unpaid or already invoiced.
+  async fn issue(
+    &self,
+    order_id: Uuid,
+    billing_address: BillingAddress,
+    tax_rate_bps: i32,
+  ) -> Result<Invoice, RepoError>;
+  async fn get_for_order(&self, order_id: Uuid) -> Result<Invoice, RepoError>;
+  async fn list_credit_notes(&self, order_id: Uuid) -> Result<Vec<Invoice>, RepoError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct NewProductImage {
+  pub id: Uuid,
+  pub blob_key: String,
+  pub content_type: String,
+  pub byte_size: i64,
+}
+
+#[async_trait]
+pub trait ProductImageRepository: Send + Sync + 'static {
+  /// Appends an image after the product's existing ones. `NotFound` when the
+  /// product does not exist.
+  async fn create(
+    &self,
+    product_id: Uuid,
+    input: NewProductImage,
+  ) -> Result<ProductImage, RepoError>;
+  /// Images in display order; `NotFound` when the product does not exist.
+  async fn list(&self, product_id: Uuid) -> Result<Vec<ProductImage>, RepoError>;
+  async fn get(&self, id: Uuid) -> Result<ProductImage, RepoError>;
+  /// Sets the display order; `image_ids` must list every image of the
+  /// product exactly once.
+  async fn reorder(
+    &self,
+    product_id: Uuid,
+    image_ids: &[Uuid],
+  ) -> Result<Vec<ProductImage>, RepoError>;
+  /// Deletes the image row and returns it so its blobs can be removed.
+  async fn delete(&self, product_id: Uuid, id: Uuid) -> Result<ProductImage, RepoError>;
+  /// Marks up to `limit` images as `processing` and returns them, oldest
+  /// first: pending ones, plus ones whose claim is older than
+  /// `stale_after_secs` because a worker died mid-job. Concurrent workers
+  /// never receive the same image.
+  async fn claim_thumbnail_jobs(
+    &self,
+    limit: i64,
+    stale_after_secs: i64,
+  ) -> Result<Vec<ProductImage>, RepoError>;
+  /// Records the generated variants and marks the image `ready`. `NotFound`
+  /// when the image was deleted in the meantime.
+  async fn save_variants(&self, id: Uuid, variants: Vec<ImageVariant>) -> Result<(), RepoError>;
+  /// Marks the image `failed`, e.g. because its file cannot be decoded.
+  async fn mark_thumbnails_failed(&self, id: Uuid, error: &str) -> Result<(), RepoError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct NewReview {
+  pub user_id: Uuid,
+  pub rating: i16,
+  pub title: String,
+  pub body: String,
+}
+
+#[async_trait]
+pub trait ReviewRepository: Send + Sync + 'static {
+  /// Fails with `NotFound` when the product or user does not exist,
+  /// `Invalid` unless the user has a delivered order containing the product,
+  /// and `Conflict` when the user already reviewed it.
+  async fn create(&self, product_id: Uuid, input: NewReview) -> Result<Review, RepoError>;
+  async fn get(&self, id: Uuid) -> Result<Review, RepoError>;
+  /// One page of reviews in `status`, newest first, optionally of a single
+  /// product, with the total count. `NotFound` when the product does not
+  /// exist.
+  async fn list(
+    &self,
+    product_id: Option<Uuid>,
+    status: &str,
+    limit: i64,
+    offset: i64,
+  ) -> Result<(Vec<Review>, i64), RepoError>;
+  /// Sets the moderation status and refreshes the product rating.
+  async fn moderate(
+    &self,
+    id: Uuid,
+    status: &str,
+    note: Option<String>,
+  ) -> Result<Review, RepoError>;
+  /// Deletes the review and refreshes the product rating.
+  async fn delete(&self, id: Uuid) -> Result<(), RepoError>;
+}
+
+#[async_trait]
+pub trait WishlistRepository: Send + Sync + 'static {
+  /// `NotFound` when the user does not exist, `Conflict` when they already
+  /// have a wishlist with that name.
+  async fn create(&self, user_id: Uuid, name: &str) -> Result<Wishlist, RepoError>;
+  /// The user's wishlists by name; `NotFound` when the user does not exist.
+  async fn list(&self, user_id: Uuid) -> Result<Vec<Wishlist>, RepoError>;
+  /// `NotFound` unless the wishlist exists and belongs to the user, here and
+  /// in the methods below.
+  async fn get(&self, user_id: Uuid, id: Uuid) -> Result<Wishlist, RepoError>;
+  async fn rename(&self, user_id: Uuid, id: Uuid, name: &str) -> Result<Wishlist, RepoError>;
+  async fn delete(&self, user_id: Uuid, id: Uuid) -> Result<(), RepoError>;
+  /// Adds a product at its current price. `Invalid` when the product does not
+  /// exist and `Conflict` when it is already on the list.
+  async fn add_item(
+    &self,
+    user_id: Uuid,
+    id: Uuid,
+    product_id: Uuid,
+  ) -> Result<WishlistItem, RepoError>;
+  async fn remove_item(&self, user_id: Uuid, id: Uuid, product_id: Uuid) -> Result<(), RepoError>;
+  /// Moves an item to the user's cart, the order in `cart` status, creating
+  /// it when needed. The item is added at the current price and leaves the
+  /// wishlist in the same transaction.
+  async fn move_to_cart(
+    &self,
+    user_id: Uuid,
+    id: Uuid,
+    product_id: Uuid,
+    quantity: i32,
+    ctx: &AuditContext,
+  ) -> Result<OrderItem, RepoError>;
+}
+
+/// Narrows the audit log; unset fields match every entry.
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+  pub actor: Option<String>,
+  pub action: Option<String>,
+  pub resource_type: Option<String>,
+  pub resource_id: Option<Uuid>,
+  pub request_id: Option<String>,
+  /// Inclusive.
+  pub from: Option<DateTime<Utc>>,
+  /// Exclusive.
+  pub to: Option<DateTime<Utc>>,
+}
+
+/// Read side of the audit log; repositories write entries as part of their
+/// own transactions.
+#[async_trait]
+pub trait AuditRepository: Send + Sync + 'static {
+  /// Matching entries, newest first, with the total number of matches.
+  async fn list(
+    &self,
+    filter: AuditFilter,
+    limit: i64,
+    offset: i64,
+  ) -> Result<(Vec<AuditEntry>, i64), RepoError>;
+}
+
+/// Read side of the transactional outbox; repositories write events as part
+/// of their own transactions.
+#[async_trait]
+pub trait OutboxRepository: Send + Sync + 'static {
+  async fn get(&self, id: i64) -> Result<OutboxEvent, RepoError>;
+  /// Leases up to `limit` due events, oldest first, and counts the attempt.
+  /// A leased event is not handed out again for `lease_secs`, so an event
+  /// whose dispatcher dies mid-delivery is retried once the lease expires.
+  async fn claim_due(&self, limit: i64, lease_secs: i64) -> Result<Vec<OutboxEvent>, RepoError>;
+  async fn mark_published(&self, id: i64) -> Result<(), RepoError>;
+  /// Records a failed attempt and schedules the next one after
+  /// `retry_after_secs`, or gives up on the event when `retry_after_secs` is
+  /// `None`.
+  async fn mark_failed(
+    &self,
+    id: i64,
+    error: &str,
+    retry_after_secs: Option<i64>,
+  ) -> Result<(), RepoError>;
+}
+
+/// Delivers outbox events to the outside world. Delivery is at least once, so
+/// implementations may see the same `event_id` more than once.
+#[async_trait]
+pub trait EventPublisher: Send + Sync + 'static {
+  async fn publish(&self, event: &OutboxEvent) -> Result<(), RepoError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct NewWebhookSubscription {
+  pub url: String,
+  pub secret: String,
+  pub event_types: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UpdateWebhookSubscription {
+  pub url: Option<String>,
+  pub secret: Option<String>,
+  pub event_types: Option<Vec<String>>,
+  pub active: Option<bool>,
+}
+
+/// A claimed delivery with what is needed to send it.
+#[derive(Debug, Clone)]
+pub struct DueWebhookDelivery {
+  pub delivery: WebhookDelivery,
+  pub url: String,
+  pub secret: String,
+}
+
+/// Outcome of one attempt to send a delivery.
+#[derive(Debug, Clone)]
+pub struct WebhookAttemptResult {
+  pub succeeded: bool,
+  pub response_status: Option<i32>,
+  pub error: Option<String>,
+  pub duration_ms: i32,
+}
+
+#[async_trait]
+pub trait WebhookRepository: Send + Sync + 'static {
+  async fn create(&self, input: NewWebhookSubscription) -> Result<WebhookSubscription, RepoError>;
+  async fn list(&self) -> Result<Vec<WebhookSubscription>, RepoError>;
+  async fn get(&self, id: Uuid) -> Result<WebhookSubscription, RepoError>;
+  /// Activating a subscription also clears its failure count.
+  async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateWebhookSubscription,
+  ) -> Result<WebhookSubscription, RepoError>;
   async fn delete(&self, id: Uuid) -> Result<(), RepoError>;
+  /// One page of the subscription's deliveries, newest first, with the total
+  /// count. `NotFound` when the subscription does not exist.
+  async fn list_deliveries(
+    &self,
+    subscription_id: Uuid,
+    limit: i64,
+    offset: i64,
+  ) -> Result<(Vec<WebhookDelivery>, i64), RepoError>;
+  /// `NotFound` unless the delivery belongs to the subscription, here and in
+  /// `redeliver`.
+  async fn get_delivery(
+    &self,
+    subscription_id: Uuid,
+    id: Uuid,
+  ) -> Result<WebhookDelivery, RepoError>;
+  /// Queues the delivery to be sent again right away with a fresh attempt
+  /// budget. `Invalid` when the subscription is disabled.
+  async fn redeliver(&self, subscription_id: Uuid, id: Uuid) -> Result<WebhookDelivery, RepoError>;
+  /// Creates a pending delivery of the event for every active subscription
+  /// to its type and returns how many were created. Publishing the same
+  /// event again creates none.
+  async fn enqueue_deliveries(&self, event: &OutboxEvent) -> Result<u64, RepoError>;
+  /// Leases up to `limit` due deliveries of active subscriptions and counts
+  /// the attempt, like `OutboxRepository::claim_due`.
+  async fn claim_deliveries(
+    &self,
+    limit: i64,
+    lease_secs: i64,
+  ) -> Result<Vec<DueWebhookDelivery>, RepoError>;
+  /// Logs the attempt. A success completes the delivery and resets the
+  /// subscription's failure count. A failure schedules the next attempt after
+  /// `retry_after_secs`, or fails the delivery when it is `None`, and
+  /// disables the subscription once it has failed `disable_after_failures`
+  /// times in a row. `NotFound` when the delivery was deleted meanwhile.
+  async fn record_attempt(
+    &self,
+    id: Uuid,
+    result: WebhookAttemptResult,
+    retry_after_secs: Option<i64>,
+    disable_after_failures: i32,
+  ) -> Result<(), RepoError>;
+}
+
+/// Opaque byte storage addressed by key, such as a directory or an S3 bucket.
+#[async_trait]
+pub trait BlobStore: Send + Sync + 'static {
+  async fn put(&self, key: &str, content_type: &str, bytes: Vec<u8>) -> Result<(), RepoError>;
+  /// `NotFound` when nothing is stored under `key`.
+  async fn get(&self, key: &str) -> Result<Vec<u8>, RepoError>;
+  /// Deleting a missing key succeeds.
+  async fn delete(&self, key: &str) -> Result<(), RepoError>;
 }
@@ -1,8 +1,22 @@
 use crate::application::ports::{
-  NewOrder, NewProduct, NewUser, OrderRepository, ProductRepository, RepoError, UpdateOrder,
-  UpdateProduct, UpdateUser, UserRepository,
+  AuditFilter, AuditRepository, BlobStore, CategoryRepository, InventoryRepository,
+  InvoiceRepository, NewCategory, NewOrder, NewOrderItem, NewPayment, NewProduct, NewProductImage,
+  NewProductOption, NewReturn, NewReview, NewUser, NewVariant, NewWebhookSubscription,
+  OrderRepository, PaymentEvent, PaymentEventOutcome, PaymentRepository, PaymentTransition,
+  ProductImageRepository, ProductRepository, RepoError, ReturnRepository, ReviewRepository,
+  UnitOfWork, UnitOfWorkFactory, UpdateCategory, UpdateOrder, UpdateProduct, UpdateUser,
+  UpdateVariant, UpdateWebhookSubscription, UserRepository, VariantRepository, WebhookRepository,
+  WishlistRepository,
 };
-use crate::domain::models::{Order, Product, User};
+use crate::domain::models::{
+  AuditContext, AuditEntry, BillingAddress, Category, CategoryNode, ImageVariant, Invoice, Order,
+  OrderHistoryEntry, OrderItem, Page, Payment, Product, ProductImage, ProductOption, ProductPrice,
+  ProductSearchHit, ProductVariant, Refund, ReturnRequest, Review, SimilarityHit, StockLevel, User,
+  WebhookDelivery, WebhookSubscription, Wishlist, WishlistItem,
+};
+use crate::domain::{catalog, events, media, order_history};
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -18,8 +32,8 @@ impl<R: UserRepository> UserService<R> {
     }
   }
 
-  pub async fn create(&self, input: NewUser) -> Result<User, RepoError> {
-    self.repo.create(input).await
+  pub async fn create(&self, input: NewUser, ctx: &AuditContext) -> Result<User, RepoError> {
+    self.repo.create(input, ctx).await
   }
   pub async fn list(&self) -> Result<Vec<User>, RepoError> {
     self.repo.list().await
@@ -27,12 +41,48 @@ impl<R: UserRepository> UserService<R> {
   pub async fn get(&self, id: Uuid) -> Result<User, RepoError> {
     self.repo.get(id).await
   }
-  pub async fn update(&self, id: Uuid, input: UpdateUser) -> Result<User, RepoError> {
-    self.repo.update(id, input).await
+  pub async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateUser,
+    ctx: &AuditContext,
+  ) -> Result<User, RepoError> {
+    self.repo.update(id, input, ctx).await
   }
-  pub async fn delete(&self, id: Uuid) -> Result<(), RepoError> {
-    self.repo.delete(id).await
+  pub async fn delete(&self, id: Uuid, ctx: &AuditContext) -> Result<(), RepoError> {
+    self.repo.delete(id, ctx).await
+  }
+  pub async fn fuzzy_search(
+    &self,
+    text: &str,
+    threshold: f32,
+    limit: u32,
+  ) -> Result<Vec<SimilarityHit<User>>, RepoError> {
+    let text = validate_fuzzy_search(text, threshold, limit)?;
+    self
+      .repo
+      .fuzzy_search(text, threshold, i64::from(limit))
+      .await
+  }
+}
+
+/// Checks fuzzy search arguments and returns the trimmed search text.
+fn validate_fuzzy_search(text: &str, threshold: f32, limit: u32) -> Result<&str, RepoError> {
+  let text = text.trim();
+  if text.is_empty() {
+    return Err(RepoError::Invalid("search text is required".into()));
+  }
+  if !(0.0..=1.0).contains(&threshold) {
+    return Err(RepoError::Invalid(
+      "similarity threshold must be between 0 and 1".into(),
+    ));
+  }
+  if !(1..=MAX_PER_PAGE).contains(&limit) {
+    return Err(RepoError::Invalid(format!(
+      "limit must be between 1 and {MAX_PER_PAGE}"
+    )));
   }
+  Ok(text)
 }
 
 #[derive(Clone)]
@@ -47,8 +97,9 @@ impl<R: ProductRepository> ProductService<R> {
     }
   }
 
-  pub async fn create(&self, input: NewProduct) -> Result<Product, RepoError> {
-    self.repo.create(input).await
+  pub async fn create(&self, input: NewProduct, ctx: &AuditContext) -> Result<Product, RepoError> {
+    validate_price(Some(input.price_cents))?;
+    self.repo.create(input, ctx).await
   }
   pub async fn list(&self) -> Result<Vec<Product>, RepoError> {
     self.repo.list().await
@@ -56,7 +107,209 @@ impl<R: ProductRepository> ProductService<R> {
   pub async fn get(&self, id: Uuid) -> Result<Product, RepoError> {
     self.repo.get(id).await
   }
-  pub async fn update(&self, id: Uuid, input: UpdateProduct) -> Result<Product, RepoError> {
+  /// A new `price_cents` takes effect immediately and is kept in the price
+  /// history.
+  pub async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateProduct,
+    ctx: &AuditContext,
+  ) -> Result<Product, RepoError> {
+    validate_price(input.price_cents)?;
+    self.repo.update(id, input, ctx).await
+  }
+  pub async fn delete(&self, id: Uuid, ctx: &AuditContext) -> Result<(), RepoError> {
+    self.repo.delete(id, ctx).await
+  }
+  pub async fn list_by_category(
+    &self,
+    slug: &str,
+    include_descendants: bool,
+  ) -> Result<Vec<Product>, RepoError> {
+    self.repo.list_by_category(slug, include_descendants).await
+  }
+  /// Prefix-matches every word of `text` against names and descriptions.
+  pub async fn search(
+    &self,
+    text: &str,
+    page: u32,
+    per_page: u32,
+  ) -> Result<Page<ProductSearchHit>, RepoError> {
+    let Some(tsquery) = catalog::prefix_tsquery(text) else {
+      return Err(RepoError::Invalid(
+        "search text needs at least one word".into(),
+      ));
+    };
+    let offset = page_offset(page, per_page)?;
+    let (items, total) = self
+      .repo
+      .search(&tsquery, i64::from(per_page), offset)
+      .await?;
+    Ok(Page {
+      items,
+      page,
+      per_page,
+      total,
+    })
+  }
+  pub async fn price_history(&self, id: Uuid) -> Result<Vec<ProductPrice>, RepoError> {
+    self.repo.price_history(id).await
+  }
+  /// The price in effect at `at`; `NotFound` before the product existed.
+  pub async fn price_at(&self, id: Uuid, at: DateTime<Utc>) -> Result<ProductPrice, RepoError> {
+    let history = self.repo.price_history(id).await?;
+    catalog::price_at(&history, at)
+      .cloned()
+      .ok_or(RepoError::NotFound)
+  }
+  /// Schedules `price_cents` to take effect at `valid_from`, which must be in
+  /// the future.
+  pub async fn schedule_price(
+    &self,
+    id: Uuid,
+    price_cents: i64,
+    valid_from: DateTime<Utc>,
+    ctx: &AuditContext,
+  ) -> Result<ProductPrice, RepoError> {
+    validate_price(Some(price_cents))?;
+    if valid_from <= Utc::now() {
+      return Err(RepoError::Invalid(
+        "scheduled prices must start in the future".into(),
+      ));
+    }
+    self
+      .repo
+      .schedule_price(id, price_cents, valid_from, ctx)
+      .await
+  }
+  pub async fn cancel_scheduled_price(
+    &self,
+    id: Uuid,
+    price_id: Uuid,
+    ctx: &AuditContext,
+  ) -> Result<(), RepoError> {
+    self.repo.cancel_scheduled_price(id, price_id, ctx).await
+  }
+  /// Matches `text` against SKUs and names, tolerating typos.
+  pub async fn fuzzy_search(
+    &self,
+    text: &str,
+    threshold: f32,
+    limit: u32,
+  ) -> Result<Vec<SimilarityHit<Product>>, RepoError> {
+    let text = validate_fuzzy_search(text, threshold, limit)?;
+    self
+      .repo
+      .fuzzy_search(text, threshold, i64::from(limit))
+      .await
+  }
+}
+
+fn validate_price(price_cents: Option<i64>) -> Result<(), RepoError> {
+  if price_cents.is_some_and(|p| p < 0) {
+    return Err(RepoError::Invalid("price must not be negative".into()));
+  }
+  Ok(())
+}
+
+/// Upper bound for `per_page` in paginated listings and for search limits.
+pub const MAX_PER_PAGE: u32 = 100;
+
+/// Checks 1-based pagination arguments and returns the row offset.
+fn page_offset(page: u32, per_page: u32) -> Result<i64, RepoError> {
+  if page == 0 {
+    return Err(RepoError::Invalid("page starts at 1".into()));
+  }
+  if !(1..=MAX_PER_PAGE).contains(&per_page) {
+    return Err(RepoError::Invalid(format!(
+      "per_page must be between 1 and {MAX_PER_PAGE}"
+    )));
+  }
+  Ok(i64::from(page - 1) * i64::from(per_page))
+}
+
+#[derive(Clone)]
+pub struct VariantService<R: VariantRepository> {
+  repo: Arc<R>,
+}
+
+fn validate_variant(
+  sku: Option<&str>,
+  price_override_cents: Option<i64>,
+  stock: Option<i64>,
+) -> Result<(), RepoError> {
+  if sku.is_some_and(|s| s.trim().is_empty()) {
+    return Err(RepoError::Invalid("sku is required".into()));
+  }
+  if price_override_cents.is_some_and(|p| p < 0) {
+    return Err(RepoError::Invalid("price must not be negative".into()));
+  }
+  if stock.is_some_and(|s| s < 0) {
+    return Err(RepoError::Invalid("stock must not be negative".into()));
+  }
+  Ok(())
+}
+
+impl<R: VariantRepository> VariantService<R> {
+  pub fn new(repo: R) -> Self {
+    Self {
+      repo: Arc::new(repo),
+    }
+  }
+
+  pub async fn add_option(
+    &self,
+    product_id: Uuid,
+    input: NewProductOption,
+  ) -> Result<ProductOption, RepoError> {
+    if input.name.trim().is_empty() {
+      return Err(RepoError::Invalid("option name is required".into()));
+    }
+    if input.values.is_empty() {
+      return Err(RepoError::Invalid(
+        "an option needs at least one value".into(),
+      ));
+    }
+    let mut seen = HashSet::new();
+    for value in &input.values {
+      if value.trim().is_empty() {
+        return Err(RepoError::Invalid("option values must not be empty".into()));
+      }
+      if !seen.insert(value.as_str()) {
+        return Err(RepoError::Invalid(format!(
+          "duplicate option value `{value}`"
+        )));
+      }
+    }
+    self.repo.add_option(product_id, input).await
+  }
+  pub async fn list_options(&self, product_id: Uuid) -> Result<Vec<ProductOption>, RepoError> {
+    self.repo.list_options(product_id).await
+  }
+  pub async fn create(
+    &self,
+    product_id: Uuid,
+    input: NewVariant,
+  ) -> Result<ProductVariant, RepoError> {
+    validate_variant(
+      Some(&input.sku),
+      input.price_override_cents,
+      Some(input.stock),
+    )?;
+    self.repo.create(product_id, input).await
+  }
+  pub async fn list_by_product(&self, product_id: Uuid) -> Result<Vec<ProductVariant>, RepoError> {
+    self.repo.list_by_product(product_id).await
+  }
+  pub async fn get(&self, id: Uuid) -> Result<ProductVariant, RepoError> {
+    self.repo.get(id).await
+  }
+  pub async fn update(&self, id: Uuid, input: UpdateVariant) -> Result<ProductVariant, RepoError> {
+    validate_variant(
+      input.sku.as_deref(),
+      input.price_override_cents.flatten(),
+      input.stock,
+    )?;
     self.repo.update(id, input).await
   }
   pub async fn delete(&self, id: Uuid) -> Result<(), RepoError> {
@@ -64,6 +317,65 @@ impl<R: ProductRepository> ProductService<R> {
   }
 }
 
+#[derive(Clone)]
+pub struct CategoryService<R: CategoryRepository> {
+  repo: Arc<R>,
+}
+
+fn validate_category(slug: Option<&str>, name: Option<&str>) -> Result<(), RepoError> {
+  if slug.is_some_and(|s| !catalog::is_valid_slug(s)) {
+    return Err(RepoError::Invalid(
+      "slug must be lowercase letters, digits and single hyphens".into(),
+    ));
+  }
+  if name.is_some_and(|n| n.trim().is_empty()) {
+    return Err(RepoError::Invalid("name is required".into()));
+  }
+  Ok(())
+}
+
+impl<R: CategoryRepository> CategoryService<R> {
+  pub fn new(repo: R) -> Self {
+    Self {
+      repo: Arc::new(repo),
+    }
+  }
+
+  pub async fn create(&self, input: NewCategory) -> Result<Category, RepoError> {
+    validate_category(Some(&input.slug), Some(&input.name))?;
+    self.repo.create(input).await
+  }
+  /// The whole taxonomy, nested.
+  pub async fn tree(&self) -> Result<Vec<CategoryNode>, RepoError> {
+    Ok(catalog::build_tree(self.repo.list().await?))
+  }
+  pub async fn get(&self, id: Uuid) -> Result<Category, RepoError> {
+    self.repo.get(id).await
+  }
+  pub async fn update(&self, id: Uuid, input: UpdateCategory) -> Result<Category, RepoError> {
+    validate_category(input.slug.as_deref(), input.name.as_deref())?;
+    self.repo.update(id, input).await
+  }
+  pub async fn delete(&self, id: Uuid) -> Result<(), RepoError> {
+    self.repo.delete(id).await
+  }
+  pub async fn set_product_categories(
+    &self,
+    product_id: Uuid,
+    mut category_ids: Vec<Uuid>,
+  ) -> Result<Vec<Category>, RepoError> {
+    category_ids.sort();
+    category_ids.dedup();
+    self
+      .repo
+      .set_product_categories(product_id, category_ids)
+      .await
+  }
+  pub async fn list_for_product(&self, product_id: Uuid) -> Result<Vec<Category>, RepoError> {
+    self.repo.list_for_product(product_id).await
+  }
+}
+
 #[derive(Clone)]
 pub struct OrderService<R: OrderRepository> {
   repo: Arc<R>,
@@ -76,8 +388,8 @@ impl<R: OrderRepository> OrderService<R> {
     }
   }
 
-  pub async fn create(&self, input: NewOrder) -> Result<Order, RepoError> {
-    self.repo.create(input).await
+  pub async fn create(&self, input: NewOrder, ctx: &AuditContext) -> Result<Order, RepoError> {
+    self.repo.create(input, ctx).await
   }
   pub async fn list(&self) -> Result<Vec<Order>, RepoError> {
     self.repo.list().await
@@ -85,86 +397,805 @@ impl<R: OrderRepository> OrderService<R> {
   pub async fn get(&self, id: Uuid) -> Result<Order, RepoError> {
     self.repo.get(id).await
   }
-  pub async fn update(&self, id: Uuid, input: UpdateOrder) -> Result<Order, RepoError> {
-    self.repo.update(id, input).await
+  pub async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateOrder,
+    ctx: &AuditContext,
+  ) -> Result<Order, RepoError> {
+    self.repo.update(id, input, ctx).await
   }
-  pub async fn delete(&self, id: Uuid) -> Result<(), RepoError> {
-    self.repo.delete(id).await
+  pub async fn delete(&self, id: Uuid, ctx: &AuditContext) -> Result<(), RepoError> {
+    self.repo.delete(id, ctx).await
+  }
+  pub async fn add_item(
+    &self,
+    order_id: Uuid,
+    input: NewOrderItem,
+    ctx: &AuditContext,
+  ) -> Result<OrderItem, RepoError> {
+    if input.quantity <= 0 {
+      return Err(RepoError::Invalid("quantity must be positive".into()));
+    }
+    self.repo.add_item(order_id, input, ctx).await
+  }
+  pub async fn list_items(&self, order_id: Uuid) -> Result<Vec<OrderItem>, RepoError> {
+    self.repo.list_items(order_id).await
+  }
+  pub async fn history(&self, order_id: Uuid) -> Result<Vec<OrderHistoryEntry>, RepoError> {
+    self.repo.history(order_id).await
+  }
+  /// The order as it was at `at`, rebuilt from its history; `NotFound` if it
+  /// did not exist then.
+  pub async fn state_at(&self, order_id: Uuid, at: DateTime<Utc>) -> Result<Order, RepoError> {
+    let history = self.repo.history(order_id).await?;
+    order_history::replay(order_id, &history, at).ok_or(RepoError::NotFound)
   }
 }
 
-#[cfg(test)]
-mod tests {
-  use super::*;
-  use crate::application::ports::{NewUser, UpdateUser, UserRepository};
-  use async_trait::async_trait;
-  use chrono::Utc;
-  use std::collections::HashMap;
-  use tokio::sync::Mutex;
-  use uuid::Uuid;
+/// Places orders, reserving stock for them in the same transaction.
+#[derive(Clone)]
+pub struct CheckoutService<U: UnitOfWorkFactory> {
+  units: Arc<U>,
+}
 
-  #[derive(Clone, Default)]
-  struct FakeUserRepo {
-    store: Arc<Mutex<HashMap<Uuid, User>>>,
+impl<U: UnitOfWorkFactory> CheckoutService<U> {
+  pub fn new(units: U) -> Self {
+    Self {
+      units: Arc::new(units),
+    }
   }
 
-  #[async_trait]
-  impl UserRepository for FakeUserRepo {
-    async fn create(&self, input: NewUser) -> Result<User, RepoError> {
-      let id = Uuid::new_v4();
-      let now = Utc::now();
-      let user = User {
-        id,
-        email: input.email,
-        name: input.name,
-        created_at: now,
-        updated_at: now,
-      };
-      self.store.lock().await.insert(id, user.clone());
-      Ok(user)
-    }
-    async fn list(&self) -> Result<Vec<User>, RepoError> {
-      Ok(self.store.lock().await.values().cloned().collect())
-    }
-    async fn get(&self, id: Uuid) -> Result<User, RepoError> {
-      self
-        .store
-        .lock()
+  /// Creates a `pending_payment` order with `items` at current prices and
+  /// takes their units out of stock; all or nothing. `Conflict` when a
+  /// product is unknown or short of stock.
+  pub async fn checkout(
+    &self,
+    user_id: Uuid,
+    items: Vec<NewOrderItem>,
+    ctx: &AuditContext,
+  ) -> Result<Order, RepoError> {
+    if items.is_empty() {
+      return Err(RepoError::Invalid(
+        "an order needs at least one item".into(),
+      ));
+    }
+    if items.iter().any(|item| item.quantity <= 0) {
+      return Err(RepoError::Invalid("quantity must be positive".into()));
+    }
+
+    // Returning early drops `work`, which rolls everything back.
+    let work = self.units.begin().await?;
+    let order = work
+      .orders()
+      .create(
+        NewOrder {
+          user_id,
+          status: "pending_payment".into(),
+          total_cents: 0,
+        },
+        ctx,
+      )
+      .await?;
+    for item in items {
+      work
+        .inventory()
+        .adjust(item.product_id, -i64::from(item.quantity))
         .await
-        .get(&id)
-        .cloned()
-        .ok_or(RepoError::NotFound)
-    }
-    async fn update(&self, id: Uuid, input: UpdateUser) -> Result<User, RepoError> {
-      let mut guard = self.store.lock().await;
-      let u = guard.get_mut(&id).ok_or(RepoError::NotFound)?;
-      if let Some(email) = input.email {
-        u.email = email;
+        .map_err(|err| match err {
+          RepoError::NotFound => RepoError::Conflict,
+          err => err,
+        })?;
+      work.orders().add_item(order.id, item, ctx).await?;
+    }
+    let order = work.orders().get(order.id).await?;
+    work.commit().await?;
+    Ok(order)
+  }
+}
+
+#[derive(Clone)]
+pub struct InventoryService<R: InventoryRepository> {
+  repo: Arc<R>,
+}
+
+impl<R: InventoryRepository> InventoryService<R> {
+  pub fn new(repo: R) -> Self {
+    Self {
+      repo: Arc::new(repo),
+    }
+  }
+
+  pub async fn get(&self, product_id: Uuid) -> Result<StockLevel, RepoError> {
+    self.repo.get(product_id).await
+  }
+  pub async fn set(&self, product_id: Uuid, quantity: i64) -> Result<StockLevel, RepoError> {
+    if quantity < 0 {
+      return Err(RepoError::Invalid("quantity must not be negative".into()));
+    }
+    self.repo.set(product_id, quantity).await
+  }
+}
+
+#[derive(Clone)]
+pub struct PaymentService<R: PaymentRepository> {
+  repo: Arc<R>,
+}
+
+impl<R: PaymentRepository> PaymentService<R> {
+  pub fn new(repo: R) -> Self {
+    Self {
+      repo: Arc::new(repo),
+    }
+  }
+
+  pub async fn create(&self, input: NewPayment) -> Result<Payment, RepoError> {
+    self.repo.create(input).await
+  }
+  pub async fn list_by_order(&self, order_id: Uuid) -> Result<Vec<Payment>, RepoError> {
+    self.repo.list_by_order(order_id).await
+  }
+
+  /// Applies a verified provider event. Event types we do not act on are
+  /// acknowledged without being stored.
+  pub async fn handle_event(
+    &self,
+    event: PaymentEvent,
+    ctx: &AuditContext,
+  ) -> Result<PaymentEventOutcome, RepoError> {
+    match transition_for(&event.event_type) {
+      Some(transition) => self.repo.apply_event(event, transition, ctx).await,
+      None => Ok(PaymentEventOutcome::Ignored),
+    }
+  }
+}
+
+fn transition_for(event_type: &str) -> Option<PaymentTransition> {
+  match event_type {
+    // A failed attempt can still be followed by a successful retry.
+    "payment.succeeded" => Some(PaymentTransition {
+      from: &["pending", "failed"],
+      payment_status: "succeeded",
+      order_status: "paid",
+    }),
+    "payment.failed" => Some(PaymentTransition {
+      from: &["pending"],
+      payment_status: "failed",
+      order_status: "payment_failed",
+    }),
+    _ => None,
+  }
+}
+
+/// Reasons a customer may give for returning an item.
+pub const RETURN_REASONS: &[&str] = &[
+  "damaged",
+  "defective",
+  "wrong_item",
+  "not_as_described",
+  "no_longer_needed",
+  "other",
+];
+
+#[derive(Clone)]
+pub struct ReturnService<R: ReturnRepository> {
+  repo: Arc<R>,
+}
+
+impl<R: ReturnRepository> ReturnService<R> {
+  pub fn new(repo: R) -> Self {
+    Self {
+      repo: Arc::new(repo),
+    }
+  }
+
+  pub async fn create(&self, order_id: Uuid, input: NewReturn) -> Result<ReturnRequest, RepoError> {
+    if input.items.is_empty() {
+      return Err(RepoError::Invalid(
+        "a return needs at least one item".into(),
+      ));
+    }
+    let mut seen = HashSet::new();
+    for item in &input.items {
+      if item.quantity <= 0 {
+        return Err(RepoError::Invalid("quantity must be positive".into()));
+      }
+      if !RETURN_REASONS.contains(&item.reason.as_str()) {
+        return Err(RepoError::Invalid(format!(
+          "unknown return reason `{}`",
+          item.reason
+        )));
+      }
+      if !seen.insert(item.order_item_id) {
+        return Err(RepoError::Invalid("order items must not repeat".into()));
+      }
+    }
+    self.repo.create(order_id, input).await
+  }
+  pub async fn get(&self, id: Uuid) -> Result<ReturnRequest, RepoError> {
+    self.repo.get(id).await
+  }
+  pub async fn list_by_order(&self, order_id: Uuid) -> Result<Vec<ReturnRequest>, RepoError> {
+    self.repo.list_by_order(order_id).await
+  }
+  pub async fn approve(
+    &self,
+    id: Uuid,
+    restock: bool,
+    note: Option<String>,
+  ) -> Result<ReturnRequest, RepoError> {
+    self.repo.approve(id, restock, note).await
+  }
+  pub async fn reject(&self, id: Uuid, note: Option<String>) -> Result<ReturnRequest, RepoError> {
+    self.repo.reject(id, note).await
+  }
+  pub async fn refund(
+    &self,
+    id: Uuid,
+    amount_cents: Option<i64>,
+    ctx: &AuditContext,
+  ) -> Result<(ReturnRequest, Refund), RepoError> {
+    if amount_cents.is_some_and(|a| a <= 0) {
+      return Err(RepoError::Invalid("refund amount must be positive".into()));
+    }
+    self.repo.refund(id, amount_cents, ctx).await
+  }
+  pub async fn list_refunds(&self, order_id: Uuid) -> Result<Vec<Refund>, RepoError> {
+    self.repo.list_refunds(order_id).await
+  }
+}
+
+#[derive(Clone)]
+pub struct InvoiceService<R: InvoiceRepository> {
+  repo: Arc<R>,
+  tax_rate_bps: i32,
+}
+
+impl<R: InvoiceRepository> InvoiceService<R> {
+  pub fn new(repo: R, tax_rate_bps: i32) -> Self {
+    Self {
+      repo: Arc::new(repo),
+      tax_rate_bps,
+    }
+  }
+
+  pub async fn issue(
+    &self,
+    order_id: Uuid,
+    billing_address: BillingAddress,
+  ) -> Result<Invoice, RepoError> {
+    let required = [
+      ("name", &billing_address.name),
+      ("line1", &billing_address.line1),
+      ("city", &billing_address.city),
+      ("postal_code", &billing_address.postal_code),
+      ("country", &billing_address.country),
+    ];
+    if let Some((field, _)) = required.iter().find(|(_, v)| v.trim().is_empty()) {
+      return Err(RepoError::Invalid(format!(
+        "billing address {field} is required"
+      )));
+    }
+    self
+      .repo
+      .issue(order_id, billing_address, self.tax_rate_bps)
+      .await
+  }
+  pub async fn get_for_order(&self, order_id: Uuid) -> Result<Invoice, RepoError> {
+    self.repo.get_for_order(order_id).await
+  }
+  pub async fn list_credit_notes(&self, order_id: Uuid) -> Result<Vec<Invoice>, RepoError> {
+    self.repo.list_credit_notes(order_id).await
+  }
+}
+
+#[derive(Clone)]
+pub struct ProductImageService<R: ProductImageRepository> {
+  repo: Arc<R>,
+  blobs: Arc<dyn BlobStore>,
+}
+
+impl<R: ProductImageRepository> ProductImageService<R> {
+  pub fn new(repo: R, blobs: Arc<dyn BlobStore>) -> Self {
+    Self {
+      repo: Arc::new(repo),
+      blobs,
+    }
+  }
+
+  /// Stores an uploaded image. The file must be JPEG, PNG, GIF or WebP, and
+  /// match `declared_content_type` when the client sent one.
+  pub async fn upload(
+    &self,
+    product_id: Uuid,
+    declared_content_type: Option<&str>,
+    bytes: Vec<u8>,
+  ) -> Result<ProductImage, RepoError> {
+    if bytes.is_empty() {
+      return Err(RepoError::Invalid("image file is empty".into()));
+    }
+    let Some(content_type) = media::sniff_image_type(&bytes) else {
+      return Err(RepoError::Invalid(format!(
+        "unsupported image format, expected one of {}",
+        media::IMAGE_CONTENT_TYPES.join(", ")
+      )));
+    };
+    if let Some(declared) = declared_content_type.map(media::essence) {
+      if declared != content_type {
+        return Err(RepoError::Invalid(format!(
+          "declared content type {declared} does not match the {content_type} file"
+        )));
       }
-      if let Some(name) = input.name {
-        u.name = name;
+    }
+
+    let id = Uuid::new_v4();
+    let blob_key = media::image_blob_key(product_id, id);
+    let byte_size = bytes.len() as i64;
+    self.blobs.put(&blob_key, content_type, bytes).await?;
+    let created = self
+      .repo
+      .create(
+        product_id,
+        NewProductImage {
+          id,
+          blob_key: blob_key.clone(),
+          content_type: content_type.to_string(),
+          byte_size,
+        },
+      )
+      .await;
+    if created.is_err() {
+      self.discard_blob(&blob_key).await;
+    }
+    created
+  }
+  pub async fn list(&self, product_id: Uuid) -> Result<Vec<ProductImage>, RepoError> {
+    self.repo.list(product_id).await
+  }
+  pub async fn reorder(
+    &self,
+    product_id: Uuid,
+    image_ids: &[Uuid],
+  ) -> Result<Vec<ProductImage>, RepoError> {
+    let unique: HashSet<&Uuid> = image_ids.iter().collect();
+    if unique.len() != image_ids.len() {
+      return Err(RepoError::Invalid("image_ids must not repeat".into()));
+    }
+    self.repo.reorder(product_id, image_ids).await
+  }
+  pub async fn delete(&self, product_id: Uuid, id: Uuid) -> Result<(), RepoError> {
+    let image = self.repo.delete(product_id, id).await?;
+    self.discard_blobs(&[image]).await;
+    Ok(())
+  }
+  /// Removes the stored files of images whose rows are already gone, e.g.
+  /// after their product was deleted.
+  pub async fn discard_blobs(&self, images: &[ProductImage]) {
+    for image in images {
+      self.discard_blob(&image.blob_key).await;
+      for variant in &image.variants {
+        self.discard_blob(&variant.blob_key).await;
       }
-      u.updated_at = Utc::now();
-      Ok(u.clone())
     }
-    async fn delete(&self, id: Uuid) -> Result<(), RepoError> {
-      let removed = self.store.lock().await.remove(&id);
-      if removed.is_none() {
-        return Err(RepoError::NotFound);
+  }
+  /// The image and its bytes.
+  pub async fn download(&self, id: Uuid) -> Result<(ProductImage, Vec<u8>), RepoError> {
+    let image = self.repo.get(id).await?;
+    let bytes = self.blobs.get(&image.blob_key).await?;
+    Ok((image, bytes))
+  }
+  /// A generated variant of the image, e.g. `480.webp`, and its bytes.
+  pub async fn download_variant(
+    &self,
+    id: Uuid,
+    name: &str,
+  ) -> Result<(ImageVariant, Vec<u8>), RepoError> {
+    let image = self.repo.get(id).await?;
+    let Some(variant) = image.variants.into_iter().find(|v| v.name == name) else {
+      return Err(RepoError::NotFound);
+    };
+    let bytes = self.blobs.get(&variant.blob_key).await?;
+    Ok((variant, bytes))
+  }
+
+  /// Deletes a blob whose row is gone. A failure only leaves an orphaned
+  /// file behind, so it is logged rather than returned.
+  async fn discard_blob(&self, key: &str) {
+    if let Err(err) = self.blobs.delete(key).await {
+      tracing::warn!(key, error = %err, "failed to delete blob");
+    }
+  }
+}
+
+/// Moderation states of a review.
+pub const REVIEW_STATUSES: &[&str] = &["pending", "approved", "rejected"];
+const MAX_REVIEW_TITLE_CHARS: usize = 120;
+const MAX_REVIEW_BODY_CHARS: usize = 5000;
+
+#[derive(Clone)]
+pub struct ReviewService<R: ReviewRepository> {
+  repo: Arc<R>,
+}
+
+impl<R: ReviewRepository> ReviewService<R> {
+  pub fn new(repo: R) -> Self {
+    Self {
+      repo: Arc::new(repo),
+    }
+  }
+
+  /// Submits a review for moderation. Only customers with a delivered order
+  /// containing the product may review it, once.
+  pub async fn create(&self, product_id: Uuid, input: NewReview) -> Result<Review, RepoError> {
+    if !(1..=5).contains(&input.rating) {
+      return Err(RepoError::Invalid("rating must be between 1 and 5".into()));
+    }
+    let title = input.title.trim();
+    if title.is_empty() || title.chars().count() > MAX_REVIEW_TITLE_CHARS {
+      return Err(RepoError::Invalid(format!(
+        "title must have between 1 and {MAX_REVIEW_TITLE_CHARS} characters"
+      )));
+    }
+    let body = input.body.trim();
+    if body.chars().count() > MAX_REVIEW_BODY_CHARS {
+      return Err(RepoError::Invalid(format!(
+        "body must not exceed {MAX_REVIEW_BODY_CHARS} characters"
+      )));
+    }
+    let input = NewReview {
+      title: title.to_string(),
+      body: body.to_string(),
+      ..input
+    };
+    self.repo.create(product_id, input).await
+  }
+  pub async fn get(&self, id: Uuid) -> Result<Review, RepoError> {
+    self.repo.get(id).await
+  }
+  /// Approved reviews of a product, newest first.
+  pub async fn list_published(
+    &self,
+    product_id: Uuid,
+    page: u32,
+    per_page: u32,
+  ) -> Result<Page<Review>, RepoError> {
+    self
+      .list(Some(product_id), "approved", page, per_page)
+      .await
+  }
+  /// Reviews of every product in `status`, e.g. the `pending` moderation
+  /// queue.
+  pub async fn list_by_status(
+    &self,
+    status: &str,
+    page: u32,
+    per_page: u32,
+  ) -> Result<Page<Review>, RepoError> {
+    if !REVIEW_STATUSES.contains(&status) {
+      return Err(RepoError::Invalid(format!(
+        "status must be one of {}",
+        REVIEW_STATUSES.join(", ")
+      )));
+    }
+    self.list(None, status, page, per_page).await
+  }
+  pub async fn approve(&self, id: Uuid, note: Option<String>) -> Result<Review, RepoError> {
+    self.repo.moderate(id, "approved", note).await
+  }
+  /// Rejecting an approved review takes it down again.
+  pub async fn reject(&self, id: Uuid, note: Option<String>) -> Result<Review, RepoError> {
+    self.repo.moderate(id, "rejected", note).await
+  }
+  pub async fn delete(&self, id: Uuid) -> Result<(), RepoError> {
+    self.repo.delete(id).await
+  }
+
+  async fn list(
+    &self,
+    product_id: Option<Uuid>,
+    status: &str,
+    page: u32,
+    per_page: u32,
+  ) -> Result<Page<Review>, RepoError> {
+    let offset = page_offset(page, per_page)?;
+    let (items, total) = self
+      .repo
+      .list(product_id, status, i64::from(per_page), offset)
+      .await?;
+    Ok(Page {
+      items,
+      page,
+      per_page,
+      total,
+    })
+  }
+}
+
+const MAX_WISHLIST_NAME_CHARS: usize = 100;
+
+fn validate_wishlist_name(name: &str) -> Result<&str, RepoError> {
+  let name = name.trim();
+  if name.is_empty() || name.chars().count() > MAX_WISHLIST_NAME_CHARS {
+    return Err(RepoError::Invalid(format!(
+      "name must have between 1 and {MAX_WISHLIST_NAME_CHARS} characters"
+    )));
+  }
+  Ok(name)
+}
+
+#[derive(Clone)]
+pub struct WishlistService<R: WishlistRepository> {
+  repo: Arc<R>,
+}
+
+impl<R: WishlistRepository> WishlistService<R> {
+  pub fn new(repo: R) -> Self {
+    Self {
+      repo: Arc::new(repo),
+    }
+  }
+
+  pub async fn create(&self, user_id: Uuid, name: &str) -> Result<Wishlist, RepoError> {
+    let name = validate_wishlist_name(name)?;
+    self.repo.create(user_id, name).await
+  }
+  pub async fn list(&self, user_id: Uuid) -> Result<Vec<Wishlist>, RepoError> {
+    self.repo.list(user_id).await
+  }
+  pub async fn get(&self, user_id: Uuid, id: Uuid) -> Result<Wishlist, RepoError> {
+    self.repo.get(user_id, id).await
+  }
+  pub async fn rename(&self, user_id: Uuid, id: Uuid, name: &str) -> Result<Wishlist, RepoError> {
+    let name = validate_wishlist_name(name)?;
+    self.repo.rename(user_id, id, name).await
+  }
+  pub async fn delete(&self, user_id: Uuid, id: Uuid) -> Result<(), RepoError> {
+    self.repo.delete(user_id, id).await
+  }
+  pub async fn add_item(
+    &self,
+    user_id: Uuid,
+    id: Uuid,
+    product_id: Uuid,
+  ) -> Result<WishlistItem, RepoError> {
+    self.repo.add_item(user_id, id, product_id).await
+  }
+  pub async fn remove_item(
+    &self,
+    user_id: Uuid,
+    id: Uuid,
+    product_id: Uuid,
+  ) -> Result<(), RepoError> {
+    self.repo.remove_item(user_id, id, product_id).await
+  }
+  pub async fn move_to_cart(
+    &self,
+    user_id: Uuid,
+    id: Uuid,
+    product_id: Uuid,
+    quantity: i32,
+    ctx: &AuditContext,
+  ) -> Result<OrderItem, RepoError> {
+    if quantity <= 0 {
+      return Err(RepoError::Invalid("quantity must be positive".into()));
+    }
+    self
+      .repo
+      .move_to_cart(user_id, id, product_id, quantity, ctx)
+      .await
+  }
+}
+
+const MIN_WEBHOOK_SECRET_CHARS: usize = 16;
+
+/// Accepts absolute `http`/`https` URLs with a host.
+fn validate_webhook_url(url: &str) -> Result<String, RepoError> {
+  let url = url.trim();
+  let rest = url
+    .strip_prefix("https://")
+    .or_else(|| url.strip_prefix("http://"));
+  let host = rest.map(|rest| rest.split(['/', '?', '#']).next().unwrap_or(""));
+  if host.is_none_or(|host| host.is_empty()) || url.chars().any(char::is_whitespace) {
+    return Err(RepoError::Invalid(
+      "url must be an absolute http or https URL".into(),
+    ));
+  }
+  Ok(url.to_string())
+}
+
+fn validate_webhook_secret(secret: &str) -> Result<(), RepoError> {
+  if secret.chars().count() < MIN_WEBHOOK_SECRET_CHARS {
+    return Err(RepoError::Invalid(format!(
+      "secret must have at least {MIN_WEBHOOK_SECRET_CHARS} characters"
+    )));
+  }
+  Ok(())
+}
+
+/// Deduplicates the event types, keeping their order, and rejects unknown
+/// ones.
+fn validate_event_types(event_types: Vec<String>) -> Result<Vec<String>, RepoError> {
+  if event_types.is_empty() {
+    return Err(RepoError::Invalid(
+      "event_types must list at least one event type".into(),
+    ));
+  }
+  let mut unique = Vec::with_capacity(event_types.len());
+  for event_type in event_types {
+    if !events::EVENT_TYPES.contains(&event_type.as_str()) {
+      return Err(RepoError::Invalid(format!(
+        "unknown event type {event_type:?}; expected one of {}",
+        events::EVENT_TYPES.join(", ")
+      )));
+    }
+    if !unique.contains(&event_type) {
+      unique.push(event_type);
+    }
+  }
+  Ok(unique)
+}
+
+#[derive(Clone)]
+pub struct WebhookService<R: WebhookRepository> {
+  repo: Arc<R>,
+}
+
+impl<R: WebhookRepository> WebhookService<R> {
+  pub fn new(repo: R) -> Self {
+    Self {
+      repo: Arc::new(repo),
+    }
+  }
+
+  pub async fn create(
+    &self,
+    input: NewWebhookSubscription,
+  ) -> Result<WebhookSubscription, RepoError> {
+    let url = validate_webhook_url(&input.url)?;
+    validate_webhook_secret(&input.secret)?;
+    let event_types = validate_event_types(input.event_types)?;
+    self
+      .repo
+      .create(NewWebhookSubscription {
+        url,
+        secret: input.secret,
+        event_types,
+      })
+      .await
+  }
+  pub async fn list(&self) -> Result<Vec<WebhookSubscription>, RepoError> {
+    self.repo.list().await
+  }
+  pub async fn get(&self, id: Uuid) -> Result<WebhookSubscription, RepoError> {
+    self.repo.get(id).await
+  }
+  pub async fn update(
+    &self,
+    id: Uuid,
+    input: UpdateWebhookSubscription,
+  ) -> Result<WebhookSubscription, RepoError> {
+    let url = input.url.as_deref().map(validate_webhook_url).transpose()?;
+    if let Some(secret) = &input.secret {
+      validate_webhook_secret(secret)?;
+    }
+    let event_types = input.event_types.map(validate_event_types).transpose()?;
+    self
+      .repo
+      .update(
+        id,
+        UpdateWebhookSubscription {
+          url,
+          event_types,
+          ..input
+        },
+      )
+      .await
+  }
+  pub async fn delete(&self, id: Uuid) -> Result<(), RepoError> {
+    self.repo.delete(id).await
+  }
+  /// The subscription's deliveries, newest first, each with its attempt log.
+  pub async fn list_deliveries(
+    &self,
+    subscription_id: Uuid,
+    page: u32,
+    per_page: u32,
+  ) -> Result<Page<WebhookDelivery>, RepoError> {
+    let offset = page_offset(page, per_page)?;
+    let (items, total) = self
+      .repo
+      .list_deliveries(subscription_id, i64::from(per_page), offset)
+      .await?;
+    Ok(Page {
+      items,
+      page,
+      per_page,
+      total,
+    })
+  }
+  pub async fn get_delivery(
+    &self,
+    subscription_id: Uuid,
+    id: Uuid,
+  ) -> Result<WebhookDelivery, RepoError> {
+    self.repo.get_delivery(subscription_id, id).await
+  }
+  pub async fn redeliver(
+    &self,
+    subscription_id: Uuid,
+    id: Uuid,
+  ) -> Result<WebhookDelivery, RepoError> {
+    self.repo.redeliver(subscription_id, id).await
+  }
+}
+
+#[derive(Clone)]
+pub struct AuditService<R: AuditRepository> {
+  repo: Arc<R>,
+}
+
+impl<R: AuditRepository> AuditService<R> {
+  pub fn new(repo: R) -> Self {
+    Self {
+      repo: Arc::new(repo),
+    }
+  }
+
+  /// Matching entries, newest first.
+  pub async fn list(
+    &self,
+    filter: AuditFilter,
+    page: u32,
+    per_page: u32,
+  ) -> Result<Page<AuditEntry>, RepoError> {
+    let offset = page_offset(page, per_page)?;
+    if let (Some(from), Some(to)) = (filter.from, filter.to) {
+      if from >= to {
+        return Err(RepoError::Invalid("from must be before to".into()));
       }
-      Ok(())
+    }
+    let (items, total) = self.repo.list(filter, i64::from(per_page), offset).await?;
+    Ok(Page {
+      items,
+      page,
+      per_page,
+      total,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::adapters::memory::inventory_repo::MemoryInventoryRepository;
+  use crate::adapters::memory::orders_repo::MemoryOrderRepository;
+  use crate::adapters::memory::products_repo::MemoryProductRepository;
+  use crate::adapters::memory::unit_of_work::MemoryUnitOfWorkFactory;
+  use crate::adapters::memory::users_repo::MemoryUserRepository;
+  use crate::adapters::memory::MemoryStore;
+  use crate::application::ports::{NewUser, UpdateUser};
+  use crate::domain::models::Actor;
+  use uuid::Uuid;
+
+  fn admin() -> AuditContext {
+    AuditContext {
+      actor: Actor::Admin,
+      request_id: None,
     }
   }
 
   #[tokio::test]
-  async fn user_service_crud_works_with_fake_repo() {
-    let svc = UserService::new(FakeUserRepo::default());
+  async fn user_service_crud_works_with_memory_repo() {
+    let svc = UserService::new(MemoryUserRepository::new(MemoryStore::new()));
 
     let created = svc
-      .create(NewUser {
-        email: "a@b.com".into(),
-        name: "Alice".into(),
-      })
+      .create(
+        NewUser {
+          email: "a@b.com".into(),
+          name: "Alice".into(),
+        },
+        &admin(),
+      )
       .await
       .unwrap();
 
@@ -178,13 +1209,151 @@ mod tests {
           email: None,
           name: Some("Alicia".into()),
         },
+        &admin(),
       )
       .await
       .unwrap();
     assert_eq!(updated.name, "Alicia");
 
-    svc.delete(created.id).await.unwrap();
+    svc.delete(created.id, &admin()).await.unwrap();
     let err = svc.get(created.id).await.unwrap_err();
     assert!(matches!(err, RepoError::NotFound));
   }
+
+  #[tokio::test]
+  async fn fuzzy_search_validates_arguments() {
+    let svc = UserService::new(MemoryUserRepository::new(MemoryStore::new()));
+    svc
+      .create(
+        NewUser {
+          email: "a@b.com".into(),
+          name: "Alice".into(),
+        },
+        &admin(),
+      )
+      .await
+      .unwrap();
+
+    let hits = svc.fuzzy_search("  Alice ", 0.5, 10).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    for (text, threshold, limit) in [
+      (" ", 0.5, 10),
+      ("Alice", 1.5, 10),
+      ("Alice", -0.1, 10),
+      ("Alice", 0.5, 0),
+      ("Alice", 0.5, MAX_PER_PAGE + 1),
+    ] {
+      let err = svc.fuzzy_search(text, threshold, limit).await.unwrap_err();
+      assert!(
+        matches!(err, RepoError::Invalid(_)),
+        "{text:?} {threshold} {limit}"
+      );
+    }
+  }
+
+  #[test]
+  fn webhook_settings_are_validated() {
+    for url in ["https://example.com/hooks", "http://127.0.0.1:8080"] {
+      assert_eq!(validate_webhook_url(url).unwrap(), url);
+    }
+    for url in [
+      "ftp://example.com",
+      "https:///path",
+      "example.com",
+      "https://a b.com",
+    ] {
+      assert!(validate_webhook_url(url).is_err(), "{url}");
+    }
+    assert!(validate_webhook_secret("too-short").is_err());
+    assert_eq!(
+      validate_event_types(vec!["OrderCreated".into(), "OrderCreated".into()]).unwrap(),
+      ["OrderCreated"]
+    );
+    assert!(validate_event_types(Vec::new()).is_err());
+    assert!(validate_event_types(vec!["OrderShipped".into()]).is_err());
+  }
+
+  #[tokio::test]
+  async fn checkout_reserves_stock_or_changes_nothing() {
+    let store = MemoryStore::new();
+    let user = MemoryUserRepository::new(store.clone())
+      .create(
+        NewUser {
+          email: "ana@example.com".into(),
+          name: "Ana".into(),
+        },
+        &admin(),
+      )
+      .await
+      .unwrap();
+    let products = MemoryProductRepository::new(store.clone());
+    let product = |sku: &str, price_cents| NewProduct {
+      sku: sku.into(),
+      name: sku.into(),
+      description: String::new(),
+      price_cents,
+    };
+    let shirt = products
+      .create(product("shirt", 1500), &admin())
+      .await
+      .unwrap()
+      .id;
+    let jacket = products
+      .create(product("jacket", 4000), &admin())
+      .await
+      .unwrap()
+      .id;
+    let inventory = MemoryInventoryRepository::new(store.clone());
+    inventory.set(shirt, 3).await.unwrap();
+    inventory.set(jacket, 1).await.unwrap();
+    let orders = MemoryOrderRepository::new(store.clone());
+    let svc = CheckoutService::new(MemoryUnitOfWorkFactory::new(store));
+    let line = |product_id, quantity| NewOrderItem {
+      product_id,
+      quantity,
+    };
+
+    let order = svc
+      .checkout(user.id, vec![line(shirt, 2), line(jacket, 1)], &admin())
+      .await
+      .unwrap();
+    assert_eq!(order.status, "pending_payment");
+    assert_eq!(order.total_cents, 7000);
+    assert_eq!(orders.list().await.unwrap().len(), 1);
+    assert_eq!(orders.list_items(order.id).await.unwrap().len(), 2);
+    assert_eq!(inventory.get(shirt).await.unwrap().quantity, 1);
+    assert_eq!(inventory.get(jacket).await.unwrap().quantity, 0);
+
+    // The shirt is reserved before the jacket turns out to be sold out.
+    let err = svc
+      .checkout(user.id, vec![line(shirt, 1), line(jacket, 1)], &admin())
+      .await
+      .unwrap_err();
+    assert!(matches!(err, RepoError::Conflict));
+    let err = svc
+      .checkout(user.id, vec![line(Uuid::new_v4(), 1)], &admin())
+      .await
+      .unwrap_err();
+    assert!(matches!(err, RepoError::Conflict));
+    assert_eq!(orders.list().await.unwrap().len(), 1);
+    assert_eq!(inventory.get(shirt).await.unwrap().quantity, 1);
+  }
+
+  #[tokio::test]
+  async fn checkout_validates_items() {
+    let svc = CheckoutService::new(MemoryUnitOfWorkFactory::new(MemoryStore::new()));
+    for items in [
+      Vec::new(),
+      vec![NewOrderItem {
+        product_id: Uuid::new_v4(),
+        quantity: 0,
+      }],
+    ] {
+      let err = svc
+        .checkout(Uuid::new_v4(), items, &admin())
+        .await
+        .unwrap_err();
+      assert!(matches!(err, RepoError::Invalid(_)));
+    }
+  }
 }
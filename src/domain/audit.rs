@@ -0,0 +1,109 @@
+//! Field-level diffs of any record for the audit log.
+
+use crate::domain::models::FieldChange;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Left out of diffs: the id is the entry's resource and the timestamps
+/// follow from the entries.
+const UNTRACKED: [&str; 3] = ["id", "created_at", "updated_at"];
+
+fn fields<T: Serialize>(record: Option<&T>) -> Map<String, Value> {
+  match record.map(serde_json::to_value) {
+    Some(Ok(Value::Object(fields))) => fields,
+    _ => Map::new(),
+  }
+}
+
+/// The serialized fields that differ between `before` and `after`, where
+/// `None` is the record not existing (yet, or any more).
+pub fn diff<T: Serialize>(before: Option<&T>, after: Option<&T>) -> BTreeMap<String, FieldChange> {
+  let before = fields(before);
+  let after = fields(after);
+  let names: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+  names
+    .into_iter()
+    .filter(|name| !UNTRACKED.contains(&name.as_str()))
+    .filter_map(|name| {
+      let change = FieldChange {
+        before: before.get(name).cloned().unwrap_or(Value::Null),
+        after: after.get(name).cloned().unwrap_or(Value::Null),
+      };
+      (change.before != change.after).then(|| (name.clone(), change))
+    })
+    .collect()
+}
+
+/// The change to the price starting at `valid_from`, as the one
+/// `scheduled_price` field of a product entry; `None` is no price starting
+/// then.
+pub fn scheduled_price(
+  valid_from: DateTime<Utc>,
+  before: Option<i64>,
+  after: Option<i64>,
+) -> BTreeMap<String, FieldChange> {
+  let price = |price_cents: Option<i64>| match price_cents {
+    Some(price_cents) => json!({ "price_cents": price_cents, "valid_from": valid_from }),
+    None => Value::Null,
+  };
+  let change = FieldChange {
+    before: price(before),
+    after: price(after),
+  };
+  (change.before != change.after)
+    .then(|| ("scheduled_price".to_string(), change))
+    .into_iter()
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::domain::models::User;
+  use chrono::{TimeZone, Utc};
+  use uuid::Uuid;
+
+  fn user(email: &str, name: &str, secs: i64) -> User {
+    let at = Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap();
+    User {
+      id: Uuid::from_u128(1),
+      email: email.into(),
+      name: name.into(),
+      created_at: at,
+      updated_at: at,
+    }
+  }
+
+  #[test]
+  fn diffs_hold_changed_fields_but_no_ids_or_timestamps() {
+    let before = user("ana@example.com", "Ana", 0);
+    let after = user("ana@example.com", "Ana María", 60);
+    let changes = diff(Some(&before), Some(&after));
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes["name"].before, "Ana");
+    assert_eq!(changes["name"].after, "Ana María");
+    assert!(diff(Some(&before), Some(&user("ana@example.com", "Ana", 60))).is_empty());
+
+    let created = diff(None, Some(&before));
+    assert_eq!(created.keys().collect::<Vec<_>>(), ["email", "name"]);
+    assert_eq!(created["email"].before, Value::Null);
+    let deleted = diff(Some(&before), None);
+    assert_eq!(deleted["email"].after, Value::Null);
+  }
+
+  #[test]
+  fn scheduled_prices_are_one_field_with_their_start() {
+    let at = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+    let changes = scheduled_price(at, None, Some(800));
+    assert_eq!(changes["scheduled_price"].before, Value::Null);
+    assert_eq!(
+      changes["scheduled_price"].after,
+      json!({ "price_cents": 800, "valid_from": "2023-11-14T22:13:20Z" })
+    );
+    let cancelled = scheduled_price(at, Some(800), None);
+    assert_eq!(cancelled["scheduled_price"].after, Value::Null);
+    assert!(scheduled_price(at, Some(800), Some(800)).is_empty());
+  }
+}
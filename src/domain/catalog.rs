@@ -0,0 +1,204 @@
+//! Pure catalog rules: category slugs, the category tree, variant keys,
+//! search queries and price history.
+
+use crate::domain::models::{Category, CategoryNode, ProductPrice};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Slugs are lowercase ASCII words joined by single hyphens, e.g. `t-shirts`.
+pub fn is_valid_slug(slug: &str) -> bool {
+  !slug.is_empty()
+    && slug.split('-').all(|part| {
+      !part.is_empty()
+        && part
+          .bytes()
+          .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+    })
+}
+
+/// Nests a flat category list under its parents. Siblings are ordered by
+/// `position`, then name. Categories whose parent is missing from the list
+/// are treated as roots.
+pub fn build_tree(categories: Vec<Category>) -> Vec<CategoryNode> {
+  let ids: Vec<Uuid> = categories.iter().map(|c| c.id).collect();
+  let mut children: HashMap<Option<Uuid>, Vec<Category>> = HashMap::new();
+  for category in categories {
+    let parent = category.parent_id.filter(|p| ids.contains(p));
+    children.entry(parent).or_default().push(category);
+  }
+  attach(None, &mut children)
+}
+
+fn attach(
+  parent: Option<Uuid>,
+  children: &mut HashMap<Option<Uuid>, Vec<Category>>,
+) -> Vec<CategoryNode> {
+  let mut level = children.remove(&parent).unwrap_or_default();
+  level.sort_by(|a, b| {
+    a.position
+      .cmp(&b.position)
+      .then_with(|| a.name.cmp(&b.name))
+  });
+  level
+    .into_iter()
+    .map(|category| {
+      let nested = attach(Some(category.id), children);
+      CategoryNode {
+        category,
+        children: nested,
+      }
+    })
+    .collect()
+}
+
+/// Canonical key for a variant's `(option id, value id)` choices. Two variants
+/// of a product with the same key would be the same combination.
+pub fn variant_key(choices: &[(Uuid, Uuid)]) -> String {
+  let mut sorted = choices.to_vec();
+  sorted.sort();
+  sorted
+    .iter()
+    .map(|(option, value)| format!("{option}={value}"))
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+/// Turns free text into a `to_tsquery` expression that matches every word as
+/// a prefix, e.g. `blue jack` becomes `blue:* & jack:*`. Anything but letters
+/// and digits separates words, so user input cannot inject query operators.
+/// Returns `None` when no words remain.
+pub fn prefix_tsquery(text: &str) -> Option<String> {
+  let terms: Vec<String> = text
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|word| !word.is_empty())
+    .map(|word| format!("{}:*", word.to_lowercase()))
+    .collect();
+  (!terms.is_empty()).then(|| terms.join(" & "))
+}
+
+/// The entry of a price history in effect at `at`, if any.
+pub fn price_at(history: &[ProductPrice], at: DateTime<Utc>) -> Option<&ProductPrice> {
+  history
+    .iter()
+    .find(|price| price.valid_from <= at && price.valid_to.is_none_or(|to| at < to))
+}
+
+/// How much cheaper a product is now than when it was saved, if it is.
+pub fn price_drop(saved_cents: i64, current_cents: i64) -> Option<i64> {
+  (current_cents < saved_cents).then(|| saved_cents - current_cents)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::{Duration, TimeZone};
+
+  fn category(id: u128, parent: Option<u128>, name: &str, position: i32) -> Category {
+    Category {
+      id: Uuid::from_u128(id),
+      parent_id: parent.map(Uuid::from_u128),
+      slug: name.to_lowercase(),
+      name: name.to_string(),
+      position,
+      created_at: Utc::now(),
+      updated_at: Utc::now(),
+    }
+  }
+
+  #[test]
+  fn validates_slugs() {
+    assert!(is_valid_slug("t-shirts"));
+    assert!(is_valid_slug("size-42"));
+    for bad in [
+      "",
+      "T-Shirts",
+      "-shirts",
+      "shirts-",
+      "t--shirts",
+      "t shirts",
+      "camisetas-ñ",
+    ] {
+      assert!(!is_valid_slug(bad), "{bad} should be rejected");
+    }
+  }
+
+  #[test]
+  fn nests_and_orders_siblings() {
+    let tree = build_tree(vec![
+      category(3, Some(1), "Shirts", 1),
+      category(2, None, "Shoes", 0),
+      category(1, None, "Clothing", 0),
+      category(4, Some(1), "Jackets", 0),
+      category(5, Some(4), "Rain", 0),
+    ]);
+    let names = |nodes: &[CategoryNode]| {
+      nodes
+        .iter()
+        .map(|n| n.category.name.clone())
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(names(&tree), ["Clothing", "Shoes"]);
+    assert_eq!(names(&tree[0].children), ["Jackets", "Shirts"]);
+    assert_eq!(names(&tree[0].children[0].children), ["Rain"]);
+    assert!(tree[1].children.is_empty());
+  }
+
+  #[test]
+  fn variant_keys_ignore_choice_order() {
+    let (size, m) = (Uuid::from_u128(1), Uuid::from_u128(10));
+    let (color, red) = (Uuid::from_u128(2), Uuid::from_u128(20));
+    assert_eq!(
+      variant_key(&[(color, red), (size, m)]),
+      variant_key(&[(size, m), (color, red)])
+    );
+    assert_ne!(variant_key(&[(size, m)]), variant_key(&[(size, red)]));
+    assert_eq!(variant_key(&[]), "");
+  }
+
+  #[test]
+  fn builds_prefix_queries_without_operators() {
+    assert_eq!(
+      prefix_tsquery("Blue jack").as_deref(),
+      Some("blue:* & jack:*")
+    );
+    assert_eq!(
+      prefix_tsquery("a&b | !c:* (d)").as_deref(),
+      Some("a:* & b:* & c:* & d:*")
+    );
+    assert_eq!(prefix_tsquery("camión").as_deref(), Some("camión:*"));
+    assert_eq!(prefix_tsquery(" &!| "), None);
+  }
+
+  #[test]
+  fn resolves_price_at_a_point_in_time() {
+    let day = |d: u32| Utc.with_ymd_and_hms(2024, 3, d, 0, 0, 0).unwrap();
+    let price = |cents: i64, from: DateTime<Utc>, to: Option<DateTime<Utc>>| ProductPrice {
+      id: Uuid::new_v4(),
+      product_id: Uuid::nil(),
+      price_cents: cents,
+      valid_from: from,
+      valid_to: to,
+      created_at: from,
+    };
+    let history = [
+      price(1000, day(1), Some(day(10))),
+      price(800, day(10), Some(day(20))),
+      price(1200, day(20), None),
+    ];
+    let cents = |at| price_at(&history, at).map(|p| p.price_cents);
+
+    assert_eq!(cents(day(1) - Duration::seconds(1)), None);
+    assert_eq!(cents(day(1)), Some(1000));
+    assert_eq!(cents(day(10) - Duration::seconds(1)), Some(1000));
+    assert_eq!(cents(day(10)), Some(800));
+    assert_eq!(cents(day(25)), Some(1200));
+  }
+
+  #[test]
+  fn detects_price_drops_only() {
+    assert_eq!(price_drop(1000, 750), Some(250));
+    assert_eq!(price_drop(1000, 1000), None);
+    assert_eq!(price_drop(1000, 1200), None);
+  }
+}
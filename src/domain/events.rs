@@ -0,0 +1,135 @@
+//! Domain events recorded in the outbox in the same transaction as the change
+//! they describe.
+
+use crate::domain::models::{Order, Product, User};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Every value of `DomainEvent::event_type`.
+pub const EVENT_TYPES: [&str; 10] = [
+  "UserCreated",
+  "UserUpdated",
+  "UserDeleted",
+  "ProductCreated",
+  "ProductUpdated",
+  "ProductDeleted",
+  "OrderCreated",
+  "OrderUpdated",
+  "OrderStatusChanged",
+  "OrderDeleted",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum DomainEvent {
+  UserCreated {
+    user: User,
+  },
+  UserUpdated {
+    user: User,
+  },
+  UserDeleted {
+    user_id: Uuid,
+  },
+  ProductCreated {
+    product: Product,
+  },
+  ProductUpdated {
+    product: Product,
+  },
+  ProductDeleted {
+    product_id: Uuid,
+  },
+  OrderCreated {
+    order: Order,
+  },
+  /// Any change to an order other than its status, such as a new line.
+  OrderUpdated {
+    order: Order,
+  },
+  /// `order` carries the new status.
+  OrderStatusChanged {
+    order: Order,
+    from: String,
+  },
+  OrderDeleted {
+    order_id: Uuid,
+    user_id: Uuid,
+  },
+}
+
+impl DomainEvent {
+  /// Name stored as the event type, e.g. `OrderStatusChanged`.
+  pub fn event_type(&self) -> &'static str {
+    match self {
+      DomainEvent::UserCreated { .. } => "UserCreated",
+      DomainEvent::UserUpdated { .. } => "UserUpdated",
+      DomainEvent::UserDeleted { .. } => "UserDeleted",
+      DomainEvent::ProductCreated { .. } => "ProductCreated",
+      DomainEvent::ProductUpdated { .. } => "ProductUpdated",
+      DomainEvent::ProductDeleted { .. } => "ProductDeleted",
+      DomainEvent::OrderCreated { .. } => "OrderCreated",
+      DomainEvent::OrderUpdated { .. } => "OrderUpdated",
+      DomainEvent::OrderStatusChanged { .. } => "OrderStatusChanged",
+      DomainEvent::OrderDeleted { .. } => "OrderDeleted",
+    }
+  }
+
+  /// Kind and id of the entity the event belongs to.
+  pub fn aggregate(&self) -> (&'static str, Uuid) {
+    match self {
+      DomainEvent::UserCreated { user } | DomainEvent::UserUpdated { user } => ("user", user.id),
+      DomainEvent::UserDeleted { user_id } => ("user", *user_id),
+      DomainEvent::ProductCreated { product } | DomainEvent::ProductUpdated { product } => {
+        ("product", product.id)
+      }
+      DomainEvent::ProductDeleted { product_id } => ("product", *product_id),
+      DomainEvent::OrderCreated { order }
+      | DomainEvent::OrderUpdated { order }
+      | DomainEvent::OrderStatusChanged { order, .. } => ("order", order.id),
+      DomainEvent::OrderDeleted { order_id, .. } => ("order", *order_id),
+    }
+  }
+
+  /// The event's fields as a JSON object, without the type name.
+  pub fn payload(&self) -> serde_json::Value {
+    serde_json::to_value(self).expect("domain events serialize to JSON")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::Utc;
+  use serde_json::json;
+
+  #[test]
+  fn describes_status_changes() {
+    let order = Order {
+      id: Uuid::new_v4(),
+      user_id: Uuid::new_v4(),
+      status: "paid".into(),
+      total_cents: 1200,
+      created_at: Utc::now(),
+      updated_at: Utc::now(),
+    };
+    let event = DomainEvent::OrderStatusChanged {
+      order: order.clone(),
+      from: "pending_payment".into(),
+    };
+    assert_eq!(event.event_type(), "OrderStatusChanged");
+    assert!(EVENT_TYPES.contains(&event.event_type()));
+    assert_eq!(event.aggregate(), ("order", order.id));
+    let payload = event.payload();
+    assert_eq!(payload["from"], "pending_payment");
+    assert_eq!(payload["order"]["status"], "paid");
+  }
+
+  #[test]
+  fn deletions_carry_only_ids() {
+    let user_id = Uuid::new_v4();
+    let event = DomainEvent::UserDeleted { user_id };
+    assert_eq!(event.aggregate(), ("user", user_id));
+    assert_eq!(event.payload(), json!({ "user_id": user_id }));
+  }
+}
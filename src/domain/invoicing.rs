@@ -0,0 +1,46 @@
+//! Pure invoicing rules shared by the adapters that issue documents.
+
+/// Splits a tax-inclusive amount into `(net, tax)` for a rate expressed in
+/// basis points (2100 = 21%). The tax is rounded half-up to the cent.
+pub fn split_gross(gross_cents: i64, tax_rate_bps: i32) -> (i64, i64) {
+  let bps = i64::from(tax_rate_bps);
+  let divisor = 10_000 + bps;
+  let tax = (gross_cents * bps * 2 + divisor) / (divisor * 2);
+  (gross_cents - tax, tax)
+}
+
+/// Formats a document number such as `INV-2026-000042`.
+pub fn document_number(prefix: &str, year: i32, sequence: i32) -> String {
+  format!("{prefix}-{year}-{sequence:06}")
+}
+
+/// Formats cents as a plain decimal amount, e.g. `-12.05`.
+pub fn format_cents(cents: i64) -> String {
+  let sign = if cents < 0 { "-" } else { "" };
+  format!("{sign}{}.{:02}", cents.abs() / 100, cents.abs() % 100)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn splits_tax_inclusive_amounts() {
+    assert_eq!(split_gross(12_100, 2100), (10_000, 2_100));
+    assert_eq!(split_gross(999, 0), (999, 0));
+    // 1000 / 1.21 = 826.45 -> tax 173.55 rounds to 174
+    assert_eq!(split_gross(1_000, 2100), (826, 174));
+  }
+
+  #[test]
+  fn pads_document_numbers() {
+    assert_eq!(document_number("INV", 2026, 42), "INV-2026-000042");
+  }
+
+  #[test]
+  fn formats_negative_cents() {
+    assert_eq!(format_cents(7_000), "70.00");
+    assert_eq!(format_cents(-1_205), "-12.05");
+    assert_eq!(format_cents(-5), "-0.05");
+  }
+}
@@ -0,0 +1,109 @@
+//! Pure rules for product media: accepted image formats and blob keys.
+
+use uuid::Uuid;
+
+/// Image formats accepted for upload, by MIME type.
+pub const IMAGE_CONTENT_TYPES: [&str; 4] = ["image/jpeg", "image/png", "image/gif", "image/webp"];
+
+/// Detects the image format from the file signature rather than trusting the
+/// declared content type.
+pub fn sniff_image_type(bytes: &[u8]) -> Option<&'static str> {
+  if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+    Some("image/jpeg")
+  } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+    Some("image/png")
+  } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+    Some("image/gif")
+  } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+    Some("image/webp")
+  } else {
+    None
+  }
+}
+
+/// The MIME type of a `Content-Type` header value, lowercased and without
+/// parameters, e.g. `Image/PNG; q=1` becomes `image/png`.
+pub fn essence(content_type: &str) -> String {
+  content_type
+    .split(';')
+    .next()
+    .unwrap_or_default()
+    .trim()
+    .to_ascii_lowercase()
+}
+
+/// Where the original upload of an image is stored.
+pub fn image_blob_key(product_id: Uuid, image_id: Uuid) -> String {
+  format!("products/{product_id}/{image_id}")
+}
+
+/// Where a resized variant of an image is stored, next to the original.
+pub fn variant_blob_key(product_id: Uuid, image_id: Uuid, name: &str) -> String {
+  format!("{}-{name}", image_blob_key(product_id, image_id))
+}
+
+/// The variant name for a bounding box and file extension, e.g. `480.webp`.
+pub fn variant_name(size: u32, extension: &str) -> String {
+  format!("{size}.{extension}")
+}
+
+/// Dimensions that fit `width`×`height` inside a `size`×`size` box while
+/// keeping the aspect ratio. Images already inside the box are not enlarged.
+pub fn fit_within(width: u32, height: u32, size: u32) -> (u32, u32) {
+  let longest = width.max(height);
+  if longest <= size {
+    return (width, height);
+  }
+  let scale =
+    |side: u32| ((side as u64 * size as u64 + longest as u64 / 2) / longest as u64).max(1);
+  (scale(width) as u32, scale(height) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn sniffs_supported_formats() {
+    assert_eq!(
+      sniff_image_type(b"\xFF\xD8\xFF\xE0rest"),
+      Some("image/jpeg")
+    );
+    assert_eq!(
+      sniff_image_type(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+      Some("image/png")
+    );
+    assert_eq!(sniff_image_type(b"GIF89a...."), Some("image/gif"));
+    assert_eq!(
+      sniff_image_type(b"RIFF\x24\0\0\0WEBPVP8 "),
+      Some("image/webp")
+    );
+    assert_eq!(sniff_image_type(b"RIFF\x24\0\0\0WAVEfmt "), None);
+    assert_eq!(sniff_image_type(b"<svg xmlns="), None);
+    assert_eq!(sniff_image_type(b""), None);
+  }
+
+  #[test]
+  fn normalizes_content_types() {
+    assert_eq!(essence("Image/PNG; charset=binary"), "image/png");
+    assert_eq!(essence(" image/jpeg "), "image/jpeg");
+  }
+
+  #[test]
+  fn fits_images_in_a_box_without_enlarging() {
+    assert_eq!(fit_within(1600, 1200, 480), (480, 360));
+    assert_eq!(fit_within(1200, 1600, 480), (360, 480));
+    assert_eq!(fit_within(300, 200, 100), (100, 67));
+    assert_eq!(fit_within(2000, 1, 100), (100, 1));
+    assert_eq!(fit_within(300, 200, 480), (300, 200));
+  }
+
+  #[test]
+  fn variant_keys_sit_next_to_the_original() {
+    let (product, image) = (Uuid::nil(), Uuid::max());
+    assert_eq!(
+      variant_blob_key(product, image, &variant_name(160, "webp")),
+      format!("{}-160.webp", image_blob_key(product, image))
+    );
+  }
+}
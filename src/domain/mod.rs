@@ -1 +1,7 @@
+pub mod audit;
+pub mod catalog;
+pub mod events;
+pub mod invoicing;
+pub mod media;
 pub mod models;
+pub mod order_history;
@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -11,16 +12,164 @@ pub struct User {
   pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Product {
   pub id: Uuid,
   pub sku: String,
   pub name: String,
+  pub description: String,
   pub price_cents: i64,
+  /// Mean of the approved review ratings, rounded to two decimals; `None`
+  /// until the first review is approved.
+  pub rating_average: Option<f64>,
+  pub rating_count: i32,
   pub created_at: DateTime<Utc>,
   pub updated_at: DateTime<Utc>,
 }
 
+/// A product price valid from `valid_from` until `valid_to` (exclusive), or
+/// indefinitely when `valid_to` is `None`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProductPrice {
+  pub id: Uuid,
+  pub product_id: Uuid,
+  pub price_cents: i64,
+  pub valid_from: DateTime<Utc>,
+  pub valid_to: Option<DateTime<Utc>>,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProductSearchHit {
+  #[serde(flatten)]
+  pub product: Product,
+  pub rank: f32,
+  /// HTML-escaped excerpt with matches wrapped in `<mark>`.
+  pub snippet: String,
+}
+
+/// An uploaded product image; images are shown in `position` order.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProductImage {
+  pub id: Uuid,
+  pub product_id: Uuid,
+  /// Storage location of the bytes; never exposed to clients.
+  #[serde(skip)]
+  pub blob_key: String,
+  pub content_type: String,
+  pub byte_size: i64,
+  pub position: i32,
+  /// Progress of the background thumbnail job: `pending`, `processing`,
+  /// `ready` or `failed`.
+  pub thumbnail_status: String,
+  pub variants: Vec<ImageVariant>,
+  pub created_at: DateTime<Utc>,
+}
+
+/// A resized rendition of a product image, e.g. `480.webp`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImageVariant {
+  pub name: String,
+  #[serde(skip)]
+  pub blob_key: String,
+  pub content_type: String,
+  pub width: i32,
+  pub height: i32,
+  pub byte_size: i64,
+}
+
+/// A fuzzy search result with its trigram similarity, from 0 to 1.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SimilarityHit<T> {
+  #[serde(flatten)]
+  pub item: T,
+  pub similarity: f32,
+}
+
+/// One page of a paginated listing; `page` is 1-based.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Page<T> {
+  pub items: Vec<T>,
+  pub page: u32,
+  pub per_page: u32,
+  pub total: i64,
+}
+
+/// An option such as size or color, with its allowed values in display order.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProductOption {
+  pub id: Uuid,
+  pub product_id: Uuid,
+  pub name: String,
+  pub position: i32,
+  pub values: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProductVariant {
+  pub id: Uuid,
+  pub product_id: Uuid,
+  pub sku: String,
+  /// What the variant sells for: the override, or the product price.
+  pub price_cents: i64,
+  pub price_override_cents: Option<i64>,
+  pub stock: i64,
+  /// Option name to chosen value, one entry per product option.
+  pub options: BTreeMap<String, String>,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+/// A product with its options and variants, as served by `GET /products/:id`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProductDetails {
+  #[serde(flatten)]
+  pub product: Product,
+  pub options: Vec<ProductOption>,
+  pub variants: Vec<ProductVariant>,
+}
+
+/// A customer's review of a product. Reviews start `pending`; only
+/// `approved` ones are public and count towards the product rating.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Review {
+  pub id: Uuid,
+  pub product_id: Uuid,
+  pub user_id: Uuid,
+  /// From 1 to 5 stars.
+  pub rating: i16,
+  pub title: String,
+  pub body: String,
+  /// `pending`, `approved` or `rejected`.
+  pub status: String,
+  pub moderation_note: Option<String>,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+/// A named list of products a user saved for later.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Wishlist {
+  pub id: Uuid,
+  pub user_id: Uuid,
+  pub name: String,
+  pub items: Vec<WishlistItem>,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WishlistItem {
+  pub product_id: Uuid,
+  pub name: String,
+  /// The product price when the item was added.
+  pub added_price_cents: i64,
+  pub price_cents: i64,
+  /// How much cheaper the product is now than when it was added.
+  pub price_drop_cents: Option<i64>,
+  pub added_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Order {
   pub id: Uuid,
@@ -30,3 +179,260 @@ pub struct Order {
   pub created_at: DateTime<Utc>,
   pub updated_at: DateTime<Utc>,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Payment {
+  pub id: Uuid,
+  pub order_id: Uuid,
+  pub provider: String,
+  pub provider_payment_id: String,
+  pub status: String,
+  pub amount_cents: i64,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OrderItem {
+  pub id: Uuid,
+  pub order_id: Uuid,
+  pub product_id: Uuid,
+  pub quantity: i32,
+  /// Product price captured when the item was added to the order.
+  pub unit_price_cents: i64,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StockLevel {
+  pub product_id: Uuid,
+  pub quantity: i64,
+  pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReturnRequest {
+  pub id: Uuid,
+  pub order_id: Uuid,
+  /// `requested`, `approved`, `rejected` or `refunded`.
+  pub status: String,
+  pub note: Option<String>,
+  pub decision_note: Option<String>,
+  pub restocked: bool,
+  pub items: Vec<ReturnItem>,
+  pub refunded_cents: i64,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReturnItem {
+  pub order_item_id: Uuid,
+  pub product_id: Uuid,
+  pub quantity: i32,
+  pub unit_price_cents: i64,
+  pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Refund {
+  pub id: Uuid,
+  pub payment_id: Uuid,
+  pub return_id: Option<Uuid>,
+  pub amount_cents: i64,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BillingAddress {
+  pub name: String,
+  pub line1: String,
+  pub line2: Option<String>,
+  pub city: String,
+  pub postal_code: String,
+  pub country: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InvoiceLine {
+  pub description: String,
+  pub product_id: Option<Uuid>,
+  pub quantity: i32,
+  pub unit_price_cents: i64,
+  pub net_cents: i64,
+  pub tax_cents: i64,
+  pub total_cents: i64,
+}
+
+/// Immutable snapshot of an issued invoice or credit note. Amounts are
+/// tax-inclusive, matching what the customer was charged.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Invoice {
+  pub id: Uuid,
+  pub order_id: Uuid,
+  /// `invoice` or `credit_note`.
+  pub kind: String,
+  pub number: String,
+  pub issued_at: DateTime<Utc>,
+  pub billing_address: BillingAddress,
+  pub lines: Vec<InvoiceLine>,
+  pub tax_rate_bps: i32,
+  pub net_cents: i64,
+  pub tax_cents: i64,
+  pub total_cents: i64,
+  /// For credit notes, the invoice being credited.
+  pub credited_invoice_id: Option<Uuid>,
+  pub refund_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Category {
+  pub id: Uuid,
+  pub parent_id: Option<Uuid>,
+  pub slug: String,
+  pub name: String,
+  /// Sort key among siblings; ties are broken by name.
+  pub position: i32,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+/// A category with its subcategories, as served by `GET /categories`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CategoryNode {
+  #[serde(flatten)]
+  pub category: Category,
+  pub children: Vec<CategoryNode>,
+}
+
+/// A domain event as stored in the outbox, ready to be published.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OutboxEvent {
+  /// Increases with insertion order.
+  pub id: i64,
+  /// Stable across redeliveries, for consumers that deduplicate.
+  pub event_id: Uuid,
+  pub event_type: String,
+  pub aggregate_type: String,
+  pub aggregate_id: Uuid,
+  pub payload: serde_json::Value,
+  /// Delivery attempts so far, including the current one.
+  pub attempts: i32,
+  pub created_at: DateTime<Utc>,
+}
+
+/// A partner endpoint notified of the listed event types.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WebhookSubscription {
+  pub id: Uuid,
+  pub url: String,
+  /// Key of the HMAC signature on every delivery; never exposed to clients.
+  #[serde(skip)]
+  pub secret: String,
+  pub event_types: Vec<String>,
+  /// `false` once disabled by hand or after too many failed attempts in a
+  /// row.
+  pub active: bool,
+  pub consecutive_failures: i32,
+  pub disabled_at: Option<DateTime<Utc>>,
+  pub created_at: DateTime<Utc>,
+  pub updated_at: DateTime<Utc>,
+}
+
+/// One event sent, or to be sent, to one subscription.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WebhookDelivery {
+  pub id: Uuid,
+  pub subscription_id: Uuid,
+  pub event_id: Uuid,
+  pub event_type: String,
+  pub payload: serde_json::Value,
+  pub occurred_at: DateTime<Utc>,
+  /// `pending`, `succeeded` or `failed` (no attempts left).
+  pub status: String,
+  pub attempts: i32,
+  pub next_attempt_at: DateTime<Utc>,
+  /// HTTP status of the latest attempt, if the receiver answered.
+  pub response_status: Option<i32>,
+  pub last_error: Option<String>,
+  pub delivered_at: Option<DateTime<Utc>>,
+  /// Every attempt, oldest first.
+  pub log: Vec<WebhookAttempt>,
+  pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WebhookAttempt {
+  pub attempted_at: DateTime<Utc>,
+  pub response_status: Option<i32>,
+  pub error: Option<String>,
+  pub duration_ms: i32,
+}
+
+/// Who makes a change, as recorded in histories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Actor {
+  Admin,
+  User(Uuid),
+  /// An API caller without credentials.
+  Anonymous,
+  /// A background process or integration, e.g. `payments:acme`.
+  System(String),
+}
+
+impl std::fmt::Display for Actor {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Actor::Admin => f.write_str("admin"),
+      Actor::User(id) => write!(f, "user:{id}"),
+      Actor::Anonymous => f.write_str("anonymous"),
+      Actor::System(name) => write!(f, "system:{name}"),
+    }
+  }
+}
+
+/// A field's value before and after a change; `null` when the record did not
+/// exist on that side.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FieldChange {
+  pub before: serde_json::Value,
+  pub after: serde_json::Value,
+}
+
+/// One append-only entry of an order's history.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OrderHistoryEntry {
+  pub order_id: Uuid,
+  /// 1 for the creation, then one more per change.
+  pub version: i32,
+  /// `created`, `updated` or `deleted`.
+  pub action: String,
+  pub actor: String,
+  pub occurred_at: DateTime<Utc>,
+  /// Changed fields only.
+  pub changes: BTreeMap<String, FieldChange>,
+}
+
+/// Who makes a change and the request it came in, for the audit log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditContext {
+  pub actor: Actor,
+  /// The request's `x-request-id`.
+  pub request_id: Option<String>,
+}
+
+/// One append-only entry of the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditEntry {
+  pub id: i64,
+  pub occurred_at: DateTime<Utc>,
+  pub actor: String,
+  /// `created`, `updated` or `deleted`.
+  pub action: String,
+  /// `user`, `product` or `order`.
+  pub resource_type: String,
+  pub resource_id: Uuid,
+  /// Changed fields only.
+  pub changes: BTreeMap<String, FieldChange>,
+  pub request_id: Option<String>,
+}
@@ -0,0 +1,91 @@
+//! Customer access tokens: `<user id>.<expires unix seconds>.<hex digest>`,
+//! where the HMAC-SHA256 digest covers `"<user id>\n<expires>"`. The backend
+//! that signs customers in asks for one (admin) and hands it to the app.
+
+use crate::infrastructure::signature::SignatureError;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn mac(secret: &str, user_id: Uuid, expires: i64) -> HmacSha256 {
+  let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+  mac.update(user_id.to_string().as_bytes());
+  mac.update(b"\n");
+  mac.update(expires.to_string().as_bytes());
+  mac
+}
+
+/// Issues a token that identifies `user_id` until `expires`.
+pub fn issue(secret: &str, user_id: Uuid, expires: i64) -> String {
+  let digest = mac(secret, user_id, expires).finalize().into_bytes();
+  format!("{user_id}.{expires}.{}", hex::encode(digest))
+}
+
+/// Checks a token produced by [`issue`] and returns the user it identifies.
+pub fn verify(secret: &str, token: &str, now: i64) -> Result<Uuid, SignatureError> {
+  let mut parts = token.splitn(3, '.');
+  let (Some(user_id), Some(expires), Some(signature)) = (parts.next(), parts.next(), parts.next())
+  else {
+    return Err(SignatureError::Malformed);
+  };
+  let user_id = user_id
+    .parse::<Uuid>()
+    .map_err(|_| SignatureError::Malformed)?;
+  let expires = expires
+    .parse::<i64>()
+    .map_err(|_| SignatureError::Malformed)?;
+  let signature = hex::decode(signature).map_err(|_| SignatureError::Malformed)?;
+  mac(secret, user_id, expires)
+    .verify_slice(&signature)
+    .map_err(|_| SignatureError::Mismatch)?;
+  if now >= expires {
+    return Err(SignatureError::Expired);
+  }
+  Ok(user_id)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn tokens_identify_the_user_until_they_expire() {
+    let user_id = Uuid::new_v4();
+    let token = issue("s3cret", user_id, 1_700_000_900);
+    assert!(token.starts_with(&format!("{user_id}.1700000900.")));
+    assert_eq!(verify("s3cret", &token, 1_700_000_000), Ok(user_id));
+    assert_eq!(
+      verify("s3cret", &token, 1_700_000_900),
+      Err(SignatureError::Expired)
+    );
+    assert_eq!(
+      verify("other", &token, 1_700_000_000),
+      Err(SignatureError::Mismatch)
+    );
+  }
+
+  #[test]
+  fn tampered_tokens_are_rejected() {
+    let user_id = Uuid::new_v4();
+    let token = issue("s3cret", user_id, 1_700_000_900);
+    let signature = token.rsplit('.').next().unwrap();
+    let forged = format!("{}.1700000900.{signature}", Uuid::new_v4());
+    assert_eq!(
+      verify("s3cret", &forged, 1_700_000_000),
+      Err(SignatureError::Mismatch)
+    );
+    let extended = format!("{user_id}.1800000000.{signature}");
+    assert_eq!(
+      verify("s3cret", &extended, 1_700_000_000),
+      Err(SignatureError::Mismatch)
+    );
+    for malformed in ["", "abc", "not-a-uuid.1.00", &format!("{user_id}.soon.00")] {
+      assert_eq!(
+        verify("s3cret", malformed, 1_700_000_000),
+        Err(SignatureError::Malformed)
+      );
+    }
+  }
+}
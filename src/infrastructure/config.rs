@@ -1,10 +1,98 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use uuid::Uuid;
 
 #[derive(Debug, Clone)]
 pub struct AppConfig {
   pub host: String,
   pub port: u16,
+  /// Where users, products and orders are kept.
+  pub storage: Storage,
   pub database_url: String,
+  /// Bearer token for admin-only endpoints; those endpoints reject every
+  /// request when unset.
+  pub admin_api_token: Option<String>,
+  /// Shared webhook signing secrets keyed by payment provider name.
+  pub payment_webhook_secrets: HashMap<String, String>,
+  /// Maximum accepted age (and clock skew) of a signed webhook, in seconds.
+  pub payment_webhook_tolerance_secs: u64,
+  /// Tax rate included in product prices, in basis points (2100 = 21%).
+  pub invoice_tax_rate_bps: i32,
+  /// Postgres text search configuration used to index and query products.
+  pub product_search_language: String,
+  /// Minimum trigram similarity for `GET /products/fuzzy-search` hits.
+  pub product_fuzzy_threshold: f32,
+  /// Minimum trigram similarity for the admin `GET /users/search` hits.
+  pub user_fuzzy_threshold: f32,
+  /// Where uploaded media is stored.
+  pub media_storage: MediaStorage,
+  /// Key for signing media download URLs. Without `MEDIA_URL_SECRET` a random
+  /// key is used, so URLs stop working when the process restarts.
+  pub media_url_secret: String,
+  /// How long a signed media URL stays valid, in seconds.
+  pub media_url_ttl_secs: u64,
+  /// Largest accepted upload, in bytes.
+  pub media_max_upload_bytes: usize,
+  /// Bounding boxes, in pixels, of the thumbnails generated for each image.
+  pub thumbnail_sizes: Vec<u32>,
+  /// How often the thumbnail worker looks for new images, in seconds.
+  pub thumbnail_poll_interval_secs: u64,
+  /// How often the outbox dispatcher looks for due events, in seconds.
+  pub outbox_poll_interval_secs: u64,
+  /// Delivery attempts before an outbox event is given up on.
+  pub outbox_max_attempts: i32,
+  /// How often the webhook worker looks for due deliveries, in seconds.
+  pub webhook_poll_interval_secs: u64,
+  /// How long a partner has to answer a delivery, in seconds.
+  pub webhook_timeout_secs: u64,
+  /// Attempts before a delivery is given up on.
+  pub webhook_max_attempts: i32,
+  /// Failed attempts in a row that disable a subscription.
+  pub webhook_disable_after_failures: i32,
+  /// Key for signing customer access tokens. Without `ACCESS_TOKEN_SECRET` a
+  /// random key is used, so tokens stop working when the process restarts.
+  pub access_token_secret: String,
+  /// How long a customer access token stays valid, in seconds.
+  pub access_token_ttl_secs: u64,
+  /// Order events each instance keeps for `Last-Event-ID` resumption.
+  pub order_stream_replay_size: usize,
+  /// How often order tracking WebSockets are pinged, in seconds; a client
+  /// silent for two intervals is dropped.
+  pub ws_heartbeat_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Storage {
+  /// Postgres at `DATABASE_URL`.
+  Postgres,
+  /// In-memory users, products, orders and stock, lost on restart; for
+  /// demos without Postgres. Checkout works on them too. Every other endpoint still needs the database.
+  Memory,
+  /// Users, products and orders in the SQLite database at `path`, created
+  /// if missing; for deployments without Postgres. `/checkout` answers
+  /// `503`, since orders and stock cannot be written together here. Every
+  /// other endpoint still needs the database.
+  Sqlite { path: PathBuf },
+}
+
+#[derive(Debug, Clone)]
+pub enum MediaStorage {
+  /// Files under a local directory.
+  Filesystem { root: PathBuf },
+  /// Objects in an S3-compatible bucket.
+  S3(S3Config),
+}
+
+#[derive(Debug, Clone)]
+pub struct S3Config {
+  /// Base URL such as `https://s3.eu-west-1.amazonaws.com` or
+  /// `http://localhost:9000` for MinIO; buckets are addressed path-style.
+  pub endpoint: String,
+  pub bucket: String,
+  pub region: String,
+  pub access_key_id: String,
+  pub secret_access_key: String,
 }
 
 impl AppConfig {
@@ -14,11 +102,204 @@ impl AppConfig {
       .unwrap_or_else(|_| "8080".to_string())
       .parse::<u16>()
       .context("APP_PORT must be a u16")?;
-    let database_url = std::env::var("DATABASE_URL").context("DATABASE_URL is required")?;
+    let storage = match std::env::var("STORAGE").as_deref() {
+      Err(_) | Ok("postgres") => Storage::Postgres,
+      Ok("memory") => Storage::Memory,
+      Ok("sqlite") => Storage::Sqlite {
+        path: std::env::var("SQLITE_PATH")
+          .unwrap_or_else(|_| "asgard.db".to_string())
+          .into(),
+      },
+      Ok(other) => bail!("STORAGE must be postgres, memory or sqlite, got {other}"),
+    };
+    let database_url = match storage {
+      Storage::Postgres => std::env::var("DATABASE_URL").context("DATABASE_URL is required")?,
+      Storage::Memory | Storage::Sqlite { .. } => {
+        std::env::var("DATABASE_URL").unwrap_or_else(|_| "postgres://localhost/asgard".to_string())
+      }
+    };
+    let admin_api_token = std::env::var("ADMIN_API_TOKEN")
+      .ok()
+      .filter(|t| !t.is_empty());
+    let payment_webhook_secrets =
+      parse_secrets(&std::env::var("PAYMENT_WEBHOOK_SECRETS").unwrap_or_default())?;
+    let payment_webhook_tolerance_secs = std::env::var("PAYMENT_WEBHOOK_TOLERANCE_SECS")
+      .unwrap_or_else(|_| "300".to_string())
+      .parse::<u64>()
+      .context("PAYMENT_WEBHOOK_TOLERANCE_SECS must be a u64")?;
+    let invoice_tax_rate_bps = std::env::var("INVOICE_TAX_RATE_BPS")
+      .unwrap_or_else(|_| "0".to_string())
+      .parse::<u16>()
+      .context("INVOICE_TAX_RATE_BPS must be a u16")?
+      .into();
+    let product_search_language =
+      std::env::var("PRODUCT_SEARCH_LANGUAGE").unwrap_or_else(|_| "english".to_string());
+    if product_search_language.is_empty()
+      || !product_search_language
+        .bytes()
+        .all(|b| b.is_ascii_lowercase() || b == b'_')
+    {
+      bail!("PRODUCT_SEARCH_LANGUAGE must name a text search configuration, e.g. english");
+    }
+    let product_fuzzy_threshold = parse_threshold("PRODUCT_FUZZY_THRESHOLD", "0.4")?;
+    let user_fuzzy_threshold = parse_threshold("USER_FUZZY_THRESHOLD", "0.5")?;
+    let media_storage = match std::env::var("MEDIA_STORAGE").as_deref() {
+      Err(_) | Ok("fs") => MediaStorage::Filesystem {
+        root: std::env::var("MEDIA_DIR")
+          .unwrap_or_else(|_| "media".to_string())
+          .into(),
+      },
+      Ok("s3") => MediaStorage::S3(S3Config {
+        endpoint: std::env::var("S3_ENDPOINT").context("S3_ENDPOINT is required for s3 storage")?,
+        bucket: std::env::var("S3_BUCKET").context("S3_BUCKET is required for s3 storage")?,
+        region: std::env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+        access_key_id: std::env::var("S3_ACCESS_KEY_ID")
+          .context("S3_ACCESS_KEY_ID is required for s3 storage")?,
+        secret_access_key: std::env::var("S3_SECRET_ACCESS_KEY")
+          .context("S3_SECRET_ACCESS_KEY is required for s3 storage")?,
+      }),
+      Ok(other) => bail!("MEDIA_STORAGE must be fs or s3, got {other}"),
+    };
+    let media_url_secret = std::env::var("MEDIA_URL_SECRET")
+      .ok()
+      .filter(|s| !s.is_empty())
+      .unwrap_or_else(|| format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()));
+    let media_url_ttl_secs = std::env::var("MEDIA_URL_TTL_SECS")
+      .unwrap_or_else(|_| "900".to_string())
+      .parse::<u64>()
+      .context("MEDIA_URL_TTL_SECS must be a u64")?;
+    let media_max_upload_bytes = std::env::var("MEDIA_MAX_UPLOAD_BYTES")
+      .unwrap_or_else(|_| (5 * 1024 * 1024).to_string())
+      .parse::<usize>()
+      .context("MEDIA_MAX_UPLOAD_BYTES must be a usize")?;
+    let thumbnail_sizes =
+      parse_sizes(&std::env::var("THUMBNAIL_SIZES").unwrap_or_else(|_| "160,480,1024".into()))?;
+    let thumbnail_poll_interval_secs = std::env::var("THUMBNAIL_POLL_INTERVAL_SECS")
+      .unwrap_or_else(|_| "5".to_string())
+      .parse::<u64>()
+      .context("THUMBNAIL_POLL_INTERVAL_SECS must be a u64")?;
+    let outbox_poll_interval_secs = std::env::var("OUTBOX_POLL_INTERVAL_SECS")
+      .unwrap_or_else(|_| "1".to_string())
+      .parse::<u64>()
+      .context("OUTBOX_POLL_INTERVAL_SECS must be a u64")?;
+    let outbox_max_attempts = std::env::var("OUTBOX_MAX_ATTEMPTS")
+      .unwrap_or_else(|_| "10".to_string())
+      .parse::<i32>()
+      .context("OUTBOX_MAX_ATTEMPTS must be an i32")?;
+    if outbox_max_attempts < 1 {
+      bail!("OUTBOX_MAX_ATTEMPTS must be at least 1");
+    }
+    let webhook_poll_interval_secs = std::env::var("WEBHOOK_POLL_INTERVAL_SECS")
+      .unwrap_or_else(|_| "2".to_string())
+      .parse::<u64>()
+      .context("WEBHOOK_POLL_INTERVAL_SECS must be a u64")?;
+    let webhook_timeout_secs = std::env::var("WEBHOOK_TIMEOUT_SECS")
+      .unwrap_or_else(|_| "10".to_string())
+      .parse::<u64>()
+      .context("WEBHOOK_TIMEOUT_SECS must be a u64")?;
+    let webhook_max_attempts = std::env::var("WEBHOOK_MAX_ATTEMPTS")
+      .unwrap_or_else(|_| "8".to_string())
+      .parse::<i32>()
+      .context("WEBHOOK_MAX_ATTEMPTS must be an i32")?;
+    let webhook_disable_after_failures = std::env::var("WEBHOOK_DISABLE_AFTER_FAILURES")
+      .unwrap_or_else(|_| "20".to_string())
+      .parse::<i32>()
+      .context("WEBHOOK_DISABLE_AFTER_FAILURES must be an i32")?;
+    if webhook_max_attempts < 1 || webhook_disable_after_failures < 1 {
+      bail!("WEBHOOK_MAX_ATTEMPTS and WEBHOOK_DISABLE_AFTER_FAILURES must be at least 1");
+    }
+    let access_token_secret = std::env::var("ACCESS_TOKEN_SECRET")
+      .ok()
+      .filter(|s| !s.is_empty())
+      .unwrap_or_else(|| format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()));
+    let access_token_ttl_secs = std::env::var("ACCESS_TOKEN_TTL_SECS")
+      .unwrap_or_else(|_| "3600".to_string())
+      .parse::<u64>()
+      .context("ACCESS_TOKEN_TTL_SECS must be a u64")?;
+    let order_stream_replay_size = std::env::var("ORDER_STREAM_REPLAY_SIZE")
+      .unwrap_or_else(|_| "1000".to_string())
+      .parse::<usize>()
+      .context("ORDER_STREAM_REPLAY_SIZE must be a usize")?;
+    if order_stream_replay_size == 0 {
+      bail!("ORDER_STREAM_REPLAY_SIZE must be at least 1");
+    }
+    let ws_heartbeat_interval_secs = std::env::var("WS_HEARTBEAT_INTERVAL_SECS")
+      .unwrap_or_else(|_| "30".to_string())
+      .parse::<u64>()
+      .context("WS_HEARTBEAT_INTERVAL_SECS must be a u64")?;
+    if ws_heartbeat_interval_secs == 0 {
+      bail!("WS_HEARTBEAT_INTERVAL_SECS must be at least 1");
+    }
     Ok(Self {
       host,
       port,
+      storage,
       database_url,
+      admin_api_token,
+      payment_webhook_secrets,
+      payment_webhook_tolerance_secs,
+      invoice_tax_rate_bps,
+      product_search_language,
+      product_fuzzy_threshold,
+      user_fuzzy_threshold,
+      media_storage,
+      media_url_secret,
+      media_url_ttl_secs,
+      media_max_upload_bytes,
+      thumbnail_sizes,
+      thumbnail_poll_interval_secs,
+      outbox_poll_interval_secs,
+      outbox_max_attempts,
+      webhook_poll_interval_secs,
+      webhook_timeout_secs,
+      webhook_max_attempts,
+      webhook_disable_after_failures,
+      access_token_secret,
+      access_token_ttl_secs,
+      order_stream_replay_size,
+      ws_heartbeat_interval_secs,
     })
   }
 }
+
+/// Reads a similarity threshold between 0 and 1 from `var`.
+fn parse_threshold(var: &str, default: &str) -> Result<f32> {
+  let threshold = std::env::var(var)
+    .unwrap_or_else(|_| default.to_string())
+    .parse::<f32>()
+    .with_context(|| format!("{var} must be a number"))?;
+  if !(0.0..=1.0).contains(&threshold) {
+    bail!("{var} must be between 0 and 1");
+  }
+  Ok(threshold)
+}
+
+/// Parses comma-separated thumbnail sizes into a sorted list without
+/// duplicates.
+fn parse_sizes(raw: &str) -> Result<Vec<u32>> {
+  let mut sizes = Vec::new();
+  for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+    let size = entry
+      .parse::<u32>()
+      .context("THUMBNAIL_SIZES must be a comma-separated list of pixel sizes")?;
+    if !(1..=4096).contains(&size) {
+      bail!("THUMBNAIL_SIZES entries must be between 1 and 4096");
+    }
+    sizes.push(size);
+  }
+  sizes.sort_unstable();
+  sizes.dedup();
+  Ok(sizes)
+}
+
+/// Parses `provider=secret` pairs separated by commas.
+fn parse_secrets(raw: &str) -> Result<HashMap<String, String>> {
+  let mut secrets = HashMap::new();
+  for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+    let Some((provider, secret)) = entry.split_once('=') else {
+      bail!("PAYMENT_WEBHOOK_SECRETS entries must look like provider=secret");
+    };
+    secrets.insert(provider.trim().to_string(), secret.trim().to_string());
+  }
+  Ok(secrets)
+}
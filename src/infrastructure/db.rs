@@ -1,6 +1,8 @@
 use anyhow::Result;
 use sqlx::postgres::PgPoolOptions;
-use sqlx::PgPool;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+use sqlx::{PgPool, SqlitePool};
+use std::path::Path;
 use std::time::Duration;
 
 pub async fn create_pool(database_url: &str) -> Result<PgPool> {
@@ -12,7 +14,38 @@ pub async fn create_pool(database_url: &str) -> Result<PgPool> {
   Ok(pool)
 }
 
+/// A pool that only connects when a query needs it, so the process can start
+/// without a reachable database.
+pub fn create_lazy_pool(database_url: &str) -> Result<PgPool> {
+  let pool = PgPoolOptions::new()
+    .max_connections(10)
+    .acquire_timeout(Duration::from_secs(2))
+    .connect_lazy(database_url)?;
+  Ok(pool)
+}
+
 pub async fn run_migrations(pool: &PgPool) -> Result<()> {
   sqlx::migrate!("./migrations").run(pool).await?;
   Ok(())
 }
+
+/// Opens the SQLite database at `path`, creating it if missing. Foreign keys
+/// are enforced, and the write-ahead log lets reads run alongside a write.
+pub async fn create_sqlite_pool(path: &Path) -> Result<SqlitePool> {
+  let options = SqliteConnectOptions::new()
+    .filename(path)
+    .create_if_missing(true)
+    .foreign_keys(true)
+    .journal_mode(SqliteJournalMode::Wal)
+    .busy_timeout(Duration::from_secs(5));
+  let pool = SqlitePoolOptions::new()
+    .max_connections(5)
+    .connect_with(options)
+    .await?;
+  Ok(pool)
+}
+
+pub async fn run_sqlite_migrations(pool: &SqlitePool) -> Result<()> {
+  sqlx::migrate!("./migrations_sqlite").run(pool).await?;
+  Ok(())
+}
@@ -1,3 +1,8 @@
+pub mod access_token;
 pub mod config;
 pub mod db;
 pub mod logging;
+pub mod outbox;
+pub mod shutdown;
+pub mod signature;
+pub mod signed_url;
@@ -0,0 +1,126 @@
+//! Publishes the events repositories write to the outbox.
+//!
+//! Delivery is at least once: an event is marked published only after the
+//! publisher accepts it, so a crash in between publishes it again. Failed
+//! events are retried with exponential backoff until `max_attempts`.
+
+use crate::application::ports::{EventPublisher, OutboxRepository, RepoError};
+use crate::domain::models::OutboxEvent;
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Events claimed per round.
+const BATCH_SIZE: i64 = 50;
+/// A claimed event is handed out again after this long, in case its
+/// dispatcher died before recording the outcome.
+const LEASE_SECS: i64 = 60;
+const MAX_RETRY_DELAY_SECS: i64 = 3600;
+
+/// Seconds to wait after the `attempts`-th failed attempt: 1, 2, 4, ... up to
+/// an hour.
+pub fn retry_delay_secs(attempts: i32) -> i64 {
+  let exponent = attempts.saturating_sub(1).clamp(0, 12) as u32;
+  (1i64 << exponent).min(MAX_RETRY_DELAY_SECS)
+}
+
+/// Publisher that only logs each event; the default when nothing else
+/// consumes them.
+pub struct LogPublisher;
+
+#[async_trait]
+impl EventPublisher for LogPublisher {
+  async fn publish(&self, event: &OutboxEvent) -> Result<(), RepoError> {
+    tracing::info!(
+      event_id = %event.event_id,
+      event_type = event.event_type,
+      aggregate_type = event.aggregate_type,
+      aggregate_id = %event.aggregate_id,
+      "domain event"
+    );
+    Ok(())
+  }
+}
+
+pub struct OutboxDispatcher<R: OutboxRepository> {
+  repo: Arc<R>,
+  publisher: Arc<dyn EventPublisher>,
+  max_attempts: i32,
+}
+
+impl<R: OutboxRepository> OutboxDispatcher<R> {
+  pub fn new(repo: R, publisher: Arc<dyn EventPublisher>, max_attempts: i32) -> Self {
+    Self {
+      repo: Arc::new(repo),
+      publisher,
+      max_attempts,
+    }
+  }
+
+  /// Publishes one batch of due events in outbox order and returns how many
+  /// were claimed.
+  pub async fn run_once(&self) -> Result<usize, RepoError> {
+    let events = self.repo.claim_due(BATCH_SIZE, LEASE_SECS).await?;
+    for event in &events {
+      match self.publisher.publish(event).await {
+        Ok(()) => self.repo.mark_published(event.id).await?,
+        Err(err) => {
+          let reason = err.to_string();
+          let retry_after =
+            (event.attempts < self.max_attempts).then(|| retry_delay_secs(event.attempts));
+          match retry_after {
+            Some(secs) => tracing::warn!(
+              event_id = %event.event_id,
+              attempts = event.attempts,
+              retry_in_secs = secs,
+              error = %reason,
+              "event publication failed"
+            ),
+            None => tracing::error!(
+              event_id = %event.event_id,
+              attempts = event.attempts,
+              error = %reason,
+              "giving up on event"
+            ),
+          }
+          self
+            .repo
+            .mark_failed(event.id, &reason, retry_after)
+            .await?;
+        }
+      }
+    }
+    Ok(events.len())
+  }
+
+  /// Polls for events until the process exits, draining full batches without
+  /// waiting.
+  pub fn spawn(self, poll_interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+      loop {
+        match self.run_once().await {
+          Ok(claimed) if claimed as i64 == BATCH_SIZE => continue,
+          Ok(_) => {}
+          Err(err) => tracing::error!(error = %err, "outbox dispatcher round failed"),
+        }
+        tokio::time::sleep(poll_interval).await;
+      }
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn retry_delay_doubles_up_to_an_hour() {
+    assert_eq!(retry_delay_secs(1), 1);
+    assert_eq!(retry_delay_secs(2), 2);
+    assert_eq!(retry_delay_secs(5), 16);
+    assert_eq!(retry_delay_secs(12), 2048);
+    assert_eq!(retry_delay_secs(13), 3600);
+    assert_eq!(retry_delay_secs(i32::MAX), 3600);
+  }
+}
@@ -0,0 +1,78 @@
+//! Coordinates a graceful stop: the HTTP server stops accepting requests
+//! when shutdown is triggered, and long-lived connections (WebSockets) close
+//! themselves and are waited for before the process exits.
+
+use std::sync::Arc;
+use tokio::sync::{watch, OwnedRwLockReadGuard, RwLock};
+
+#[derive(Clone)]
+pub struct Shutdown {
+  signal: Arc<watch::Sender<bool>>,
+  // Each open connection holds a read guard; draining takes the write lock.
+  connections: Arc<RwLock<()>>,
+}
+
+impl Default for Shutdown {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Shutdown {
+  pub fn new() -> Self {
+    let (signal, _) = watch::channel(false);
+    Self {
+      signal: Arc::new(signal),
+      connections: Arc::new(RwLock::new(())),
+    }
+  }
+
+  pub fn trigger(&self) {
+    self.signal.send_replace(true);
+  }
+
+  pub fn is_triggered(&self) -> bool {
+    *self.signal.borrow()
+  }
+
+  /// Completes once shutdown has been triggered.
+  pub async fn triggered(&self) {
+    let mut signal = self.signal.subscribe();
+    // The sender lives as long as `self`, so this cannot fail.
+    let _ = signal.wait_for(|triggered| *triggered).await;
+  }
+
+  /// Registers a connection that must close before [`Shutdown::drained`]
+  /// completes; it counts until the guard is dropped. `None` once shutdown
+  /// has been triggered.
+  pub async fn hold(&self) -> Option<OwnedRwLockReadGuard<()>> {
+    if self.is_triggered() {
+      return None;
+    }
+    Some(self.connections.clone().read_owned().await)
+  }
+
+  /// Completes once every held connection has been released.
+  pub async fn drained(&self) {
+    let _ = self.connections.write().await;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::Duration;
+
+  #[tokio::test]
+  async fn draining_waits_for_held_connections() {
+    let shutdown = Shutdown::new();
+    let connection = shutdown.hold().await.unwrap();
+    shutdown.trigger();
+    shutdown.triggered().await;
+    assert!(shutdown.hold().await.is_none());
+    let drained = tokio::time::timeout(Duration::from_millis(50), shutdown.drained()).await;
+    assert!(drained.is_err());
+    drop(connection);
+    shutdown.drained().await;
+  }
+}
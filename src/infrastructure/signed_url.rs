@@ -0,0 +1,97 @@
+//! Time-limited URLs: `<path>?expires=<unix seconds>&signature=<hex digest>`,
+//! where the HMAC-SHA256 digest covers `"<path>\n<expires>"`. Whoever holds
+//! the URL can use it until it expires.
+
+use crate::infrastructure::signature::SignatureError;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn mac(secret: &str, path: &str, expires: i64) -> HmacSha256 {
+  let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+  mac.update(path.as_bytes());
+  mac.update(b"\n");
+  mac.update(expires.to_string().as_bytes());
+  mac
+}
+
+/// Signs `path` until `expires` and returns the full URL path with query.
+pub fn sign(secret: &str, path: &str, expires: i64) -> String {
+  let digest = mac(secret, path, expires).finalize().into_bytes();
+  format!("{path}?expires={expires}&signature={}", hex::encode(digest))
+}
+
+/// Checks a signature produced by [`sign`] for `path` and `expires`.
+pub fn verify(
+  secret: &str,
+  path: &str,
+  expires: i64,
+  signature: &str,
+  now: i64,
+) -> Result<(), SignatureError> {
+  let signature = hex::decode(signature).map_err(|_| SignatureError::Malformed)?;
+  mac(secret, path, expires)
+    .verify_slice(&signature)
+    .map_err(|_| SignatureError::Mismatch)?;
+  if now >= expires {
+    return Err(SignatureError::Expired);
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn signature_of(url: &str) -> &str {
+    url.split_once("signature=").unwrap().1
+  }
+
+  #[test]
+  fn signed_urls_verify_until_they_expire() {
+    let url = sign("s3cret", "/media/abc", 1_700_000_900);
+    assert!(url.starts_with("/media/abc?expires=1700000900&signature="));
+    let signature = signature_of(&url);
+    assert_eq!(
+      verify(
+        "s3cret",
+        "/media/abc",
+        1_700_000_900,
+        signature,
+        1_700_000_000
+      ),
+      Ok(())
+    );
+    assert_eq!(
+      verify(
+        "s3cret",
+        "/media/abc",
+        1_700_000_900,
+        signature,
+        1_700_000_900
+      ),
+      Err(SignatureError::Expired)
+    );
+  }
+
+  #[test]
+  fn rejects_tampered_urls() {
+    let url = sign("s3cret", "/media/abc", 1_700_000_900);
+    let signature = signature_of(&url);
+    for (secret, path, expires) in [
+      ("other", "/media/abc", 1_700_000_900),
+      ("s3cret", "/media/abd", 1_700_000_900),
+      ("s3cret", "/media/abc", 1_700_009_999),
+    ] {
+      assert_eq!(
+        verify(secret, path, expires, signature, 1_700_000_000),
+        Err(SignatureError::Mismatch)
+      );
+    }
+    assert_eq!(
+      verify("s3cret", "/media/abc", 1_700_000_900, "zz", 1_700_000_000),
+      Err(SignatureError::Malformed)
+    );
+  }
+}
@@ -4,19 +4,50 @@ pub mod domain;
 pub mod infrastructure;
 
 use axum::Router;
-use sqlx::PgPool;
+use sqlx::{PgPool, SqlitePool};
 use std::sync::Arc;
 
+use crate::adapters::order_stream::OrderStream;
 use crate::adapters::{db, web};
-use crate::application::services::{OrderService, ProductService, UserService};
+use crate::application::ports::{
+  BoxedUnitOfWorkFactory, InventoryRepository, OrderRepository, ProductRepository, UserRepository,
+};
+use crate::application::services::{
+  AuditService, CategoryService, CheckoutService, InventoryService, InvoiceService, OrderService,
+  PaymentService, ProductImageService, ProductService, ReturnService, ReviewService, UserService,
+  VariantService, WebhookService, WishlistService,
+};
 use crate::infrastructure::config::AppConfig;
+use crate::infrastructure::shutdown::Shutdown;
 
 #[derive(Clone)]
 pub struct AppState {
   pub pool: PgPool,
-  pub users: Arc<UserService<db::users_repo::PgUserRepository>>,
-  pub products: Arc<ProductService<db::products_repo::PgProductRepository>>,
-  pub orders: Arc<OrderService<db::orders_repo::PgOrderRepository>>,
+  /// The database behind users, products and orders with
+  /// `STORAGE=sqlite`, which `/health` checks instead of `pool`.
+  pub sqlite_pool: Option<SqlitePool>,
+  /// Users, products and orders live in Postgres, in memory or in SQLite,
+  /// see `AppConfig::storage`.
+  pub users: Arc<UserService<Arc<dyn UserRepository>>>,
+  pub products: Arc<ProductService<Arc<dyn ProductRepository>>>,
+  pub variants: Arc<VariantService<db::variants_repo::PgVariantRepository>>,
+  pub images: Arc<ProductImageService<db::images_repo::PgProductImageRepository>>,
+  pub categories: Arc<CategoryService<db::categories_repo::PgCategoryRepository>>,
+  pub orders: Arc<OrderService<Arc<dyn OrderRepository>>>,
+  /// Writes orders and stock together, in Postgres or in memory; `None`
+  /// with `STORAGE=sqlite`, where `/checkout` answers `503`.
+  pub checkout: Option<Arc<CheckoutService<BoxedUnitOfWorkFactory>>>,
+  pub payments: Arc<PaymentService<db::payments_repo::PgPaymentRepository>>,
+  /// Stock lives in memory with `STORAGE=memory` and in Postgres otherwise.
+  pub inventory: Arc<InventoryService<Arc<dyn InventoryRepository>>>,
+  pub returns: Arc<ReturnService<db::returns_repo::PgReturnRepository>>,
+  pub reviews: Arc<ReviewService<db::reviews_repo::PgReviewRepository>>,
+  pub wishlists: Arc<WishlistService<db::wishlists_repo::PgWishlistRepository>>,
+  pub invoices: Arc<InvoiceService<db::invoices_repo::PgInvoiceRepository>>,
+  pub webhooks: Arc<WebhookService<db::webhooks_repo::PgWebhookRepository>>,
+  pub audit: Arc<AuditService<db::audit_repo::PgAuditRepository>>,
+  pub order_stream: Arc<OrderStream>,
+  pub shutdown: Shutdown,
   pub config: AppConfig,
 }
 
@@ -1,10 +1,39 @@
-use asgard_rust::adapters::db;
-use asgard_rust::application::services::{OrderService, ProductService, UserService};
-use asgard_rust::infrastructure::{config::AppConfig, db as infra_db, logging};
+use asgard_rust::adapters::memory::{self, MemoryStore};
+use asgard_rust::adapters::order_stream::OrderStream;
+use asgard_rust::adapters::thumbnails::ThumbnailWorker;
+use asgard_rust::adapters::webhooks::{WebhookPublisher, WebhookWorker};
+use asgard_rust::adapters::{blob, db, sqlite};
+use asgard_rust::application::ports::{
+  BlobStore, BoxedUnitOfWorkFactory, InventoryRepository, OrderRepository, ProductRepository,
+  UserRepository,
+};
+use asgard_rust::application::services::{
+  AuditService, CategoryService, CheckoutService, InventoryService, InvoiceService, OrderService,
+  PaymentService, ProductImageService, ProductService, ReturnService, ReviewService, UserService,
+  VariantService, WebhookService, WishlistService,
+};
+use asgard_rust::infrastructure::config::{AppConfig, MediaStorage, Storage};
+use asgard_rust::infrastructure::outbox::OutboxDispatcher;
+use asgard_rust::infrastructure::shutdown::Shutdown;
+use asgard_rust::infrastructure::{db as infra_db, logging};
 use asgard_rust::{build_app, AppState};
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tracing::info;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// The repositories `STORAGE` picks, and the units of work over them
+/// unless checkout is unavailable.
+type StorageRepositories = (
+  Arc<dyn UserRepository>,
+  Arc<dyn ProductRepository>,
+  Arc<dyn OrderRepository>,
+  Arc<dyn InventoryRepository>,
+  Option<BoxedUnitOfWorkFactory>,
+);
+
+/// How long open connections get to close after shutdown is triggered.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(10);
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -14,27 +43,208 @@ async fn main() -> anyhow::Result<()> {
   logging::init_tracing();
   let config = AppConfig::from_env()?;
 
-  let pool = infra_db::create_pool(&config.database_url).await?;
-  infra_db::run_migrations(&pool).await?;
+  let pool = match &config.storage {
+    Storage::Postgres => {
+      let pool = infra_db::create_pool(&config.database_url).await?;
+      infra_db::run_migrations(&pool).await?;
+      pool
+    }
+    Storage::Memory => {
+      warn!("STORAGE=memory: users, products and orders are lost on restart");
+      infra_db::create_lazy_pool(&config.database_url)?
+    }
+    Storage::Sqlite { .. } => infra_db::create_lazy_pool(&config.database_url)?,
+  };
+  let sqlite_pool = match &config.storage {
+    Storage::Sqlite { path } => {
+      let sqlite_pool = infra_db::create_sqlite_pool(path).await?;
+      infra_db::run_sqlite_migrations(&sqlite_pool).await?;
+      Some(sqlite_pool)
+    }
+    Storage::Postgres | Storage::Memory => None,
+  };
+
+  let (users_repo, products_repo, orders_repo, inventory_repo, units): StorageRepositories =
+    match (&config.storage, &sqlite_pool) {
+      (Storage::Postgres, _) => (
+        Arc::new(db::users_repo::PgUserRepository::new(pool.clone())),
+        Arc::new(db::products_repo::PgProductRepository::new(
+          pool.clone(),
+          config.product_search_language.clone(),
+        )),
+        Arc::new(db::orders_repo::PgOrderRepository::new(pool.clone())),
+        Arc::new(db::inventory_repo::PgInventoryRepository::new(pool.clone())),
+        Some(BoxedUnitOfWorkFactory::new(
+          db::unit_of_work::PgUnitOfWorkFactory::new(pool.clone()),
+        )),
+      ),
+      (Storage::Memory, _) => {
+        let store = MemoryStore::new();
+        (
+          Arc::new(memory::users_repo::MemoryUserRepository::new(store.clone())),
+          Arc::new(memory::products_repo::MemoryProductRepository::new(
+            store.clone(),
+          )),
+          Arc::new(memory::orders_repo::MemoryOrderRepository::new(
+            store.clone(),
+          )),
+          Arc::new(memory::inventory_repo::MemoryInventoryRepository::new(
+            store.clone(),
+          )),
+          Some(BoxedUnitOfWorkFactory::new(
+            memory::unit_of_work::MemoryUnitOfWorkFactory::new(store),
+          )),
+        )
+      }
+      (Storage::Sqlite { .. }, Some(sqlite_pool)) => (
+        Arc::new(sqlite::users_repo::SqliteUserRepository::new(
+          sqlite_pool.clone(),
+        )),
+        Arc::new(sqlite::products_repo::SqliteProductRepository::new(
+          sqlite_pool.clone(),
+        )),
+        Arc::new(sqlite::orders_repo::SqliteOrderRepository::new(
+          sqlite_pool.clone(),
+        )),
+        Arc::new(db::inventory_repo::PgInventoryRepository::new(pool.clone())),
+        None,
+      ),
+      (Storage::Sqlite { .. }, None) => unreachable!("the SQLite pool is opened above"),
+    };
+  let variants_repo = db::variants_repo::PgVariantRepository::new(pool.clone());
+  let images_repo = db::images_repo::PgProductImageRepository::new(pool.clone());
+  let categories_repo = db::categories_repo::PgCategoryRepository::new(pool.clone());
+  let payments_repo = db::payments_repo::PgPaymentRepository::new(pool.clone());
+  let returns_repo = db::returns_repo::PgReturnRepository::new(pool.clone());
+  let invoices_repo = db::invoices_repo::PgInvoiceRepository::new(pool.clone());
+
+  let blobs: Arc<dyn BlobStore> = match &config.media_storage {
+    MediaStorage::Filesystem { root } => Arc::new(blob::fs::FsBlobStore::new(root)),
+    MediaStorage::S3(s3) => Arc::new(blob::s3::S3BlobStore::new(s3.clone())?),
+  };
 
-  let users_repo = db::users_repo::PgUserRepository::new(pool.clone());
-  let products_repo = db::products_repo::PgProductRepository::new(pool.clone());
-  let orders_repo = db::orders_repo::PgOrderRepository::new(pool.clone());
+  let shutdown = Shutdown::new();
+  let order_stream = Arc::new(OrderStream::new(config.order_stream_replay_size));
+  if config.storage == Storage::Postgres {
+    order_stream.clone().listen(pool.clone()).await?;
+  }
 
   let state = AppState {
     pool: pool.clone(),
+    sqlite_pool,
     users: Arc::new(UserService::new(users_repo)),
     products: Arc::new(ProductService::new(products_repo)),
+    variants: Arc::new(VariantService::new(variants_repo)),
+    images: Arc::new(ProductImageService::new(images_repo, blobs.clone())),
+    categories: Arc::new(CategoryService::new(categories_repo)),
     orders: Arc::new(OrderService::new(orders_repo)),
+    checkout: units.map(|units| Arc::new(CheckoutService::new(units))),
+    payments: Arc::new(PaymentService::new(payments_repo)),
+    inventory: Arc::new(InventoryService::new(inventory_repo)),
+    returns: Arc::new(ReturnService::new(returns_repo)),
+    wishlists: Arc::new(WishlistService::new(
+      db::wishlists_repo::PgWishlistRepository::new(pool.clone()),
+    )),
+    reviews: Arc::new(ReviewService::new(
+      db::reviews_repo::PgReviewRepository::new(pool.clone()),
+    )),
+    invoices: Arc::new(InvoiceService::new(
+      invoices_repo,
+      config.invoice_tax_rate_bps,
+    )),
+    webhooks: Arc::new(WebhookService::new(
+      db::webhooks_repo::PgWebhookRepository::new(pool.clone()),
+    )),
+    audit: Arc::new(AuditService::new(db::audit_repo::PgAuditRepository::new(
+      pool.clone(),
+    ))),
+    order_stream,
+    shutdown: shutdown.clone(),
     config: config.clone(),
   };
 
+  // The workers poll Postgres tables that memory and SQLite storage leave
+  // empty.
+  if config.storage == Storage::Postgres {
+    ThumbnailWorker::new(
+      db::images_repo::PgProductImageRepository::new(pool.clone()),
+      blobs,
+      config.thumbnail_sizes.clone(),
+    )
+    .spawn(Duration::from_secs(config.thumbnail_poll_interval_secs));
+
+    OutboxDispatcher::new(
+      db::outbox_repo::PgOutboxRepository::new(pool.clone()),
+      Arc::new(WebhookPublisher::new(
+        db::webhooks_repo::PgWebhookRepository::new(pool.clone()),
+      )),
+      config.outbox_max_attempts,
+    )
+    .spawn(Duration::from_secs(config.outbox_poll_interval_secs));
+
+    WebhookWorker::new(
+      db::webhooks_repo::PgWebhookRepository::new(pool.clone()),
+      Duration::from_secs(config.webhook_timeout_secs),
+      config.webhook_max_attempts,
+      config.webhook_disable_after_failures,
+    )
+    .spawn(Duration::from_secs(config.webhook_poll_interval_secs));
+  }
+
   let app = build_app(state);
   let addr: SocketAddr = format!("{}:{}", config.host, config.port).parse()?;
 
   info!("listening on http://{addr}");
-  axum::Server::bind(&addr)
-    .serve(app.into_make_service())
-    .await?;
+  tokio::spawn({
+    let shutdown = shutdown.clone();
+    async move {
+      shutdown_signal().await;
+      info!("shutting down");
+      shutdown.trigger();
+    }
+  });
+  let serve = async {
+    axum::Server::bind(&addr)
+      .serve(app.into_make_service())
+      .with_graceful_shutdown({
+        let shutdown = shutdown.clone();
+        async move { shutdown.triggered().await }
+      })
+      .await?;
+    // WebSockets are not tracked by the server; give them time to say goodbye.
+    shutdown.drained().await;
+    anyhow::Ok(())
+  };
+  // A connection that never finishes must not keep the process alive.
+  let grace = async {
+    shutdown.triggered().await;
+    tokio::time::sleep(SHUTDOWN_GRACE).await;
+  };
+  tokio::select! {
+    served = serve => served?,
+    _ = grace => warn!("connections still open after the shutdown grace period"),
+  }
   Ok(())
 }
+
+/// Ctrl-C, or SIGTERM from the orchestrator.
+async fn shutdown_signal() {
+  let ctrl_c = async {
+    let _ = tokio::signal::ctrl_c().await;
+  };
+  #[cfg(unix)]
+  let terminate = async {
+    match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+      Ok(mut signal) => {
+        signal.recv().await;
+      }
+      Err(_) => std::future::pending().await,
+    }
+  };
+  #[cfg(not(unix))]
+  let terminate = std::future::pending::<()>();
+  tokio::select! {
+    _ = ctrl_c => {}
+    _ = terminate => {}
+  }
+}
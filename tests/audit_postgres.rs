@@ -0,0 +1,293 @@
+mod common;
+
+use asgard_rust::build_app;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use common::{admin, delete, get, post_json, put_json, send, setup};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+/// Entries for one resource, oldest first.
+async fn trail(app: &Router, resource_id: &Value) -> Vec<Value> {
+  let (status, page) = send(
+    app,
+    admin(get(&format!(
+      "/audit?resource_id={}",
+      resource_id.as_str().unwrap()
+    ))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::OK);
+  let mut entries = page["items"].as_array().unwrap().clone();
+  entries.reverse();
+  entries
+}
+
+fn with_request_id(mut req: Request<Body>, id: &str) -> Request<Body> {
+  req
+    .headers_mut()
+    .insert("x-request-id", id.parse().unwrap());
+  req
+}
+
+#[tokio::test]
+async fn changes_are_audited_with_actor_diff_and_request_id() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+
+  let (_, user) = send(
+    &app,
+    with_request_id(
+      post_json(
+        "/users",
+        json!({ "email": "ana@example.com", "name": "Ana" }),
+      ),
+      "req-create-user",
+    ),
+  )
+  .await;
+  send(
+    &app,
+    admin(put_json(
+      &format!("/users/{}", user["id"].as_str().unwrap()),
+      json!({ "name": "Ana María" }),
+    )),
+  )
+  .await;
+  // Nothing changes, so nothing is recorded.
+  send(
+    &app,
+    admin(put_json(
+      &format!("/users/{}", user["id"].as_str().unwrap()),
+      json!({ "name": "Ana María" }),
+    )),
+  )
+  .await;
+
+  let entries = trail(&app, &user["id"]).await;
+  assert_eq!(entries.len(), 2);
+  assert_eq!(entries[0]["action"], "created");
+  assert_eq!(entries[0]["resource_type"], "user");
+  assert_eq!(entries[0]["actor"], "anonymous");
+  assert_eq!(entries[0]["request_id"], "req-create-user");
+  assert_eq!(
+    entries[0]["changes"],
+    json!({
+      "email": { "before": null, "after": "ana@example.com" },
+      "name": { "before": null, "after": "Ana" },
+    })
+  );
+  assert_eq!(entries[1]["action"], "updated");
+  assert_eq!(entries[1]["actor"], "admin");
+  assert_eq!(
+    entries[1]["changes"],
+    json!({ "name": { "before": "Ana", "after": "Ana María" } })
+  );
+  // Requests without an id get a generated one, echoed in the response.
+  let generated = entries[1]["request_id"].as_str().unwrap();
+  assert!(uuid::Uuid::parse_str(generated).is_ok());
+  let res = app
+    .clone()
+    .oneshot(with_request_id(get("/health"), "req-health"))
+    .await
+    .unwrap();
+  assert_eq!(res.headers()["x-request-id"], "req-health");
+
+  let (_, product) = send(
+    &app,
+    post_json(
+      "/products",
+      json!({ "sku": "lamp", "name": "Lamp", "price_cents": 1000 }),
+    ),
+  )
+  .await;
+  let product_uri = format!("/products/{}", product["id"].as_str().unwrap());
+  send(
+    &app,
+    admin(put_json(&product_uri, json!({ "price_cents": 1200 }))),
+  )
+  .await;
+  let (status, _) = send(&app, admin(delete(&product_uri))).await;
+  assert_eq!(status, StatusCode::NO_CONTENT);
+  let entries = trail(&app, &product["id"]).await;
+  let actions: Vec<&str> = entries
+    .iter()
+    .map(|entry| entry["action"].as_str().unwrap())
+    .collect();
+  assert_eq!(actions, ["created", "updated", "deleted"]);
+  assert_eq!(
+    entries[1]["changes"],
+    json!({ "price_cents": { "before": 1000, "after": 1200 } })
+  );
+  assert_eq!(entries[2]["changes"]["sku"]["before"], "lamp");
+  assert_eq!(entries[2]["changes"]["sku"]["after"], Value::Null);
+
+  let (_, order) = send(
+    &app,
+    post_json(
+      "/orders",
+      json!({ "user_id": user["id"], "status": "cart", "total_cents": 0 }),
+    ),
+  )
+  .await;
+  send(
+    &app,
+    admin(put_json(
+      &format!("/orders/{}", order["id"].as_str().unwrap()),
+      json!({ "status": "cancelled" }),
+    )),
+  )
+  .await;
+  let entries = trail(&app, &order["id"]).await;
+  assert_eq!(entries.len(), 2);
+  assert_eq!(entries[0]["resource_type"], "order");
+  assert_eq!(
+    entries[1]["changes"],
+    json!({ "status": { "before": "cart", "after": "cancelled" } })
+  );
+
+  let user_id = user["id"].as_str().unwrap();
+  let (status, _) = send(
+    &app,
+    admin(delete(&format!(
+      "/orders/{}",
+      order["id"].as_str().unwrap()
+    ))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::NO_CONTENT);
+  let (status, _) = send(&app, admin(delete(&format!("/users/{user_id}")))).await;
+  assert_eq!(status, StatusCode::NO_CONTENT);
+  let entries = trail(&app, &user["id"]).await;
+  assert_eq!(entries[2]["action"], "deleted");
+  assert_eq!(entries[2]["changes"]["email"]["before"], "ana@example.com");
+}
+
+#[tokio::test]
+async fn scheduled_price_changes_are_audited() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let (_, product) = send(
+    &app,
+    admin(post_json(
+      "/products",
+      json!({ "sku": "lamp", "name": "Lamp", "price_cents": 1000 }),
+    )),
+  )
+  .await;
+  let prices_uri = format!("/products/{}/prices", product["id"].as_str().unwrap());
+  let valid_from = "2100-01-01T00:00:00Z";
+  let (status, scheduled) = send(
+    &app,
+    admin(post_json(
+      &prices_uri,
+      json!({ "price_cents": 800, "valid_from": valid_from }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+  let (status, _) = send(
+    &app,
+    admin(delete(&format!(
+      "{prices_uri}/{}",
+      scheduled["id"].as_str().unwrap()
+    ))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::NO_CONTENT);
+
+  let entries = trail(&app, &product["id"]).await;
+  assert_eq!(entries.len(), 3);
+  let scheduled = json!({ "price_cents": 800, "valid_from": valid_from });
+  assert_eq!(entries[1]["action"], "updated");
+  assert_eq!(entries[1]["actor"], "admin");
+  assert_eq!(
+    entries[1]["changes"],
+    json!({ "scheduled_price": { "before": null, "after": scheduled } })
+  );
+  assert_eq!(entries[2]["action"], "updated");
+  assert_eq!(
+    entries[2]["changes"],
+    json!({ "scheduled_price": { "before": scheduled, "after": null } })
+  );
+}
+
+#[tokio::test]
+async fn audit_log_is_filtered_and_paginated_for_admins_only() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  for i in 0..3 {
+    send(
+      &app,
+      admin(post_json(
+        "/products",
+        json!({ "sku": format!("sku-{i}"), "name": "Lamp", "price_cents": 1000 }),
+      )),
+    )
+    .await;
+  }
+  send(
+    &app,
+    with_request_id(
+      post_json(
+        "/users",
+        json!({ "email": "ben@example.com", "name": "Ben" }),
+      ),
+      "req-ben",
+    ),
+  )
+  .await;
+
+  let (status, _) = send(&app, get("/audit")).await;
+  assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+  let (_, page) = send(&app, admin(get("/audit"))).await;
+  assert_eq!(page["total"], 4);
+  assert_eq!(page["items"][0]["resource_type"], "user");
+  let newest = page["items"][0]["occurred_at"]
+    .as_str()
+    .unwrap()
+    .replace('+', "%2B");
+
+  let (_, page) = send(
+    &app,
+    admin(get("/audit?resource_type=product&per_page=2&page=2")),
+  )
+  .await;
+  assert_eq!(page["total"], 3);
+  assert_eq!(page["items"].as_array().unwrap().len(), 1);
+  assert_eq!(page["items"][0]["changes"]["sku"]["after"], "sku-0");
+
+  let (_, page) = send(&app, admin(get("/audit?actor=admin&action=created"))).await;
+  assert_eq!(page["total"], 3);
+  let (_, page) = send(&app, admin(get("/audit?request_id=req-ben"))).await;
+  assert_eq!(page["total"], 1);
+  assert_eq!(page["items"][0]["actor"], "anonymous");
+  let (_, page) = send(
+    &app,
+    admin(get("/audit?resource_type=product&per_page=2&page=3")),
+  )
+  .await;
+  assert_eq!(page["total"], 3);
+  assert_eq!(page["items"], json!([]));
+
+  let (_, page) = send(&app, admin(get(&format!("/audit?from={newest}")))).await;
+  assert_eq!(page["total"], 1);
+  let (_, page) = send(&app, admin(get(&format!("/audit?to={newest}")))).await;
+  assert_eq!(page["total"], 3);
+  let (status, _) = send(
+    &app,
+    admin(get(&format!("/audit?from={newest}&to={newest}"))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+  let (status, _) = send(&app, admin(get("/audit?per_page=0"))).await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,141 @@
+use asgard_rust::adapters::blob::fs::FsBlobStore;
+use asgard_rust::adapters::blob::s3::S3BlobStore;
+use asgard_rust::application::ports::{BlobStore, RepoError};
+use asgard_rust::infrastructure::config::S3Config;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, Method, StatusCode, Uri};
+use axum::Router;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+type Objects = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+/// Exercises the `BlobStore` contract shared by every adapter.
+async fn round_trip(store: &dyn BlobStore) {
+  let key = "products/p1/image-1";
+  assert!(matches!(store.get(key).await, Err(RepoError::NotFound)));
+
+  store
+    .put(key, "image/png", b"first".to_vec())
+    .await
+    .unwrap();
+  store
+    .put(key, "image/png", b"second".to_vec())
+    .await
+    .unwrap();
+  assert_eq!(store.get(key).await.unwrap(), b"second");
+
+  store.delete(key).await.unwrap();
+  assert!(matches!(store.get(key).await, Err(RepoError::NotFound)));
+  store.delete(key).await.unwrap();
+}
+
+#[tokio::test]
+async fn filesystem_store_round_trips_and_stays_in_its_root() {
+  let root = std::env::temp_dir().join(format!("asgard-blobs-{}", uuid::Uuid::new_v4()));
+  let store = FsBlobStore::new(&root);
+  round_trip(&store).await;
+
+  for key in [
+    "../escape",
+    "products//x",
+    "/absolute",
+    "a/./b",
+    "spaces are bad",
+  ] {
+    let err = store.put(key, "image/png", b"x".to_vec()).await;
+    assert!(matches!(err, Err(RepoError::Invalid(_))), "{key}");
+  }
+  std::fs::remove_dir_all(root).unwrap();
+}
+
+/// Minimal stand-in for an S3 endpoint: keeps objects in memory and rejects
+/// requests that are unsigned or whose payload hash does not match the body.
+async fn fake_s3(
+  State(objects): State<Objects>,
+  method: Method,
+  uri: Uri,
+  headers: HeaderMap,
+  body: Bytes,
+) -> (StatusCode, Vec<u8>) {
+  let header = |name: &str| {
+    headers
+      .get(name)
+      .and_then(|v| v.to_str().ok())
+      .unwrap_or("")
+  };
+  let signed = header("authorization").starts_with("AWS4-HMAC-SHA256 Credential=test-key/")
+    && header("authorization").contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date,")
+    && header("x-amz-content-sha256") == hex::encode(Sha256::digest(&body));
+  if !signed {
+    return (StatusCode::FORBIDDEN, Vec::new());
+  }
+
+  let mut objects = objects.lock().unwrap();
+  let path = uri.path().to_string();
+  match method {
+    Method::PUT => {
+      assert_eq!(header("content-type"), "image/png");
+      objects.insert(path, body.to_vec());
+      (StatusCode::OK, Vec::new())
+    }
+    Method::GET => match objects.get(&path) {
+      Some(bytes) => (StatusCode::OK, bytes.clone()),
+      None => (
+        StatusCode::NOT_FOUND,
+        b"<Error><Code>NoSuchKey</Code></Error>".to_vec(),
+      ),
+    },
+    Method::DELETE => {
+      objects.remove(&path);
+      (StatusCode::NO_CONTENT, Vec::new())
+    }
+    _ => (StatusCode::METHOD_NOT_ALLOWED, Vec::new()),
+  }
+}
+
+#[tokio::test]
+async fn s3_store_round_trips_against_a_local_stand_in() {
+  let objects = Objects::default();
+  let app = Router::new().fallback(fake_s3).with_state(objects.clone());
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let addr = listener.local_addr().unwrap();
+  tokio::spawn(
+    axum::Server::from_tcp(listener)
+      .unwrap()
+      .serve(app.into_make_service()),
+  );
+
+  let config = S3Config {
+    endpoint: format!("http://{addr}"),
+    bucket: "media".into(),
+    region: "eu-west-1".into(),
+    access_key_id: "test-key".into(),
+    secret_access_key: "test-secret".into(),
+  };
+  let store = S3BlobStore::new(config.clone()).unwrap();
+  round_trip(&store).await;
+
+  // Objects are addressed path-style under the bucket.
+  store
+    .put("products/p2/image", "image/png", b"png".to_vec())
+    .await
+    .unwrap();
+  assert_eq!(
+    objects.lock().unwrap().get("/media/products/p2/image"),
+    Some(&b"png".to_vec())
+  );
+
+  let stranger = S3BlobStore::new(S3Config {
+    access_key_id: "someone-else".into(),
+    ..config
+  })
+  .unwrap();
+  assert!(matches!(
+    stranger.get("products/p2/image").await,
+    Err(RepoError::Unexpected(_))
+  ));
+}
@@ -0,0 +1,269 @@
+mod common;
+
+use asgard_rust::build_app;
+use axum::http::StatusCode;
+use axum::Router;
+use common::{admin, delete, get, post_json, put_json, send, setup};
+use serde_json::{json, Value};
+
+async fn category(app: &Router, slug: &str, parent: Option<&Value>, position: i32) -> Value {
+  let (status, created) = send(
+    app,
+    admin(post_json(
+      "/categories",
+      json!({
+        "slug": slug,
+        "name": slug.replace('-', " "),
+        "parent_id": parent.map(|p| p["id"].clone()),
+        "position": position,
+      }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+  created
+}
+
+async fn product(app: &Router, sku: &str) -> Value {
+  let (_, created) = send(
+    app,
+    post_json(
+      "/products",
+      json!({ "sku": sku, "name": sku, "price_cents": 1000 }),
+    ),
+  )
+  .await;
+  created
+}
+
+fn skus(products: &Value) -> Vec<&str> {
+  let mut skus: Vec<&str> = products
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|p| p["sku"].as_str().unwrap())
+    .collect();
+  skus.sort();
+  skus
+}
+
+#[tokio::test]
+async fn categories_form_an_ordered_tree() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+
+  let clothing = category(&app, "clothing", None, 0).await;
+  category(&app, "shoes", None, 1).await;
+  let shirts = category(&app, "shirts", Some(&clothing), 1).await;
+  category(&app, "jackets", Some(&clothing), 0).await;
+  category(&app, "t-shirts", Some(&shirts), 0).await;
+
+  let (status, tree) = send(&app, get("/categories")).await;
+  assert_eq!(status, StatusCode::OK);
+  let roots: Vec<&str> = tree
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|n| n["slug"].as_str().unwrap())
+    .collect();
+  assert_eq!(roots, ["clothing", "shoes"]);
+  assert_eq!(tree[0]["children"][0]["slug"], "jackets");
+  assert_eq!(tree[0]["children"][1]["slug"], "shirts");
+  assert_eq!(tree[0]["children"][1]["children"][0]["slug"], "t-shirts");
+  assert_eq!(tree[0]["children"][1]["parent_id"], clothing["id"]);
+
+  let (status, _) = send(
+    &app,
+    admin(post_json(
+      "/categories",
+      json!({ "slug": "clothing", "name": "Dup" }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CONFLICT);
+  let (status, _) = send(
+    &app,
+    admin(post_json(
+      "/categories",
+      json!({ "slug": "Bad Slug", "name": "Bad" }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn moves_reject_cycles_and_parents_cannot_be_deleted() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+
+  let clothing = category(&app, "clothing", None, 0).await;
+  let shirts = category(&app, "shirts", Some(&clothing), 0).await;
+  let t_shirts = category(&app, "t-shirts", Some(&shirts), 0).await;
+  let clothing_uri = format!("/categories/{}", clothing["id"].as_str().unwrap());
+  let shirts_uri = format!("/categories/{}", shirts["id"].as_str().unwrap());
+
+  let (status, _) = send(
+    &app,
+    admin(put_json(
+      &clothing_uri,
+      json!({ "parent_id": t_shirts["id"] }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+  let (status, _) = send(
+    &app,
+    admin(put_json(
+      &clothing_uri,
+      json!({ "parent_id": clothing["id"] }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+
+  // Renaming keeps the parent; an explicit null moves to the top level.
+  let (status, renamed) = send(
+    &app,
+    admin(put_json(&shirts_uri, json!({ "name": "Tops" }))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(renamed["parent_id"], clothing["id"]);
+  let (status, moved) = send(
+    &app,
+    admin(put_json(&shirts_uri, json!({ "parent_id": null }))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(moved["parent_id"], Value::Null);
+
+  let (status, _) = send(&app, admin(delete(&shirts_uri))).await;
+  assert_eq!(status, StatusCode::CONFLICT);
+  let (status, _) = send(
+    &app,
+    admin(delete(&format!(
+      "/categories/{}",
+      t_shirts["id"].as_str().unwrap()
+    ))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::NO_CONTENT);
+  let (status, _) = send(&app, admin(delete(&shirts_uri))).await;
+  assert_eq!(status, StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn products_filter_by_category_and_descendants() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+
+  let clothing = category(&app, "clothing", None, 0).await;
+  let shirts = category(&app, "shirts", Some(&clothing), 0).await;
+  let t_shirts = category(&app, "t-shirts", Some(&shirts), 0).await;
+  let shoes = category(&app, "shoes", None, 0).await;
+
+  let coat = product(&app, "coat").await;
+  let tee = product(&app, "tee").await;
+  let boot = product(&app, "boot").await;
+  product(&app, "uncategorized").await;
+
+  let assign = |product: &Value, categories: Vec<&Value>| {
+    admin(put_json(
+      &format!("/products/{}/categories", product["id"].as_str().unwrap()),
+      json!({ "category_ids": categories.iter().map(|c| c["id"].clone()).collect::<Vec<_>>() }),
+    ))
+  };
+  send(&app, assign(&coat, vec![&clothing])).await;
+  // The tee sits in two places; duplicates in the request are ignored.
+  let (status, assigned) = send(&app, assign(&tee, vec![&t_shirts, &shoes, &t_shirts])).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(assigned.as_array().unwrap().len(), 2);
+  send(&app, assign(&boot, vec![&shoes])).await;
+
+  let (status, direct) = send(&app, get("/products?category=clothing")).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(skus(&direct), ["coat"]);
+  let (_, nested) = send(
+    &app,
+    get("/products?category=clothing&include_descendants=true"),
+  )
+  .await;
+  assert_eq!(skus(&nested), ["coat", "tee"]);
+  let (_, shoes_products) = send(&app, get("/products?category=shoes")).await;
+  assert_eq!(skus(&shoes_products), ["boot", "tee"]);
+  let (_, all) = send(&app, get("/products")).await;
+  assert_eq!(all.as_array().unwrap().len(), 4);
+
+  let (status, _) = send(&app, get("/products?category=missing")).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+
+  // Replacing assignments drops the old ones.
+  send(&app, assign(&tee, vec![&shirts])).await;
+  let (_, tee_categories) = send(
+    &app,
+    get(&format!(
+      "/products/{}/categories",
+      tee["id"].as_str().unwrap()
+    )),
+  )
+  .await;
+  assert_eq!(tee_categories[0]["slug"], "shirts");
+  assert_eq!(tee_categories.as_array().unwrap().len(), 1);
+
+  let (status, _) = send(
+    &app,
+    admin(put_json(
+      &format!("/products/{}/categories", boot["id"].as_str().unwrap()),
+      json!({ "category_ids": [uuid::Uuid::new_v4()] }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn only_admins_change_categories() {
+  let Some((_pool, mut state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state.clone());
+  let clothing = category(&app, "clothing", None, 0).await;
+  let clothing_uri = format!("/categories/{}", clothing["id"].as_str().unwrap());
+  let coat = product(&app, "coat").await;
+  let coat_categories = format!("/products/{}/categories", coat["id"].as_str().unwrap());
+
+  let changes = || {
+    [
+      post_json("/categories", json!({ "slug": "shoes", "name": "Shoes" })),
+      put_json(&clothing_uri, json!({ "name": "Apparel" })),
+      delete(&clothing_uri),
+      put_json(
+        &coat_categories,
+        json!({ "category_ids": [clothing["id"]] }),
+      ),
+    ]
+  };
+  for req in changes() {
+    let (status, _) = send(&app, req).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+  }
+  state.config.admin_api_token = None;
+  let app = build_app(state);
+  for req in changes() {
+    let (status, _) = send(&app, req).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+  }
+
+  let (_, tree) = send(&app, get("/categories")).await;
+  assert_eq!(tree.as_array().unwrap().len(), 1);
+  assert_eq!(tree[0]["name"], "clothing");
+  let (_, assigned) = send(&app, get(&coat_categories)).await;
+  assert_eq!(assigned, json!([]));
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use asgard_rust::build_app;
+use axum::http::StatusCode;
+use axum::Router;
+use common::{admin, get, post_json, put_json, send, setup};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+async fn product_with_stock(app: &Router, sku: &str, price_cents: i64, stock: i64) -> Value {
+  let (_, product) = send(
+    app,
+    post_json(
+      "/products",
+      json!({ "sku": sku, "name": sku, "price_cents": price_cents }),
+    ),
+  )
+  .await;
+  let (status, _) = send(
+    app,
+    admin(put_json(
+      &format!("/products/{}/stock", product["id"].as_str().unwrap()),
+      json!({ "quantity": stock }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::OK);
+  product["id"].clone()
+}
+
+async fn stock(app: &Router, product_id: &Value) -> i64 {
+  let (_, stock) = send(
+    app,
+    get(&format!("/products/{}/stock", product_id.as_str().unwrap())),
+  )
+  .await;
+  stock["quantity"].as_i64().unwrap()
+}
+
+async fn order_count(pool: &PgPool) -> i64 {
+  sqlx::query_scalar("SELECT COUNT(*) FROM orders")
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn checkout_places_the_order_and_reserves_stock() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let (_, user) = send(
+    &app,
+    post_json(
+      "/users",
+      json!({ "email": "ana@example.com", "name": "Ana" }),
+    ),
+  )
+  .await;
+  let shirt = product_with_stock(&app, "shirt", 1500, 5).await;
+  let jacket = product_with_stock(&app, "jacket", 4000, 1).await;
+
+  let (status, order) = send(
+    &app,
+    post_json(
+      "/checkout",
+      json!({
+        "user_id": user["id"],
+        "items": [
+          { "product_id": shirt, "quantity": 2 },
+          { "product_id": jacket, "quantity": 1 },
+        ],
+      }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(order["status"], "pending_payment");
+  assert_eq!(order["total_cents"], 7000);
+  let (_, items) = send(
+    &app,
+    get(&format!("/orders/{}/items", order["id"].as_str().unwrap())),
+  )
+  .await;
+  assert_eq!(items.as_array().unwrap().len(), 2);
+  assert_eq!(stock(&app, &shirt).await, 3);
+  assert_eq!(stock(&app, &jacket).await, 0);
+}
+
+#[tokio::test]
+async fn checkout_leaves_nothing_behind_when_stock_is_short() {
+  let Some((pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let (_, user) = send(
+    &app,
+    post_json(
+      "/users",
+      json!({ "email": "ben@example.com", "name": "Ben" }),
+    ),
+  )
+  .await;
+  let shirt = product_with_stock(&app, "shirt", 1500, 5).await;
+  let jacket = product_with_stock(&app, "jacket", 4000, 1).await;
+
+  // The shirt is reserved before the jacket turns out to be short.
+  let (status, _) = send(
+    &app,
+    post_json(
+      "/checkout",
+      json!({
+        "user_id": user["id"],
+        "items": [
+          { "product_id": shirt, "quantity": 2 },
+          { "product_id": jacket, "quantity": 2 },
+        ],
+      }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CONFLICT);
+  assert_eq!(order_count(&pool).await, 0);
+  assert_eq!(stock(&app, &shirt).await, 5);
+  assert_eq!(stock(&app, &jacket).await, 1);
+  let (_, page) = send(&app, admin(get("/audit?resource_type=order"))).await;
+  assert_eq!(page["total"], 0);
+
+  let (status, _) = send(
+    &app,
+    post_json("/checkout", json!({ "user_id": user["id"], "items": [] })),
+  )
+  .await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,551 @@
+#![allow(dead_code)]
+
+use asgard_rust::adapters::memory::inventory_repo::MemoryInventoryRepository;
+use asgard_rust::adapters::memory::orders_repo::MemoryOrderRepository;
+use asgard_rust::adapters::memory::products_repo::MemoryProductRepository;
+use asgard_rust::adapters::memory::unit_of_work::MemoryUnitOfWorkFactory;
+use asgard_rust::adapters::memory::users_repo::MemoryUserRepository;
+use asgard_rust::adapters::memory::MemoryStore;
+use asgard_rust::adapters::order_stream::OrderStream;
+use asgard_rust::adapters::sqlite::orders_repo::SqliteOrderRepository;
+use asgard_rust::adapters::sqlite::products_repo::SqliteProductRepository;
+use asgard_rust::adapters::sqlite::users_repo::SqliteUserRepository;
+use asgard_rust::adapters::{blob, db};
+use asgard_rust::application::ports::{
+  BoxedUnitOfWorkFactory, InventoryRepository, OrderRepository, ProductRepository, UserRepository,
+};
+use asgard_rust::application::services::{
+  AuditService, CategoryService, CheckoutService, InventoryService, InvoiceService, OrderService,
+  PaymentService, ProductImageService, ProductService, ReturnService, ReviewService, UserService,
+  VariantService, WebhookService, WishlistService,
+};
+use asgard_rust::infrastructure::config::{AppConfig, MediaStorage, Storage};
+use asgard_rust::infrastructure::db as infra_db;
+use asgard_rust::infrastructure::shutdown::Shutdown;
+use asgard_rust::infrastructure::signature;
+use asgard_rust::AppState;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use hyper::body::to_bytes;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{Connection, PgConnection, PgPool, SqlitePool};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower::ServiceExt;
+use url::Url;
+use uuid::Uuid;
+
+pub const PAYMENT_PROVIDER: &str = "acme";
+pub const PAYMENT_WEBHOOK_SECRET: &str = "whsec_test";
+pub const ADMIN_TOKEN: &str = "admin-test-token";
+pub const TAX_RATE_BPS: i32 = 2100;
+pub const SEARCH_LANGUAGE: &str = "english";
+pub const PRODUCT_FUZZY_THRESHOLD: f32 = 0.4;
+pub const USER_FUZZY_THRESHOLD: f32 = 0.5;
+pub const THUMBNAIL_SIZES: [u32; 2] = [160, 480];
+pub const MEDIA_URL_SECRET: &str = "media-test-secret";
+pub const MEDIA_MAX_UPLOAD_BYTES: usize = 64 * 1024;
+pub const ACCESS_TOKEN_SECRET: &str = "access-test-secret";
+pub const ORDER_STREAM_REPLAY_SIZE: usize = 100;
+
+/// Advisory lock key held while a test process builds the template database.
+const TEMPLATE_LOCK: i64 = 0x6173_6761_7264;
+
+/// Per-process media directory, so concurrent test binaries stay apart.
+pub fn media_dir() -> PathBuf {
+  std::env::temp_dir().join(format!("asgard-test-media-{}", std::process::id()))
+}
+
+pub fn test_config(database_url: String) -> AppConfig {
+  AppConfig {
+    host: "127.0.0.1".into(),
+    port: 0,
+    storage: Storage::Postgres,
+    database_url,
+    admin_api_token: Some(ADMIN_TOKEN.to_string()),
+    payment_webhook_secrets: HashMap::from([(
+      PAYMENT_PROVIDER.to_string(),
+      PAYMENT_WEBHOOK_SECRET.to_string(),
+    )]),
+    payment_webhook_tolerance_secs: 300,
+    invoice_tax_rate_bps: TAX_RATE_BPS,
+    product_search_language: SEARCH_LANGUAGE.into(),
+    product_fuzzy_threshold: PRODUCT_FUZZY_THRESHOLD,
+    user_fuzzy_threshold: USER_FUZZY_THRESHOLD,
+    media_storage: MediaStorage::Filesystem { root: media_dir() },
+    media_url_secret: MEDIA_URL_SECRET.into(),
+    media_url_ttl_secs: 900,
+    media_max_upload_bytes: MEDIA_MAX_UPLOAD_BYTES,
+    thumbnail_sizes: THUMBNAIL_SIZES.to_vec(),
+    thumbnail_poll_interval_secs: 5,
+    outbox_poll_interval_secs: 1,
+    outbox_max_attempts: 10,
+    webhook_poll_interval_secs: 2,
+    webhook_timeout_secs: 5,
+    webhook_max_attempts: 8,
+    webhook_disable_after_failures: 20,
+    access_token_secret: ACCESS_TOKEN_SECRET.into(),
+    access_token_ttl_secs: 3600,
+    order_stream_replay_size: ORDER_STREAM_REPLAY_SIZE,
+    ws_heartbeat_interval_secs: 1,
+  }
+}
+
+/// A database of its own for one test, dropped when this goes out of scope,
+/// so tests do not share rows and can run in parallel.
+pub struct TestDb {
+  /// Where `CREATE DATABASE` and `DROP DATABASE` are issued: the database
+  /// `DATABASE_URL` names.
+  admin_url: String,
+  name: String,
+}
+
+impl Drop for TestDb {
+  fn drop(&mut self) {
+    let admin_url = self.admin_url.clone();
+    let statement = format!(r#"DROP DATABASE IF EXISTS "{}" WITH (FORCE)"#, self.name);
+    // The test's runtime cannot block on the drop, so it gets a runtime of
+    // its own. `FORCE` closes the connections the test's pool still holds.
+    let dropped = std::thread::spawn(move || {
+      let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+      runtime.block_on(async {
+        let mut admin = PgConnection::connect(&admin_url).await?;
+        sqlx::query(&statement).execute(&mut admin).await?;
+        admin.close().await
+      })
+    })
+    .join();
+    if let Ok(Err(err)) = dropped {
+      eprintln!("could not drop test database {}: {err}", self.name);
+    }
+  }
+}
+
+/// Name of the template database for the current migrations, which the
+/// first test process to need it creates. A template is only ever
+/// migrated once, and changing a migration names a new one.
+async fn migrated_template(admin: &mut PgConnection, admin_url: &Url) -> String {
+  let mut hasher = Sha256::new();
+  for migration in sqlx::migrate!("./migrations").iter() {
+    hasher.update(migration.version.to_le_bytes());
+    hasher.update(&*migration.checksum);
+  }
+  let name = format!("asgard_template_{}", &hex::encode(hasher.finalize())[..16]);
+
+  sqlx::query("SELECT pg_advisory_lock($1)")
+    .bind(TEMPLATE_LOCK)
+    .execute(&mut *admin)
+    .await
+    .unwrap();
+  let exists: bool =
+    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_database WHERE datname = $1)")
+      .bind(&name)
+      .fetch_one(&mut *admin)
+      .await
+      .unwrap();
+  if !exists {
+    // Templates of older migrations are of no more use.
+    let stale: Vec<String> = sqlx::query_scalar(
+      "SELECT datname FROM pg_database WHERE datname LIKE 'asgard\\_template\\_%'",
+    )
+    .fetch_all(&mut *admin)
+    .await
+    .unwrap();
+    for stale in stale {
+      sqlx::query(&format!(r#"DROP DATABASE "{stale}" WITH (FORCE)"#))
+        .execute(&mut *admin)
+        .await
+        .unwrap();
+    }
+    // Migrated under another name and only then renamed, so a run that dies
+    // halfway never leaves a template without every migration.
+    let building = format!("{name}_building");
+    sqlx::query(&format!(r#"CREATE DATABASE "{building}""#))
+      .execute(&mut *admin)
+      .await
+      .unwrap();
+    let mut url = admin_url.clone();
+    url.set_path(&building);
+    let pool = infra_db::create_pool(url.as_str()).await.unwrap();
+    infra_db::run_migrations(&pool).await.unwrap();
+    pool.close().await;
+    sqlx::query(&format!(
+      r#"ALTER DATABASE "{building}" RENAME TO "{name}""#
+    ))
+    .execute(&mut *admin)
+    .await
+    .unwrap();
+  }
+  sqlx::query("SELECT pg_advisory_unlock($1)")
+    .bind(TEMPLATE_LOCK)
+    .execute(&mut *admin)
+    .await
+    .unwrap();
+  name
+}
+
+/// A migrated database of the test's own, with an app over it. `None`
+/// without `DATABASE_URL`, outside CI.
+pub async fn setup() -> Option<(PgPool, AppState, TestDb)> {
+  let database_url = match std::env::var("DATABASE_URL") {
+    Ok(v) => v,
+    Err(_) => {
+      if std::env::var("CI").is_ok() {
+        panic!("DATABASE_URL must be set in CI");
+      }
+      eprintln!("skipping: DATABASE_URL is not set");
+      return None;
+    }
+  };
+
+  let admin_url = Url::parse(&database_url).expect("DATABASE_URL is a URL");
+  let mut admin = PgConnection::connect(admin_url.as_str())
+    .await
+    .expect("DATABASE_URL is reachable");
+  let template = migrated_template(&mut admin, &admin_url).await;
+  let test_db = TestDb {
+    admin_url: database_url,
+    name: format!("asgard_test_{}", Uuid::new_v4().simple()),
+  };
+  sqlx::query(&format!(
+    r#"CREATE DATABASE "{}" TEMPLATE "{template}""#,
+    test_db.name
+  ))
+  .execute(&mut admin)
+  .await
+  .unwrap();
+  admin.close().await.unwrap();
+
+  let mut url = admin_url;
+  url.set_path(&test_db.name);
+  // Small pools, so that parallel tests stay under the server's connection
+  // limit.
+  let pool = PgPoolOptions::new()
+    .max_connections(4)
+    .connect(url.as_str())
+    .await
+    .unwrap();
+
+  let state = app_state(
+    pool.clone(),
+    None,
+    Repositories {
+      users: Arc::new(db::users_repo::PgUserRepository::new(pool.clone())),
+      products: Arc::new(db::products_repo::PgProductRepository::new(
+        pool.clone(),
+        SEARCH_LANGUAGE,
+      )),
+      orders: Arc::new(db::orders_repo::PgOrderRepository::new(pool.clone())),
+      inventory: Arc::new(db::inventory_repo::PgInventoryRepository::new(pool.clone())),
+      units: Some(BoxedUnitOfWorkFactory::new(
+        db::unit_of_work::PgUnitOfWorkFactory::new(pool.clone()),
+      )),
+    },
+    test_config(url.into()),
+  );
+  Some((pool, state, test_db))
+}
+
+/// An app over memory storage. Every other service points at an unreachable
+/// Postgres, so a handler that reaches for it fails instead of passing.
+pub fn memory_state() -> AppState {
+  let store = MemoryStore::new();
+  app_state(
+    unreachable_pool(),
+    None,
+    Repositories {
+      users: Arc::new(MemoryUserRepository::new(store.clone())),
+      products: Arc::new(MemoryProductRepository::new(store.clone())),
+      orders: Arc::new(MemoryOrderRepository::new(store.clone())),
+      inventory: Arc::new(MemoryInventoryRepository::new(store.clone())),
+      units: Some(BoxedUnitOfWorkFactory::new(MemoryUnitOfWorkFactory::new(
+        store,
+      ))),
+    },
+    AppConfig {
+      storage: Storage::Memory,
+      ..test_config(UNREACHABLE_DATABASE_URL.into())
+    },
+  )
+}
+
+/// An app over SQLite storage in `db`, with every other service pointing at
+/// an unreachable Postgres like `memory_state`.
+pub fn sqlite_state(db: &SqliteFile) -> AppState {
+  let pool = unreachable_pool();
+  app_state(
+    pool.clone(),
+    Some(db.pool.clone()),
+    Repositories {
+      users: Arc::new(SqliteUserRepository::new(db.pool.clone())),
+      products: Arc::new(SqliteProductRepository::new(db.pool.clone())),
+      orders: Arc::new(SqliteOrderRepository::new(db.pool.clone())),
+      inventory: Arc::new(db::inventory_repo::PgInventoryRepository::new(pool.clone())),
+      units: None,
+    },
+    AppConfig {
+      storage: Storage::Sqlite {
+        path: db.path.clone(),
+      },
+      ..test_config(UNREACHABLE_DATABASE_URL.into())
+    },
+  )
+}
+
+const UNREACHABLE_DATABASE_URL: &str = "postgres://asgard@127.0.0.1:1/unreachable";
+
+fn unreachable_pool() -> PgPool {
+  infra_db::create_lazy_pool(UNREACHABLE_DATABASE_URL).unwrap()
+}
+
+/// A fresh, migrated SQLite file, so queries go through several connections
+/// like a deployment would.
+pub struct SqliteFile {
+  pub path: PathBuf,
+  pub pool: SqlitePool,
+}
+
+impl SqliteFile {
+  pub async fn new() -> Self {
+    let path = std::env::temp_dir().join(format!("asgard-test-{}.db", Uuid::new_v4()));
+    let pool = infra_db::create_sqlite_pool(&path).await.unwrap();
+    infra_db::run_sqlite_migrations(&pool).await.unwrap();
+    Self { path, pool }
+  }
+
+  pub async fn remove(self) {
+    self.pool.close().await;
+    for suffix in ["", "-wal", "-shm"] {
+      let mut path = self.path.clone().into_os_string();
+      path.push(suffix);
+      let _ = std::fs::remove_file(path);
+    }
+  }
+}
+
+/// The repositories `main` picks by `STORAGE`.
+struct Repositories {
+  users: Arc<dyn UserRepository>,
+  products: Arc<dyn ProductRepository>,
+  orders: Arc<dyn OrderRepository>,
+  inventory: Arc<dyn InventoryRepository>,
+  units: Option<BoxedUnitOfWorkFactory>,
+}
+
+/// Wires the services the way `main` does, with `repos` for the storage
+/// and everything else in `pool`.
+fn app_state(
+  pool: PgPool,
+  sqlite_pool: Option<SqlitePool>,
+  repos: Repositories,
+  config: AppConfig,
+) -> AppState {
+  let variants_repo = db::variants_repo::PgVariantRepository::new(pool.clone());
+  let images_repo = db::images_repo::PgProductImageRepository::new(pool.clone());
+  let categories_repo = db::categories_repo::PgCategoryRepository::new(pool.clone());
+  let payments_repo = db::payments_repo::PgPaymentRepository::new(pool.clone());
+  let returns_repo = db::returns_repo::PgReturnRepository::new(pool.clone());
+  let invoices_repo = db::invoices_repo::PgInvoiceRepository::new(pool.clone());
+
+  AppState {
+    pool: pool.clone(),
+    sqlite_pool,
+    users: Arc::new(UserService::new(repos.users)),
+    products: Arc::new(ProductService::new(repos.products)),
+    variants: Arc::new(VariantService::new(variants_repo)),
+    images: Arc::new(ProductImageService::new(
+      images_repo,
+      Arc::new(blob::fs::FsBlobStore::new(media_dir())),
+    )),
+    categories: Arc::new(CategoryService::new(categories_repo)),
+    orders: Arc::new(OrderService::new(repos.orders)),
+    checkout: repos
+      .units
+      .map(|units| Arc::new(CheckoutService::new(units))),
+    payments: Arc::new(PaymentService::new(payments_repo)),
+    inventory: Arc::new(InventoryService::new(repos.inventory)),
+    returns: Arc::new(ReturnService::new(returns_repo)),
+    wishlists: Arc::new(WishlistService::new(
+      db::wishlists_repo::PgWishlistRepository::new(pool.clone()),
+    )),
+    reviews: Arc::new(ReviewService::new(
+      db::reviews_repo::PgReviewRepository::new(pool.clone()),
+    )),
+    invoices: Arc::new(InvoiceService::new(invoices_repo, TAX_RATE_BPS)),
+    webhooks: Arc::new(WebhookService::new(
+      db::webhooks_repo::PgWebhookRepository::new(pool.clone()),
+    )),
+    audit: Arc::new(AuditService::new(db::audit_repo::PgAuditRepository::new(
+      pool,
+    ))),
+    order_stream: Arc::new(OrderStream::new(ORDER_STREAM_REPLAY_SIZE)),
+    shutdown: Shutdown::new(),
+    config,
+  }
+}
+
+pub fn json_id(body: &[u8]) -> Uuid {
+  let value: Value = serde_json::from_slice(body).unwrap();
+  value.get("id").unwrap().as_str().unwrap().parse().unwrap()
+}
+
+/// Sends `req` and returns the status with the JSON body (`Null` when empty).
+pub async fn send(app: &Router, req: Request<Body>) -> (StatusCode, Value) {
+  let res = app.clone().oneshot(req).await.unwrap();
+  let status = res.status();
+  let bytes = to_bytes(res.into_body()).await.unwrap();
+  let body = if bytes.is_empty() {
+    Value::Null
+  } else {
+    serde_json::from_slice(&bytes).unwrap()
+  };
+  (status, body)
+}
+
+pub fn post_json(uri: &str, body: Value) -> Request<Body> {
+  Request::builder()
+    .method("POST")
+    .uri(uri)
+    .header("content-type", "application/json")
+    .body(Body::from(body.to_string()))
+    .unwrap()
+}
+
+pub fn get(uri: &str) -> Request<Body> {
+  Request::builder().uri(uri).body(Body::empty()).unwrap()
+}
+
+/// Authenticates `req` as an admin.
+pub fn admin(mut req: Request<Body>) -> Request<Body> {
+  req.headers_mut().insert(
+    "authorization",
+    format!("Bearer {ADMIN_TOKEN}").parse().unwrap(),
+  );
+  req
+}
+
+pub fn put_json(uri: &str, body: Value) -> Request<Body> {
+  Request::builder()
+    .method("PUT")
+    .uri(uri)
+    .header("content-type", "application/json")
+    .body(Body::from(body.to_string()))
+    .unwrap()
+}
+
+pub fn delete(uri: &str) -> Request<Body> {
+  Request::builder()
+    .method("DELETE")
+    .uri(uri)
+    .body(Body::empty())
+    .unwrap()
+}
+
+pub struct PaidOrder {
+  pub order_id: String,
+  pub product_id: String,
+  /// Line item with 2 units at 1500 cents.
+  pub shirt_item: String,
+  /// Line item with 1 unit at 4000 cents.
+  pub jacket_item: String,
+}
+
+/// Builds an order with two line items and a captured 7000 cent payment.
+pub async fn paid_order(app: &Router) -> PaidOrder {
+  // Unique keys let a test build several orders.
+  let tag = Uuid::new_v4().simple().to_string();
+  let (_, user) = send(
+    app,
+    post_json(
+      "/users",
+      json!({ "email": format!("{tag}@example.com"), "name": "Buyer" }),
+    ),
+  )
+  .await;
+  let (_, shirt) = send(
+    app,
+    post_json(
+      "/products",
+      json!({ "sku": format!("shirt-{tag}"), "name": "Shirt", "price_cents": 1500 }),
+    ),
+  )
+  .await;
+  let (_, jacket) = send(
+    app,
+    post_json(
+      "/products",
+      json!({ "sku": format!("jacket-{tag}"), "name": "Jacket", "price_cents": 4000 }),
+    ),
+  )
+  .await;
+  let (_, order) = send(
+    app,
+    post_json(
+      "/orders",
+      json!({ "user_id": user["id"], "status": "pending_payment", "total_cents": 0 }),
+    ),
+  )
+  .await;
+  let order_id = order["id"].as_str().unwrap().to_string();
+
+  let (status, shirt_item) = send(
+    app,
+    post_json(
+      &format!("/orders/{order_id}/items"),
+      json!({ "product_id": shirt["id"], "quantity": 2 }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+  let (_, jacket_item) = send(
+    app,
+    post_json(
+      &format!("/orders/{order_id}/items"),
+      json!({ "product_id": jacket["id"], "quantity": 1 }),
+    ),
+  )
+  .await;
+  let (_, order) = send(app, get(&format!("/orders/{order_id}"))).await;
+  assert_eq!(order["total_cents"], 7000);
+
+  send(
+    app,
+    admin(post_json(
+      &format!("/orders/{order_id}/payments"),
+      json!({ "provider": PAYMENT_PROVIDER, "provider_payment_id": format!("pi_{tag}"), "amount_cents": 7000 }),
+    )),
+  )
+  .await;
+  let body =
+    json!({ "id": format!("evt_{tag}"), "type": "payment.succeeded", "payment_id": format!("pi_{tag}") }).to_string();
+  let header = signature::sign(
+    PAYMENT_WEBHOOK_SECRET,
+    chrono::Utc::now().timestamp(),
+    body.as_bytes(),
+  );
+  let (status, _) = send(
+    app,
+    Request::builder()
+      .method("POST")
+      .uri(format!("/webhooks/payments/{PAYMENT_PROVIDER}"))
+      .header("x-webhook-signature", header)
+      .body(Body::from(body))
+      .unwrap(),
+  )
+  .await;
+  assert_eq!(status, StatusCode::OK);
+
+  PaidOrder {
+    order_id,
+    product_id: shirt["id"].as_str().unwrap().to_string(),
+    shirt_item: shirt_item["id"].as_str().unwrap().to_string(),
+    jacket_item: jacket_item["id"].as_str().unwrap().to_string(),
+  }
+}
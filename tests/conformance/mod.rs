@@ -0,0 +1,339 @@
+//! Behaviour every `UserRepository`, `ProductRepository` and
+//! `OrderRepository` shares, whatever keeps the data. Each check expects
+//! repositories over empty tables; the order checks also take the user and
+//! product repositories over the same store.
+
+use asgard_rust::application::ports::{
+  NewOrder, NewOrderItem, NewProduct, NewUser, OrderRepository, ProductRepository, RepoError,
+  UpdateOrder, UpdateProduct, UpdateUser, UserRepository,
+};
+use asgard_rust::domain::models::{Actor, AuditContext};
+use futures::future::join_all;
+use uuid::Uuid;
+
+/// Concurrent calls made by the concurrency checks.
+const CONCURRENCY: usize = 8;
+
+fn ctx() -> AuditContext {
+  AuditContext {
+    actor: Actor::Admin,
+    request_id: None,
+  }
+}
+
+fn new_user(email: &str) -> NewUser {
+  NewUser {
+    email: email.into(),
+    name: "Ana".into(),
+  }
+}
+
+fn new_product(sku: &str, price_cents: i64) -> NewProduct {
+  NewProduct {
+    sku: sku.into(),
+    name: "Lamp".into(),
+    description: "A desk lamp".into(),
+    price_cents,
+  }
+}
+
+fn new_order(user_id: Uuid) -> NewOrder {
+  NewOrder {
+    user_id,
+    status: "cart".into(),
+    total_cents: 0,
+  }
+}
+
+fn price_update(price_cents: i64) -> UpdateProduct {
+  UpdateProduct {
+    sku: None,
+    name: None,
+    description: None,
+    price_cents: Some(price_cents),
+  }
+}
+
+pub async fn users(repo: &impl UserRepository) {
+  // CRUD
+  let ana = repo
+    .create(new_user("ana@example.com"), &ctx())
+    .await
+    .unwrap();
+  assert_eq!(ana.email, "ana@example.com");
+  assert_eq!(ana.created_at, ana.updated_at);
+  assert_eq!(repo.get(ana.id).await.unwrap(), ana);
+  let rename = UpdateUser {
+    email: None,
+    name: Some("Ana María".into()),
+  };
+  let renamed = repo.update(ana.id, rename, &ctx()).await.unwrap();
+  assert_eq!(renamed.name, "Ana María");
+  assert_eq!(renamed.email, ana.email);
+  assert!(renamed.updated_at >= ana.updated_at);
+  assert_eq!(repo.get(ana.id).await.unwrap(), renamed);
+
+  // Ordering: newest first.
+  let ben = repo
+    .create(new_user("ben@example.com"), &ctx())
+    .await
+    .unwrap();
+  let listed: Vec<Uuid> = repo.list().await.unwrap().iter().map(|u| u.id).collect();
+  assert_eq!(listed, [ben.id, ana.id]);
+
+  // Conflict: emails are unique, on create and on update.
+  let err = repo
+    .create(new_user("ana@example.com"), &ctx())
+    .await
+    .unwrap_err();
+  assert!(matches!(err, RepoError::Conflict), "{err:?}");
+  let taken = UpdateUser {
+    email: Some("ana@example.com".into()),
+    name: None,
+  };
+  let err = repo.update(ben.id, taken, &ctx()).await.unwrap_err();
+  assert!(matches!(err, RepoError::Conflict), "{err:?}");
+  assert_eq!(repo.get(ben.id).await.unwrap().email, "ben@example.com");
+
+  // Not found
+  repo.delete(ben.id, &ctx()).await.unwrap();
+  let err = repo.get(ben.id).await.unwrap_err();
+  assert!(matches!(err, RepoError::NotFound), "{err:?}");
+  let noop = UpdateUser {
+    email: None,
+    name: None,
+  };
+  let err = repo.update(ben.id, noop, &ctx()).await.unwrap_err();
+  assert!(matches!(err, RepoError::NotFound), "{err:?}");
+  let err = repo.delete(ben.id, &ctx()).await.unwrap_err();
+  assert!(matches!(err, RepoError::NotFound), "{err:?}");
+
+  // Concurrency: exactly one of several racing creates gets the email.
+  let ctx = ctx();
+  let results =
+    join_all((0..CONCURRENCY).map(|_| repo.create(new_user("cleo@example.com"), &ctx))).await;
+  let created = results.iter().filter(|result| result.is_ok()).count();
+  assert_eq!(created, 1, "{results:?}");
+  assert!(results
+    .iter()
+    .all(|result| matches!(result, Ok(_) | Err(RepoError::Conflict))));
+}
+
+pub async fn products(repo: &impl ProductRepository) {
+  // CRUD, with the price kept as history.
+  let lamp = repo
+    .create(new_product("lamp", 1000), &ctx())
+    .await
+    .unwrap();
+  assert_eq!(lamp.price_cents, 1000);
+  assert_eq!(repo.get(lamp.id).await.unwrap(), lamp);
+  let update = UpdateProduct {
+    sku: None,
+    name: Some("Desk lamp".into()),
+    description: None,
+    price_cents: Some(1200),
+  };
+  let updated = repo.update(lamp.id, update, &ctx()).await.unwrap();
+  assert_eq!(updated.name, "Desk lamp");
+  assert_eq!(updated.description, "A desk lamp");
+  assert_eq!(updated.price_cents, 1200);
+  assert_eq!(repo.get(lamp.id).await.unwrap(), updated);
+  let prices: Vec<i64> = repo
+    .price_history(lamp.id)
+    .await
+    .unwrap()
+    .iter()
+    .map(|price| price.price_cents)
+    .collect();
+  assert_eq!(prices, [1000, 1200]);
+
+  // Ordering: newest first.
+  let shade = repo
+    .create(new_product("shade", 300), &ctx())
+    .await
+    .unwrap();
+  let listed: Vec<Uuid> = repo.list().await.unwrap().iter().map(|p| p.id).collect();
+  assert_eq!(listed, [shade.id, lamp.id]);
+
+  // Conflict: SKUs are unique, on create and on update.
+  let err = repo
+    .create(new_product("lamp", 500), &ctx())
+    .await
+    .unwrap_err();
+  assert!(matches!(err, RepoError::Conflict), "{err:?}");
+  let taken = UpdateProduct {
+    sku: Some("lamp".into()),
+    name: None,
+    description: None,
+    price_cents: None,
+  };
+  let err = repo.update(shade.id, taken, &ctx()).await.unwrap_err();
+  assert!(matches!(err, RepoError::Conflict), "{err:?}");
+  assert_eq!(repo.get(shade.id).await.unwrap().sku, "shade");
+
+  // Not found
+  repo.delete(shade.id, &ctx()).await.unwrap();
+  let err = repo.get(shade.id).await.unwrap_err();
+  assert!(matches!(err, RepoError::NotFound), "{err:?}");
+  let err = repo
+    .update(shade.id, price_update(100), &ctx())
+    .await
+    .unwrap_err();
+  assert!(matches!(err, RepoError::NotFound), "{err:?}");
+  let err = repo.delete(shade.id, &ctx()).await.unwrap_err();
+  assert!(matches!(err, RepoError::NotFound), "{err:?}");
+  let err = repo.price_history(shade.id).await.unwrap_err();
+  assert!(matches!(err, RepoError::NotFound), "{err:?}");
+
+  // Concurrency: racing price changes leave a history without gaps or
+  // overlaps.
+  let ctx = ctx();
+  let prices: Vec<i64> = (1..=CONCURRENCY as i64).map(|i| 2000 + i).collect();
+  let results = join_all(
+    prices
+      .iter()
+      .map(|price| repo.update(lamp.id, price_update(*price), &ctx)),
+  )
+  .await;
+  assert!(results.iter().all(Result::is_ok), "{results:?}");
+  let history = repo.price_history(lamp.id).await.unwrap();
+  for pair in history.windows(2) {
+    assert_eq!(pair[0].valid_to, Some(pair[1].valid_from), "{history:?}");
+  }
+  assert_eq!(history.last().unwrap().valid_to, None);
+  assert!(prices.contains(&repo.get(lamp.id).await.unwrap().price_cents));
+}
+
+pub async fn orders(
+  repo: &impl OrderRepository,
+  users: &impl UserRepository,
+  products: &impl ProductRepository,
+) {
+  let user = users
+    .create(new_user("buyer@example.com"), &ctx())
+    .await
+    .unwrap();
+  let lamp = products
+    .create(new_product("lamp", 250), &ctx())
+    .await
+    .unwrap();
+
+  // CRUD, with items at the product's price.
+  let order = repo.create(new_order(user.id), &ctx()).await.unwrap();
+  assert_eq!(order.user_id, user.id);
+  assert_eq!(order.total_cents, 0);
+  assert_eq!(repo.get(order.id).await.unwrap(), order);
+  let ship = UpdateOrder {
+    status: Some("shipped".into()),
+    total_cents: None,
+  };
+  let shipped = repo.update(order.id, ship, &ctx()).await.unwrap();
+  assert_eq!(shipped.status, "shipped");
+  assert_eq!(repo.get(order.id).await.unwrap(), shipped);
+  let line = NewOrderItem {
+    product_id: lamp.id,
+    quantity: 2,
+  };
+  let item = repo.add_item(order.id, line, &ctx()).await.unwrap();
+  assert_eq!(item.unit_price_cents, 250);
+  assert_eq!(repo.get(order.id).await.unwrap().total_cents, 500);
+  let actions: Vec<String> = repo
+    .history(order.id)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|entry| entry.action)
+    .collect();
+  assert_eq!(actions, ["created", "updated", "updated"]);
+
+  // Ordering: newest orders first, items in the order they were added.
+  let second = repo.create(new_order(user.id), &ctx()).await.unwrap();
+  let listed: Vec<Uuid> = repo.list().await.unwrap().iter().map(|o| o.id).collect();
+  assert_eq!(listed, [second.id, order.id]);
+  let line = NewOrderItem {
+    product_id: lamp.id,
+    quantity: 1,
+  };
+  let other = repo.add_item(order.id, line, &ctx()).await.unwrap();
+  assert_eq!(repo.list_items(order.id).await.unwrap(), [item, other]);
+  assert!(repo.list_items(second.id).await.unwrap().is_empty());
+
+  // Conflict: references must exist, and referenced rows cannot go.
+  let err = repo
+    .create(new_order(Uuid::new_v4()), &ctx())
+    .await
+    .unwrap_err();
+  assert!(matches!(err, RepoError::Conflict), "{err:?}");
+  let unknown_product = NewOrderItem {
+    product_id: Uuid::new_v4(),
+    quantity: 1,
+  };
+  let err = repo
+    .add_item(order.id, unknown_product, &ctx())
+    .await
+    .unwrap_err();
+  assert!(matches!(err, RepoError::Conflict), "{err:?}");
+  let err = users.delete(user.id, &ctx()).await.unwrap_err();
+  assert!(matches!(err, RepoError::Conflict), "{err:?}");
+  let err = products.delete(lamp.id, &ctx()).await.unwrap_err();
+  assert!(matches!(err, RepoError::Conflict), "{err:?}");
+
+  // Not found, except for the history, which outlives the order.
+  repo.delete(second.id, &ctx()).await.unwrap();
+  let err = repo.get(second.id).await.unwrap_err();
+  assert!(matches!(err, RepoError::NotFound), "{err:?}");
+  let noop = UpdateOrder {
+    status: None,
+    total_cents: None,
+  };
+  let err = repo.update(second.id, noop, &ctx()).await.unwrap_err();
+  assert!(matches!(err, RepoError::NotFound), "{err:?}");
+  let err = repo.delete(second.id, &ctx()).await.unwrap_err();
+  assert!(matches!(err, RepoError::NotFound), "{err:?}");
+  let line = NewOrderItem {
+    product_id: lamp.id,
+    quantity: 1,
+  };
+  let err = repo.add_item(second.id, line, &ctx()).await.unwrap_err();
+  assert!(matches!(err, RepoError::NotFound), "{err:?}");
+  let err = repo.list_items(second.id).await.unwrap_err();
+  assert!(matches!(err, RepoError::NotFound), "{err:?}");
+  let actions: Vec<String> = repo
+    .history(second.id)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|entry| entry.action)
+    .collect();
+  assert_eq!(actions, ["created", "deleted"]);
+  let err = repo.history(Uuid::new_v4()).await.unwrap_err();
+  assert!(matches!(err, RepoError::NotFound), "{err:?}");
+
+  // Concurrency: racing items all count towards the total, each with its
+  // own history version.
+  let ctx = ctx();
+  let results = join_all((0..CONCURRENCY).map(|_| {
+    let line = NewOrderItem {
+      product_id: lamp.id,
+      quantity: 1,
+    };
+    repo.add_item(order.id, line, &ctx)
+  }))
+  .await;
+  assert!(results.iter().all(Result::is_ok), "{results:?}");
+  let order = repo.get(order.id).await.unwrap();
+  assert_eq!(order.total_cents, 750 + 250 * CONCURRENCY as i64);
+  assert_eq!(
+    repo.list_items(order.id).await.unwrap().len(),
+    2 + CONCURRENCY
+  );
+  let versions: Vec<i32> = repo
+    .history(order.id)
+    .await
+    .unwrap()
+    .iter()
+    .map(|entry| entry.version)
+    .collect();
+  let expected: Vec<i32> = (1..=4 + CONCURRENCY as i32).collect();
+  assert_eq!(versions, expected);
+}
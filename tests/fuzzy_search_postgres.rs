@@ -0,0 +1,106 @@
+mod common;
+
+use asgard_rust::build_app;
+use axum::http::StatusCode;
+use axum::Router;
+use common::{admin, get, post_json, send, setup};
+use serde_json::{json, Value};
+
+fn field<'a>(hits: &'a Value, name: &str) -> Vec<&'a str> {
+  hits
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|hit| hit[name].as_str().unwrap())
+    .collect()
+}
+
+async fn seed_products(app: &Router) {
+  for (sku, name) in [
+    ("RAIN-JACKET-01", "Blue rain jacket"),
+    ("OXF-SHIRT", "Oxford shirt"),
+    ("PEELER", "Potato peeler"),
+  ] {
+    let (status, _) = send(
+      app,
+      post_json(
+        "/products",
+        json!({ "sku": sku, "name": name, "price_cents": 1000 }),
+      ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+  }
+}
+
+#[tokio::test]
+async fn products_match_misspelled_names_and_skus() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  seed_products(&app).await;
+
+  let (status, hits) = send(&app, get("/products/fuzzy-search?q=jackt")).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(field(&hits, "sku"), ["RAIN-JACKET-01"]);
+  let similarity = hits[0]["similarity"].as_f64().unwrap();
+  assert!(similarity > 0.4 && similarity < 1.0, "{similarity}");
+  assert_eq!(hits[0]["name"], "Blue rain jacket");
+
+  let (_, hits) = send(&app, get("/products/fuzzy-search?q=oxfrod")).await;
+  assert_eq!(field(&hits, "sku"), ["OXF-SHIRT"]);
+  let (_, hits) = send(&app, get("/products/fuzzy-search?q=rain-jakcet")).await;
+  assert_eq!(field(&hits, "sku"), ["RAIN-JACKET-01"]);
+
+  // Exact matches score 1 and come first.
+  let (_, hits) = send(&app, get("/products/fuzzy-search?q=shirt")).await;
+  assert_eq!(hits[0]["sku"], "OXF-SHIRT");
+  assert_eq!(hits[0]["similarity"], 1.0);
+
+  let (_, hits) = send(&app, get("/products/fuzzy-search?q=sandals")).await;
+  assert_eq!(hits, json!([]));
+
+  for uri in [
+    "/products/fuzzy-search?q=%20",
+    "/products/fuzzy-search?q=jacket&limit=0",
+    "/products/fuzzy-search?q=jacket&limit=101",
+  ] {
+    let (status, _) = send(&app, get(uri)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+  }
+}
+
+#[tokio::test]
+async fn admins_find_users_by_misspelled_email_or_name() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  for (email, name) in [
+    ("john.doe@example.com", "John Smith"),
+    ("maria@example.org", "María García"),
+  ] {
+    let (status, _) = send(
+      &app,
+      post_json("/users", json!({ "email": email, "name": name })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+  }
+
+  let (status, _) = send(&app, get("/users/search?q=smith")).await;
+  assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+  let (status, hits) = send(&app, admin(get("/users/search?q=jon%20smith"))).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(field(&hits, "email"), ["john.doe@example.com"]);
+  assert!(hits[0]["similarity"].as_f64().unwrap() >= 0.5);
+
+  let (_, hits) = send(&app, admin(get("/users/search?q=maria@exmple.org"))).await;
+  assert_eq!(field(&hits, "email"), ["maria@example.org"]);
+
+  // Too far from any email or name to match.
+  let (_, hits) = send(&app, admin(get("/users/search?q=jhn"))).await;
+  assert_eq!(hits, json!([]));
+}
@@ -1,63 +1,14 @@
-use asgard_rust::adapters::db;
-use asgard_rust::application::services::{OrderService, ProductService, UserService};
-use asgard_rust::infrastructure::db as infra_db;
-use asgard_rust::{build_app, AppState};
+mod common;
+
+use asgard_rust::build_app;
 use axum::body::Body;
 use axum::http::{Request, StatusCode};
+use common::{json_id, setup};
 use hyper::body::to_bytes;
 use serde_json::Value;
-use sqlx::PgPool;
-use std::sync::Arc;
-use tokio::sync::{Mutex, MutexGuard};
 use tower::ServiceExt;
 use uuid::Uuid;
 
-static DB_LOCK: Mutex<()> = Mutex::const_new(());
-
-async fn setup() -> Option<(PgPool, AppState, MutexGuard<'static, ()>)> {
-  let guard = DB_LOCK.lock().await;
-
-  let database_url = match std::env::var("DATABASE_URL") {
-    Ok(v) => v,
-    Err(_) => {
-      if std::env::var("CI").is_ok() {
-        panic!("DATABASE_URL must be set in CI");
-      }
-      return None;
-    }
-  };
-
-  let pool = infra_db::create_pool(&database_url).await.ok()?;
-  infra_db::run_migrations(&pool).await.ok()?;
-  sqlx::query("TRUNCATE orders, products, users CASCADE")
-    .execute(&pool)
-    .await
-    .ok()?;
-
-  let users_repo = db::users_repo::PgUserRepository::new(pool.clone());
-  let products_repo = db::products_repo::PgProductRepository::new(pool.clone());
-  let orders_repo = db::orders_repo::PgOrderRepository::new(pool.clone());
-
-  let state = AppState {
-    pool: pool.clone(),
-    users: Arc::new(UserService::new(users_repo)),
-    products: Arc::new(ProductService::new(products_repo)),
-    orders: Arc::new(OrderService::new(orders_repo)),
-    config: asgard_rust::infrastructure::config::AppConfig {
-      host: "127.0.0.1".into(),
-      port: 0,
-      database_url,
-    },
-  };
-
-  Some((pool, state, guard))
-}
-
-fn json_id(body: &[u8]) -> Uuid {
-  let value: Value = serde_json::from_slice(body).unwrap();
-  value.get("id").unwrap().as_str().unwrap().parse().unwrap()
-}
-
 #[tokio::test]
 async fn health_is_ok_with_db() {
   let Some((_pool, state, _guard)) = setup().await else {
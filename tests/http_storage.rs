@@ -0,0 +1,142 @@
+mod common;
+
+use asgard_rust::{build_app, AppState};
+use axum::http::StatusCode;
+use common::{
+  admin, delete, get, memory_state, post_json, put_json, send, sqlite_state, SqliteFile,
+};
+use serde_json::json;
+
+/// Product CRUD through the router, which must not reach for the
+/// Postgres-only services (variants, images) when products live elsewhere.
+async fn product_crud(state: AppState) {
+  let app = build_app(state);
+
+  let (status, product) = send(
+    &app,
+    admin(post_json(
+      "/products",
+      json!({ "sku": "lamp", "name": "Lamp", "price_cents": 1000 }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+  let uri = format!("/products/{}", product["id"].as_str().unwrap());
+
+  let (status, details) = send(&app, get(&uri)).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(details["sku"], "lamp");
+  assert_eq!(details["price_cents"], 1000);
+  assert_eq!(details["options"], json!([]));
+  assert_eq!(details["variants"], json!([]));
+  assert_eq!(details["images"], json!([]));
+
+  let (status, updated) = send(&app, admin(put_json(&uri, json!({ "name": "Desk lamp" })))).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(updated["name"], "Desk lamp");
+
+  let (status, _) = send(&app, admin(delete(&uri))).await;
+  assert_eq!(status, StatusCode::NO_CONTENT);
+  let (status, _) = send(&app, get(&uri)).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+  let (status, _) = send(&app, admin(delete(&uri))).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn memory_product_crud() {
+  product_crud(memory_state()).await;
+}
+
+#[tokio::test]
+async fn sqlite_product_crud() {
+  let db = SqliteFile::new().await;
+  product_crud(sqlite_state(&db)).await;
+  db.remove().await;
+}
+
+#[tokio::test]
+async fn health_checks_the_configured_storage() {
+  let app = build_app(memory_state());
+  let (status, body) = send(&app, get("/health")).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(body, json!({ "status": "ok", "db": "ok" }));
+
+  let db = SqliteFile::new().await;
+  let app = build_app(sqlite_state(&db));
+  let (_, body) = send(&app, get("/health")).await;
+  assert_eq!(body["db"], "ok");
+  db.pool.close().await;
+  let (status, body) = send(&app, get("/health")).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(body["db"], "error");
+  db.remove().await;
+}
+
+#[tokio::test]
+async fn memory_checkout_reserves_memory_stock() {
+  let app = build_app(memory_state());
+  let (_, user) = send(
+    &app,
+    post_json(
+      "/users",
+      json!({ "email": "ada@example.com", "name": "Ada" }),
+    ),
+  )
+  .await;
+  let (_, product) = send(
+    &app,
+    admin(post_json(
+      "/products",
+      json!({ "sku": "lamp", "name": "Lamp", "price_cents": 1000 }),
+    )),
+  )
+  .await;
+  let stock_uri = format!("/products/{}/stock", product["id"].as_str().unwrap());
+  let (status, _) = send(&app, admin(put_json(&stock_uri, json!({ "quantity": 2 })))).await;
+  assert_eq!(status, StatusCode::OK);
+
+  let checkout = || {
+    post_json(
+      "/checkout",
+      json!({
+        "user_id": user["id"],
+        "items": [{ "product_id": product["id"], "quantity": 2 }],
+      }),
+    )
+  };
+  let (status, order) = send(&app, checkout()).await;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(order["total_cents"], 2000);
+  let (_, stock) = send(&app, get(&stock_uri)).await;
+  assert_eq!(stock["quantity"], 0);
+
+  let (status, _) = send(&app, checkout()).await;
+  assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn sqlite_has_no_checkout() {
+  let db = SqliteFile::new().await;
+  let app = build_app(sqlite_state(&db));
+  let (_, user) = send(
+    &app,
+    post_json(
+      "/users",
+      json!({ "email": "ada@example.com", "name": "Ada" }),
+    ),
+  )
+  .await;
+
+  let (status, body) = send(
+    &app,
+    post_json("/checkout", json!({ "user_id": user["id"], "items": [] })),
+  )
+  .await;
+  assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+  assert_eq!(
+    body["error"],
+    "checkout is not available with STORAGE=sqlite"
+  );
+  db.remove().await;
+}
@@ -0,0 +1,274 @@
+mod common;
+
+use asgard_rust::build_app;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use chrono::Datelike;
+use common::{admin, get, paid_order, post_json, send, setup};
+use hyper::body::to_bytes;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+fn billing_address() -> Value {
+  json!({
+    "billing_address": {
+      "name": "Ada Lovelace",
+      "line1": "12 St James's Square",
+      "line2": null,
+      "city": "London",
+      "postal_code": "SW1Y 4JH",
+      "country": "GB"
+    }
+  })
+}
+
+#[tokio::test]
+async fn paid_order_gets_one_immutable_invoice() {
+  let Some((pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let order = paid_order(&app).await;
+  let uri = format!("/orders/{}/invoice", order.order_id);
+  let year = chrono::Utc::now().year();
+
+  let (status, _) = send(&app, get(&uri)).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+
+  let (status, _) = send(&app, post_json(&uri, billing_address())).await;
+  assert_eq!(status, StatusCode::UNAUTHORIZED);
+  let (status, invoice) = send(&app, admin(post_json(&uri, billing_address()))).await;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(invoice["number"], format!("INV-{year}-000001"));
+  assert_eq!(invoice["lines"].as_array().unwrap().len(), 2);
+  // 21% tax included in 3000 and 4000 cent lines: 521 + 694.
+  assert_eq!(invoice["tax_cents"], 1215);
+  assert_eq!(invoice["net_cents"], 5785);
+  assert_eq!(invoice["total_cents"], 7000);
+
+  let (status, _) = send(&app, admin(post_json(&uri, billing_address()))).await;
+  assert_eq!(status, StatusCode::CONFLICT);
+
+  let (status, fetched) = send(&app, get(&uri)).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(fetched, invoice);
+
+  let res = app
+    .clone()
+    .oneshot(
+      Request::builder()
+        .uri(&uri)
+        .header("accept", "text/html")
+        .body(Body::empty())
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+  assert_eq!(res.status(), StatusCode::OK);
+  assert!(res.headers()["content-type"]
+    .to_str()
+    .unwrap()
+    .starts_with("text/html"));
+  let html = String::from_utf8(to_bytes(res.into_body()).await.unwrap().to_vec()).unwrap();
+  assert!(html.contains(&format!("Invoice INV-{year}-000001")));
+  assert!(html.contains("St James&#39;s Square"));
+  assert!(html.contains("Total: 70.00"));
+
+  let err = sqlx::query("UPDATE invoices SET total_cents = 1")
+    .execute(&pool)
+    .await
+    .unwrap_err();
+  assert!(err.to_string().contains("immutable"));
+}
+
+#[tokio::test]
+async fn unpaid_orders_and_bad_addresses_are_rejected() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+
+  let (_, user) = send(
+    &app,
+    post_json("/users", json!({ "email": "np@example.com", "name": "Np" })),
+  )
+  .await;
+  let (_, order) = send(
+    &app,
+    post_json(
+      "/orders",
+      json!({ "user_id": user["id"], "status": "pending_payment", "total_cents": 100 }),
+    ),
+  )
+  .await;
+  let (status, _) = send(
+    &app,
+    admin(post_json(
+      &format!("/orders/{}/invoice", order["id"].as_str().unwrap()),
+      billing_address(),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CONFLICT);
+
+  let paid = paid_order(&app).await;
+  let mut address = billing_address();
+  address["billing_address"]["city"] = json!(" ");
+  let (status, _) = send(
+    &app,
+    admin(post_json(
+      &format!("/orders/{}/invoice", paid.order_id),
+      address,
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_invoices_get_gapless_numbers() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let year = chrono::Utc::now().year();
+
+  let mut orders = Vec::new();
+  for _ in 0..6 {
+    orders.push(paid_order(&app).await.order_id);
+  }
+  let handles: Vec<_> = orders
+    .iter()
+    .map(|order_id| {
+      let app = app.clone();
+      let uri = format!("/orders/{order_id}/invoice");
+      // `Router` is not `Sync`, so each task owns its clone.
+      tokio::spawn(async move {
+        let res = app
+          .oneshot(admin(post_json(&uri, billing_address())))
+          .await
+          .unwrap();
+        let status = res.status();
+        let body = to_bytes(res.into_body()).await.unwrap();
+        (status, serde_json::from_slice::<Value>(&body).unwrap())
+      })
+    })
+    .collect();
+
+  let mut numbers = Vec::new();
+  for handle in handles {
+    let (status, invoice) = handle.await.unwrap();
+    assert_eq!(status, StatusCode::CREATED);
+    numbers.push(invoice["number"].as_str().unwrap().to_string());
+  }
+  numbers.sort();
+  let expected: Vec<String> = (1..=6).map(|n| format!("INV-{year}-{n:06}")).collect();
+  assert_eq!(numbers, expected);
+}
+
+#[tokio::test]
+async fn refunds_of_invoiced_orders_issue_credit_notes() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let order = paid_order(&app).await;
+  let year = chrono::Utc::now().year();
+
+  let (_, invoice) = send(
+    &app,
+    admin(post_json(
+      &format!("/orders/{}/invoice", order.order_id),
+      billing_address(),
+    )),
+  )
+  .await;
+
+  let (_, rma) = send(
+    &app,
+    post_json(
+      &format!("/orders/{}/returns", order.order_id),
+      json!({ "items": [{ "order_item_id": order.jacket_item, "quantity": 1, "reason": "defective" }] }),
+    ),
+  )
+  .await;
+  let rma_id = rma["id"].as_str().unwrap();
+  send(
+    &app,
+    admin(post_json(&format!("/returns/{rma_id}/approve"), json!({}))),
+  )
+  .await;
+  let (_, refunded) = send(
+    &app,
+    admin(post_json(&format!("/returns/{rma_id}/refunds"), json!({}))),
+  )
+  .await;
+
+  let (status, notes) = send(
+    &app,
+    get(&format!("/orders/{}/credit-notes", order.order_id)),
+  )
+  .await;
+  assert_eq!(status, StatusCode::OK);
+  let notes = notes.as_array().unwrap();
+  assert_eq!(notes.len(), 1);
+  assert_eq!(notes[0]["kind"], "credit_note");
+  assert_eq!(notes[0]["number"], format!("CN-{year}-000001"));
+  assert_eq!(notes[0]["total_cents"], 4000);
+  assert_eq!(notes[0]["credited_invoice_id"], invoice["id"]);
+  assert_eq!(notes[0]["refund_id"], refunded["refund"]["id"]);
+  assert_eq!(notes[0]["billing_address"], invoice["billing_address"]);
+}
+
+#[tokio::test]
+async fn invoices_and_packing_slips_render_as_pdf() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let order = paid_order(&app).await;
+  let year = chrono::Utc::now().year();
+
+  let fetch = |uri: String| {
+    let app = app.clone();
+    async move {
+      let res = app.oneshot(get(&uri)).await.unwrap();
+      let status = res.status();
+      let headers = res.headers().clone();
+      let body = to_bytes(res.into_body()).await.unwrap();
+      (status, headers, String::from_utf8(body.to_vec()).unwrap())
+    }
+  };
+
+  // The warehouse can pick an order before it has been invoiced.
+  let (status, headers, slip) = fetch(format!("/orders/{}/packing-slip.pdf", order.order_id)).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(headers["content-type"], "application/pdf");
+  assert!(slip.starts_with("%PDF-1.4"));
+  assert!(slip.contains("(Shirt) Tj"));
+  assert!(slip.contains("(Total units: 3) Tj"));
+
+  let (status, _, _) = fetch(format!("/orders/{}/invoice.pdf", order.order_id)).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+
+  send(
+    &app,
+    admin(post_json(
+      &format!("/orders/{}/invoice", order.order_id),
+      billing_address(),
+    )),
+  )
+  .await;
+  let (status, headers, pdf) = fetch(format!("/orders/{}/invoice.pdf", order.order_id)).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(
+    headers["content-disposition"],
+    format!("inline; filename=\"INV-{year}-000001.pdf\"")
+  );
+  assert!(pdf.contains(&format!("(Invoice INV-{year}-000001) Tj")));
+  assert!(pdf.contains("(Total: 70.00) Tj"));
+  assert!(pdf.ends_with("%%EOF\n"));
+
+  let (status, _, _) = fetch(format!("/orders/{}/packing-slip.pdf", uuid::Uuid::new_v4())).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,254 @@
+mod common;
+
+use asgard_rust::build_app;
+use axum::http::StatusCode;
+use axum::Router;
+use chrono::{DateTime, Utc};
+use common::{admin, delete, get, paid_order, post_json, put_json, send, setup};
+use serde_json::{json, Value};
+use sqlx::Executor;
+
+fn actors(history: &Value) -> Vec<&str> {
+  history
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|entry| entry["actor"].as_str().unwrap())
+    .collect()
+}
+
+/// Creates an order without lines, which can still be deleted.
+async fn empty_order(app: &Router, user_id: &Value) -> String {
+  let (status, order) = send(
+    app,
+    post_json(
+      "/orders",
+      json!({ "user_id": user_id, "status": "cart", "total_cents": 0 }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+  order["id"].as_str().unwrap().to_string()
+}
+
+fn state_at(order_id: &str, at: &Value) -> String {
+  let at: DateTime<Utc> = at.as_str().unwrap().parse().unwrap();
+  format!(
+    "/orders/{order_id}/history/state?at={}",
+    at.to_rfc3339().replace('+', "%2B")
+  )
+}
+
+#[tokio::test]
+async fn every_order_change_is_recorded_with_its_actor() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let order = paid_order(&app).await;
+  let history_uri = format!("/orders/{}/history", order.order_id);
+
+  let (status, _) = send(&app, get(&history_uri)).await;
+  assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+  let (status, history) = send(&app, admin(get(&history_uri))).await;
+  assert_eq!(status, StatusCode::OK);
+  // Creation, two line items and the captured payment.
+  assert_eq!(
+    actors(&history),
+    [
+      "anonymous",
+      "anonymous",
+      "anonymous",
+      "system:payments:acme"
+    ]
+  );
+  let versions: Vec<i64> = history
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|entry| entry["version"].as_i64().unwrap())
+    .collect();
+  assert_eq!(versions, [1, 2, 3, 4]);
+  assert_eq!(history[0]["action"], "created");
+  assert_eq!(history[0]["changes"]["status"]["before"], Value::Null);
+  assert_eq!(history[0]["changes"]["status"]["after"], "pending_payment");
+  assert_eq!(history[1]["action"], "updated");
+  assert_eq!(
+    history[1]["changes"],
+    json!({ "total_cents": { "before": 0, "after": 3000 } })
+  );
+  assert_eq!(history[2]["changes"]["total_cents"]["after"], 7000);
+  assert_eq!(history[3]["changes"]["status"]["before"], "pending_payment");
+
+  // Updates that change nothing leave no entry.
+  let order_uri = format!("/orders/{}", order.order_id);
+  let (_, current) = send(&app, get(&order_uri)).await;
+  send(
+    &app,
+    admin(put_json(&order_uri, json!({ "status": current["status"] }))),
+  )
+  .await;
+  send(
+    &app,
+    admin(put_json(&order_uri, json!({ "status": "shipped" }))),
+  )
+  .await;
+  let (_, history) = send(&app, admin(get(&history_uri))).await;
+  assert_eq!(history.as_array().unwrap().len(), 5);
+  assert_eq!(history[4]["actor"], "admin");
+  assert_eq!(
+    history[4]["changes"],
+    json!({ "status": { "before": current["status"], "after": "shipped" } })
+  );
+
+  // The history outlives the order.
+  let cart_id = empty_order(&app, &current["user_id"]).await;
+  let (status, _) = send(&app, admin(delete(&format!("/orders/{cart_id}")))).await;
+  assert_eq!(status, StatusCode::NO_CONTENT);
+  let (status, history) = send(&app, admin(get(&format!("/orders/{cart_id}/history")))).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(history[1]["action"], "deleted");
+  assert_eq!(history[1]["actor"], "admin");
+  assert_eq!(history[1]["changes"]["status"]["before"], "cart");
+  assert_eq!(history[1]["changes"]["status"]["after"], Value::Null);
+
+  let unknown = format!("/orders/{}/history", uuid::Uuid::new_v4());
+  let (status, _) = send(&app, admin(get(&unknown))).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn orders_can_be_rebuilt_as_they_were_at_any_time() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let order = paid_order(&app).await;
+  let (_, paid) = send(&app, get(&format!("/orders/{}", order.order_id))).await;
+  let order_id = empty_order(&app, &paid["user_id"]).await;
+  let order_uri = format!("/orders/{order_id}");
+  send(
+    &app,
+    admin(put_json(&order_uri, json!({ "total_cents": 2500 }))),
+  )
+  .await;
+  send(
+    &app,
+    admin(put_json(&order_uri, json!({ "status": "shipped" }))),
+  )
+  .await;
+  send(&app, admin(delete(&order_uri))).await;
+  let (_, history) = send(&app, admin(get(&format!("/orders/{order_id}/history")))).await;
+  assert_eq!(history.as_array().unwrap().len(), 4);
+
+  let (status, created) = send(
+    &app,
+    admin(get(&state_at(&order_id, &history[0]["occurred_at"]))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(created["id"], order_id.as_str());
+  assert_eq!(created["user_id"], paid["user_id"]);
+  assert_eq!(created["status"], "cart");
+  assert_eq!(created["total_cents"], 0);
+
+  let (_, filled) = send(
+    &app,
+    admin(get(&state_at(&order_id, &history[1]["occurred_at"]))),
+  )
+  .await;
+  assert_eq!(filled["status"], "cart");
+  assert_eq!(filled["total_cents"], 2500);
+
+  let (_, shipped) = send(
+    &app,
+    admin(get(&state_at(&order_id, &history[2]["occurred_at"]))),
+  )
+  .await;
+  assert_eq!(shipped["status"], "shipped");
+  assert_eq!(shipped["total_cents"], 2500);
+  assert_eq!(shipped["created_at"], history[0]["occurred_at"]);
+  assert_eq!(shipped["updated_at"], history[2]["occurred_at"]);
+
+  // Neither before its creation nor after its deletion.
+  let (status, _) = send(
+    &app,
+    admin(get(&state_at(&order_id, &history[3]["occurred_at"]))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+  let (status, _) = send(
+    &app,
+    admin(get(&state_at(&order_id, &json!("2000-01-01T00:00:00Z")))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn the_migration_backfills_orders_without_history() {
+  let Some((pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let order = paid_order(&app).await;
+  let order_uri = format!("/orders/{}", order.order_id);
+  let (_, current) = send(&app, get(&order_uri)).await;
+  // As if the order predated the history table.
+  pool
+    .execute(
+      r#"
+      ALTER TABLE order_history DISABLE TRIGGER order_history_append_only;
+      DELETE FROM order_history;
+      ALTER TABLE order_history ENABLE TRIGGER order_history_append_only;
+      "#,
+    )
+    .await
+    .unwrap();
+  let (status, _) = send(&app, admin(get(&format!("{order_uri}/history")))).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+
+  pool
+    .execute(include_str!("../migrations/0017_order_history.sql"))
+    .await
+    .unwrap();
+  let (status, history) = send(&app, admin(get(&format!("{order_uri}/history")))).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(actors(&history), ["system:migration"]);
+  assert_eq!(history[0]["version"], 1);
+  assert_eq!(history[0]["action"], "created");
+  assert_eq!(history[0]["occurred_at"], current["created_at"]);
+  assert_eq!(
+    history[0]["changes"],
+    json!({
+      "user_id": { "before": null, "after": current["user_id"] },
+      "status": { "before": null, "after": current["status"] },
+      "total_cents": { "before": null, "after": current["total_cents"] },
+    })
+  );
+
+  // Later changes replay onto the full order.
+  send(
+    &app,
+    admin(put_json(&order_uri, json!({ "status": "shipped" }))),
+  )
+  .await;
+  let (_, history) = send(&app, admin(get(&format!("{order_uri}/history")))).await;
+  let (_, shipped) = send(
+    &app,
+    admin(get(&state_at(&order.order_id, &history[1]["occurred_at"]))),
+  )
+  .await;
+  assert_eq!(shipped["user_id"], current["user_id"]);
+  assert_eq!(shipped["status"], "shipped");
+  assert_eq!(shipped["total_cents"], current["total_cents"]);
+
+  // Running it again adds nothing.
+  pool
+    .execute(include_str!("../migrations/0017_order_history.sql"))
+    .await
+    .unwrap();
+  let (_, again) = send(&app, admin(get(&format!("{order_uri}/history")))).await;
+  assert_eq!(again.as_array().unwrap().len(), 2);
+}
@@ -0,0 +1,323 @@
+mod common;
+
+use asgard_rust::build_app;
+use axum::body::{Body, BoxBody};
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use common::{admin, delete, get, post_json, put_json, send, setup};
+use hyper::body::HttpBody;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tower::ServiceExt;
+
+/// One server-sent event.
+#[derive(Debug)]
+struct SseEvent {
+  id: Option<String>,
+  event: String,
+  data: Value,
+}
+
+/// Reads server-sent events off a streaming response.
+struct EventStream {
+  body: BoxBody,
+  buffer: String,
+}
+
+impl EventStream {
+  async fn open(app: &Router, req: Request<Body>) -> Result<Self, StatusCode> {
+    let res = app.clone().oneshot(req).await.unwrap();
+    if res.status() != StatusCode::OK {
+      return Err(res.status());
+    }
+    assert_eq!(res.headers()["content-type"], "text/event-stream");
+    Ok(Self {
+      body: res.into_body(),
+      buffer: String::new(),
+    })
+  }
+
+  /// The next event, skipping keep-alive comments; `None` if nothing arrives
+  /// within `wait`.
+  async fn next_within(&mut self, wait: Duration) -> Option<SseEvent> {
+    loop {
+      while let Some(end) = self.buffer.find("\n\n") {
+        let frame: String = self.buffer.drain(..end + 2).collect();
+        let mut event = SseEvent {
+          id: None,
+          event: "message".into(),
+          data: Value::Null,
+        };
+        let mut has_data = false;
+        for line in frame.lines() {
+          let Some((field, value)) = line.split_once(':') else {
+            continue;
+          };
+          let value = value.strip_prefix(' ').unwrap_or(value);
+          match field {
+            "id" => event.id = Some(value.to_string()),
+            "event" => event.event = value.to_string(),
+            "data" => {
+              event.data = serde_json::from_str(value).unwrap();
+              has_data = true;
+            }
+            _ => {}
+          }
+        }
+        if has_data {
+          return Some(event);
+        }
+      }
+      let chunk = tokio::time::timeout(wait, self.body.data()).await.ok()??;
+      self
+        .buffer
+        .push_str(std::str::from_utf8(&chunk.unwrap()).unwrap());
+    }
+  }
+
+  async fn next(&mut self) -> SseEvent {
+    self
+      .next_within(Duration::from_secs(5))
+      .await
+      .expect("an event within 5 s")
+  }
+
+  /// Whether the response ends within `wait`, once the pending events are
+  /// read.
+  async fn ends_within(&mut self, wait: Duration) -> bool {
+    matches!(
+      tokio::time::timeout(wait, async { while self.body.data().await.is_some() {} }).await,
+      Ok(())
+    )
+  }
+
+  async fn assert_idle(&mut self) {
+    if let Some(event) = self.next_within(Duration::from_millis(300)).await {
+      panic!("unexpected event {event:?}");
+    }
+  }
+}
+
+fn stream_as(token: &str, last_event_id: Option<&str>) -> Request<Body> {
+  let mut req = Request::builder()
+    .uri("/orders/stream")
+    .header("authorization", format!("Bearer {token}"));
+  if let Some(id) = last_event_id {
+    req = req.header("last-event-id", id);
+  }
+  req.body(Body::empty()).unwrap()
+}
+
+fn admin_stream(last_event_id: Option<&str>) -> Request<Body> {
+  stream_as(common::ADMIN_TOKEN, last_event_id)
+}
+
+/// Creates a user and returns its id with a fresh access token.
+async fn customer(app: &Router, name: &str) -> (String, String) {
+  let (_, user) = send(
+    app,
+    post_json(
+      "/users",
+      json!({ "email": format!("{name}@example.com"), "name": name }),
+    ),
+  )
+  .await;
+  let user_id = user["id"].as_str().unwrap().to_string();
+  let (status, token) = send(
+    app,
+    admin(post_json(
+      &format!("/users/{user_id}/access-tokens"),
+      json!({}),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(token["user_id"], user_id.as_str());
+  (user_id, token["token"].as_str().unwrap().to_string())
+}
+
+async fn create_order(app: &Router, user_id: &str) -> String {
+  let (_, order) = send(
+    app,
+    post_json(
+      "/orders",
+      json!({ "user_id": user_id, "status": "pending_payment", "total_cents": 0 }),
+    ),
+  )
+  .await;
+  order["id"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn stream_sends_the_order_events_the_caller_may_see() {
+  let Some((pool, state, _guard)) = setup().await else {
+    return;
+  };
+  state
+    .order_stream
+    .clone()
+    .listen(pool.clone())
+    .await
+    .unwrap();
+  let app = build_app(state);
+
+  let (ana_id, ana_token) = customer(&app, "ana").await;
+  let (ben_id, _) = customer(&app, "ben").await;
+  let (status, _) = send(
+    &app,
+    post_json(&format!("/users/{ana_id}/access-tokens"), json!({})),
+  )
+  .await;
+  assert_eq!(status, StatusCode::UNAUTHORIZED);
+  let (status, _) = send(
+    &app,
+    admin(post_json(
+      &format!("/users/{}/access-tokens", uuid::Uuid::new_v4()),
+      json!({}),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+
+  let unauthenticated = EventStream::open(&app, get("/orders/stream")).await;
+  assert_eq!(unauthenticated.err(), Some(StatusCode::UNAUTHORIZED));
+  let forged = format!("{ben_id}{}", &ana_token[ana_id.len()..]);
+  let forged = EventStream::open(&app, stream_as(&forged, None)).await;
+  assert_eq!(forged.err(), Some(StatusCode::UNAUTHORIZED));
+  // Only customer tokens, and only on the streaming routes, may come in the
+  // query string.
+  let admin_in_query = format!("/orders/stream?access_token={}", common::ADMIN_TOKEN);
+  let admin_in_query = EventStream::open(&app, get(&admin_in_query)).await;
+  assert_eq!(admin_in_query.err(), Some(StatusCode::UNAUTHORIZED));
+  let anas_in_query = format!("/orders/stream?access_token={ana_token}");
+  assert!(EventStream::open(&app, get(&anas_in_query)).await.is_ok());
+  let (_, order) = send(
+    &app,
+    post_json(
+      &format!("/orders?access_token={ana_token}"),
+      json!({ "user_id": ana_id, "status": "pending_payment", "total_cents": 0 }),
+    ),
+  )
+  .await;
+  let (_, trail) = send(
+    &app,
+    admin(get(&format!(
+      "/audit?resource_id={}",
+      order["id"].as_str().unwrap()
+    ))),
+  )
+  .await;
+  assert_eq!(trail["items"][0]["actor"], "anonymous");
+  send(
+    &app,
+    delete(&format!("/orders/{}", order["id"].as_str().unwrap())),
+  )
+  .await;
+
+  let mut everything = EventStream::open(&app, admin_stream(None)).await.unwrap();
+  let mut anas = EventStream::open(&app, stream_as(&ana_token, None))
+    .await
+    .unwrap();
+
+  let bens_order = create_order(&app, &ben_id).await;
+  let anas_order = create_order(&app, &ana_id).await;
+  let order_uri = format!("/orders/{anas_order}");
+  send(&app, put_json(&order_uri, json!({ "status": "paid" }))).await;
+  send(&app, delete(&order_uri)).await;
+
+  let event = everything.next().await;
+  assert_eq!(event.event, "order.created");
+  assert_eq!(event.data["id"], bens_order.as_str());
+  for stream in [&mut everything, &mut anas] {
+    let created = stream.next().await;
+    assert_eq!(created.event, "order.created");
+    assert_eq!(created.data["id"], anas_order.as_str());
+    assert_eq!(created.data["status"], "pending_payment");
+    let updated = stream.next().await;
+    assert_eq!(updated.event, "order.updated");
+    assert_eq!(updated.data["status"], "paid");
+    let deleted = stream.next().await;
+    assert_eq!(deleted.event, "order.deleted");
+    assert_eq!(deleted.data, json!({ "id": anas_order, "user_id": ana_id }));
+    assert_ne!(created.id, updated.id);
+    stream.assert_idle().await;
+  }
+}
+
+#[tokio::test]
+async fn reconnecting_with_last_event_id_replays_what_was_missed() {
+  let Some((pool, state, _guard)) = setup().await else {
+    return;
+  };
+  state
+    .order_stream
+    .clone()
+    .listen(pool.clone())
+    .await
+    .unwrap();
+  let app = build_app(state);
+  let (ana_id, ana_token) = customer(&app, "ana").await;
+  let (ben_id, _) = customer(&app, "ben").await;
+
+  let mut watcher = EventStream::open(&app, admin_stream(None)).await.unwrap();
+  let mut anas = EventStream::open(&app, stream_as(&ana_token, None))
+    .await
+    .unwrap();
+  let order_id = create_order(&app, &ana_id).await;
+  let last_seen = anas.next().await.id.unwrap();
+  drop(anas);
+
+  // Changes made while Ana is disconnected.
+  let order_uri = format!("/orders/{order_id}");
+  send(&app, put_json(&order_uri, json!({ "status": "paid" }))).await;
+  create_order(&app, &ben_id).await;
+  send(&app, put_json(&order_uri, json!({ "status": "shipped" }))).await;
+  for _ in 0..4 {
+    watcher.next().await;
+  }
+
+  let mut anas = EventStream::open(&app, stream_as(&ana_token, Some(&last_seen)))
+    .await
+    .unwrap();
+  let paid = anas.next().await;
+  assert_eq!(paid.event, "order.updated");
+  assert_eq!(paid.data["status"], "paid");
+  let shipped = anas.next().await;
+  assert_eq!(shipped.data["status"], "shipped");
+  anas.assert_idle().await;
+  // Live events follow the replay.
+  send(&app, delete(&order_uri)).await;
+  assert_eq!(anas.next().await.event, "order.deleted");
+
+  // Resuming from the latest event replays nothing.
+  let last = watcher.next().await.id.unwrap();
+  let mut resumed = EventStream::open(&app, admin_stream(Some(&last)))
+    .await
+    .unwrap();
+  resumed.assert_idle().await;
+
+  // An id no longer buffered asks the client to reload.
+  for unknown in ["0", "not-an-id"] {
+    let mut stale = EventStream::open(&app, admin_stream(Some(unknown)))
+      .await
+      .unwrap();
+    let event = stale.next().await;
+    assert_eq!(event.event, "resync");
+    assert_eq!(event.id, None);
+    stale.assert_idle().await;
+  }
+}
+
+#[tokio::test]
+async fn stream_ends_on_shutdown() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let shutdown = state.shutdown.clone();
+  let app = build_app(state);
+
+  let mut events = EventStream::open(&app, admin_stream(None)).await.unwrap();
+  assert!(!events.ends_within(Duration::from_millis(300)).await);
+  shutdown.trigger();
+  assert!(events.ends_within(Duration::from_secs(5)).await);
+}
@@ -0,0 +1,213 @@
+mod common;
+
+use asgard_rust::build_app;
+use asgard_rust::infrastructure::shutdown::Shutdown;
+use axum::http::StatusCode;
+use axum::Router;
+use common::{admin, delete, post_json, put_json, send, setup};
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::{Error, Message};
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Serves `app` on a free port until `shutdown` triggers.
+fn serve(app: Router, shutdown: Shutdown) -> SocketAddr {
+  let server =
+    axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(app.into_make_service());
+  let addr = server.local_addr();
+  tokio::spawn(server.with_graceful_shutdown(async move { shutdown.triggered().await }));
+  addr
+}
+
+async fn connect(
+  addr: SocketAddr,
+  order_id: &str,
+  token: Option<&str>,
+) -> Result<Socket, StatusCode> {
+  let mut req = format!("ws://{addr}/ws/orders/{order_id}")
+    .into_client_request()
+    .unwrap();
+  if let Some(token) = token {
+    req
+      .headers_mut()
+      .insert("authorization", format!("Bearer {token}").parse().unwrap());
+  }
+  match connect_async(req).await {
+    Ok((socket, _)) => Ok(socket),
+    Err(Error::Http(res)) => Err(StatusCode::from_u16(res.status().as_u16()).unwrap()),
+    Err(err) => panic!("{err}"),
+  }
+}
+
+/// The next frame other than pings and pongs.
+async fn next_frame(socket: &mut Socket) -> Message {
+  loop {
+    let frame = tokio::time::timeout(Duration::from_secs(5), socket.next())
+      .await
+      .expect("a frame within 5 s")
+      .expect("an open socket")
+      .unwrap();
+    if !matches!(frame, Message::Ping(_) | Message::Pong(_)) {
+      return frame;
+    }
+  }
+}
+
+async fn next_update(socket: &mut Socket) -> Value {
+  match next_frame(socket).await {
+    Message::Text(text) => serde_json::from_str(&text).unwrap(),
+    other => panic!("expected a text frame, got {other:?}"),
+  }
+}
+
+async fn close_code(socket: &mut Socket) -> CloseCode {
+  match next_frame(socket).await {
+    Message::Close(Some(frame)) => frame.code,
+    other => panic!("expected a close frame, got {other:?}"),
+  }
+}
+
+/// Creates a user with an order and returns the order id and an access token.
+async fn customer_with_order(app: &Router, name: &str) -> (String, String) {
+  let (_, user) = send(
+    app,
+    post_json(
+      "/users",
+      json!({ "email": format!("{name}@example.com"), "name": name }),
+    ),
+  )
+  .await;
+  let user_id = user["id"].as_str().unwrap();
+  let (_, token) = send(
+    app,
+    admin(post_json(
+      &format!("/users/{user_id}/access-tokens"),
+      json!({}),
+    )),
+  )
+  .await;
+  let (_, order) = send(
+    app,
+    post_json(
+      "/orders",
+      json!({ "user_id": user_id, "status": "pending_payment", "total_cents": 0 }),
+    ),
+  )
+  .await;
+  (
+    order["id"].as_str().unwrap().to_string(),
+    token["token"].as_str().unwrap().to_string(),
+  )
+}
+
+#[tokio::test]
+async fn customers_follow_their_order_until_it_is_deleted() {
+  let Some((pool, state, _guard)) = setup().await else {
+    return;
+  };
+  state
+    .order_stream
+    .clone()
+    .listen(pool.clone())
+    .await
+    .unwrap();
+  let shutdown = state.shutdown.clone();
+  let app = build_app(state);
+  let addr = serve(app.clone(), shutdown);
+  let (order_id, token) = customer_with_order(&app, "ana").await;
+  let (other_order, _) = customer_with_order(&app, "ben").await;
+
+  assert_eq!(
+    connect(addr, &order_id, None).await.err(),
+    Some(StatusCode::UNAUTHORIZED)
+  );
+  assert_eq!(
+    connect(addr, &other_order, Some(&token)).await.err(),
+    Some(StatusCode::NOT_FOUND)
+  );
+  assert_eq!(
+    connect(addr, &uuid::Uuid::new_v4().to_string(), Some(&token))
+      .await
+      .err(),
+    Some(StatusCode::NOT_FOUND)
+  );
+  // Browsers pass the token in the query string.
+  let (mut socket, _) = connect_async(format!(
+    "ws://{addr}/ws/orders/{order_id}?access_token={token}"
+  ))
+  .await
+  .unwrap();
+
+  let snapshot = next_update(&mut socket).await;
+  assert_eq!(snapshot["type"], "snapshot");
+  assert_eq!(snapshot["order"]["id"], order_id.as_str());
+  assert_eq!(snapshot["order"]["status"], "pending_payment");
+
+  let order_uri = format!("/orders/{order_id}");
+  send(
+    &app,
+    put_json(
+      &format!("/orders/{other_order}"),
+      json!({ "status": "paid" }),
+    ),
+  )
+  .await;
+  send(&app, put_json(&order_uri, json!({ "total_cents": 500 }))).await;
+  send(&app, put_json(&order_uri, json!({ "status": "paid" }))).await;
+  let changed = next_update(&mut socket).await;
+  assert_eq!(changed["type"], "status_changed");
+  assert_eq!(changed["from"], "pending_payment");
+  assert_eq!(changed["status"], "paid");
+  assert_eq!(changed["order"]["total_cents"], 500);
+
+  send(&app, delete(&order_uri)).await;
+  let deleted = next_update(&mut socket).await;
+  assert_eq!(deleted, json!({ "type": "deleted", "order_id": order_id }));
+  assert_eq!(close_code(&mut socket).await, CloseCode::Normal);
+}
+
+#[tokio::test]
+async fn sockets_get_heartbeats_and_close_on_shutdown() {
+  let Some((pool, state, _guard)) = setup().await else {
+    return;
+  };
+  state
+    .order_stream
+    .clone()
+    .listen(pool.clone())
+    .await
+    .unwrap();
+  let shutdown = state.shutdown.clone();
+  let app = build_app(state);
+  let addr = serve(app.clone(), shutdown.clone());
+  let (order_id, _) = customer_with_order(&app, "ana").await;
+
+  let mut socket = connect(addr, &order_id, Some(common::ADMIN_TOKEN))
+    .await
+    .unwrap();
+  assert_eq!(next_update(&mut socket).await["type"], "snapshot");
+  // Heartbeats every second (the test interval); the client answers pings.
+  let ping = tokio::time::timeout(Duration::from_secs(3), socket.next())
+    .await
+    .unwrap()
+    .unwrap()
+    .unwrap();
+  assert!(matches!(ping, Message::Ping(_)));
+
+  shutdown.trigger();
+  assert_eq!(close_code(&mut socket).await, CloseCode::Away);
+  tokio::time::timeout(Duration::from_secs(5), shutdown.drained())
+    .await
+    .expect("connections drained");
+  // The server is gone.
+  assert!(connect_async(format!("ws://{addr}/ws/orders/{order_id}"))
+    .await
+    .is_err());
+}
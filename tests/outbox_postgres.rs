@@ -0,0 +1,265 @@
+mod common;
+
+use asgard_rust::adapters::db::outbox_repo::PgOutboxRepository;
+use asgard_rust::application::ports::{EventPublisher, RepoError};
+use asgard_rust::build_app;
+use asgard_rust::domain::models::OutboxEvent;
+use asgard_rust::infrastructure::outbox::OutboxDispatcher;
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use common::{delete, post_json, put_json, send, setup};
+use serde_json::{json, Value};
+use sqlx::{PgPool, Row};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// Records every delivery and fails the first `failures` of them.
+#[derive(Default)]
+struct Recorder {
+  failures: AtomicUsize,
+  seen: Mutex<Vec<Uuid>>,
+}
+
+impl Recorder {
+  fn failing(failures: usize) -> Arc<Self> {
+    Arc::new(Self {
+      failures: AtomicUsize::new(failures),
+      ..Self::default()
+    })
+  }
+
+  fn seen(&self) -> Vec<Uuid> {
+    self.seen.lock().unwrap().clone()
+  }
+}
+
+#[async_trait]
+impl EventPublisher for Recorder {
+  async fn publish(&self, event: &OutboxEvent) -> Result<(), RepoError> {
+    self.seen.lock().unwrap().push(event.event_id);
+    let left = self.failures.load(Ordering::SeqCst);
+    if left > 0 {
+      self.failures.store(left - 1, Ordering::SeqCst);
+      return Err(RepoError::Unexpected("broker unavailable".into()));
+    }
+    Ok(())
+  }
+}
+
+async fn events(pool: &PgPool) -> Vec<(String, Value)> {
+  sqlx::query("SELECT event_type, payload FROM outbox ORDER BY id")
+    .fetch_all(pool)
+    .await
+    .unwrap()
+    .iter()
+    .map(|row| (row.get("event_type"), row.get("payload")))
+    .collect()
+}
+
+#[tokio::test]
+async fn state_changes_write_events_in_the_same_transaction() {
+  let Some((pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+
+  let (_, user) = send(
+    &app,
+    post_json(
+      "/users",
+      json!({ "email": "ana@example.com", "name": "Ana" }),
+    ),
+  )
+  .await;
+  let user_id = user["id"].as_str().unwrap();
+  // A rolled-back change leaves no event behind.
+  let (status, _) = send(
+    &app,
+    post_json(
+      "/users",
+      json!({ "email": "ana@example.com", "name": "Copy" }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CONFLICT);
+  send(
+    &app,
+    put_json(&format!("/users/{user_id}"), json!({ "name": "Ana B." })),
+  )
+  .await;
+
+  let (_, product) = send(
+    &app,
+    post_json(
+      "/products",
+      json!({ "sku": "lamp", "name": "Lamp", "price_cents": 1500 }),
+    ),
+  )
+  .await;
+  let product_id = product["id"].as_str().unwrap();
+  send(
+    &app,
+    put_json(
+      &format!("/products/{product_id}"),
+      json!({ "price_cents": 1200 }),
+    ),
+  )
+  .await;
+
+  let (_, order) = send(
+    &app,
+    post_json(
+      "/orders",
+      json!({ "user_id": user_id, "status": "pending_payment", "total_cents": 0 }),
+    ),
+  )
+  .await;
+  let order_uri = format!("/orders/{}", order["id"].as_str().unwrap());
+  send(
+    &app,
+    post_json(
+      &format!("{order_uri}/items"),
+      json!({ "product_id": product_id, "quantity": 2 }),
+    ),
+  )
+  .await;
+  send(&app, put_json(&order_uri, json!({ "status": "paid" }))).await;
+  send(&app, delete(&order_uri)).await;
+  send(&app, delete(&format!("/products/{product_id}"))).await;
+  send(&app, delete(&format!("/users/{user_id}"))).await;
+
+  let events = events(&pool).await;
+  let types: Vec<_> = events.iter().map(|(t, _)| t.as_str()).collect();
+  assert_eq!(
+    types,
+    [
+      "UserCreated",
+      "UserUpdated",
+      "ProductCreated",
+      "ProductUpdated",
+      "OrderCreated",
+      "OrderUpdated",
+      "OrderStatusChanged",
+      "OrderDeleted",
+      "ProductDeleted",
+      "UserDeleted",
+    ]
+  );
+  assert_eq!(events[1].1["user"]["name"], "Ana B.");
+  assert_eq!(events[3].1["product"]["price_cents"], 1200);
+  assert_eq!(events[5].1["order"]["total_cents"], 2400);
+  assert_eq!(events[6].1["from"], "pending_payment");
+  assert_eq!(events[6].1["order"]["status"], "paid");
+  assert_eq!(events[7].1["user_id"], user_id);
+}
+
+#[tokio::test]
+async fn failed_events_are_retried_with_backoff_until_the_limit() {
+  let Some((pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  send(
+    &app,
+    post_json(
+      "/users",
+      json!({ "email": "ana@example.com", "name": "Ana" }),
+    ),
+  )
+  .await;
+
+  let recorder = Recorder::failing(1);
+  let dispatcher =
+    OutboxDispatcher::new(PgOutboxRepository::new(pool.clone()), recorder.clone(), 3);
+  assert_eq!(dispatcher.run_once().await.unwrap(), 1);
+  let row = sqlx::query(
+    "SELECT attempts, last_error, published_at IS NULL AS pending, next_attempt_at > now() AS backing_off FROM outbox",
+  )
+  .fetch_one(&pool)
+  .await
+  .unwrap();
+  assert_eq!(row.get::<i32, _>("attempts"), 1);
+  assert_eq!(
+    row.get::<Option<String>, _>("last_error").as_deref(),
+    Some("unexpected repository error: broker unavailable")
+  );
+  assert!(row.get::<bool, _>("pending"));
+  assert!(row.get::<bool, _>("backing_off"));
+  // Not due yet.
+  assert_eq!(dispatcher.run_once().await.unwrap(), 0);
+
+  sqlx::query("UPDATE outbox SET next_attempt_at = now()")
+    .execute(&pool)
+    .await
+    .unwrap();
+  assert_eq!(dispatcher.run_once().await.unwrap(), 1);
+  let seen = recorder.seen();
+  assert_eq!(seen.len(), 2);
+  assert_eq!(seen[0], seen[1]);
+  let published: bool = sqlx::query_scalar("SELECT published_at IS NOT NULL FROM outbox")
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+  assert!(published);
+  assert_eq!(dispatcher.run_once().await.unwrap(), 0);
+
+  // An event that keeps failing is parked after the last attempt.
+  send(
+    &app,
+    post_json(
+      "/users",
+      json!({ "email": "ben@example.com", "name": "Ben" }),
+    ),
+  )
+  .await;
+  let dispatcher = OutboxDispatcher::new(
+    PgOutboxRepository::new(pool.clone()),
+    Recorder::failing(usize::MAX),
+    2,
+  );
+  for _ in 0..2 {
+    assert_eq!(dispatcher.run_once().await.unwrap(), 1);
+    sqlx::query("UPDATE outbox SET next_attempt_at = now() WHERE published_at IS NULL")
+      .execute(&pool)
+      .await
+      .unwrap();
+  }
+  assert_eq!(dispatcher.run_once().await.unwrap(), 0);
+  let failed: i64 =
+    sqlx::query_scalar("SELECT COUNT(*) FROM outbox WHERE failed_at IS NOT NULL AND attempts = 2")
+      .fetch_one(&pool)
+      .await
+      .unwrap();
+  assert_eq!(failed, 1);
+}
+
+#[tokio::test]
+async fn concurrent_dispatchers_publish_each_event_once() {
+  let Some((pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  for n in 0..20 {
+    send(
+      &app,
+      post_json(
+        "/users",
+        json!({ "email": format!("user{n}@example.com"), "name": "User" }),
+      ),
+    )
+    .await;
+  }
+
+  let recorder = Recorder::failing(0);
+  let first = OutboxDispatcher::new(PgOutboxRepository::new(pool.clone()), recorder.clone(), 3);
+  let second = OutboxDispatcher::new(PgOutboxRepository::new(pool.clone()), recorder.clone(), 3);
+  let (a, b) = tokio::join!(first.run_once(), second.run_once());
+  assert_eq!(a.unwrap() + b.unwrap(), 20);
+
+  let mut seen = recorder.seen();
+  seen.sort();
+  seen.dedup();
+  assert_eq!(seen.len(), 20);
+  assert_eq!(recorder.seen().len(), 20);
+}
@@ -0,0 +1,193 @@
+mod common;
+
+use asgard_rust::build_app;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use common::{
+  admin, get, json_id, post_json, send, setup, PAYMENT_PROVIDER, PAYMENT_WEBHOOK_SECRET,
+};
+use hmac::{Hmac, Mac};
+use hyper::body::to_bytes;
+use serde_json::json;
+use sha2::Sha256;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+/// Local stand-in for a payment provider: signs payloads the way a real
+/// provider would before posting them to our webhook endpoint.
+struct StandInProvider {
+  name: &'static str,
+  secret: &'static str,
+}
+
+impl StandInProvider {
+  fn acme() -> Self {
+    Self {
+      name: PAYMENT_PROVIDER,
+      secret: PAYMENT_WEBHOOK_SECRET,
+    }
+  }
+
+  fn event(&self, event_id: &str, event_type: &str, payment_id: &str) -> Request<Body> {
+    self.event_at(
+      event_id,
+      event_type,
+      payment_id,
+      chrono::Utc::now().timestamp(),
+    )
+  }
+
+  fn event_at(
+    &self,
+    event_id: &str,
+    event_type: &str,
+    payment_id: &str,
+    timestamp: i64,
+  ) -> Request<Body> {
+    let body = json!({ "id": event_id, "type": event_type, "payment_id": payment_id }).to_string();
+    let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes()).unwrap();
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+    let signature = hex::encode(mac.finalize().into_bytes());
+
+    Request::builder()
+      .method("POST")
+      .uri(format!("/webhooks/payments/{}", self.name))
+      .header("content-type", "application/json")
+      .header(
+        "x-webhook-signature",
+        format!("t={timestamp},v1={signature}"),
+      )
+      .body(Body::from(body))
+      .unwrap()
+  }
+}
+
+/// Creates a user, an order awaiting payment and a pending payment for it.
+async fn pending_payment(app: &Router, provider_payment_id: &str) -> Uuid {
+  let res = app
+    .clone()
+    .oneshot(post_json(
+      "/users",
+      json!({ "email": "payer@example.com", "name": "Payer" }),
+    ))
+    .await
+    .unwrap();
+  let user_id = json_id(&to_bytes(res.into_body()).await.unwrap());
+
+  let res = app
+    .clone()
+    .oneshot(post_json(
+      "/orders",
+      json!({ "user_id": user_id, "status": "pending_payment", "total_cents": 4200 }),
+    ))
+    .await
+    .unwrap();
+  let order_id = json_id(&to_bytes(res.into_body()).await.unwrap());
+
+  let payment = json!({
+    "provider": PAYMENT_PROVIDER,
+    "provider_payment_id": provider_payment_id,
+    "amount_cents": 4200
+  });
+  let payments_uri = format!("/orders/{order_id}/payments");
+  let (status, _) = send(app, post_json(&payments_uri, payment.clone())).await;
+  assert_eq!(status, StatusCode::UNAUTHORIZED);
+  let (status, _) = send(app, admin(post_json(&payments_uri, payment))).await;
+  assert_eq!(status, StatusCode::CREATED);
+  order_id
+}
+
+#[tokio::test]
+async fn succeeded_event_pays_order_exactly_once() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let provider = StandInProvider::acme();
+  let order_id = pending_payment(&app, "pi_1").await;
+
+  let (status, body) = send(&app, provider.event("evt_1", "payment.succeeded", "pi_1")).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(body["status"], "applied");
+
+  let (_, order) = send(&app, get(&format!("/orders/{order_id}"))).await;
+  assert_eq!(order["status"], "paid");
+  let (_, payments) = send(&app, get(&format!("/orders/{order_id}/payments"))).await;
+  assert_eq!(payments[0]["status"], "succeeded");
+
+  let (status, body) = send(&app, provider.event("evt_1", "payment.succeeded", "pi_1")).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(body["status"], "duplicate");
+
+  // A late failure notification must not undo a successful payment.
+  let (status, body) = send(&app, provider.event("evt_2", "payment.failed", "pi_1")).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(body["status"], "ignored");
+  let (_, order) = send(&app, get(&format!("/orders/{order_id}"))).await;
+  assert_eq!(order["status"], "paid");
+}
+
+#[tokio::test]
+async fn failed_payment_can_be_retried_successfully() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let provider = StandInProvider::acme();
+  let order_id = pending_payment(&app, "pi_2").await;
+
+  let (_, body) = send(&app, provider.event("evt_10", "payment.failed", "pi_2")).await;
+  assert_eq!(body["status"], "applied");
+  let (_, order) = send(&app, get(&format!("/orders/{order_id}"))).await;
+  assert_eq!(order["status"], "payment_failed");
+
+  let (_, body) = send(&app, provider.event("evt_11", "payment.succeeded", "pi_2")).await;
+  assert_eq!(body["status"], "applied");
+  let (_, order) = send(&app, get(&format!("/orders/{order_id}"))).await;
+  assert_eq!(order["status"], "paid");
+}
+
+#[tokio::test]
+async fn rejects_unverified_or_unmatched_events() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let provider = StandInProvider::acme();
+  let order_id = pending_payment(&app, "pi_3").await;
+
+  let forged = StandInProvider {
+    name: PAYMENT_PROVIDER,
+    secret: "not-the-secret",
+  };
+  let (status, _) = send(&app, forged.event("evt_20", "payment.succeeded", "pi_3")).await;
+  assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+  let stale = chrono::Utc::now().timestamp() - 3600;
+  let (status, _) = send(
+    &app,
+    provider.event_at("evt_21", "payment.succeeded", "pi_3", stale),
+  )
+  .await;
+  assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+  let unknown = StandInProvider {
+    name: "nobody",
+    secret: PAYMENT_WEBHOOK_SECRET,
+  };
+  let (status, _) = send(&app, unknown.event("evt_22", "payment.succeeded", "pi_3")).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+
+  let mut unsigned = provider.event("evt_23", "payment.succeeded", "pi_3");
+  unsigned.headers_mut().remove("x-webhook-signature");
+  let (status, _) = send(&app, unsigned).await;
+  assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+  // Unknown payments are not recorded, so the provider's retry can still apply.
+  let (status, _) = send(&app, provider.event("evt_24", "payment.succeeded", "pi_x")).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+
+  let (_, order) = send(&app, get(&format!("/orders/{order_id}"))).await;
+  assert_eq!(order["status"], "pending_payment");
+}
@@ -0,0 +1,185 @@
+use asgard_rust::adapters::pdf::{
+  render_invoice, render_packing_slip, PackingSlip, PackingSlipLine,
+};
+use asgard_rust::domain::models::{BillingAddress, Invoice, InvoiceLine};
+use chrono::{TimeZone, Utc};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Pulls the visible text out of a rendered PDF, one output line per
+/// baseline, so snapshots stay readable and ignore layout coordinates.
+fn extract_text(pdf: &[u8]) -> String {
+  let pdf = std::str::from_utf8(pdf).expect("renderer only emits ASCII");
+  let mut out = String::new();
+  for (page, stream) in pdf.split(">>\nstream\n").skip(1).enumerate() {
+    let content = stream.split("endstream").next().unwrap();
+    out.push_str(&format!("--- page {} ---\n", page + 1));
+    let mut current: Option<(String, Vec<String>)> = None;
+    for op in content.lines().filter(|l| l.ends_with("Tj ET")) {
+      let before_text = &op[..op.find(" Td (").unwrap()];
+      let y = before_text.rsplit(' ').next().unwrap().to_string();
+      let literal = &op[op.find(" Td (").unwrap() + 5..op.rfind(") Tj").unwrap()];
+      let text = decode_literal(literal);
+      match &mut current {
+        Some((line_y, cells)) if *line_y == y => cells.push(text),
+        _ => {
+          if let Some((_, cells)) = current.take() {
+            out.push_str(&cells.join(" | "));
+            out.push('\n');
+          }
+          current = Some((y, vec![text]));
+        }
+      }
+    }
+    if let Some((_, cells)) = current {
+      out.push_str(&cells.join(" | "));
+      out.push('\n');
+    }
+  }
+  out
+}
+
+/// Decodes a PDF literal string written with WinAnsi octal escapes.
+fn decode_literal(literal: &str) -> String {
+  let mut out = String::new();
+  let mut chars = literal.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      out.push(c);
+      continue;
+    }
+    let next = chars.next().unwrap();
+    if next.is_ascii_digit() {
+      let octal: String = std::iter::once(next)
+        .chain(chars.by_ref().take(2))
+        .collect();
+      let byte = u8::from_str_radix(&octal, 8).unwrap();
+      out.push(if byte == 0x80 { '€' } else { byte as char });
+    } else {
+      out.push(next);
+    }
+  }
+  out
+}
+
+/// Compares against `tests/snapshots/<name>.txt`; run with
+/// `UPDATE_SNAPSHOTS=1` to rewrite the file after an intended change.
+fn assert_snapshot(name: &str, actual: &str) {
+  let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+    .join("tests/snapshots")
+    .join(format!("{name}.txt"));
+  if std::env::var("UPDATE_SNAPSHOTS").is_ok() {
+    std::fs::write(&path, actual).unwrap();
+    return;
+  }
+  let expected = std::fs::read_to_string(&path)
+    .unwrap_or_else(|_| panic!("missing snapshot {}", path.display()));
+  assert_eq!(actual, expected, "snapshot {name} differs");
+}
+
+fn invoice(kind: &str, number: &str, lines: Vec<InvoiceLine>) -> Invoice {
+  let net_cents = lines.iter().map(|l| l.net_cents).sum();
+  let tax_cents = lines.iter().map(|l| l.tax_cents).sum();
+  let total_cents = lines.iter().map(|l| l.total_cents).sum();
+  Invoice {
+    id: Uuid::from_u128(1),
+    order_id: Uuid::from_u128(2),
+    kind: kind.to_string(),
+    number: number.to_string(),
+    issued_at: Utc.with_ymd_and_hms(2026, 3, 14, 9, 30, 0).unwrap(),
+    billing_address: BillingAddress {
+      name: "Ada Lovelace".to_string(),
+      line1: "12 St James's Square".to_string(),
+      line2: Some("Flat (B)".to_string()),
+      city: "Málaga".to_string(),
+      postal_code: "29001".to_string(),
+      country: "ES".to_string(),
+    },
+    lines,
+    tax_rate_bps: 2100,
+    net_cents,
+    tax_cents,
+    total_cents,
+    credited_invoice_id: None,
+    refund_id: None,
+  }
+}
+
+fn line(
+  description: &str,
+  quantity: i32,
+  unit_price_cents: i64,
+  net: i64,
+  tax: i64,
+) -> InvoiceLine {
+  InvoiceLine {
+    description: description.to_string(),
+    product_id: None,
+    quantity,
+    unit_price_cents,
+    net_cents: net,
+    tax_cents: tax,
+    total_cents: net + tax,
+  }
+}
+
+#[test]
+fn invoice_text_matches_snapshot() {
+  let invoice = invoice(
+    "invoice",
+    "INV-2026-000042",
+    vec![
+      line("Shirt", 2, 1500, 2479, 521),
+      line("Jacket", 1, 4000, 3306, 694),
+    ],
+  );
+  assert_snapshot("invoice", &extract_text(&render_invoice(&invoice)));
+}
+
+#[test]
+fn credit_note_text_matches_snapshot() {
+  let note = invoice(
+    "credit_note",
+    "CN-2026-000007",
+    vec![line("Jacket", 1, 4000, 3306, 694)],
+  );
+  assert_snapshot("credit_note", &extract_text(&render_invoice(&note)));
+}
+
+#[test]
+fn packing_slip_text_matches_snapshot() {
+  let slip = PackingSlip {
+    order_id: Uuid::from_u128(2),
+    ordered_at: Utc.with_ymd_and_hms(2026, 3, 13, 18, 0, 0).unwrap(),
+    customer_name: "Ada Lovelace".to_string(),
+    customer_email: "ada@example.com".to_string(),
+    lines: vec![
+      PackingSlipLine {
+        sku: "shirt-m".to_string(),
+        name: "Shirt".to_string(),
+        quantity: 2,
+      },
+      PackingSlipLine {
+        sku: "jacket-l".to_string(),
+        name: "Jacket".to_string(),
+        quantity: 1,
+      },
+    ],
+  };
+  assert_snapshot("packing_slip", &extract_text(&render_packing_slip(&slip)));
+}
+
+#[test]
+fn long_documents_flow_onto_new_pages() {
+  let lines = (0..80)
+    .map(|i| line(&format!("Item {i}"), 1, 100, 83, 17))
+    .collect();
+  let text = extract_text(&render_invoice(&invoice(
+    "invoice",
+    "INV-2026-000043",
+    lines,
+  )));
+  assert!(text.contains("--- page 2 ---"));
+  assert!(text.contains("Item 79"));
+  assert!(text.ends_with("Total: 80.00\n"));
+}
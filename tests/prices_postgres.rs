@@ -0,0 +1,189 @@
+mod common;
+
+use asgard_rust::build_app;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use common::{admin, delete, get, post_json, put_json, send, setup};
+use serde_json::{json, Value};
+
+async fn product(app: &Router, price_cents: i64) -> String {
+  let (status, product) = send(
+    app,
+    post_json(
+      "/products",
+      json!({ "sku": "lamp", "name": "Lamp", "price_cents": price_cents }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+  product["id"].as_str().unwrap().to_string()
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+  at.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn schedule(id: &str, price_cents: i64, valid_from: DateTime<Utc>) -> Request<Body> {
+  admin(post_json(
+    &format!("/products/{id}/prices"),
+    json!({ "price_cents": price_cents, "valid_from": timestamp(valid_from) }),
+  ))
+}
+
+fn at(value: &Value) -> DateTime<Utc> {
+  value.as_str().unwrap().parse().unwrap()
+}
+
+#[tokio::test]
+async fn price_changes_are_kept_as_contiguous_history() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let id = product(&app, 1000).await;
+  let prices_uri = format!("/products/{id}/prices");
+
+  let (status, history) = send(&app, get(&prices_uri)).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(history.as_array().unwrap().len(), 1);
+  assert_eq!(history[0]["price_cents"], 1000);
+  assert_eq!(history[0]["valid_to"], Value::Null);
+
+  let (_, updated) = send(
+    &app,
+    put_json(&format!("/products/{id}"), json!({ "price_cents": 1200 })),
+  )
+  .await;
+  assert_eq!(updated["price_cents"], 1200);
+
+  let tomorrow = Utc::now() + Duration::days(1);
+  let (status, scheduled) = send(&app, schedule(&id, 900, tomorrow)).await;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(scheduled["valid_to"], Value::Null);
+
+  let (_, history) = send(&app, get(&prices_uri)).await;
+  let cents: Vec<i64> = history
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|p| p["price_cents"].as_i64().unwrap())
+    .collect();
+  assert_eq!(cents, [1000, 1200, 900]);
+  assert_eq!(history[0]["valid_to"], history[1]["valid_from"]);
+  assert_eq!(history[1]["valid_to"], history[2]["valid_from"]);
+
+  // The product still shows the current price until the change is due.
+  let (_, details) = send(&app, get(&format!("/products/{id}"))).await;
+  assert_eq!(details["price_cents"], 1200);
+
+  let price_at = |when: DateTime<Utc>| {
+    get(&format!(
+      "/products/{id}/price?at={}",
+      timestamp(when).replace('+', "%2B")
+    ))
+  };
+  let (_, current) = send(&app, get(&format!("/products/{id}/price"))).await;
+  assert_eq!(current["price_cents"], 1200);
+  let (_, future) = send(&app, price_at(tomorrow + Duration::hours(1))).await;
+  assert_eq!(future["price_cents"], 900);
+  let (_, original) = send(&app, price_at(at(&history[0]["valid_from"]))).await;
+  assert_eq!(original["price_cents"], 1000);
+  let (status, _) = send(&app, price_at(Utc::now() - Duration::days(1))).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+
+  // An immediate change only lasts until the scheduled one.
+  send(
+    &app,
+    put_json(&format!("/products/{id}"), json!({ "price_cents": 1300 })),
+  )
+  .await;
+  let (_, history) = send(&app, get(&prices_uri)).await;
+  assert_eq!(history[2]["price_cents"], 1300);
+  assert_eq!(at(&history[2]["valid_to"]), at(&history[3]["valid_from"]));
+  assert_eq!(history[3]["price_cents"], 900);
+}
+
+#[tokio::test]
+async fn scheduled_prices_take_effect_and_can_be_cancelled() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let id = product(&app, 1000).await;
+  let prices_uri = format!("/products/{id}/prices");
+
+  let (status, _) = send(
+    &app,
+    post_json(
+      &prices_uri,
+      json!({ "price_cents": 1, "valid_from": timestamp(Utc::now() + Duration::days(1)) }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::UNAUTHORIZED);
+  let (status, _) = send(&app, schedule(&id, 500, Utc::now() - Duration::hours(1))).await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+  let (status, _) = send(&app, schedule(&id, -1, Utc::now() + Duration::days(1))).await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+
+  // Cancelling hands the range back to the previous price.
+  let (_, soon) = send(&app, schedule(&id, 700, Utc::now() + Duration::days(1))).await;
+  let (_, later) = send(&app, schedule(&id, 600, Utc::now() + Duration::days(2))).await;
+  let (status, _) = send(
+    &app,
+    admin(delete(&format!(
+      "{prices_uri}/{}",
+      soon["id"].as_str().unwrap()
+    ))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::NO_CONTENT);
+  let (_, history) = send(&app, get(&prices_uri)).await;
+  assert_eq!(history.as_array().unwrap().len(), 2);
+  assert_eq!(at(&history[0]["valid_to"]), at(&later["valid_from"]));
+
+  let (status, _) = send(
+    &app,
+    admin(delete(&format!(
+      "{prices_uri}/{}",
+      history[0]["id"].as_str().unwrap()
+    ))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CONFLICT);
+
+  // Once due, the scheduled price applies to the product and new orders.
+  send(
+    &app,
+    schedule(&id, 800, Utc::now() + Duration::milliseconds(500)),
+  )
+  .await;
+  tokio::time::sleep(std::time::Duration::from_millis(700)).await;
+  let (_, details) = send(&app, get(&format!("/products/{id}"))).await;
+  assert_eq!(details["price_cents"], 800);
+
+  let (_, user) = send(
+    &app,
+    post_json("/users", json!({ "email": "p@example.com", "name": "P" })),
+  )
+  .await;
+  let (_, order) = send(
+    &app,
+    post_json(
+      "/orders",
+      json!({ "user_id": user["id"], "status": "pending_payment", "total_cents": 0 }),
+    ),
+  )
+  .await;
+  let (_, item) = send(
+    &app,
+    post_json(
+      &format!("/orders/{}/items", order["id"].as_str().unwrap()),
+      json!({ "product_id": id, "quantity": 2 }),
+    ),
+  )
+  .await;
+  assert_eq!(item["unit_price_cents"], 800);
+}
@@ -0,0 +1,202 @@
+mod common;
+
+use asgard_rust::build_app;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use common::{admin, delete, get, post_json, put_json, send, setup, MEDIA_MAX_UPLOAD_BYTES};
+use hyper::body::to_bytes;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR fake png";
+const JPEG: &[u8] = b"\xFF\xD8\xFF\xE0 fake jpeg";
+const BOUNDARY: &str = "asgard-test-boundary";
+
+/// A `multipart/form-data` upload with one file field.
+fn upload(product_id: &str, field: &str, content_type: &str, bytes: &[u8]) -> Request<Body> {
+  let mut body = format!(
+    "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"{field}\"; filename=\"f\"\r\n\
+     Content-Type: {content_type}\r\n\r\n"
+  )
+  .into_bytes();
+  body.extend_from_slice(bytes);
+  body.extend_from_slice(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+  admin(
+    Request::builder()
+      .method("POST")
+      .uri(format!("/products/{product_id}/images"))
+      .header(
+        "content-type",
+        format!("multipart/form-data; boundary={BOUNDARY}"),
+      )
+      .body(Body::from(body))
+      .unwrap(),
+  )
+}
+
+async fn product(app: &Router) -> String {
+  let (_, product) = send(
+    app,
+    post_json(
+      "/products",
+      json!({ "sku": "poster", "name": "Poster", "price_cents": 500 }),
+    ),
+  )
+  .await;
+  product["id"].as_str().unwrap().to_string()
+}
+
+/// Fetches a signed media URL and returns the status, content type and body.
+async fn download(app: &Router, url: &str) -> (StatusCode, String, Vec<u8>) {
+  let res = app.clone().oneshot(get(url)).await.unwrap();
+  let status = res.status();
+  let content_type = res
+    .headers()
+    .get("content-type")
+    .map(|v| v.to_str().unwrap().to_string())
+    .unwrap_or_default();
+  let body = to_bytes(res.into_body()).await.unwrap().to_vec();
+  (status, content_type, body)
+}
+
+fn ids(images: &Value) -> Vec<&str> {
+  images
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|image| image["id"].as_str().unwrap())
+    .collect()
+}
+
+#[tokio::test]
+async fn uploads_are_ordered_and_served_through_signed_urls() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let id = product(&app).await;
+
+  let (status, first) = send(&app, upload(&id, "file", "image/png", PNG)).await;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(first["content_type"], "image/png");
+  assert_eq!(first["byte_size"], PNG.len());
+  assert_eq!(first["position"], 0);
+  assert!(first.get("blob_key").is_none());
+  let (_, second) = send(&app, upload(&id, "file", "image/jpeg", JPEG)).await;
+  assert_eq!(second["position"], 1);
+
+  let (status, content_type, body) = download(&app, first["url"].as_str().unwrap()).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(content_type, "image/png");
+  assert_eq!(body, PNG);
+
+  // Tampering with the URL invalidates it.
+  let url = second["url"].as_str().unwrap();
+  let (status, _, _) = download(&app, &url.replace("expires=", "expires=9")).await;
+  assert_eq!(status, StatusCode::FORBIDDEN);
+  let (status, _, _) = download(
+    &app,
+    &url.replace(
+      second["id"].as_str().unwrap(),
+      first["id"].as_str().unwrap(),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::FORBIDDEN);
+
+  let images_uri = format!("/products/{id}/images");
+  let (_, images) = send(&app, get(&images_uri)).await;
+  let (first_id, second_id) = (
+    first["id"].as_str().unwrap(),
+    second["id"].as_str().unwrap(),
+  );
+  assert_eq!(ids(&images), [first_id, second_id]);
+
+  let (status, reordered) = send(
+    &app,
+    admin(put_json(
+      &format!("{images_uri}/order"),
+      json!({ "image_ids": [second_id, first_id] }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(ids(&reordered), [second_id, first_id]);
+  assert_eq!(reordered[0]["position"], 0);
+  for image_ids in [
+    json!([second_id]),
+    json!([second_id, second_id]),
+    json!([second_id, first_id, uuid::Uuid::new_v4()]),
+  ] {
+    let (status, _) = send(
+      &app,
+      admin(put_json(
+        &format!("{images_uri}/order"),
+        json!({ "image_ids": image_ids }),
+      )),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{image_ids}");
+  }
+
+  // Deleting removes the stored file; old URLs stop resolving.
+  let (status, _) = send(&app, admin(delete(&format!("{images_uri}/{first_id}")))).await;
+  assert_eq!(status, StatusCode::NO_CONTENT);
+  let (status, _, _) = download(&app, first["url"].as_str().unwrap()).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+  let (_, images) = send(&app, get(&images_uri)).await;
+  assert_eq!(ids(&images), [second_id]);
+}
+
+#[tokio::test]
+async fn uploads_are_validated() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let id = product(&app).await;
+
+  let unauthenticated = {
+    let mut req = upload(&id, "file", "image/png", PNG);
+    req.headers_mut().remove("authorization");
+    req
+  };
+  let (status, _) = send(&app, unauthenticated).await;
+  assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+  let too_large = [PNG, &vec![0; MEDIA_MAX_UPLOAD_BYTES]].concat();
+  for (req, expected) in [
+    (
+      upload(&id, "file", "image/svg+xml", b"<svg/>"),
+      StatusCode::BAD_REQUEST,
+    ),
+    // The declared type must match the file contents.
+    (
+      upload(&id, "file", "image/jpeg", PNG),
+      StatusCode::BAD_REQUEST,
+    ),
+    (
+      upload(&id, "file", "image/png", b""),
+      StatusCode::BAD_REQUEST,
+    ),
+    (
+      upload(&id, "picture", "image/png", PNG),
+      StatusCode::BAD_REQUEST,
+    ),
+    (
+      upload(&id, "file", "image/png", &too_large),
+      StatusCode::PAYLOAD_TOO_LARGE,
+    ),
+    (
+      upload(&uuid::Uuid::new_v4().to_string(), "file", "image/png", PNG),
+      StatusCode::NOT_FOUND,
+    ),
+  ] {
+    let (status, body) = send(&app, req).await;
+    assert_eq!(status, expected, "{body}");
+  }
+
+  let (_, images) = send(&app, get(&format!("/products/{id}/images"))).await;
+  assert_eq!(images, json!([]));
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use asgard_rust::build_app;
+use axum::http::StatusCode;
+use axum::Router;
+use common::{get, post_json, send, setup};
+use serde_json::{json, Value};
+
+async fn product(app: &Router, sku: &str, name: &str, description: &str) {
+  let (status, _) = send(
+    app,
+    post_json(
+      "/products",
+      json!({ "sku": sku, "name": name, "description": description, "price_cents": 1000 }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+}
+
+async fn seed(app: &Router) {
+  product(
+    app,
+    "rain-jacket",
+    "Blue rain jacket",
+    "Waterproof shell with taped seams.",
+  )
+  .await;
+  product(
+    app,
+    "oxford",
+    "Oxford shirt",
+    "Cotton shirt that pairs well with jackets.",
+  )
+  .await;
+  product(app, "peeler", "Potato peeler", "Stainless steel.").await;
+  product(
+    app,
+    "bold",
+    "<b>Bold</b> jacket",
+    "Stands out & keeps you warm.",
+  )
+  .await;
+}
+
+fn skus(page: &Value) -> Vec<&str> {
+  page["items"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|hit| hit["sku"].as_str().unwrap())
+    .collect()
+}
+
+#[tokio::test]
+async fn search_ranks_prefix_matches_and_highlights() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  seed(&app).await;
+
+  // `jack` prefix-matches `jacket` and, through stemming, `jackets`; name
+  // matches outrank the description match.
+  let (status, page) = send(&app, get("/products/search?q=jack")).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(page["total"], 3);
+  let found = skus(&page);
+  assert_eq!(found.len(), 3);
+  assert_eq!(found[2], "oxford");
+  let first = &page["items"][0];
+  assert!(first["rank"].as_f64().unwrap() > page["items"][2]["rank"].as_f64().unwrap());
+  assert!(first["snippet"].as_str().unwrap().contains("<mark>"));
+  assert_eq!(first["price_cents"], 1000);
+
+  let (_, page) = send(&app, get("/products/search?q=Blue%20JACK")).await;
+  assert_eq!(skus(&page), ["rain-jacket"]);
+  assert_eq!(
+    page["items"][0]["snippet"],
+    "<mark>Blue</mark> rain <mark>jacket</mark> Waterproof shell with taped seams."
+  );
+
+  // Product text is escaped; only the highlighting is markup.
+  let (_, page) = send(&app, get("/products/search?q=bold")).await;
+  let snippet = page["items"][0]["snippet"].as_str().unwrap();
+  assert!(
+    snippet.contains("<mark>Bold</mark>&lt;/b&gt; jacket"),
+    "{snippet}"
+  );
+  assert!(snippet.contains("out &amp; keeps"), "{snippet}");
+  assert!(!snippet.contains("<b>"), "{snippet}");
+
+  let (_, page) = send(&app, get("/products/search?q=waterproof")).await;
+  assert_eq!(skus(&page), ["rain-jacket"]);
+  let (_, page) = send(&app, get("/products/search?q=sandals")).await;
+  assert_eq!(page["total"], 0);
+}
+
+#[tokio::test]
+async fn search_paginates_and_validates() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  seed(&app).await;
+
+  let mut seen = Vec::new();
+  for page_number in 1..=3 {
+    let (_, page) = send(
+      &app,
+      get(&format!(
+        "/products/search?q=jack&per_page=1&page={page_number}"
+      )),
+    )
+    .await;
+    assert_eq!(page["page"], page_number);
+    assert_eq!(page["per_page"], 1);
+    assert_eq!(page["total"], 3);
+    seen.extend(skus(&page).into_iter().map(String::from));
+  }
+  seen.sort();
+  assert_eq!(seen, ["bold", "oxford", "rain-jacket"]);
+
+  let (status, past_end) = send(&app, get("/products/search?q=jack&per_page=2&page=9")).await;
+  assert_eq!(status, StatusCode::OK);
+  assert!(past_end["items"].as_array().unwrap().is_empty());
+  assert_eq!(past_end["total"], 3);
+
+  for uri in [
+    "/products/search?q=%20%26%21",
+    "/products/search?q=jack&page=0",
+    "/products/search?q=jack&per_page=101",
+  ] {
+    let (status, _) = send(&app, get(uri)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+  }
+}
@@ -0,0 +1,58 @@
+mod common;
+mod conformance;
+
+use asgard_rust::adapters::memory::orders_repo::MemoryOrderRepository;
+use asgard_rust::adapters::memory::products_repo::MemoryProductRepository;
+use asgard_rust::adapters::memory::users_repo::MemoryUserRepository;
+use asgard_rust::adapters::memory::MemoryStore;
+use asgard_rust::adapters::sqlite::orders_repo::SqliteOrderRepository;
+use asgard_rust::adapters::sqlite::products_repo::SqliteProductRepository;
+use asgard_rust::adapters::sqlite::users_repo::SqliteUserRepository;
+use common::SqliteFile;
+
+#[tokio::test]
+async fn memory_users_conform() {
+  conformance::users(&MemoryUserRepository::new(MemoryStore::new())).await;
+}
+
+#[tokio::test]
+async fn memory_products_conform() {
+  conformance::products(&MemoryProductRepository::new(MemoryStore::new())).await;
+}
+
+#[tokio::test]
+async fn memory_orders_conform() {
+  let store = MemoryStore::new();
+  conformance::orders(
+    &MemoryOrderRepository::new(store.clone()),
+    &MemoryUserRepository::new(store.clone()),
+    &MemoryProductRepository::new(store),
+  )
+  .await;
+}
+
+#[tokio::test]
+async fn sqlite_users_conform() {
+  let db = SqliteFile::new().await;
+  conformance::users(&SqliteUserRepository::new(db.pool.clone())).await;
+  db.remove().await;
+}
+
+#[tokio::test]
+async fn sqlite_products_conform() {
+  let db = SqliteFile::new().await;
+  conformance::products(&SqliteProductRepository::new(db.pool.clone())).await;
+  db.remove().await;
+}
+
+#[tokio::test]
+async fn sqlite_orders_conform() {
+  let db = SqliteFile::new().await;
+  conformance::orders(
+    &SqliteOrderRepository::new(db.pool.clone()),
+    &SqliteUserRepository::new(db.pool.clone()),
+    &SqliteProductRepository::new(db.pool.clone()),
+  )
+  .await;
+  db.remove().await;
+}
@@ -0,0 +1,36 @@
+mod common;
+mod conformance;
+
+use asgard_rust::adapters::db::orders_repo::PgOrderRepository;
+use asgard_rust::adapters::db::products_repo::PgProductRepository;
+use asgard_rust::adapters::db::users_repo::PgUserRepository;
+use common::{setup, SEARCH_LANGUAGE};
+
+#[tokio::test]
+async fn postgres_users_conform() {
+  let Some((pool, _state, _guard)) = setup().await else {
+    return;
+  };
+  conformance::users(&PgUserRepository::new(pool)).await;
+}
+
+#[tokio::test]
+async fn postgres_products_conform() {
+  let Some((pool, _state, _guard)) = setup().await else {
+    return;
+  };
+  conformance::products(&PgProductRepository::new(pool, SEARCH_LANGUAGE)).await;
+}
+
+#[tokio::test]
+async fn postgres_orders_conform() {
+  let Some((pool, _state, _guard)) = setup().await else {
+    return;
+  };
+  conformance::orders(
+    &PgOrderRepository::new(pool.clone()),
+    &PgUserRepository::new(pool.clone()),
+    &PgProductRepository::new(pool, SEARCH_LANGUAGE),
+  )
+  .await;
+}
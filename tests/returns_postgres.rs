@@ -0,0 +1,239 @@
+mod common;
+
+use asgard_rust::build_app;
+use axum::http::StatusCode;
+use common::{admin, get, paid_order, post_json, put_json, send, setup};
+use serde_json::json;
+
+#[tokio::test]
+async fn approved_return_restocks_and_refunds_partially_then_fully() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let order = paid_order(&app).await;
+
+  let (status, _) = send(
+    &app,
+    admin(put_json(
+      &format!("/products/{}/stock", order.product_id),
+      json!({ "quantity": 5 }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::OK);
+
+  let (status, rma) = send(
+    &app,
+    post_json(
+      &format!("/orders/{}/returns", order.order_id),
+      json!({
+        "items": [{ "order_item_id": order.shirt_item, "quantity": 2, "reason": "damaged" }],
+        "note": "arrived torn"
+      }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(rma["status"], "requested");
+  let rma_id = rma["id"].as_str().unwrap().to_string();
+
+  let approve = post_json(&format!("/returns/{rma_id}/approve"), json!({}));
+  let (status, _) = send(&app, approve).await;
+  assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+  let (status, rma) = send(
+    &app,
+    admin(post_json(&format!("/returns/{rma_id}/approve"), json!({}))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(rma["status"], "approved");
+  assert_eq!(rma["restocked"], true);
+  let (_, stock) = send(&app, get(&format!("/products/{}/stock", order.product_id))).await;
+  assert_eq!(stock["quantity"], 7);
+
+  let (status, refunded) = send(
+    &app,
+    admin(post_json(
+      &format!("/returns/{rma_id}/refunds"),
+      json!({ "amount_cents": 1000 }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(refunded["refund"]["amount_cents"], 1000);
+  assert_eq!(refunded["return"]["status"], "approved");
+
+  // Only 2000 cents of the 3000 cent return are left to refund.
+  let (status, _) = send(
+    &app,
+    admin(post_json(
+      &format!("/returns/{rma_id}/refunds"),
+      json!({ "amount_cents": 2500 }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+
+  let (status, refunded) = send(
+    &app,
+    admin(post_json(&format!("/returns/{rma_id}/refunds"), json!({}))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(refunded["refund"]["amount_cents"], 2000);
+  assert_eq!(refunded["return"]["status"], "refunded");
+  assert_eq!(refunded["return"]["refunded_cents"], 3000);
+
+  let (_, order_body) = send(&app, get(&format!("/orders/{}", order.order_id))).await;
+  assert_eq!(order_body["status"], "partially_refunded");
+
+  // Returning the rest of the order refunds it completely.
+  let (_, rma) = send(
+    &app,
+    post_json(
+      &format!("/orders/{}/returns", order.order_id),
+      json!({ "items": [{ "order_item_id": order.jacket_item, "quantity": 1, "reason": "no_longer_needed" }] }),
+    ),
+  )
+  .await;
+  let rma_id = rma["id"].as_str().unwrap();
+  send(
+    &app,
+    admin(post_json(
+      &format!("/returns/{rma_id}/approve"),
+      json!({ "restock": false }),
+    )),
+  )
+  .await;
+  let (status, _) = send(
+    &app,
+    admin(post_json(&format!("/returns/{rma_id}/refunds"), json!({}))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+
+  let (_, order_body) = send(&app, get(&format!("/orders/{}", order.order_id))).await;
+  assert_eq!(order_body["status"], "refunded");
+  let (_, payments) = send(&app, get(&format!("/orders/{}/payments", order.order_id))).await;
+  assert_eq!(payments[0]["status"], "refunded");
+  let (_, refunds) = send(&app, get(&format!("/orders/{}/refunds", order.order_id))).await;
+  assert_eq!(refunds.as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn return_requests_are_validated_and_rejections_are_final() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let order = paid_order(&app).await;
+  let returns_uri = format!("/orders/{}/returns", order.order_id);
+
+  let (status, _) = send(
+    &app,
+    post_json(
+      &returns_uri,
+      json!({ "items": [{ "order_item_id": order.shirt_item, "quantity": 3, "reason": "damaged" }] }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+
+  let (status, _) = send(
+    &app,
+    post_json(
+      &returns_uri,
+      json!({ "items": [{ "order_item_id": order.shirt_item, "quantity": 1, "reason": "because" }] }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+
+  let (_, rma) = send(
+    &app,
+    post_json(
+      &returns_uri,
+      json!({ "items": [{ "order_item_id": order.shirt_item, "quantity": 2, "reason": "wrong_item" }] }),
+    ),
+  )
+  .await;
+  let rma_id = rma["id"].as_str().unwrap();
+
+  // Both units are already part of an open return.
+  let (status, _) = send(
+    &app,
+    post_json(
+      &returns_uri,
+      json!({ "items": [{ "order_item_id": order.shirt_item, "quantity": 1, "reason": "other" }] }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+
+  let (status, rma) = send(
+    &app,
+    admin(post_json(
+      &format!("/returns/{rma_id}/reject"),
+      json!({ "note": "outside return window" }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(rma["status"], "rejected");
+
+  let (status, _) = send(
+    &app,
+    admin(post_json(&format!("/returns/{rma_id}/approve"), json!({}))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CONFLICT);
+  let (status, _) = send(
+    &app,
+    admin(post_json(&format!("/returns/{rma_id}/refunds"), json!({}))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CONFLICT);
+
+  // Rejected units become returnable again.
+  let (status, _) = send(
+    &app,
+    post_json(
+      &returns_uri,
+      json!({ "items": [{ "order_item_id": order.shirt_item, "quantity": 2, "reason": "other" }] }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+  let (_, list) = send(&app, get(&returns_uri)).await;
+  assert_eq!(list.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn only_paid_orders_take_returns() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let order = paid_order(&app).await;
+  let order_uri = format!("/orders/{}", order.order_id);
+  let request_return = || {
+    post_json(
+      &format!("{order_uri}/returns"),
+      json!({ "items": [{ "order_item_id": order.jacket_item, "quantity": 1, "reason": "other" }] }),
+    )
+  };
+
+  for status in ["pending_payment", "cancelled"] {
+    send(&app, put_json(&order_uri, json!({ "status": status }))).await;
+    let (code, _) = send(&app, request_return()).await;
+    assert_eq!(code, StatusCode::CONFLICT, "{status}");
+  }
+  let (_, returns) = send(&app, get(&format!("{order_uri}/returns"))).await;
+  assert_eq!(returns, json!([]));
+
+  send(&app, put_json(&order_uri, json!({ "status": "delivered" }))).await;
+  let (code, _) = send(&app, request_return()).await;
+  assert_eq!(code, StatusCode::CREATED);
+}
@@ -0,0 +1,198 @@
+mod common;
+
+use asgard_rust::build_app;
+use axum::http::StatusCode;
+use axum::Router;
+use common::{admin, delete, get, post_json, put_json, send, setup};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+async fn product(app: &Router, sku: &str) -> String {
+  let (_, product) = send(
+    app,
+    post_json(
+      "/products",
+      json!({ "sku": sku, "name": "Lamp", "price_cents": 2500 }),
+    ),
+  )
+  .await;
+  product["id"].as_str().unwrap().to_string()
+}
+
+/// A user with an order of `product_id` in `order_status`.
+async fn customer(app: &Router, product_id: &str, order_status: &str) -> String {
+  let tag = Uuid::new_v4().simple().to_string();
+  let (_, user) = send(
+    app,
+    post_json(
+      "/users",
+      json!({ "email": format!("{tag}@example.com"), "name": "Buyer" }),
+    ),
+  )
+  .await;
+  let (_, order) = send(
+    app,
+    post_json(
+      "/orders",
+      json!({ "user_id": user["id"], "status": "pending_payment", "total_cents": 0 }),
+    ),
+  )
+  .await;
+  let order_id = order["id"].as_str().unwrap();
+  let (status, _) = send(
+    app,
+    post_json(
+      &format!("/orders/{order_id}/items"),
+      json!({ "product_id": product_id, "quantity": 1 }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+  send(
+    app,
+    put_json(
+      &format!("/orders/{order_id}"),
+      json!({ "status": order_status }),
+    ),
+  )
+  .await;
+  user["id"].as_str().unwrap().to_string()
+}
+
+async fn review(app: &Router, product_id: &str, user_id: &str, rating: i64) -> (StatusCode, Value) {
+  send(
+    app,
+    post_json(
+      &format!("/products/{product_id}/reviews"),
+      json!({ "user_id": user_id, "rating": rating, "title": " Bright ", "body": "Lights up the room." }),
+    ),
+  )
+  .await
+}
+
+async fn moderate(app: &Router, review: &Value, action: &str) -> Value {
+  let (status, moderated) = send(
+    app,
+    admin(post_json(
+      &format!("/reviews/{}/{action}", review["id"].as_str().unwrap()),
+      json!({ "note": "checked" }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::OK);
+  moderated
+}
+
+#[tokio::test]
+async fn approved_reviews_drive_the_product_rating() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let lamp = product(&app, "lamp").await;
+
+  let (_, details) = send(&app, get(&format!("/products/{lamp}"))).await;
+  assert_eq!(details["rating_average"], Value::Null);
+  assert_eq!(details["rating_count"], 0);
+
+  let (alice, bob, carol) = (
+    customer(&app, &lamp, "delivered").await,
+    customer(&app, &lamp, "delivered").await,
+    customer(&app, &lamp, "delivered").await,
+  );
+  let (status, first) = review(&app, &lamp, &alice, 5).await;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(first["status"], "pending");
+  assert_eq!(first["title"], "Bright");
+  let (_, second) = review(&app, &lamp, &bob, 4).await;
+  let (_, third) = review(&app, &lamp, &carol, 4).await;
+
+  // Pending reviews are neither public nor counted.
+  let uri = format!("/products/{lamp}/reviews");
+  let (_, page) = send(&app, get(&uri)).await;
+  assert_eq!(page["total"], 0);
+  let (status, _) = send(&app, get("/reviews")).await;
+  assert_eq!(status, StatusCode::UNAUTHORIZED);
+  let (_, queue) = send(&app, admin(get("/reviews?status=pending"))).await;
+  assert_eq!(queue["total"], 3);
+
+  let approved = moderate(&app, &first, "approve").await;
+  assert_eq!(approved["status"], "approved");
+  assert_eq!(approved["moderation_note"], "checked");
+  moderate(&app, &second, "approve").await;
+  moderate(&app, &third, "approve").await;
+  let (_, details) = send(&app, get(&format!("/products/{lamp}"))).await;
+  assert_eq!(details["rating_average"], 4.33);
+  assert_eq!(details["rating_count"], 3);
+
+  let (_, page) = send(&app, get(&format!("{uri}?per_page=2"))).await;
+  assert_eq!(page["total"], 3);
+  assert_eq!(page["items"].as_array().unwrap().len(), 2);
+  assert_eq!(page["items"][0]["id"], third["id"]);
+
+  // Rejecting takes an approved review down; deleting removes it.
+  moderate(&app, &third, "reject").await;
+  let (_, details) = send(&app, get(&format!("/products/{lamp}"))).await;
+  assert_eq!(details["rating_average"], 4.5);
+  assert_eq!(details["rating_count"], 2);
+  let (status, _) = send(
+    &app,
+    admin(delete(&format!(
+      "/reviews/{}",
+      first["id"].as_str().unwrap()
+    ))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::NO_CONTENT);
+  let (_, details) = send(&app, get(&format!("/products/{lamp}"))).await;
+  assert_eq!(details["rating_average"], 4.0);
+  assert_eq!(details["rating_count"], 1);
+  let (_, rejected) = send(&app, admin(get("/reviews?status=rejected"))).await;
+  assert_eq!(rejected["items"][0]["id"], third["id"]);
+}
+
+#[tokio::test]
+async fn only_customers_with_a_delivered_order_review_once() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let lamp = product(&app, "lamp").await;
+  let other = product(&app, "other").await;
+
+  let waiting = customer(&app, &lamp, "paid").await;
+  let (status, _) = review(&app, &lamp, &waiting, 5).await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+
+  let buyer = customer(&app, &lamp, "delivered").await;
+  let (status, _) = review(&app, &other, &buyer, 5).await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+  let (status, _) = review(&app, &lamp, &buyer, 5).await;
+  assert_eq!(status, StatusCode::CREATED);
+  let (status, _) = review(&app, &lamp, &buyer, 3).await;
+  assert_eq!(status, StatusCode::CONFLICT);
+
+  for rating in [0, 6] {
+    let (status, _) = review(&app, &lamp, &buyer, rating).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{rating}");
+  }
+  let (status, _) = send(
+    &app,
+    post_json(
+      &format!("/products/{lamp}/reviews"),
+      json!({ "user_id": buyer, "rating": 4, "title": "  " }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+
+  let missing = Uuid::new_v4();
+  let (status, _) = review(&app, &missing.to_string(), &buyer, 5).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+  let (status, _) = review(&app, &lamp, &missing.to_string(), 5).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+  let (status, _) = send(&app, get(&format!("/products/{missing}/reviews"))).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+  let (status, _) = send(&app, admin(get("/reviews?status=spam"))).await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use asgard_rust::adapters::blob::fs::FsBlobStore;
+use asgard_rust::adapters::db::images_repo::PgProductImageRepository;
+use asgard_rust::adapters::thumbnails::ThumbnailWorker;
+use asgard_rust::build_app;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::Router;
+use common::{admin, delete, get, media_dir, post_json, send, setup, THUMBNAIL_SIZES};
+use hyper::body::to_bytes;
+use image::{DynamicImage, GenericImageView, ImageFormat, Rgba, RgbaImage};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::io::Cursor;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+const BOUNDARY: &str = "asgard-test-boundary";
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+  let mut bytes = Vec::new();
+  DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+    width,
+    height,
+    Rgba([20, 120, 200, 255]),
+  ))
+  .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+  .unwrap();
+  bytes
+}
+
+fn upload(product_id: &str, bytes: &[u8]) -> Request<Body> {
+  let mut body = format!(
+    "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"f\"\r\n\
+     Content-Type: image/png\r\n\r\n"
+  )
+  .into_bytes();
+  body.extend_from_slice(bytes);
+  body.extend_from_slice(format!("\r\n--{BOUNDARY}--\r\n").as_bytes());
+  admin(
+    Request::builder()
+      .method("POST")
+      .uri(format!("/products/{product_id}/images"))
+      .header(
+        "content-type",
+        format!("multipart/form-data; boundary={BOUNDARY}"),
+      )
+      .body(Body::from(body))
+      .unwrap(),
+  )
+}
+
+fn worker(pool: &PgPool) -> ThumbnailWorker<PgProductImageRepository> {
+  ThumbnailWorker::new(
+    PgProductImageRepository::new(pool.clone()),
+    Arc::new(FsBlobStore::new(media_dir())),
+    THUMBNAIL_SIZES.to_vec(),
+  )
+}
+
+async fn product(app: &Router) -> String {
+  let (_, product) = send(
+    app,
+    post_json(
+      "/products",
+      json!({ "sku": "poster", "name": "Poster", "price_cents": 500 }),
+    ),
+  )
+  .await;
+  product["id"].as_str().unwrap().to_string()
+}
+
+async fn download(app: &Router, url: &str) -> (StatusCode, String, Vec<u8>) {
+  let res = app.clone().oneshot(get(url)).await.unwrap();
+  let status = res.status();
+  let content_type = res
+    .headers()
+    .get("content-type")
+    .map(|v| v.to_str().unwrap().to_string())
+    .unwrap_or_default();
+  (
+    status,
+    content_type,
+    to_bytes(res.into_body()).await.unwrap().to_vec(),
+  )
+}
+
+fn keys(object: &Value) -> Vec<&str> {
+  object
+    .as_object()
+    .unwrap()
+    .keys()
+    .map(String::as_str)
+    .collect()
+}
+
+#[tokio::test]
+async fn worker_generates_variants_served_with_the_product() {
+  let Some((pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let id = product(&app).await;
+  let (status, _) = send(&app, upload(&id, &png(600, 400))).await;
+  assert_eq!(status, StatusCode::CREATED);
+
+  let (_, details) = send(&app, get(&format!("/products/{id}"))).await;
+  assert_eq!(details["sku"], "poster");
+  assert_eq!(details["images"][0]["thumbnail_status"], "pending");
+  assert_eq!(details["images"][0]["variant_urls"], json!({}));
+
+  let worker = worker(&pool);
+  assert_eq!(worker.run_once().await.unwrap(), 1);
+  assert_eq!(worker.run_once().await.unwrap(), 0);
+
+  let (_, details) = send(&app, get(&format!("/products/{id}"))).await;
+  let image = &details["images"][0];
+  assert_eq!(image["thumbnail_status"], "ready");
+  assert_eq!(
+    keys(&image["variant_urls"]),
+    ["160.png", "160.webp", "480.png", "480.webp"]
+  );
+  let dimensions: Vec<_> = image["variants"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|v| {
+      (
+        v["name"].as_str().unwrap(),
+        v["width"].clone(),
+        v["height"].clone(),
+      )
+    })
+    .collect();
+  assert_eq!(
+    dimensions,
+    [
+      ("160.png", json!(160), json!(107)),
+      ("160.webp", json!(160), json!(107)),
+      ("480.png", json!(480), json!(320)),
+      ("480.webp", json!(480), json!(320)),
+    ]
+  );
+  assert!(image["variants"][0].get("blob_key").is_none());
+
+  let url = image["variant_urls"]["480.webp"].as_str().unwrap();
+  let (status, content_type, body) = download(&app, url).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(content_type, "image/webp");
+  let decoded = image::load_from_memory_with_format(&body, ImageFormat::WebP).unwrap();
+  assert_eq!(decoded.dimensions(), (480, 320));
+
+  // A signature only covers its own variant.
+  let (status, _, _) = download(&app, &url.replace("480.webp", "160.webp")).await;
+  assert_eq!(status, StatusCode::FORBIDDEN);
+
+  // Deleting the image removes the variant files too.
+  let image_id = image["id"].as_str().unwrap();
+  let stored = media_dir().join(format!("products/{id}/{image_id}-480.webp"));
+  assert!(stored.exists());
+  let (status, _) = send(
+    &app,
+    admin(delete(&format!("/products/{id}/images/{image_id}"))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::NO_CONTENT);
+  assert!(!stored.exists());
+  let (status, _, _) = download(&app, url).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn undecodable_images_are_marked_failed() {
+  let Some((pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let id = product(&app).await;
+  let (status, _) = send(&app, upload(&id, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR fake png")).await;
+  assert_eq!(status, StatusCode::CREATED);
+
+  let worker = worker(&pool);
+  assert_eq!(worker.run_once().await.unwrap(), 1);
+  assert_eq!(worker.run_once().await.unwrap(), 0);
+
+  let (_, images) = send(&app, get(&format!("/products/{id}/images"))).await;
+  assert_eq!(images[0]["thumbnail_status"], "failed");
+  assert_eq!(images[0]["variants"], json!([]));
+  assert_eq!(images[0]["variant_urls"], json!({}));
+}
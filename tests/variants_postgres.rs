@@ -0,0 +1,257 @@
+mod common;
+
+use asgard_rust::build_app;
+use axum::http::StatusCode;
+use axum::Router;
+use common::{admin, delete, get, post_json, put_json, send, setup};
+use serde_json::{json, Value};
+
+/// Creates a 2000 cent shirt with `size` (S, M, L) and `color` (red, blue)
+/// options and returns its id.
+async fn shirt_with_options(app: &Router) -> String {
+  let (_, shirt) = send(
+    app,
+    post_json(
+      "/products",
+      json!({ "sku": "shirt", "name": "Shirt", "price_cents": 2000 }),
+    ),
+  )
+  .await;
+  let id = shirt["id"].as_str().unwrap().to_string();
+  let (status, size) = send(
+    app,
+    admin(post_json(
+      &format!("/products/{id}/options"),
+      json!({ "name": "size", "values": ["S", "M", "L"] }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(size["position"], 0);
+  let (_, color) = send(
+    app,
+    admin(post_json(
+      &format!("/products/{id}/options"),
+      json!({ "name": "color", "values": ["red", "blue"] }),
+    )),
+  )
+  .await;
+  assert_eq!(color["position"], 1);
+  id
+}
+
+#[tokio::test]
+async fn variants_nest_under_their_product() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let id = shirt_with_options(&app).await;
+
+  let (status, red_m) = send(
+    &app,
+    admin(post_json(
+      &format!("/products/{id}/variants"),
+      json!({ "sku": "shirt-m-red", "stock": 5, "options": { "size": "M", "color": "red" } }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(red_m["price_cents"], 2000);
+  assert_eq!(red_m["price_override_cents"], Value::Null);
+  let (_, blue_l) = send(
+    &app,
+    admin(post_json(
+      &format!("/products/{id}/variants"),
+      json!({
+        "sku": "shirt-l-blue",
+        "price_override_cents": 2500,
+        "options": { "size": "L", "color": "blue" }
+      }),
+    )),
+  )
+  .await;
+  assert_eq!(blue_l["price_cents"], 2500);
+  assert_eq!(blue_l["stock"], 0);
+
+  let (status, details) = send(&app, get(&format!("/products/{id}"))).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(details["sku"], "shirt");
+  assert_eq!(details["options"][0]["name"], "size");
+  assert_eq!(details["options"][0]["values"], json!(["S", "M", "L"]));
+  assert_eq!(details["options"][1]["values"], json!(["red", "blue"]));
+  let variants = details["variants"].as_array().unwrap();
+  assert_eq!(variants.len(), 2);
+  assert_eq!(variants[0]["sku"], "shirt-m-red");
+  assert_eq!(
+    variants[0]["options"],
+    json!({ "size": "M", "color": "red" })
+  );
+
+  // Clearing the override falls back to the product price.
+  let variant_uri = format!("/variants/{}", blue_l["id"].as_str().unwrap());
+  let (status, updated) = send(
+    &app,
+    admin(put_json(
+      &variant_uri,
+      json!({ "price_override_cents": null, "stock": 7 }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(updated["price_cents"], 2000);
+  assert_eq!(updated["stock"], 7);
+  let (_, kept) = send(&app, admin(put_json(&variant_uri, json!({ "stock": 3 })))).await;
+  assert_eq!(kept["price_override_cents"], Value::Null);
+  assert_eq!(kept["options"]["color"], "blue");
+
+  // Options are fixed once variants exist.
+  let (status, _) = send(
+    &app,
+    admin(post_json(
+      &format!("/products/{id}/options"),
+      json!({ "name": "fit", "values": ["slim"] }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn combinations_must_be_complete_and_unique() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let id = shirt_with_options(&app).await;
+  let uri = format!("/products/{id}/variants");
+
+  let create =
+    |sku: &str, options: Value| admin(post_json(&uri, json!({ "sku": sku, "options": options })));
+  let (status, _) = send(&app, create("a", json!({ "size": "M", "color": "red" }))).await;
+  assert_eq!(status, StatusCode::CREATED);
+
+  for (options, expected) in [
+    (json!({ "size": "M", "color": "red" }), StatusCode::CONFLICT),
+    (json!({ "size": "M" }), StatusCode::BAD_REQUEST),
+    (
+      json!({ "size": "XL", "color": "red" }),
+      StatusCode::BAD_REQUEST,
+    ),
+    (
+      json!({ "size": "M", "color": "red", "fit": "slim" }),
+      StatusCode::BAD_REQUEST,
+    ),
+  ] {
+    let (status, _) = send(&app, create("b", options.clone())).await;
+    assert_eq!(status, expected, "{options}");
+  }
+
+  let (status, _) = send(
+    &app,
+    admin(post_json(
+      &format!("/products/{id}/options"),
+      json!({ "name": "fit", "values": ["slim", "slim"] }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn skus_are_unique_across_products_and_variants() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let id = shirt_with_options(&app).await;
+  let uri = format!("/products/{id}/variants");
+
+  // A variant cannot reuse a product SKU, nor a product a variant SKU.
+  let (status, _) = send(
+    &app,
+    admin(post_json(
+      &uri,
+      json!({ "sku": "shirt", "options": { "size": "S", "color": "red" } }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CONFLICT);
+  let (_, variant) = send(
+    &app,
+    admin(post_json(
+      &uri,
+      json!({ "sku": "shirt-s-red", "options": { "size": "S", "color": "red" } }),
+    )),
+  )
+  .await;
+  let (status, _) = send(
+    &app,
+    post_json(
+      "/products",
+      json!({ "sku": "shirt-s-red", "name": "Clash", "price_cents": 1 }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CONFLICT);
+
+  // Renaming frees the old SKU.
+  let (status, _) = send(
+    &app,
+    admin(put_json(
+      &format!("/variants/{}", variant["id"].as_str().unwrap()),
+      json!({ "sku": "shirt-s-red-v2" }),
+    )),
+  )
+  .await;
+  assert_eq!(status, StatusCode::OK);
+  let (status, _) = send(
+    &app,
+    post_json(
+      "/products",
+      json!({ "sku": "shirt-s-red", "name": "Now free", "price_cents": 1 }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn only_admins_change_options_and_variants() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let id = shirt_with_options(&app).await;
+  let (_, variant) = send(
+    &app,
+    admin(post_json(
+      &format!("/products/{id}/variants"),
+      json!({ "sku": "shirt-s-red", "options": { "size": "S", "color": "red" } }),
+    )),
+  )
+  .await;
+  let variant_uri = format!("/variants/{}", variant["id"].as_str().unwrap());
+
+  for req in [
+    post_json(
+      &format!("/products/{id}/options"),
+      json!({ "name": "fit", "values": ["slim"] }),
+    ),
+    post_json(
+      &format!("/products/{id}/variants"),
+      json!({ "sku": "shirt-m-red", "options": { "size": "M", "color": "red" } }),
+    ),
+    put_json(&variant_uri, json!({ "stock": 9 })),
+    delete(&variant_uri),
+  ] {
+    let (status, _) = send(&app, req).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+  }
+
+  let (_, variants) = send(&app, get(&format!("/products/{id}/variants"))).await;
+  assert_eq!(variants.as_array().unwrap().len(), 1);
+  assert_eq!(variants[0]["stock"], 0);
+  let (status, _) = send(&app, admin(delete(&variant_uri))).await;
+  assert_eq!(status, StatusCode::NO_CONTENT);
+}
@@ -0,0 +1,298 @@
+mod common;
+
+use asgard_rust::adapters::db::outbox_repo::PgOutboxRepository;
+use asgard_rust::adapters::db::webhooks_repo::PgWebhookRepository;
+use asgard_rust::adapters::webhooks::{
+  WebhookPublisher, WebhookWorker, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER,
+};
+use asgard_rust::build_app;
+use asgard_rust::infrastructure::outbox::OutboxDispatcher;
+use asgard_rust::infrastructure::signature;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use common::{admin, get, post_json, put_json, send, setup};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const SECRET: &str = "partner-secret-0123";
+
+/// Stand-in for a partner endpoint: records each request and answers with
+/// the configured status.
+#[derive(Clone)]
+struct Receiver {
+  status: Arc<AtomicU16>,
+  requests: Arc<Mutex<Vec<(HeaderMap, Bytes)>>>,
+}
+
+impl Receiver {
+  async fn start() -> (Self, String) {
+    let receiver = Receiver {
+      status: Arc::new(AtomicU16::new(200)),
+      requests: Arc::default(),
+    };
+    let app = Router::new()
+      .route("/hook", post(receive))
+      .with_state(receiver.clone());
+    let server =
+      axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(app.into_make_service());
+    let url = format!("http://{}/hook", server.local_addr());
+    tokio::spawn(server);
+    (receiver, url)
+  }
+
+  fn answer(&self, status: u16) {
+    self.status.store(status, Ordering::SeqCst);
+  }
+
+  fn requests(&self) -> Vec<(HeaderMap, Bytes)> {
+    self.requests.lock().unwrap().clone()
+  }
+}
+
+async fn receive(State(receiver): State<Receiver>, headers: HeaderMap, body: Bytes) -> StatusCode {
+  receiver.requests.lock().unwrap().push((headers, body));
+  StatusCode::from_u16(receiver.status.load(Ordering::SeqCst)).unwrap()
+}
+
+/// Publishes pending outbox events into webhook deliveries.
+async fn dispatch(pool: &PgPool) {
+  OutboxDispatcher::new(
+    PgOutboxRepository::new(pool.clone()),
+    Arc::new(WebhookPublisher::new(PgWebhookRepository::new(
+      pool.clone(),
+    ))),
+    3,
+  )
+  .run_once()
+  .await
+  .unwrap();
+}
+
+fn worker(
+  pool: &PgPool,
+  max_attempts: i32,
+  disable_after_failures: i32,
+) -> WebhookWorker<PgWebhookRepository> {
+  WebhookWorker::new(
+    PgWebhookRepository::new(pool.clone()),
+    Duration::from_secs(5),
+    max_attempts,
+    disable_after_failures,
+  )
+}
+
+/// An order moved through `statuses`, one status change event each.
+async fn order_through(app: &Router, statuses: &[&str]) -> String {
+  let (_, user) = send(
+    app,
+    post_json(
+      "/users",
+      json!({ "email": format!("{}@example.com", uuid::Uuid::new_v4()), "name": "Buyer" }),
+    ),
+  )
+  .await;
+  let (_, order) = send(
+    app,
+    post_json(
+      "/orders",
+      json!({ "user_id": user["id"], "status": "pending_payment", "total_cents": 0 }),
+    ),
+  )
+  .await;
+  let order_id = order["id"].as_str().unwrap().to_string();
+  for status in statuses {
+    send(
+      app,
+      put_json(&format!("/orders/{order_id}"), json!({ "status": status })),
+    )
+    .await;
+  }
+  order_id
+}
+
+#[tokio::test]
+async fn subscribers_receive_signed_order_events_with_a_delivery_log() {
+  let Some((pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let (receiver, url) = Receiver::start().await;
+
+  let body = json!({ "url": url, "secret": SECRET, "event_types": ["OrderStatusChanged"] });
+  let (status, _) = send(&app, post_json("/webhook-subscriptions", body.clone())).await;
+  assert_eq!(status, StatusCode::UNAUTHORIZED);
+  for invalid in [
+    json!({ "url": "ftp://example.com", "secret": SECRET, "event_types": ["OrderCreated"] }),
+    json!({ "url": url, "secret": "short", "event_types": ["OrderCreated"] }),
+    json!({ "url": url, "secret": SECRET, "event_types": ["OrderShipped"] }),
+    json!({ "url": url, "secret": SECRET, "event_types": [] }),
+  ] {
+    let (status, _) = send(&app, admin(post_json("/webhook-subscriptions", invalid))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+  }
+  let (status, subscription) = send(&app, admin(post_json("/webhook-subscriptions", body))).await;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(subscription["active"], true);
+  assert!(subscription.get("secret").is_none());
+  let uri = format!(
+    "/webhook-subscriptions/{}",
+    subscription["id"].as_str().unwrap()
+  );
+
+  let order_id = order_through(&app, &["paid"]).await;
+  dispatch(&pool).await;
+  // Publishing again (at-least-once) does not duplicate the delivery.
+  sqlx::query("UPDATE outbox SET published_at = NULL, next_attempt_at = now()")
+    .execute(&pool)
+    .await
+    .unwrap();
+  dispatch(&pool).await;
+  assert_eq!(worker(&pool, 3, 20).run_once().await.unwrap(), 1);
+
+  let requests = receiver.requests();
+  assert_eq!(requests.len(), 1);
+  let (headers, raw) = &requests[0];
+  assert_eq!(headers[EVENT_HEADER], "OrderStatusChanged");
+  let header = headers[SIGNATURE_HEADER].to_str().unwrap();
+  let now = chrono::Utc::now().timestamp();
+  signature::verify(SECRET, header, raw, now, 300).unwrap();
+  let event: Value = serde_json::from_slice(raw).unwrap();
+  assert_eq!(event["type"], "OrderStatusChanged");
+  assert_eq!(event["data"]["order"]["id"], order_id.as_str());
+  assert_eq!(event["data"]["order"]["status"], "paid");
+  assert_eq!(event["data"]["from"], "pending_payment");
+
+  let (_, page) = send(&app, admin(get(&format!("{uri}/deliveries")))).await;
+  assert_eq!(page["total"], 1);
+  let delivery = &page["items"][0];
+  assert_eq!(delivery["status"], "succeeded");
+  assert_eq!(delivery["response_status"], 200);
+  assert_eq!(delivery["log"].as_array().unwrap().len(), 1);
+  assert_eq!(delivery["log"][0]["response_status"], 200);
+  assert_eq!(
+    headers[DELIVERY_HEADER].to_str().unwrap(),
+    delivery["id"].as_str().unwrap()
+  );
+
+  // Manual redelivery sends the same event again.
+  let delivery_uri = format!("{uri}/deliveries/{}", delivery["id"].as_str().unwrap());
+  let (status, queued) = send(
+    &app,
+    admin(post_json(&format!("{delivery_uri}/redeliver"), json!({}))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::ACCEPTED);
+  assert_eq!(queued["status"], "pending");
+  worker(&pool, 3, 20).run_once().await.unwrap();
+  let requests = receiver.requests();
+  assert_eq!(requests.len(), 2);
+  let resent: Value = serde_json::from_slice(&requests[1].1).unwrap();
+  assert_eq!(resent["id"], event["id"]);
+  let (_, delivery) = send(&app, admin(get(&delivery_uri))).await;
+  assert_eq!(delivery["status"], "succeeded");
+  assert_eq!(delivery["log"].as_array().unwrap().len(), 2);
+
+  let (status, _) = send(
+    &app,
+    admin(get(&format!("{uri}/deliveries/{}", uuid::Uuid::new_v4()))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn failing_receivers_are_retried_then_disabled() {
+  let Some((pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let (receiver, url) = Receiver::start().await;
+  receiver.answer(500);
+  let (_, subscription) = send(
+    &app,
+    admin(post_json(
+      "/webhook-subscriptions",
+      json!({ "url": url, "secret": SECRET, "event_types": ["OrderStatusChanged"] }),
+    )),
+  )
+  .await;
+  let uri = format!(
+    "/webhook-subscriptions/{}",
+    subscription["id"].as_str().unwrap()
+  );
+  let worker = worker(&pool, 2, 3);
+
+  order_through(&app, &["paid", "shipped"]).await;
+  dispatch(&pool).await;
+  assert_eq!(worker.run_once().await.unwrap(), 2);
+  let (_, page) = send(&app, admin(get(&format!("{uri}/deliveries")))).await;
+  for delivery in page["items"].as_array().unwrap() {
+    assert_eq!(delivery["status"], "pending");
+    assert_eq!(delivery["attempts"], 1);
+    assert_eq!(delivery["response_status"], 500);
+    assert_eq!(
+      delivery["log"][0]["error"],
+      "receiver answered 500 Internal Server Error"
+    );
+  }
+  // Backing off.
+  assert_eq!(worker.run_once().await.unwrap(), 0);
+  let (_, subscription) = send(&app, admin(get(&uri))).await;
+  assert_eq!(subscription["consecutive_failures"], 2);
+  assert_eq!(subscription["active"], true);
+
+  // The second round exhausts both deliveries and crosses the failure limit.
+  sqlx::query("UPDATE webhook_deliveries SET next_attempt_at = now()")
+    .execute(&pool)
+    .await
+    .unwrap();
+  assert_eq!(worker.run_once().await.unwrap(), 2);
+  let (_, page) = send(&app, admin(get(&format!("{uri}/deliveries")))).await;
+  for delivery in page["items"].as_array().unwrap() {
+    assert_eq!(delivery["status"], "failed");
+    assert_eq!(delivery["log"].as_array().unwrap().len(), 2);
+  }
+  let (_, subscription) = send(&app, admin(get(&uri))).await;
+  assert_eq!(subscription["active"], false);
+  assert!(subscription["disabled_at"].is_string());
+
+  // Disabled subscriptions get no new deliveries and cannot redeliver.
+  order_through(&app, &["paid"]).await;
+  dispatch(&pool).await;
+  let (_, page) = send(&app, admin(get(&format!("{uri}/deliveries")))).await;
+  assert_eq!(page["total"], 2);
+  let delivery_uri = format!(
+    "{uri}/deliveries/{}",
+    page["items"][0]["id"].as_str().unwrap()
+  );
+  let (status, _) = send(
+    &app,
+    admin(post_json(&format!("{delivery_uri}/redeliver"), json!({}))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+
+  let (_, subscription) = send(&app, admin(put_json(&uri, json!({ "active": true })))).await;
+  assert_eq!(subscription["active"], true);
+  assert_eq!(subscription["consecutive_failures"], 0);
+  assert_eq!(subscription["disabled_at"], Value::Null);
+  receiver.answer(204);
+  let (status, _) = send(
+    &app,
+    admin(post_json(&format!("{delivery_uri}/redeliver"), json!({}))),
+  )
+  .await;
+  assert_eq!(status, StatusCode::ACCEPTED);
+  assert_eq!(worker.run_once().await.unwrap(), 1);
+  let (_, delivery) = send(&app, admin(get(&delivery_uri))).await;
+  assert_eq!(delivery["status"], "succeeded");
+  assert_eq!(delivery["response_status"], 204);
+  assert_eq!(delivery["log"].as_array().unwrap().len(), 3);
+}
@@ -0,0 +1,207 @@
+mod common;
+
+use asgard_rust::build_app;
+use axum::http::StatusCode;
+use axum::Router;
+use common::{delete, get, post_json, put_json, send, setup};
+use serde_json::{json, Value};
+
+async fn user(app: &Router, email: &str) -> String {
+  let (_, user) = send(
+    app,
+    post_json("/users", json!({ "email": email, "name": "Wisher" })),
+  )
+  .await;
+  user["id"].as_str().unwrap().to_string()
+}
+
+async fn product(app: &Router, sku: &str, price_cents: i64) -> String {
+  let (_, product) = send(
+    app,
+    post_json(
+      "/products",
+      json!({ "sku": sku, "name": sku, "price_cents": price_cents }),
+    ),
+  )
+  .await;
+  product["id"].as_str().unwrap().to_string()
+}
+
+async fn add(app: &Router, wishlist_uri: &str, product_id: &str) -> (StatusCode, Value) {
+  send(
+    app,
+    post_json(
+      &format!("{wishlist_uri}/items"),
+      json!({ "product_id": product_id }),
+    ),
+  )
+  .await
+}
+
+#[tokio::test]
+async fn wishlists_are_managed_per_user_and_report_price_drops() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let ana = user(&app, "ana@example.com").await;
+  let ben = user(&app, "ben@example.com").await;
+  let lamp = product(&app, "lamp", 1000).await;
+  let rug = product(&app, "rug", 2000).await;
+
+  let lists = format!("/users/{ana}/wishlists");
+  let (status, birthday) = send(&app, post_json(&lists, json!({ "name": " Birthday " }))).await;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(birthday["name"], "Birthday");
+  assert_eq!(birthday["items"], json!([]));
+  for (body, expected) in [
+    (json!({ "name": "Birthday" }), StatusCode::CONFLICT),
+    (json!({ "name": "  " }), StatusCode::BAD_REQUEST),
+  ] {
+    let (status, _) = send(&app, post_json(&lists, body)).await;
+    assert_eq!(status, expected);
+  }
+  let (status, _) = send(
+    &app,
+    post_json(
+      &format!("/users/{}/wishlists", uuid::Uuid::new_v4()),
+      json!({ "name": "Ghost" }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+
+  let uri = format!("{lists}/{}", birthday["id"].as_str().unwrap());
+  let (status, item) = add(&app, &uri, &lamp).await;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(item["added_price_cents"], 1000);
+  assert_eq!(item["price_drop_cents"], Value::Null);
+  let (status, _) = add(&app, &uri, &lamp).await;
+  assert_eq!(status, StatusCode::CONFLICT);
+  let (status, _) = add(&app, &uri, &uuid::Uuid::new_v4().to_string()).await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+  add(&app, &uri, &rug).await;
+
+  // Cheaper now than when saved: reported. More expensive: not.
+  send(
+    &app,
+    put_json(&format!("/products/{lamp}"), json!({ "price_cents": 750 })),
+  )
+  .await;
+  send(
+    &app,
+    put_json(&format!("/products/{rug}"), json!({ "price_cents": 2100 })),
+  )
+  .await;
+  let (_, wishlist) = send(&app, get(&uri)).await;
+  let items = wishlist["items"].as_array().unwrap();
+  assert_eq!(items[0]["name"], "lamp");
+  assert_eq!(items[0]["price_cents"], 750);
+  assert_eq!(items[0]["price_drop_cents"], 250);
+  assert_eq!(items[1]["price_drop_cents"], Value::Null);
+
+  // Another user's wishlist is out of reach.
+  let foreign = uri.replace(&ana, &ben);
+  let (status, _) = send(&app, get(&foreign)).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+  let (status, _) = add(&app, &foreign, &lamp).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+
+  let (status, renamed) = send(&app, put_json(&uri, json!({ "name": "Home" }))).await;
+  assert_eq!(status, StatusCode::OK);
+  assert_eq!(renamed["name"], "Home");
+  assert_eq!(renamed["items"].as_array().unwrap().len(), 2);
+  send(&app, post_json(&lists, json!({ "name": "Garden" }))).await;
+  let (_, all) = send(&app, get(&lists)).await;
+  let names: Vec<_> = all
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|w| w["name"].as_str().unwrap())
+    .collect();
+  assert_eq!(names, ["Garden", "Home"]);
+
+  let (status, _) = send(&app, delete(&format!("{uri}/items/{rug}"))).await;
+  assert_eq!(status, StatusCode::NO_CONTENT);
+  let (status, _) = send(&app, delete(&format!("{uri}/items/{rug}"))).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+  let (status, _) = send(&app, delete(&uri)).await;
+  assert_eq!(status, StatusCode::NO_CONTENT);
+  let (status, _) = send(&app, get(&uri)).await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn items_move_to_a_single_cart_at_the_current_price() {
+  let Some((_pool, state, _guard)) = setup().await else {
+    return;
+  };
+  let app = build_app(state);
+  let ana = user(&app, "ana@example.com").await;
+  let lamp = product(&app, "lamp", 1000).await;
+  let rug = product(&app, "rug", 2000).await;
+  let (_, wishlist) = send(
+    &app,
+    post_json(
+      &format!("/users/{ana}/wishlists"),
+      json!({ "name": "Later" }),
+    ),
+  )
+  .await;
+  let uri = format!(
+    "/users/{ana}/wishlists/{}",
+    wishlist["id"].as_str().unwrap()
+  );
+  add(&app, &uri, &lamp).await;
+  add(&app, &uri, &rug).await;
+  send(
+    &app,
+    put_json(&format!("/products/{lamp}"), json!({ "price_cents": 900 })),
+  )
+  .await;
+
+  let (status, _) = send(
+    &app,
+    post_json(
+      &format!("{uri}/items/{lamp}/move-to-cart"),
+      json!({ "quantity": 0 }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::BAD_REQUEST);
+
+  let (status, line) = send(
+    &app,
+    post_json(
+      &format!("{uri}/items/{lamp}/move-to-cart"),
+      json!({ "quantity": 2 }),
+    ),
+  )
+  .await;
+  assert_eq!(status, StatusCode::CREATED);
+  assert_eq!(line["unit_price_cents"], 900);
+  assert_eq!(line["quantity"], 2);
+  let cart_id = line["order_id"].as_str().unwrap();
+
+  let (_, line) = send(
+    &app,
+    post_json(&format!("{uri}/items/{rug}/move-to-cart"), json!({})),
+  )
+  .await;
+  assert_eq!(line["order_id"], cart_id);
+  assert_eq!(line["quantity"], 1);
+
+  let (_, cart) = send(&app, get(&format!("/orders/{cart_id}"))).await;
+  assert_eq!(cart["status"], "cart");
+  assert_eq!(cart["user_id"], ana.as_str());
+  assert_eq!(cart["total_cents"], 3800);
+  let (_, wishlist) = send(&app, get(&uri)).await;
+  assert_eq!(wishlist["items"], json!([]));
+
+  let (status, _) = send(
+    &app,
+    post_json(&format!("{uri}/items/{rug}/move-to-cart"), json!({})),
+  )
+  .await;
+  assert_eq!(status, StatusCode::NOT_FOUND);
+}